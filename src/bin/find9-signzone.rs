@@ -0,0 +1,125 @@
+use std::{env, fs, io};
+use std::process::exit;
+use rlibdns::zone::zone_store::ZoneStore;
+use find9::dnssec::dnssec_key::DnssecKey;
+use find9::dnssec::nsec3::Nsec3Params;
+use find9::dnssec::canonical::check_encodable;
+use find9::dnssec::presentation::{to_zone_line, type_to_string};
+use find9::dnssec::signer::Signer;
+use find9::dnssec::zone_signer::{sign_zone, strip_generated_records};
+use find9::utils::domain_utils::normalize;
+
+const USAGE: &str = "usage: find9-signzone -o origin [-f output] [-3 salt] [-H iterations] [-A] zonefile key...
+
+  -o origin      zone apex, IE find9.net
+  -f output      file to write, defaults to <zonefile>.signed
+  -3 salt        use NSEC3 with the hex salt, - for no salt
+  -H iterations  NSEC3 iterations, defaults to 0
+  -A             set the NSEC3 opt-out flag
+  key            key file without the .key / .private extension, IE Kfind9.net.+013+37601";
+
+//dnssec-signzone STYLE ARGUMENTS SO EXISTING SIGNING SCRIPTS ONLY NEED THE BINARY NAME CHANGED
+fn main() {
+    if let Err(e) = run(env::args().skip(1).collect()) {
+        eprintln!("find9-signzone: {}", e);
+        exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> io::Result<()> {
+    let mut origin = None;
+    let mut output = None;
+    let mut salt = None;
+    let mut iterations = 0;
+    let mut opt_out = false;
+    let mut files = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => origin = Some(next_arg(&mut args, "-o")?),
+            "-f" => output = Some(next_arg(&mut args, "-f")?),
+            "-3" => salt = Some(parse_salt(&next_arg(&mut args, "-3")?)?),
+            "-H" => {
+                iterations = next_arg(&mut args, "-H")?.parse::<u16>()
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "-H needs a number"))?;
+            }
+            "-A" => opt_out = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => files.push(arg)
+        }
+    }
+
+    let origin = origin.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("missing -o\n{}", USAGE)))?;
+    if files.len() < 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("missing zone file or keys\n{}", USAGE)));
+    }
+
+    let zone_file = files.remove(0);
+    let output = output.unwrap_or_else(|| format!("{}.signed", zone_file));
+
+    let mut signer = Signer::new();
+    for file_path in &files {
+        let key = DnssecKey::open(file_path)?;
+        if key.get_owner() != normalize(&origin) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} doesn't belong to {}", file_path, origin)));
+        }
+        signer.add_key(&origin, key);
+    }
+
+    if let Some(salt) = salt {
+        signer.set_nsec3(&origin, Nsec3Params::new(iterations, &salt, opt_out));
+    }
+
+    let mut store = ZoneStore::new();
+    store.open(&zone_file, &origin)?;
+    let zone = store.get_zone_exact(&normalize(&origin))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "zone file has no records for the origin"))?;
+    check_encodable(zone, &origin)?;
+
+    let (records, unsigned) = sign_zone(&signer, &origin, zone);
+    if records.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "zone has no SOA or no usable keys"));
+    }
+
+    for (name, _type) in unsigned {
+        eprintln!("find9-signzone: warning: {} {} can't be signed and will fail validation", name, type_to_string(&_type));
+    }
+
+    let mut text = strip_generated_records(&fs::read_to_string(&zone_file)?);
+    text.push_str("\n; DNSSEC records generated by find9-signzone\n");
+    for (owner, record) in records {
+        if let Some(line) = to_zone_line(&owner, record.as_ref()) {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+
+    let tmp = format!("{}.tmp", output);
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, &output)?;
+
+    println!("{}", output);
+    Ok(())
+}
+
+fn next_arg(args: &mut impl Iterator<Item = String>, flag: &str) -> io::Result<String> {
+    args.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} needs a value", flag)))
+}
+
+fn parse_salt(s: &str) -> io::Result<Vec<u8>> {
+    if s == "-" {
+        return Ok(Vec::new());
+    }
+
+    if s.len() % 2 != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "salt must be hex"));
+    }
+
+    (0..s.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "salt must be hex")))
+        .collect()
+}
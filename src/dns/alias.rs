@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use crate::dns::upstream::Upstream;
+use crate::utils::domain_utils::normalize;
+use crate::{MAX_ALIAS_CACHE_TTL, MIN_ALIAS_CACHE_TTL};
+use crate::utils::time_utils::get_now;
+
+const MAX_CACHED_ALIASES: usize = 10000;
+
+type AliasCache = HashMap<(String, RRTypes), (u64, Vec<Box<dyn RecordBase>>)>;
+
+/// ALIAS / ANAME pseudo-records, a name answers A and AAAA queries with the addresses of the target
+/// so a CNAME like pointer can sit at the apex next to the SOA and NS. External targets are resolved
+/// through the upstream servers and cached for their TTL.
+pub struct Aliases {
+    aliases: HashMap<String, String>,
+    upstream: Upstream,
+    cache: Mutex<AliasCache>
+}
+
+impl Aliases {
+
+    pub fn new() -> Self {
+        Self {
+            aliases: HashMap::new(),
+            upstream: Upstream::new(),
+            cache: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn add_alias(&mut self, name: &str, target: &str) {
+        self.aliases.insert(normalize(name), normalize(target));
+    }
+
+    pub fn remove_alias(&mut self, name: &str) {
+        self.aliases.remove(&normalize(name));
+    }
+
+    pub fn get_alias(&self, name: &str) -> Option<&String> {
+        self.aliases.get(&normalize(name))
+    }
+
+    pub fn get_upstream(&self) -> &Upstream {
+        &self.upstream
+    }
+
+    pub fn get_upstream_mut(&mut self) -> &mut Upstream {
+        &mut self.upstream
+    }
+
+    /// The cached address records of an external target with the TTL counted down, None once they
+    /// expire. Negative answers are cached as an empty RRset.
+    pub fn get_cached(&self, target: &str, _type: RRTypes) -> Option<Vec<Box<dyn RecordBase>>> {
+        let key = (normalize(target), _type);
+        let now = get_now();
+
+        match self.cache.lock().unwrap().get(&key) {
+            Some((expires, records)) if *expires > now => {
+                let remaining = (*expires - now) as u32;
+                Some(records.iter().map(|record| {
+                    let mut record = record.clone();
+                    record.set_ttl(record.get_ttl().min(remaining));
+                    record
+                }).collect())
+            }
+            _ => None
+        }
+    }
+
+    /// The address records of an external target, answered from the cache while it is fresh and from
+    /// the upstream servers otherwise.
+    pub fn resolve_external(&self, target: &str, _type: RRTypes) -> Result<Vec<Box<dyn RecordBase>>, ResponseCodes> {
+        if let Some(records) = self.get_cached(target, _type) {
+            return Ok(records);
+        }
+
+        let key = (normalize(target), _type);
+        let now = get_now();
+
+        let response = self.upstream.query(&key.0, _type, None).map_err(|_| ResponseCodes::ServFail)?;
+
+        let ttl = match response.get_response_code() {
+            ResponseCodes::NoError | ResponseCodes::NxDomain => MIN_ALIAS_CACHE_TTL,
+            _ => return Err(ResponseCodes::ServFail)
+        };
+
+        //THE TARGET MAY ITSELF BE A CNAME, ONLY THE ADDRESSES AT THE END OF THE CHAIN ARE KEPT
+        let records: Vec<Box<dyn RecordBase>> = response.get_answers().iter()
+            .filter(|(_, record)| record.get_type() == _type)
+            .map(|(_, record)| record.clone())
+            .collect();
+
+        let ttl = records.iter()
+            .map(|record| record.get_ttl())
+            .min()
+            .unwrap_or(ttl)
+            .clamp(MIN_ALIAS_CACHE_TTL, MAX_ALIAS_CACHE_TTL);
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_ALIASES {
+            cache.retain(|_, (expires, _)| *expires > now);
+            if cache.len() >= MAX_CACHED_ALIASES {
+                cache.clear();
+            }
+        }
+        cache.insert(key, (now + ttl as u64, records.clone()));
+
+        Ok(records.into_iter().map(|mut record| {
+            record.set_ttl(record.get_ttl().min(ttl));
+            record
+        }).collect())
+    }
+}
+
+impl Default for Aliases {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
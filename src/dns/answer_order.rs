@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use crate::utils::crypto::random::fill_random;
+use crate::utils::domain_utils::{get_parent, normalize};
+use crate::utils::message_utils::get_rrsets;
+use crate::utils::time_utils::get_now;
+use crate::utils::zone_utils::get_record_address;
+
+const DEFAULT_WEIGHT: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderTypes {
+    Fixed,
+    Cyclic,
+    Random,
+    Weighted
+}
+
+/// How the records within an RRset are ordered in answers, set per name or for a whole zone by its
+/// apex. Only the order within an RRset changes so RRsets stay whole and their RRSIGs, which are
+/// made over the canonical order, stay valid.
+pub struct AnswerOrder {
+    orders: HashMap<String, OrderTypes>,
+    weights: HashMap<(String, IpAddr), u32>,
+    counters: Mutex<HashMap<(String, RRTypes), usize>>,
+    random: Mutex<u64>
+}
+
+impl AnswerOrder {
+
+    pub fn new() -> Self {
+        Self {
+            orders: HashMap::new(),
+            weights: HashMap::new(),
+            counters: Mutex::new(HashMap::new()),
+            random: Mutex::new(get_seed())
+        }
+    }
+
+    pub fn set_order(&mut self, name: &str, order: OrderTypes) {
+        self.orders.insert(normalize(name), order);
+    }
+
+    /// The order for the name, set for the name itself or inherited from the closest ancestor it was
+    /// set for, IE the zone apex. Fixed if there is none.
+    pub fn get_order(&self, name: &str) -> OrderTypes {
+        let mut current = normalize(name);
+
+        loop {
+            if let Some(order) = self.orders.get(&current) {
+                return *order;
+            }
+
+            current = match get_parent(&current) {
+                Some(parent) => parent,
+                None => return OrderTypes::Fixed
+            };
+        }
+    }
+
+    /// Weight of an A or AAAA record at the name for weighted ordering, records without one weigh 1.
+    pub fn set_weight(&mut self, name: &str, address: IpAddr, weight: u32) {
+        self.weights.insert((normalize(name), address), weight);
+    }
+
+    pub fn get_weight(&self, name: &str, record: &dyn RecordBase) -> u32 {
+        match get_record_address(record) {
+            Some(address) => *self.weights.get(&(normalize(name), address)).unwrap_or(&DEFAULT_WEIGHT),
+            None => DEFAULT_WEIGHT
+        }
+    }
+
+    /// Orders every RRset in a section, the RRSIGs that follow an RRset stay after it.
+    pub fn apply_to_section(&self, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        for range in get_rrsets(records) {
+            let end = range.start + records[range.clone()].iter()
+                .take_while(|(_, record)| record.get_type() != RRTypes::RRSig)
+                .count();
+
+            let name = records[range.start].0.clone();
+            let mut rrset: Vec<Box<dyn RecordBase>> = records[range.start..end].iter()
+                .map(|(_, record)| record.clone())
+                .collect();
+            self.apply(&name, &mut rrset);
+
+            for (i, record) in rrset.into_iter().enumerate() {
+                records[range.start + i].1 = record;
+            }
+        }
+    }
+
+    /// Orders the RRset held at the name.
+    pub fn apply(&self, name: &str, records: &mut Vec<Box<dyn RecordBase>>) {
+        if records.len() < 2 {
+            return;
+        }
+
+        match self.get_order(name) {
+            OrderTypes::Fixed => {}
+            OrderTypes::Cyclic => {
+                let key = (normalize(name), records[0].get_type());
+
+                let mut counters = self.counters.lock().unwrap();
+                let counter = counters.entry(key).or_insert(0);
+                let offset = *counter % records.len();
+                *counter = counter.wrapping_add(1);
+
+                records.rotate_left(offset);
+            }
+            OrderTypes::Random => {
+                //FISHER-YATES
+                for i in (1..records.len()).rev() {
+                    records.swap(i, (self.next_random() % (i as u64 + 1)) as usize);
+                }
+            }
+            OrderTypes::Weighted => {
+                //EFRAIMIDIS-SPIRAKIS - EACH RECORD IS KEYED U^(1/W), HEAVIER RECORDS TEND TO COME FIRST
+                let mut keyed: Vec<(f64, Box<dyn RecordBase>)> = records.drain(..).map(|record| {
+                    let weight = self.get_weight(name, record.as_ref());
+                    let key = match weight {
+                        0 => 0.0,
+                        _ => self.next_random_unit().powf(1.0 / weight as f64)
+                    };
+                    (key, record)
+                }).collect();
+
+                keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+                records.extend(keyed.into_iter().map(|(_, record)| record));
+            }
+        }
+    }
+
+    //SPLITMIX64, ONLY SPREADS LOAD SO IT DOESN'T HAVE TO BE UNPREDICTABLE
+    fn next_random(&self) -> u64 {
+        let mut state = self.random.lock().unwrap();
+        *state = state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in (0, 1].
+    fn next_random_unit(&self) -> f64 {
+        ((self.next_random() >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+}
+
+impl Default for AnswerOrder {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The seed of the PRNG from the kernel, the clock if it can't be read.
+fn get_seed() -> u64 {
+    let mut buf = [0u8; 8];
+    match fill_random(&mut buf) {
+        Ok(_) => u64::from_be_bytes(buf),
+        Err(_) => get_now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::Ipv4Addr;
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::a_record::ARecord;
+    use super::*;
+
+    fn new_records(count: u8) -> Vec<Box<dyn RecordBase>> {
+        (1..=count).map(|i| {
+            let mut record = ARecord::new(300, RRClasses::In);
+            record.set_address(Ipv4Addr::new(192, 0, 2, i));
+            record.upcast()
+        }).collect()
+    }
+
+    fn get_addresses(records: &[Box<dyn RecordBase>]) -> Vec<IpAddr> {
+        records.iter().filter_map(|record| get_record_address(record.as_ref())).collect()
+    }
+
+    #[test]
+    fn random() {
+        let mut order = AnswerOrder::new();
+        order.set_order("test", OrderTypes::Random);
+
+        let mut records = new_records(8);
+        order.apply("www.test", &mut records);
+
+        let mut addresses = get_addresses(&records);
+        addresses.sort();
+        assert_eq!(addresses, get_addresses(&new_records(8)));
+    }
+
+    #[test]
+    fn weighted() {
+        let mut order = AnswerOrder::new();
+        order.set_order("www.test", OrderTypes::Weighted);
+        order.set_weight("www.test", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 0);
+
+        //A RECORD WEIGHING NOTHING ALWAYS COMES LAST
+        for _ in 0..20 {
+            let mut records = new_records(3);
+            order.apply("www.test", &mut records);
+            assert_eq!(get_addresses(&records)[2], IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        }
+    }
+}
@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::soa_record::SoaRecord;
+use crate::dns::client_subnet::{mask_address, ClientSubnet};
+use crate::dns::dns::ResponseResult;
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::normalize;
+use crate::{MAX_CACHE_TTL, MAX_STALE_TTL, STALE_ANSWER_TTL};
+use crate::utils::time_utils::get_now;
+
+const DEFAULT_MAX_SIZE: usize = 10000;
+
+//AN ENTRY IS PREFETCHED ONCE IT HAS BEEN USED THIS OFTEN AND IS IN THE LAST TENTH OF ITS TTL
+const PREFETCH_MIN_HITS: u32 = 3;
+const PREFETCH_MIN_TTL: u64 = 10;
+
+//ANSWERS PICKED BY CLIENT SUBNET ARE KEPT PER NETWORK AT THEIR SCOPE, RFC 7871 SECTION 7.3.1
+type CacheKey = (String, RRTypes, RRClasses, Option<(IpAddr, u8)>);
+
+//END OF THE USE LIST
+const NIL: usize = usize::MAX;
+
+struct CacheEntry {
+    code: ResponseCodes,
+    authenticated_data: bool,
+    subnet_scope: Option<u8>,
+    sections: [Vec<(String, Box<dyn RecordBase>)>; 3],
+    stored: u64,
+    expires: u64,
+    hits: u32,
+    prefetching: bool
+}
+
+/// Responses we resolved for clients, forwarded or recursive, keyed by the question. Answers are
+/// kept for their lowest TTL and NXDOMAIN / NODATA for the negative TTL of the SOA, RFC 2308. Once
+/// the cache is full the least recently used entry goes, and expired entries are still served for a
+/// while if the upstreams can't be reached, RFC 8767. Entries past that are dropped when they are
+/// next looked up or reach the end of the use list.
+///
+/// Forwarded answers with an ECS scope are only served to clients in the network they were given for.
+///
+/// Popular entries are prefetched, the first query to hit one near the end of its TTL is answered
+/// from the cache like the rest while the entry is resolved again on a thread of its own.
+pub struct Cache {
+    entries: Mutex<Entries>,
+    max_size: AtomicUsize,
+    prefetch: AtomicBool
+}
+
+impl Cache {
+
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Entries::new()),
+            max_size: AtomicUsize::new(DEFAULT_MAX_SIZE),
+            prefetch: AtomicBool::new(true)
+        }
+    }
+
+    pub fn set_prefetch(&self, prefetch: bool) {
+        self.prefetch.store(prefetch, Ordering::Relaxed);
+    }
+
+    pub fn is_prefetch(&self) -> bool {
+        self.prefetch.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_size(&self, max_size: usize) {
+        self.max_size.store(max_size, Ordering::Relaxed);
+
+        let mut entries = self.entries.lock().unwrap();
+        while entries.len() > max_size {
+            if entries.pop_lru().is_none() {
+                break;
+            }
+        }
+    }
+
+    pub fn get_max_size(&self) -> usize {
+        self.max_size.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Drops every type cached for the name.
+    pub fn flush(&self, name: &str) {
+        let name = normalize(name);
+        self.entries.lock().unwrap().retain(|(owner, _, _, _)| *owner != name);
+    }
+
+    pub fn flush_all(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Answers the query from the cache, otherwise resolves it and caches the response. When resolving
+    /// fails an expired entry is served instead if it is still within `MAX_STALE_TTL`.
+    pub fn resolve<F>(self: &Arc<Self>, event: &mut RequestEvent, resolve: F) -> ResponseResult<()>
+    where
+        F: Fn(&mut RequestEvent) -> ResponseResult<()> + Send + 'static
+    {
+        let query = event.get_query();
+        let question = (normalize(query.get_fqdn()), query.get_type(), query.get_class());
+        let now = get_now();
+
+        let found = self.find_key(&question, event.get_upstream_subnet());
+        if let Some(cached) = self.get(&found, now, false) {
+            if self.start_prefetch(&found, now) {
+                self.prefetch(event, found, resolve);
+            }
+            return cached.apply(event);
+        }
+
+        let response = resolve(event);
+
+        match response {
+            Ok(_) => self.store(event, ResponseCodes::NoError, now),
+            Err(ResponseCodes::NxDomain) => self.store(event, ResponseCodes::NxDomain, now),
+            //A BOGUS ANSWER ISN'T AN OUTAGE, STALE DATA MUST NOT HIDE IT
+            Err(code) if event.get_extended_error().is_some() => return Err(code),
+            Err(code) => {
+                return match self.get(&found, now, true) {
+                    Some(stale) => {
+                        for section in event.response_records.iter_mut() {
+                            section.clear();
+                        }
+                        stale.apply(event)
+                    }
+                    None => Err(code)
+                };
+            }
+        }
+
+        response
+    }
+
+    /// Resolves the query of the entry again on its own thread, the entry is replaced if it succeeds.
+    fn prefetch<F>(self: &Arc<Self>, event: &RequestEvent, key: CacheKey, resolve: F)
+    where
+        F: Fn(&mut RequestEvent) -> ResponseResult<()> + Send + 'static
+    {
+        let cache = self.clone();
+        let mut event = event.clone();
+        for section in event.response_records.iter_mut() {
+            section.clear();
+        }
+
+        thread::spawn(move || {
+            match resolve(&mut event) {
+                Ok(_) => cache.store(&event, ResponseCodes::NoError, get_now()),
+                Err(ResponseCodes::NxDomain) => cache.store(&event, ResponseCodes::NxDomain, get_now()),
+                Err(_) => {}
+            }
+
+            //AN ENTRY THAT WASN'T REPLACED CAN BE PREFETCHED AGAIN BY THE NEXT QUERY
+            if let Some(entry) = cache.entries.lock().unwrap().peek_mut(&key) {
+                entry.prefetching = false;
+            }
+        });
+    }
+
+    /// Caches the response for the question, answers from upstream may have come back for a narrower
+    /// scope than the entry that was looked for.
+    fn store(&self, event: &RequestEvent, code: ResponseCodes, now: u64) {
+        let query = event.get_query();
+        let (name, _type, class) = (normalize(query.get_fqdn()), query.get_type(), query.get_class());
+
+        let key = match (event.get_upstream_subnet(), event.get_subnet_scope()) {
+            (Some(subnet), Some(scope)) if scope > 0 => (name, _type, class, Some((mask_address(subnet.get_address(), scope), scope))),
+            _ => (name, _type, class, None)
+        };
+
+        self.insert(key, code, event, now);
+    }
+
+    /// The most specific entry held for the subnet, the entry for everyone if there is none.
+    fn find_key(&self, question: &(String, RRTypes, RRClasses), subnet: Option<ClientSubnet>) -> CacheKey {
+        let (name, _type, class) = question.clone();
+
+        if let Some(subnet) = subnet {
+            let entries = self.entries.lock().unwrap();
+            for scope in (1..=subnet.get_source_prefix()).rev() {
+                let key = (name.clone(), _type, class, Some((mask_address(subnet.get_address(), scope), scope)));
+                if entries.contains_key(&key) {
+                    return key;
+                }
+            }
+        }
+
+        (name, _type, class, None)
+    }
+
+    /// A copy of the entry with the TTLs counted down, stale entries are served with `STALE_ANSWER_TTL`.
+    fn get(&self, key: &CacheKey, now: u64, stale: bool) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(key)?;
+
+        let ttl = match (entry.expires > now, stale) {
+            (true, _) => None,
+            (false, true) if entry.expires + MAX_STALE_TTL as u64 > now => Some(STALE_ANSWER_TTL),
+            //PAST SERVING STALE, NOTHING WILL EVER ANSWER FROM IT AGAIN
+            (false, _) if entry.expires + MAX_STALE_TTL as u64 <= now => {
+                entries.remove(key);
+                return None;
+            }
+            _ => return None
+        };
+
+        if ttl.is_none() {
+            entry.hits = entry.hits.saturating_add(1);
+        }
+
+        //THE CLOCK CAN STEP BACK PAST WHEN THE ENTRY WAS STORED
+        let elapsed = now.saturating_sub(entry.stored) as u32;
+        let remaining = entry.expires.saturating_sub(now) as u32;
+
+        let sections = entry.sections.clone().map(|section| {
+            section.into_iter().map(|(owner, mut record)| {
+                let countdown = match ttl {
+                    Some(ttl) => ttl,
+                    None => record.get_ttl().saturating_sub(elapsed).min(remaining)
+                };
+                record.set_ttl(countdown);
+                (owner, record)
+            }).collect()
+        });
+
+        Some(CachedResponse {
+            code: entry.code,
+            authenticated_data: entry.authenticated_data,
+            subnet_scope: entry.subnet_scope,
+            sections
+        })
+    }
+
+    /// Whether the query should refresh the entry instead of being answered from it, only one query
+    /// gets to try per entry.
+    fn start_prefetch(&self, key: &CacheKey, now: u64) -> bool {
+        if !self.is_prefetch() {
+            return false;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = match entries.peek_mut(key) {
+            Some(entry) => entry,
+            None => return false
+        };
+
+        let ttl = entry.expires - entry.stored;
+        if entry.prefetching || entry.hits < PREFETCH_MIN_HITS || ttl < PREFETCH_MIN_TTL || entry.expires.saturating_sub(now) * 10 > ttl {
+            return false;
+        }
+
+        entry.prefetching = true;
+        true
+    }
+
+    fn insert(&self, key: CacheKey, code: ResponseCodes, event: &RequestEvent, now: u64) {
+        let ttl = match event.get_answers().is_empty() {
+            false => event.get_answers().iter().map(|(_, record)| record.get_ttl()).min(),
+            //RFC 2308 section 5 - NEGATIVE ANSWERS ARE CACHED FOR THE LESSER OF THE SOA TTL AND MINIMUM
+            true => event.get_authority_records().iter()
+                .find_map(|(_, record)| record.as_any().downcast_ref::<SoaRecord>())
+                .map(|soa| soa.get_ttl().min(soa.get_minimum_ttl()))
+        };
+
+        //A NEGATIVE ANSWER WITHOUT AN SOA CAN'T BE CACHED
+        let ttl = match ttl {
+            Some(ttl) => ttl.min(MAX_CACHE_TTL),
+            None => return
+        };
+
+        if ttl == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) {
+            while entries.len() >= self.get_max_size() {
+                if entries.pop_lru().is_none() {
+                    return;
+                }
+            }
+        }
+
+        entries.insert(key, CacheEntry {
+            code,
+            authenticated_data: event.is_authenticated_data(),
+            subnet_scope: event.get_subnet_scope(),
+            sections: event.response_records.clone(),
+            stored: now,
+            expires: now + ttl as u64,
+            hits: 0,
+            prefetching: false
+        });
+    }
+}
+
+impl Default for Cache {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct CachedResponse {
+    code: ResponseCodes,
+    authenticated_data: bool,
+    subnet_scope: Option<u8>,
+    sections: [Vec<(String, Box<dyn RecordBase>)>; 3]
+}
+
+impl CachedResponse {
+
+    fn apply(self, event: &mut RequestEvent) -> ResponseResult<()> {
+        event.set_authoritative(false);
+        event.set_recursion_available(true);
+        event.set_authenticated_data(self.authenticated_data);
+        if let Some(scope) = self.subnet_scope {
+            event.set_subnet_scope(scope);
+        }
+
+        let [answers, authority, additional] = self.sections;
+        for (owner, record) in answers {
+            event.add_answer(&owner, record);
+        }
+
+        for (owner, record) in authority {
+            event.add_authority_record(&owner, record);
+        }
+
+        for (owner, record) in additional {
+            event.add_additional_record(&owner, record);
+        }
+
+        match self.code {
+            ResponseCodes::NoError => Ok(()),
+            code => Err(code)
+        }
+    }
+}
+
+struct Node {
+    key: CacheKey,
+    entry: CacheEntry,
+    prev: usize,
+    next: usize
+}
+
+/// The entries in order of use. The map points into the nodes, linked from the most recently used
+/// to the least, so looking up, touching and evicting an entry are all O(1). Freed slots are reused.
+struct Entries {
+    map: HashMap<CacheKey, usize>,
+    nodes: Vec<Option<Node>>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize
+}
+
+impl Entries {
+
+    fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    fn contains_key(&self, key: &CacheKey) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// The entry, moved to the front of the use list.
+    fn get_mut(&mut self, key: &CacheKey) -> Option<&mut CacheEntry> {
+        let index = *self.map.get(key)?;
+        self.unlink(index);
+        self.push_front(index);
+        self.nodes[index].as_mut().map(|node| &mut node.entry)
+    }
+
+    /// The entry, leaving the use list as it is.
+    fn peek_mut(&mut self, key: &CacheKey) -> Option<&mut CacheEntry> {
+        let index = *self.map.get(key)?;
+        self.nodes[index].as_mut().map(|node| &mut node.entry)
+    }
+
+    fn insert(&mut self, key: CacheKey, entry: CacheEntry) {
+        if let Some(index) = self.map.get(&key).copied() {
+            self.unlink(index);
+            self.push_front(index);
+            if let Some(node) = self.nodes[index].as_mut() {
+                node.entry = entry;
+            }
+            return;
+        }
+
+        let node = Node {
+            key: key.clone(),
+            entry,
+            prev: NIL,
+            next: NIL
+        };
+
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+
+        self.map.insert(key, index);
+        self.push_front(index);
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let index = self.map.remove(key)?;
+        self.unlink(index);
+        self.free.push(index);
+        self.nodes[index].take().map(|node| node.entry)
+    }
+
+    /// Removes the least recently used entry.
+    fn pop_lru(&mut self) -> Option<(CacheKey, CacheEntry)> {
+        let key = self.nodes.get(self.tail)?.as_ref()?.key.clone();
+        let entry = self.remove(&key)?;
+        Some((key, entry))
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: Fn(&CacheKey) -> bool
+    {
+        let keys: Vec<CacheKey> = self.map.keys().filter(|key| !f(key)).cloned().collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.free.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (prev, next) = match self.nodes[index].as_ref() {
+            Some(node) => (node.prev, node.next),
+            None => return
+        };
+
+        match prev {
+            NIL => self.head = next,
+            prev => if let Some(node) = self.nodes[prev].as_mut() {
+                node.next = next;
+            }
+        }
+
+        match next {
+            NIL => self.tail = prev,
+            next => if let Some(node) = self.nodes[next].as_mut() {
+                node.prev = prev;
+            }
+        }
+    }
+
+    fn push_front(&mut self, index: usize) {
+        let head = self.head;
+        if let Some(node) = self.nodes[index].as_mut() {
+            node.prev = NIL;
+            node.next = head;
+        }
+
+        match head {
+            NIL => self.tail = index,
+            head => if let Some(node) = self.nodes[head].as_mut() {
+                node.prev = index;
+            }
+        }
+
+        self.head = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use rlibdns::messages::dns_query::DnsQuery;
+    use rlibdns::records::a_record::ARecord;
+    use super::*;
+
+    fn new_key(name: &str) -> CacheKey {
+        (name.to_string(), RRTypes::A, RRClasses::In, None)
+    }
+
+    fn new_entry(expires: u64) -> CacheEntry {
+        CacheEntry {
+            code: ResponseCodes::NoError,
+            authenticated_data: false,
+            subnet_scope: None,
+            sections: [Vec::new(), Vec::new(), Vec::new()],
+            stored: 0,
+            expires,
+            hits: 0,
+            prefetching: false
+        }
+    }
+
+    #[test]
+    fn lru() {
+        let mut entries = Entries::new();
+        entries.insert(new_key("a"), new_entry(1));
+        entries.insert(new_key("b"), new_entry(2));
+        entries.insert(new_key("c"), new_entry(3));
+
+        //TOUCHING AN ENTRY SAVES IT, PEEKING DOESN'T
+        assert!(entries.get_mut(&new_key("a")).is_some());
+        assert!(entries.peek_mut(&new_key("b")).is_some());
+        assert_eq!(entries.pop_lru().map(|(key, _)| key), Some(new_key("b")));
+
+        entries.insert(new_key("c"), new_entry(4));
+        assert_eq!(entries.pop_lru().map(|(key, entry)| (key, entry.expires)), Some((new_key("a"), 1)));
+
+        //THE FREED SLOTS ARE USED AGAIN
+        entries.insert(new_key("d"), new_entry(5));
+        entries.insert(new_key("e"), new_entry(6));
+        assert_eq!(entries.nodes.len(), 3);
+
+        entries.retain(|(name, _, _, _)| name != "d");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries.pop_lru().map(|(key, entry)| (key, entry.expires)), Some((new_key("c"), 4)));
+        assert_eq!(entries.pop_lru().map(|(key, _)| key), Some(new_key("e")));
+        assert!(entries.pop_lru().is_none());
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn clock_stepped_back() {
+        let cache = Cache::new();
+        let now = get_now();
+
+        let mut entry = new_entry(now + 360);
+        entry.stored = now + 60;
+        entry.sections[0].push(("www.test".to_string(), ARecord::new(300, RRClasses::In).upcast()));
+        cache.entries.lock().unwrap().insert(new_key("www.test"), entry);
+
+        let cached = cache.get(&new_key("www.test"), now, false).unwrap();
+        assert_eq!(cached.sections[0][0].1.get_ttl(), 300);
+    }
+
+    #[test]
+    fn prefetch() {
+        let cache = Arc::new(Cache::new());
+        let now = get_now();
+
+        //A POPULAR ENTRY IN THE LAST TENTH OF ITS TTL
+        let mut entry = new_entry(now + 5);
+        entry.stored = now - 95;
+        entry.hits = PREFETCH_MIN_HITS;
+        cache.entries.lock().unwrap().insert(new_key("www.test"), entry);
+
+        let mut event = RequestEvent::new();
+        event.query = Some(DnsQuery::new("www.test", RRTypes::A, RRClasses::In));
+
+        let (tx, rx) = mpsc::channel();
+        let response = cache.resolve(&mut event, move |event| {
+            event.add_answer("www.test", ARecord::new(300, RRClasses::In).upcast());
+            tx.send(()).unwrap();
+            Ok(())
+        });
+
+        //THE CLIENT IS ANSWERED FROM THE ENTRY IT HIT, THE REFRESH LANDS AFTERWARDS
+        assert!(response.is_ok());
+        assert!(!event.has_answers());
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        for _ in 0..100 {
+            if cache.entries.lock().unwrap().peek_mut(&new_key("www.test")).map(|entry| entry.expires >= now + 300).unwrap_or(false) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The entry wasn't prefetched");
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use rlibdns::messages::inter::rr_types::RRTypes;
+use crate::utils::domain_utils::normalize;
+
+//RFC 7871 section 6 - ADDRESS FAMILY NUMBERS FROM IANA
+pub const FAMILY_IPV4: u16 = 1;
+pub const FAMILY_IPV6: u16 = 2;
+
+//RFC 7871 section 11.1 - ADDRESSES WE PASS ON OURSELVES ARE CUT DOWN SO THE CLIENT STAYS PRIVATE
+pub const DEFAULT_IPV4_SOURCE_PREFIX: u8 = 24;
+pub const DEFAULT_IPV6_SOURCE_PREFIX: u8 = 56;
+
+/// The EDNS Client Subnet option, RFC 7871. The address only holds the source prefix, the scope
+/// prefix is what the answer was picked by and is 0 in queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClientSubnet {
+    address: IpAddr,
+    source_prefix: u8,
+    scope_prefix: u8
+}
+
+impl ClientSubnet {
+
+    /// The subnet of the address, bits past the source prefix are cleared.
+    pub fn new(address: IpAddr, source_prefix: u8) -> Self {
+        let source_prefix = source_prefix.min(get_max_prefix(address));
+
+        Self {
+            address: mask_address(address, source_prefix),
+            source_prefix,
+            scope_prefix: 0
+        }
+    }
+
+    /// The subnet we send upstream for a client that didn't pass one.
+    pub fn from_address(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(_) => Self::new(address, DEFAULT_IPV4_SOURCE_PREFIX),
+            IpAddr::V6(_) => Self::new(address, DEFAULT_IPV6_SOURCE_PREFIX)
+        }
+    }
+
+    /// RFC 7871 section 7.1.1 - options with an unknown family, prefixes too long for it, an address
+    /// that isn't cut to the source prefix or has bits set past it are invalid.
+    pub fn from_option(buf: &[u8]) -> Option<Self> {
+        if buf.len() < 4 {
+            return None;
+        }
+
+        let family = u16::from_be_bytes([buf[0], buf[1]]);
+        let source_prefix = buf[2];
+        let scope_prefix = buf[3];
+        let octets = &buf[4..];
+
+        if octets.len() != (source_prefix as usize).div_ceil(8) {
+            return None;
+        }
+
+        let address = match family {
+            FAMILY_IPV4 if octets.len() <= 4 => {
+                let mut address = [0u8; 4];
+                address[..octets.len()].copy_from_slice(octets);
+                IpAddr::V4(Ipv4Addr::from(address))
+            }
+            FAMILY_IPV6 if octets.len() <= 16 => {
+                let mut address = [0u8; 16];
+                address[..octets.len()].copy_from_slice(octets);
+                IpAddr::V6(Ipv6Addr::from(address))
+            }
+            _ => return None
+        };
+
+        let max_prefix = get_max_prefix(address);
+        if source_prefix > max_prefix || scope_prefix > max_prefix || mask_address(address, source_prefix) != address {
+            return None;
+        }
+
+        Some(Self {
+            address,
+            source_prefix,
+            scope_prefix
+        })
+    }
+
+    pub fn to_option(&self) -> Vec<u8> {
+        let (family, octets) = match self.address {
+            IpAddr::V4(address) => (FAMILY_IPV4, address.octets().to_vec()),
+            IpAddr::V6(address) => (FAMILY_IPV6, address.octets().to_vec())
+        };
+
+        let mut buf = Vec::with_capacity(4 + octets.len());
+        buf.extend_from_slice(&family.to_be_bytes());
+        buf.push(self.source_prefix);
+        buf.push(self.scope_prefix);
+        buf.extend_from_slice(&octets[..(self.source_prefix as usize).div_ceil(8)]);
+        buf
+    }
+
+    pub fn get_address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn get_source_prefix(&self) -> u8 {
+        self.source_prefix
+    }
+
+    pub fn set_scope_prefix(&mut self, scope_prefix: u8) {
+        self.scope_prefix = scope_prefix.min(get_max_prefix(self.address));
+    }
+
+    pub fn get_scope_prefix(&self) -> u8 {
+        self.scope_prefix
+    }
+
+    /// RFC 7871 section 7.3 - a response has to carry back the family, source prefix and address we
+    /// sent, only the scope is its own.
+    pub fn is_response_to(&self, query: &Self) -> bool {
+        self.address == query.address && self.source_prefix == query.source_prefix
+    }
+}
+
+struct SubnetAnswer {
+    network: IpAddr,
+    prefix: u8,
+    addresses: Vec<IpAddr>
+}
+
+/// Address records picked by the subnet of the client, IE a CDN name answered with the nearest
+/// edge. The most specific subnet holding the client wins, names without a match are answered from
+/// their zone.
+#[derive(Default)]
+pub struct SubnetAnswers {
+    answers: HashMap<String, Vec<SubnetAnswer>>
+}
+
+impl SubnetAnswers {
+
+    pub fn new() -> Self {
+        Self {
+            answers: HashMap::new()
+        }
+    }
+
+    /// The addresses served at the name to clients in the subnet, A and AAAA are picked separately so
+    /// a subnet can hold either or both.
+    pub fn add_answer(&mut self, name: &str, network: IpAddr, prefix: u8, addresses: &[IpAddr]) -> io::Result<()> {
+        if prefix > get_max_prefix(network) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid network mask"));
+        }
+
+        let answers = self.answers.entry(normalize(name)).or_default();
+        let network = mask_address(network, prefix);
+        answers.retain(|answer| answer.network != network || answer.prefix != prefix);
+        answers.push(SubnetAnswer {
+            network,
+            prefix,
+            addresses: addresses.to_vec()
+        });
+        Ok(())
+    }
+
+    pub fn remove_answers(&mut self, name: &str) {
+        self.answers.remove(&normalize(name));
+    }
+
+    /// Whether the answer for the name and type depends on the subnet at all.
+    pub fn has_answers(&self, name: &str, _type: RRTypes) -> bool {
+        self.answers.get(&normalize(name))
+            .map(|answers| answers.iter().any(|answer| answer.addresses.iter().any(|address| is_type(address, _type))))
+            .unwrap_or(false)
+    }
+
+    /// The addresses of the type for the client along with the prefix of the subnet they were picked by.
+    pub fn find(&self, name: &str, _type: RRTypes, client: IpAddr) -> Option<(u8, Vec<IpAddr>)> {
+        self.answers.get(&normalize(name))?.iter()
+            .filter(|answer| is_same_family(answer.network, client) && mask_address(client, answer.prefix) == answer.network)
+            .filter(|answer| answer.addresses.iter().any(|address| is_type(address, _type)))
+            .max_by_key(|answer| answer.prefix)
+            .map(|answer| (answer.prefix, answer.addresses.iter().filter(|address| is_type(address, _type)).copied().collect()))
+    }
+}
+
+/// Clears the bits of the address past the prefix.
+pub fn mask_address(address: IpAddr, prefix: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(address) => {
+            let mask = u32::MAX.checked_shl(32 - prefix.min(32) as u32).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(address) & mask))
+        }
+        IpAddr::V6(address) => {
+            let mask = u128::MAX.checked_shl(128 - prefix.min(128) as u32).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(address) & mask))
+        }
+    }
+}
+
+fn get_max_prefix(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128
+    }
+}
+
+fn is_same_family(a: IpAddr, b: IpAddr) -> bool {
+    a.is_ipv4() == b.is_ipv4()
+}
+
+fn is_type(address: &IpAddr, _type: RRTypes) -> bool {
+    matches!((_type, address), (RRTypes::A, IpAddr::V4(_)) | (RRTypes::Aaaa, IpAddr::V6(_)))
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex};
+
+enum CallStates<V> {
+    Running,
+    Done(V),
+    Abandoned
+}
+
+struct Call<V> {
+    state: Mutex<CallStates<V>>,
+    done: Condvar
+}
+
+/// Merges concurrent calls for the same key into one, the first caller runs it and the others wait
+/// for its result, IE identical queries to the same upstream only go out once.
+pub struct Coalescer<K, V> {
+    in_flight: Mutex<HashMap<K, Arc<Call<V>>>>
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Coalescer<K, V> {
+
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.lock().unwrap().is_empty()
+    }
+
+    pub fn run<F>(&self, key: K, call: F) -> V
+    where
+        F: FnOnce() -> V
+    {
+        let (in_flight, leader) = {
+            let mut calls = self.in_flight.lock().unwrap();
+            match calls.get(&key) {
+                Some(in_flight) => (in_flight.clone(), false),
+                None => {
+                    let in_flight = Arc::new(Call {
+                        state: Mutex::new(CallStates::Running),
+                        done: Condvar::new()
+                    });
+                    calls.insert(key.clone(), in_flight.clone());
+                    (in_flight, true)
+                }
+            }
+        };
+
+        if !leader {
+            let mut state = in_flight.state.lock().unwrap();
+            while let CallStates::Running = *state {
+                state = in_flight.done.wait(state).unwrap();
+            }
+
+            //THE CALL WE WAITED ON PANICKED, WE MAKE OUR OWN
+            return match &*state {
+                CallStates::Done(value) => value.clone(),
+                _ => {
+                    drop(state);
+                    call()
+                }
+            };
+        }
+
+        let guard = Finish {
+            coalescer: self,
+            key,
+            in_flight
+        };
+
+        let value = call();
+        *guard.in_flight.state.lock().unwrap() = CallStates::Done(value.clone());
+        value
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for Coalescer<K, V> {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for Coalescer<K, V> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalescer")
+            .field("in_flight", &self.in_flight.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Wakes the waiting callers once the leader is done, if it unwinds instead they are told to go on
+/// without it.
+struct Finish<'a, K: Eq + Hash, V> {
+    coalescer: &'a Coalescer<K, V>,
+    key: K,
+    in_flight: Arc<Call<V>>
+}
+
+impl<K: Eq + Hash, V> Drop for Finish<'_, K, V> {
+
+    fn drop(&mut self) {
+        if let Ok(mut calls) = self.coalescer.in_flight.lock() {
+            calls.remove(&self.key);
+        }
+
+        if let Ok(mut state) = self.in_flight.state.lock() {
+            if let CallStates::Running = *state {
+                *state = CallStates::Abandoned;
+            }
+        }
+
+        self.in_flight.done.notify_all();
+    }
+}
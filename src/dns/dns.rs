@@ -1,7 +1,9 @@
 use std::collections::HashMap;
-use std::io;
-use std::net::{IpAddr, Ipv4Addr};
-use std::sync::{Arc, RwLock};
+use std::{fs, io, thread};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use rlibdns::journal::inter::txn_op_codes::TxnOpCodes;
 use rlibdns::journal::journal::Journal;
 use rlibdns::messages::inter::op_codes::OpCodes;
@@ -9,40 +11,46 @@ use rlibdns::messages::inter::response_codes::ResponseCodes;
 use rlibdns::messages::inter::rr_classes::RRClasses;
 use rlibdns::messages::inter::rr_types::RRTypes;
 use rlibdns::records::a_record::ARecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
 use rlibdns::records::inter::record_base::RecordBase;
 use rlibdns::records::soa_record::SoaRecord;
 use rlibdns::zone::inter::zone_types::ZoneTypes;
 use rlibdns::zone::zone::Zone;
 use rlibdns::zone::zone_store::ZoneStore;
-use crate::dns::listeners::a_query::on_a_query;
-use crate::dns::listeners::aaaa_query::on_aaaa_query;/*
-use crate::dns::listeners::any_query::on_any_query;
+use crate::dns::cache::Cache;
+use crate::dns::answer_order::{AnswerOrder, OrderTypes};
+use crate::dns::geo::RegionTypes;
+use crate::dns::health::HealthCheck;
+use crate::dns::listeners::query::{on_query, QueryContext};
+use crate::dns::lookup::QUERY_TYPES;
+use crate::dns::resolver::MinimisationTypes;/*
+use crate::dns::listeners::any_query::on_any_query;*/
 use crate::dns::listeners::axfr_query::on_axfr_query;
-use crate::dns::listeners::cname_query::on_cname_query;
-use crate::dns::listeners::https_query::on_https_query;
 use crate::dns::listeners::ixfr_query::on_ixfr_query;
-use crate::dns::listeners::loc_query::on_uri_query;
-use crate::dns::listeners::mx_query::on_mx_query;
-use crate::dns::listeners::naptr_query::on_naptr_query;
-use crate::dns::listeners::ns_query::on_ns_query;
-use crate::dns::listeners::ptr_query::on_ptr_query;
-use crate::dns::listeners::smimea_query::on_smimea_query;
-use crate::dns::listeners::soa_query::on_soa_query;
-use crate::dns::listeners::srv_query::on_srv_query;
-use crate::dns::listeners::sshfp_query::on_sshfp_query;
-use crate::dns::listeners::svcb_query::on_svcb_query;
-use crate::dns::listeners::txt_query::on_txt_query;
-use crate::dns::listeners::uri_query::on_loc_query;*/
 use crate::dns::server::Server;
 use crate::dns::tcp_server::TcpServer;
 use crate::dns::udp_server::UdpServer;
+use crate::dns::upstream::{SelectionTypes, Upstream};
+use crate::dns::view::View;
+use crate::dnssec::canonical::check_encodable;
+use crate::dnssec::dnssec_key::{DnssecKey, SECURE_ENTRY_POINT_FLAG};
+use crate::dnssec::ds::{get_dnskey_rdata, to_ds_string};
+use crate::dnssec::key_manager::{KeyManager, KeyPolicy};
+use crate::dnssec::nsec3::Nsec3Params;
+use crate::dnssec::trust_anchor::TrustAnchors;
 use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::mmdb::Mmdb;
+use crate::utils::domain_utils::normalize;
+use crate::utils::time_utils::get_now;
+use crate::utils::zone_utils::is_presigned;
+use crate::{GEOIP_RELOAD_INTERVAL, KEY_CHECK_INTERVAL};
 
 pub type RequestMap = Arc<RwLock<HashMap<(OpCodes, RRTypes), Box<dyn Fn(&mut RequestEvent) -> ResponseResult<()> + Send + Sync>>>>;
 pub type ResponseResult<T> = Result<T, ResponseCodes>;
 
 pub struct Dns {
-    store: Arc<RwLock<ZoneStore>>,
+    context: QueryContext,
+    key_managers: Arc<Mutex<Vec<KeyManager>>>,
     udp: UdpServer,
     tcp: TcpServer
 }
@@ -50,60 +58,120 @@ pub struct Dns {
 impl Dns {
 
     pub fn new() -> Self {
-        let store = Arc::new(RwLock::new(ZoneStore::new()));
+        let context = QueryContext::new();
 
         let udp = UdpServer::new();
-        udp.register_request_listener(OpCodes::Query, RRTypes::A, on_a_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Aaaa, on_a_query(&store));/*
-        udp.register_request_listener(OpCodes::Query, RRTypes::Ns, on_ns_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Txt, on_txt_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Mx, on_mx_query(&store)); //TEST
-        udp.register_request_listener(OpCodes::Query, RRTypes::Ptr, on_ptr_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::CName, on_cname_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Srv, on_srv_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Naptr, on_naptr_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::SshFp, on_sshfp_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Smimea, on_smimea_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Https, on_https_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Svcb, on_svcb_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Uri, on_uri_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Loc, on_loc_query(&store));
-        
-        udp.register_request_listener(OpCodes::Query, RRTypes::Soa, on_soa_query(&store));
-        udp.register_request_listener(OpCodes::Query, RRTypes::Any, on_any_query(&store));*/
-
         let tcp = TcpServer::new();
-        tcp.register_request_listener(OpCodes::Query, RRTypes::A, on_a_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Aaaa, on_a_query(&store));/*
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Ns, on_ns_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Txt, on_txt_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Mx, on_mx_query(&store)); //TEST
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Ptr, on_ptr_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::CName, on_cname_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Srv, on_srv_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Naptr, on_naptr_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::SshFp, on_sshfp_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Smimea, on_smimea_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Https, on_https_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Svcb, on_svcb_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Uri, on_uri_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Loc, on_loc_query(&store));
-
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Soa, on_soa_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Axfr, on_axfr_query(&store));
-        tcp.register_request_listener(OpCodes::Query, RRTypes::Ixfr, on_ixfr_query(&store));
+
+        for _type in QUERY_TYPES {
+            udp.register_request_listener(OpCodes::Query, _type, on_query(&context));
+            tcp.register_request_listener(OpCodes::Query, _type, on_query(&context));
+        }
+
+        //ZONE TRANSFERS ONLY GO OVER TCP
+        tcp.register_request_listener(OpCodes::Query, RRTypes::Axfr, on_axfr_query(&context));
+        tcp.register_request_listener(OpCodes::Query, RRTypes::Ixfr, on_ixfr_query(&context));
+        /*
+        udp.register_request_listener(OpCodes::Query, RRTypes::Any, on_any_query(&store));
         tcp.register_request_listener(OpCodes::Query, RRTypes::Any, on_any_query(&store));*/
 
         Self {
-            store,
+            context,
+            key_managers: Arc::new(Mutex::new(Vec::new())),
             udp,
             tcp
         }
     }
 
     pub fn start(&mut self, port: u16) -> io::Result<()> {
-        self.udp.run(port)?;
-        self.tcp.run(port)?;
+        self.start_on(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)
+    }
+
+    /// Serves on the one address rather than every interface.
+    pub fn start_on(&mut self, address: IpAddr, port: u16) -> io::Result<()> {
+        self.udp.run(SocketAddr::new(address, port))?;
+        self.tcp.run(SocketAddr::new(address, port))?;
+
+        if !self.context.resolver.read().unwrap().get_hints().is_empty() {
+            thread::spawn({
+                let resolver = self.context.resolver.clone();
+
+                move || {
+                    if let Err(e) = resolver.read().unwrap().prime() {
+                        println!("Priming the root servers failed, using the hints: {}", e);
+                    }
+                }
+            });
+        }
+
+        thread::spawn({
+            let signer = self.context.signer.clone();
+            let key_managers = self.key_managers.clone();
+
+            move || {
+                loop {
+                    thread::sleep(Duration::from_secs(KEY_CHECK_INTERVAL));
+
+                    let now = get_now();
+
+                    for manager in key_managers.lock().unwrap().iter() {
+                        match manager.run(now) {
+                            Ok(keys) => signer.write().unwrap().set_keys(manager.get_apex(), keys),
+                            Err(e) => println!("Key rollover for {} failed: {}", manager.get_apex(), e)
+                        }
+                    }
+                }
+            }
+        });
+
+        thread::spawn({
+            let health = self.context.health.clone();
+
+            move || {
+                loop {
+                    thread::sleep(Duration::from_secs(1));
+
+                    let now = get_now();
+
+                    //CHECKS BLOCK FOR UP TO THEIR TIMEOUT, THE LOCK IS ONLY TAKEN TO HAND BACK THE RESULTS
+                    let due = health.read().unwrap().get_due_checks(now);
+                    for (key, check) in due {
+                        let passed = check.run().is_ok();
+                        health.write().unwrap().record(&key, passed, now);
+                    }
+                }
+            }
+        });
+
+        thread::spawn({
+            let geo = self.context.geo.clone();
+
+            move || {
+                loop {
+                    thread::sleep(Duration::from_secs(GEOIP_RELOAD_INTERVAL));
+
+                    let (file_path, modified) = {
+                        let geo = geo.read().unwrap();
+                        match geo.get_file_path() {
+                            Some(file_path) => (file_path.clone(), geo.get_modified()),
+                            None => continue
+                        }
+                    };
+
+                    //THE NEW FILE IS READ BEFORE THE LOCK IS TAKEN, A BAD ONE LEAVES THE OLD DATABASE IN USE
+                    let current = fs::metadata(&file_path).and_then(|metadata| metadata.modified()).ok();
+                    if current.is_none() || current == modified {
+                        continue;
+                    }
+
+                    match Mmdb::open(&file_path) {
+                        Ok(database) => geo.write().unwrap().set_database(database, current),
+                        Err(e) => println!("Reloading the GeoIP database {} failed: {}", file_path, e)
+                    }
+                }
+            }
+        });
+
         Ok(())
     }
 
@@ -124,7 +192,326 @@ impl Dns {
     }
 
     pub fn register_zone(&self, file_path: &str, domain: &str) -> io::Result<()> {
-        self.store.write().unwrap().open(file_path, domain)
+        self.check_zone_file(file_path, domain)?;
+
+        let mut store = self.context.store.write().unwrap();
+        store.open(file_path, domain)?;
+        self.context.names.remove(None, domain);
+        self.context.signer.read().unwrap().remove_denial_chain(None, domain);
+        //A POLICY ZONE LOADED AGAIN MAY HOLD OTHER KINDS OF TRIGGER
+        self.context.policies.write().unwrap().update_zone(&store, domain);
+        Ok(())
+    }
+
+    /// Opens a response policy zone, checked after those already registered. It is served and signed
+    /// like any other zone, the triggers in it rewrite the answers of every client.
+    pub fn register_response_policy_zone(&self, file_path: &str, domain: &str) -> io::Result<()> {
+        self.check_zone_file(file_path, domain)?;
+
+        let mut store = self.context.store.write().unwrap();
+        store.open(file_path, domain)?;
+        self.context.names.remove(None, domain);
+        self.context.signer.read().unwrap().remove_denial_chain(None, domain);
+        self.context.policies.write().unwrap().add_zone(&store, domain)
+    }
+
+    /// Stops applying the policies of the zone, the zone itself is still served.
+    pub fn remove_response_policy_zone(&self, domain: &str) {
+        self.context.policies.write().unwrap().remove_zone(domain);
+    }
+
+    /// Adds a view after the existing ones, clients are given the first view whose ACL they match.
+    /// The zones of a view are signed with the keys registered for their domain like any other.
+    pub fn add_view(&self, name: &str) {
+        self.context.views.write().unwrap().add_view(View::new(name));
+    }
+
+    pub fn remove_view(&self, name: &str) {
+        self.context.views.write().unwrap().remove_view(name);
+    }
+
+    pub fn add_view_client(&self, view: &str, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.with_view(view, |view| view.add_client(address, mask))
+    }
+
+    /// Opens a zone into the view, the same domain can be registered in every view with its own data.
+    pub fn register_view_zone(&self, view: &str, file_path: &str, domain: &str) -> io::Result<()> {
+        self.check_zone_file(file_path, domain)?;
+
+        self.with_view(view, |view| view.open_zone(file_path, domain))?;
+        self.context.names.remove(Some(view), domain);
+        self.context.signer.read().unwrap().remove_denial_chain(Some(view), domain);
+        Ok(())
+    }
+
+    pub fn register_view_journal(&self, view: &str, file_path: &str, domain: &str) -> io::Result<()> {
+        self.with_view(view, |view| view.open_journal(file_path, domain))
+    }
+
+    /// Lets the range transfer the zones registered outside of any view by AXFR and IXFR, nobody is
+    /// allowed by default.
+    pub fn allow_transfer(&self, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.context.views.write().unwrap().allow_transfer(address, mask)
+    }
+
+    /// Lets the range transfer the zones of the view, nobody is allowed by default.
+    pub fn allow_view_transfer(&self, view: &str, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.with_view(view, |view| view.allow_transfer(address, mask))
+    }
+
+    fn with_view<F>(&self, name: &str, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut View) -> io::Result<()>
+    {
+        match self.context.views.write().unwrap().get_view_mut(name) {
+            Some(view) => f(view),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("No view named {}", name)))
+        }
+    }
+
+    pub fn register_key(&self, file_path: &str, domain: &str) -> io::Result<()> {
+        let key = DnssecKey::open(file_path)?;
+        if key.get_owner() != normalize(domain) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Key owner doesn't match domain"));
+        }
+
+        self.check_zones(domain)?;
+        self.context.signer.write().unwrap().add_key(domain, key);
+        Ok(())
+    }
+
+    /// Lets find9 manage the keys for the domain, keys are generated into the directory if there are
+    /// none and rolled according to the policy.
+    pub fn register_key_directory(&self, directory: &str, domain: &str, policy: KeyPolicy) -> io::Result<()> {
+        self.check_zones(domain)?;
+
+        let now = get_now();
+
+        let manager = KeyManager::new(directory, domain, policy);
+        let keys = manager.run(now)?;
+
+        self.context.signer.write().unwrap().set_keys(domain, keys);
+        self.key_managers.lock().unwrap().push(manager);
+        Ok(())
+    }
+
+    /// Reads the zone file on its own first when we sign the domain, a zone holding records we can't put
+    /// in canonical form is refused rather than served with RRsets that fail validation.
+    fn check_zone_file(&self, file_path: &str, domain: &str) -> io::Result<()> {
+        if !self.context.signer.read().unwrap().is_signed(domain) {
+            return Ok(());
+        }
+
+        let mut store = ZoneStore::new();
+        store.open(file_path, domain)?;
+        match store.get_zone_exact(&normalize(domain)) {
+            Some(zone) if !is_presigned(zone) => check_encodable(zone, domain),
+            _ => Ok(())
+        }
+    }
+
+    /// Checks the zones already held for the domain, in or outside of views, before keys are added for it.
+    fn check_zones(&self, domain: &str) -> io::Result<()> {
+        let store = self.context.store.read().unwrap();
+        let views = self.context.views.read().unwrap();
+
+        let domain = normalize(domain);
+        let zones = store.get_zone_exact(&domain).into_iter()
+            .chain(views.get_views().iter().filter_map(|view| view.get_store().get_zone_exact(&domain)));
+
+        for zone in zones.filter(|zone| !is_presigned(zone)) {
+            check_encodable(zone, &domain)?;
+        }
+
+        Ok(())
+    }
+
+    /// The DS records to hand to the parent / registrar for the domain, generated from the keys we
+    /// sign with or the SEP flagged DNSKEYs in the zone file.
+    pub fn get_ds_records(&self, domain: &str) -> Vec<String> {
+        let signer = self.context.signer.read().unwrap();
+        if signer.is_signed(domain) {
+            return signer.get_ds_strings(domain);
+        }
+
+        let store = self.context.store.read().unwrap();
+        match store.get_zone_exact(&normalize(domain)).and_then(|zone| zone.get_records("", &RRTypes::DnsKey)) {
+            Some(records) => {
+                records.iter()
+                    .filter_map(|record| record.as_any().downcast_ref::<DnsKeyRecord>())
+                    .filter(|record| record.get_flags() & SECURE_ENTRY_POINT_FLAG != 0)
+                    .map(|record| to_ds_string(&normalize(domain), &get_dnskey_rdata(record), record.get_ttl()))
+                    .collect()
+            }
+            None => Vec::new()
+        }
+    }
+
+    /// Serves NSEC3 instead of NSEC for the domain, RFC 9276 recommends 0 iterations and no salt.
+    pub fn set_nsec3(&self, domain: &str, iterations: u16, salt: &[u8], opt_out: bool) {
+        self.context.signer.write().unwrap().set_nsec3(domain, Nsec3Params::new(iterations, salt, opt_out));
+    }
+
+    /// Answers A and AAAA queries at the name with the addresses of the target, like a CNAME that can
+    /// sit at the apex. The name should own other records in a zone we host, IE the apex.
+    pub fn register_alias(&self, name: &str, target: &str) {
+        self.context.aliases.write().unwrap().add_alias(name, target);
+    }
+
+    /// Sends every query for the domain and its subtree on to the servers and relays their response,
+    /// zones we host inside the domain are still answered by us.
+    pub fn add_forward_zone(&self, domain: &str, servers: &[SocketAddr], selection: SelectionTypes) {
+        let mut upstream = Upstream::new();
+        upstream.set_selection(selection);
+        for server in servers {
+            upstream.add_server(*server);
+        }
+
+        self.context.forwarders.write().unwrap().add_zone(domain, upstream);
+    }
+
+    /// RFC 7871 - sends the subnet of the client with queries to the forward zone, answers are cached
+    /// for the scope the servers give back.
+    pub fn set_forward_client_subnet(&self, domain: &str, client_subnet: bool) -> io::Result<()> {
+        match self.context.forwarders.write().unwrap().get_zone_mut(domain) {
+            Some(upstream) => {
+                upstream.set_client_subnet(client_subnet);
+                Ok(())
+            }
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("No forward zone for {}", domain)))
+        }
+    }
+
+    /// Forwards queries to the zone only for clients in the range, without this the clients allowed
+    /// to recurse are the ones forwarded for.
+    pub fn allow_forward(&self, domain: &str, address: IpAddr, mask: u32) -> io::Result<()> {
+        let mut forwarders = self.context.forwarders.write().unwrap();
+        match forwarders.get_zone(domain) {
+            Some(_) => forwarders.allow(domain, address, mask),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("No forward zone for {}", domain)))
+        }
+    }
+
+    /// Reads the root servers for the resolver from a hint zone, IE named.root. The port is the one
+    /// every name server is queried on, 53 unless testing against local instances.
+    pub fn register_hints(&self, file_path: &str, port: u16) -> io::Result<()> {
+        let mut resolver = self.context.resolver.write().unwrap();
+        resolver.set_port(port);
+        resolver.open_hints(file_path)
+    }
+
+    /// Resolves names we don't host for clients in the range that ask for recursion, nobody is
+    /// allowed by default.
+    pub fn allow_recursion(&self, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.context.resolver.write().unwrap().allow(address, mask)
+    }
+
+    /// RFC 9156 - how much of the name the resolver shows the servers above the zone it is in, relaxed
+    /// by default.
+    pub fn set_qname_minimisation(&self, minimisation: MinimisationTypes) {
+        self.context.resolver.write().unwrap().set_minimisation(minimisation);
+    }
+
+    /// Validates forwarded and recursive answers starting from the trust anchors in the file, IE the
+    /// root KSK as a DS or DNSKEY line. The file is rewritten as RFC 5011 adds or revokes keys.
+    pub fn set_trust_anchor(&self, file_path: &str) -> io::Result<()> {
+        let anchors = TrustAnchors::open(file_path)?;
+        self.context.validator.write().unwrap().set_trust_anchors(anchors);
+        Ok(())
+    }
+
+    /// RFC 7646 - names under the domain aren't validated, IE an internal zone that isn't signed or a
+    /// zone whose signatures are known to be broken.
+    pub fn add_negative_trust_anchor(&self, domain: &str) {
+        self.context.validator.write().unwrap().add_negative_anchor(domain);
+    }
+
+    pub fn remove_negative_trust_anchor(&self, domain: &str) {
+        self.context.validator.write().unwrap().remove_negative_anchor(domain);
+
+        //ANSWERS CACHED WHILE THE ANCHOR WAS IN PLACE WERE NEVER VALIDATED
+        self.context.cache.flush_all();
+    }
+
+    /// The most responses kept for forward zones and recursion before the least recently used go.
+    pub fn set_cache_size(&self, max_size: usize) {
+        self.context.cache.set_max_size(max_size);
+    }
+
+    /// Leaves the addresses of the hosts an answer names out of the additional section, referrals
+    /// still carry their glue. Off by default.
+    pub fn set_minimal_responses(&self, minimal_responses: bool) {
+        self.context.minimal_responses.store(minimal_responses, Ordering::Relaxed);
+    }
+
+    /// Refreshes popular responses before they expire, on by default.
+    pub fn set_cache_prefetch(&self, prefetch: bool) {
+        self.context.cache.set_prefetch(prefetch);
+    }
+
+    /// Drops the cached responses for the name, or everything along with the zone cuts the resolver
+    /// learned without one.
+    pub fn flush_cache(&self, name: Option<&str>) {
+        match name {
+            Some(name) => self.context.cache.flush(name),
+            None => {
+                self.context.cache.flush_all();
+                self.context.resolver.read().unwrap().flush();
+            }
+        }
+    }
+
+    pub fn get_cache(&self) -> &Arc<Cache> {
+        &self.context.cache
+    }
+
+    pub fn get_answer_order(&self) -> &Arc<RwLock<AnswerOrder>> {
+        &self.context.answer_order
+    }
+
+    /// Adds a server to resolve ALIAS targets outside the zones we host through.
+    pub fn add_upstream(&self, address: SocketAddr) {
+        self.context.aliases.write().unwrap().get_upstream_mut().add_server(address);
+    }
+
+    /// Sets how the records within each RRset are ordered for the name, a zone apex covers every name
+    /// in the zone that doesn't have its own.
+    pub fn set_answer_order(&self, name: &str, order: OrderTypes) {
+        self.context.answer_order.write().unwrap().set_order(name, order);
+    }
+
+    /// Weight of the A or AAAA record for the address at the name, used by weighted ordering.
+    pub fn set_answer_weight(&self, name: &str, address: IpAddr, weight: u32) {
+        self.context.answer_order.write().unwrap().set_weight(name, address, weight);
+    }
+
+    /// Only serves the A or AAAA record for the address at the name while its check passes.
+    pub fn add_health_check(&self, name: &str, address: IpAddr, check: HealthCheck) {
+        self.context.health.write().unwrap().add_check(name, address, check);
+    }
+
+    /// Served for the name in place of its A or AAAA records once every one of them is down.
+    pub fn add_backup_address(&self, name: &str, address: IpAddr) {
+        self.context.health.write().unwrap().add_backup(name, address);
+    }
+
+    /// RFC 7871 - serves the addresses at the name to clients in the subnet, going by the ECS option
+    /// a resolver passes or else the address the query came from. The zone has to hold an A or AAAA
+    /// RRset at the name, its TTL is kept.
+    pub fn add_subnet_answer(&self, name: &str, network: IpAddr, mask: u8, addresses: &[IpAddr]) -> io::Result<()> {
+        self.context.subnets.write().unwrap().add_answer(name, network, mask, addresses)
+    }
+
+    /// Opens a MaxMind DB, IE GeoLite2-Country.mmdb, to place clients by their ECS or source address.
+    /// The file is read again whenever it changes so it can be updated without a restart.
+    pub fn set_geoip_database(&self, file_path: &str) -> io::Result<()> {
+        self.context.geo.write().unwrap().open_database(file_path)
+    }
+
+    /// Serves the addresses at the name to clients in the region, a country wins over its continent
+    /// and the default covers everyone else. Subnet answers for the client come first.
+    pub fn add_geo_answer(&self, name: &str, region: RegionTypes, addresses: &[IpAddr]) {
+        self.context.geo.write().unwrap().add_answer(name, region, addresses);
     }
 
     //pub fn register_journal(&mut self, file_path: &str, domain: &str) -> io::Result<()> {
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use crate::dns::dns::ResponseResult;
+use crate::dns::upstream::{get_client_subnet, Upstream};
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::{get_parent, normalize};
+use crate::utils::net::net_mask::NetMask;
+
+/// Forward zones, every name in the subtree of a forward zone is sent on to its upstream servers and
+/// the response relayed to the client, IE conditional forwarding of an internal domain. Forwarding
+/// recurses on the client's behalf, so a zone with its own ACL forwards for the clients in it and
+/// one without forwards for the clients allowed to recurse.
+#[derive(Default)]
+pub struct Forwarders {
+    zones: HashMap<String, Upstream>,
+    acls: HashMap<String, Vec<NetMask>>
+}
+
+impl Forwarders {
+
+    pub fn new() -> Self {
+        Self {
+            zones: HashMap::new(),
+            acls: HashMap::new()
+        }
+    }
+
+    pub fn add_zone(&mut self, apex: &str, upstream: Upstream) {
+        self.zones.insert(normalize(apex), upstream);
+    }
+
+    pub fn remove_zone(&mut self, apex: &str) {
+        self.zones.remove(&normalize(apex));
+        self.acls.remove(&normalize(apex));
+    }
+
+    pub fn allow(&mut self, apex: &str, address: IpAddr, mask: u32) -> io::Result<()> {
+        let mask = NetMask::new(address, mask)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid network mask"))?;
+        self.acls.entry(normalize(apex)).or_default().push(mask);
+        Ok(())
+    }
+
+    /// Whether the client may have queries forwarded to the zone, `recursion` is whether it may
+    /// recurse and decides for zones without an ACL of their own.
+    pub fn is_allowed(&self, apex: &str, address: Option<SocketAddr>, recursion: bool) -> bool {
+        match (self.acls.get(&normalize(apex)), address) {
+            (Some(acl), Some(address)) => acl.iter().any(|mask| mask.contains(address.ip())),
+            (Some(_), None) => false,
+            (None, _) => recursion
+        }
+    }
+
+    pub fn get_zone(&self, apex: &str) -> Option<&Upstream> {
+        self.zones.get(&normalize(apex))
+    }
+
+    pub fn get_zone_mut(&mut self, apex: &str) -> Option<&mut Upstream> {
+        self.zones.get_mut(&normalize(apex))
+    }
+
+    /// The deepest forward zone the name falls in.
+    pub fn find_zone(&self, name: &str) -> Option<(String, &Upstream)> {
+        let mut current = normalize(name);
+
+        loop {
+            if let Some(upstream) = self.zones.get(&current) {
+                return Some((current, upstream));
+            }
+
+            current = get_parent(&current)?;
+        }
+    }
+}
+
+/// Sends the query to the upstream servers and copies their response into the event, the rcode of the
+/// response is returned so NXDOMAIN and the like reach the client along with the records. Upstreams
+/// that take ECS get the subnet of the client and the scope they answer with is kept on the event.
+pub fn forward(upstream: &Upstream, event: &mut RequestEvent) -> ResponseResult<()> {
+    let name = normalize(event.get_query().get_fqdn());
+    let _type = event.get_query().get_type();
+
+    let subnet = match upstream.is_client_subnet() {
+        true => event.get_upstream_subnet(),
+        false => None
+    };
+
+    let response = upstream.query(&name, _type, subnet).map_err(|_| ResponseCodes::ServFail)?;
+
+    //RFC 7871 section 7.3 - A RESPONSE FOR ANOTHER SUBNET IS DROPPED, ONE WITHOUT ECS HOLDS FOR EVERYONE
+    if let Some(subnet) = subnet {
+        match get_client_subnet(&response) {
+            Some(answer) if !answer.is_response_to(&subnet) => return Err(ResponseCodes::ServFail),
+            Some(answer) => event.set_subnet_scope(answer.get_scope_prefix().min(subnet.get_source_prefix())),
+            None => event.set_subnet_scope(0)
+        }
+    }
+
+    event.set_authoritative(false);
+    event.set_recursion_available(true);
+
+    //THE RESPONSE MAY BE SHARED WITH OTHER CLIENTS THAT ASKED THE SAME QUESTION, SO IT IS COPIED
+    for (query, record) in response.get_answers() {
+        event.add_answer(query, record.clone());
+    }
+
+    for (query, record) in response.get_authority_records() {
+        event.add_authority_record(query, record.clone());
+    }
+
+    //THE OPT RECORD DESCRIBES THE UPSTREAM CONNECTION, WE ADD OUR OWN
+    for (query, record) in response.get_additional_records() {
+        if record.get_type() != RRTypes::Opt {
+            event.add_additional_record(query, record.clone());
+        }
+    }
+
+    match response.get_response_code() {
+        ResponseCodes::NoError => Ok(()),
+        code => Err(code)
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::time::SystemTime;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use crate::utils::domain_utils::normalize;
+use crate::utils::mmdb::Mmdb;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RegionTypes {
+    Country(String),
+    Continent(String),
+    Default
+}
+
+/// Address records picked by where the client is, from a local MaxMind DB such as GeoLite2-Country.
+/// A country match wins over the continent, clients that match neither or aren't in the database get
+/// the default if there is one and the zone RRset otherwise.
+#[derive(Default)]
+pub struct GeoAnswers {
+    database: Option<Mmdb>,
+    file_path: Option<String>,
+    modified: Option<SystemTime>,
+    answers: HashMap<String, HashMap<RegionTypes, Vec<IpAddr>>>
+}
+
+impl GeoAnswers {
+
+    pub fn new() -> Self {
+        Self {
+            database: None,
+            file_path: None,
+            modified: None,
+            answers: HashMap::new()
+        }
+    }
+
+    pub fn open_database(&mut self, file_path: &str) -> io::Result<()> {
+        let modified = fs::metadata(file_path)?.modified().ok();
+        self.database = Some(Mmdb::open(file_path)?);
+        self.file_path = Some(file_path.to_string());
+        self.modified = modified;
+        Ok(())
+    }
+
+    /// The database file and the time it was last read, so it can be reloaded once it changes.
+    pub fn get_file_path(&self) -> Option<&String> {
+        self.file_path.as_ref()
+    }
+
+    pub fn get_modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Swaps in a database read elsewhere, the file is read outside the lock so lookups don't wait
+    /// on it.
+    pub fn set_database(&mut self, database: Mmdb, modified: Option<SystemTime>) {
+        self.database = Some(database);
+        self.modified = modified;
+    }
+
+    pub fn get_database(&self) -> Option<&Mmdb> {
+        self.database.as_ref()
+    }
+
+    /// The addresses served at the name to clients in the region, A and AAAA are picked separately.
+    pub fn add_answer(&mut self, name: &str, region: RegionTypes, addresses: &[IpAddr]) {
+        //CODES IN THE DATABASE ARE UPPERCASE, IE DE AND EU
+        let region = match region {
+            RegionTypes::Country(code) => RegionTypes::Country(code.to_uppercase()),
+            RegionTypes::Continent(code) => RegionTypes::Continent(code.to_uppercase()),
+            RegionTypes::Default => RegionTypes::Default
+        };
+
+        self.answers.entry(normalize(name)).or_default().insert(region, addresses.to_vec());
+    }
+
+    pub fn remove_answers(&mut self, name: &str) {
+        self.answers.remove(&normalize(name));
+    }
+
+    /// Whether the answer for the name and type depends on the region at all.
+    pub fn has_answers(&self, name: &str, _type: RRTypes) -> bool {
+        self.answers.get(&normalize(name))
+            .map(|regions| regions.values().any(|addresses| addresses.iter().any(|address| is_type(address, _type))))
+            .unwrap_or(false)
+    }
+
+    /// The addresses of the type for the client along with the prefix of the network the database
+    /// placed it in, the prefix is 0 when the database wasn't used.
+    pub fn find(&self, name: &str, _type: RRTypes, client: Option<IpAddr>) -> Option<(u8, Vec<IpAddr>)> {
+        let regions = self.answers.get(&normalize(name))?;
+
+        let (record, prefix) = match (&self.database, client) {
+            (Some(database), Some(client)) => database.lookup(client).unwrap_or((None, 0)),
+            _ => (None, 0)
+        };
+
+        let country = record.as_ref().and_then(|record| record.get_path(&["country", "iso_code"]))
+            .and_then(|code| code.as_str())
+            .map(|code| RegionTypes::Country(code.to_uppercase()));
+        let continent = record.as_ref().and_then(|record| record.get_path(&["continent", "code"]))
+            .and_then(|code| code.as_str())
+            .map(|code| RegionTypes::Continent(code.to_uppercase()));
+
+        [country, continent, Some(RegionTypes::Default)].into_iter().flatten()
+            .filter_map(|region| regions.get(&region))
+            .map(|addresses| addresses.iter().filter(|address| is_type(address, _type)).copied().collect::<Vec<_>>())
+            .find(|addresses| !addresses.is_empty())
+            .map(|addresses| (prefix, addresses))
+    }
+}
+
+fn is_type(address: &IpAddr, _type: RRTypes) -> bool {
+    matches!((_type, address), (RRTypes::A, IpAddr::V4(_)) | (RRTypes::Aaaa, IpAddr::V6(_)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn get_answers() -> GeoAnswers {
+        let mut geo = GeoAnswers::new();
+        geo.open_database("res/geo-test.mmdb").unwrap();
+        geo.add_answer("www.find9.net", RegionTypes::Country("de".to_string()), &["10.0.0.1".parse().unwrap()]);
+        geo.add_answer("www.find9.net", RegionTypes::Continent("eu".to_string()), &["10.0.0.2".parse().unwrap()]);
+        geo.add_answer("www.find9.net", RegionTypes::Default, &["10.0.0.3".parse().unwrap(), "fd00::3".parse().unwrap()]);
+        geo
+    }
+
+    #[test]
+    fn country_before_continent() {
+        let geo = get_answers();
+        assert_eq!(geo.find("www.find9.net.", RRTypes::A, Some("198.51.100.1".parse().unwrap())),
+            Some((24, vec!["10.0.0.1".parse().unwrap()])));
+        assert_eq!(geo.find("WWW.find9.net", RRTypes::A, Some("127.0.0.1".parse().unwrap())),
+            Some((8, vec!["10.0.0.2".parse().unwrap()])));
+    }
+
+    #[test]
+    fn default_answer() {
+        let geo = get_answers();
+        assert_eq!(geo.find("www.find9.net", RRTypes::A, Some("192.0.2.1".parse().unwrap())),
+            Some((24, vec!["10.0.0.3".parse().unwrap()])));
+        assert_eq!(geo.find("www.find9.net", RRTypes::A, None),
+            Some((0, vec!["10.0.0.3".parse().unwrap()])));
+
+        //THE GB CLIENT FALLS BACK TO THE DEFAULT FOR AAAA AS EU ONLY HAS A
+        assert_eq!(geo.find("www.find9.net", RRTypes::Aaaa, Some("::1".parse().unwrap())),
+            Some((128, vec!["fd00::3".parse().unwrap()])));
+    }
+
+    #[test]
+    fn unknown_name() {
+        let mut geo = get_answers();
+        assert!(geo.has_answers("www.find9.net", RRTypes::Aaaa));
+        assert!(!geo.has_answers("find9.net", RRTypes::A));
+        assert_eq!(geo.find("find9.net", RRTypes::A, Some("198.51.100.1".parse().unwrap())), None);
+
+        geo.remove_answers("www.find9.net");
+        assert!(!geo.has_answers("www.find9.net", RRTypes::A));
+    }
+}
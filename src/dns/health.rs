@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::a_record::ARecord;
+use rlibdns::records::aaaa_record::AaaaRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use crate::utils::domain_utils::normalize;
+use crate::utils::zone_utils::get_record_address;
+
+const DEFAULT_INTERVAL: u64 = 10;
+const DEFAULT_TIMEOUT: u64 = 2;
+const DEFAULT_RISE: u8 = 2;
+const DEFAULT_FALL: u8 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckTypes {
+    Tcp,
+    Http(String)
+}
+
+/// A check against the backend behind an A or AAAA record. The record starts out healthy and flips
+/// once `fall` checks in a row fail, or back once `rise` in a row succeed.
+#[derive(Clone, Debug)]
+pub struct HealthCheck {
+    _type: CheckTypes,
+    address: SocketAddr,
+    interval: u64,
+    timeout: u64,
+    rise: u8,
+    fall: u8,
+    healthy: bool,
+    streak: u8,
+    next_check: u64
+}
+
+impl HealthCheck {
+
+    pub fn new(_type: CheckTypes, address: SocketAddr) -> Self {
+        Self {
+            _type,
+            address,
+            interval: DEFAULT_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            rise: DEFAULT_RISE,
+            fall: DEFAULT_FALL,
+            healthy: true,
+            streak: 0,
+            next_check: 0
+        }
+    }
+
+    pub fn get_type(&self) -> &CheckTypes {
+        &self._type
+    }
+
+    pub fn get_address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn set_interval(&mut self, interval: u64) {
+        self.interval = interval.max(1);
+    }
+
+    pub fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn set_timeout(&mut self, timeout: u64) {
+        self.timeout = timeout.max(1);
+    }
+
+    pub fn get_timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    pub fn set_rise(&mut self, rise: u8) {
+        self.rise = rise.max(1);
+    }
+
+    pub fn get_rise(&self) -> u8 {
+        self.rise
+    }
+
+    pub fn set_fall(&mut self, fall: u8) {
+        self.fall = fall.max(1);
+    }
+
+    pub fn get_fall(&self) -> u8 {
+        self.fall
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        now >= self.next_check
+    }
+
+    /// Connects to the backend, an HTTP check also needs a 2xx or 3xx status for the path.
+    pub fn run(&self) -> io::Result<()> {
+        let timeout = Duration::from_secs(self.timeout);
+        let mut stream = TcpStream::connect_timeout(&self.address, timeout)?;
+
+        let path = match &self._type {
+            CheckTypes::Tcp => return Ok(()),
+            CheckTypes::Http(path) => path
+        };
+
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.write_all(format!("GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, self.address.ip()).as_bytes())?;
+        stream.flush()?;
+
+        //ONLY THE STATUS LINE MATTERS, IE "HTTP/1.1 200 OK"
+        let mut buf = [0u8; 64];
+        let mut len = 0;
+        while len < buf.len() && !buf[..len].contains(&b'\n') {
+            match stream.read(&mut buf[len..])? {
+                0 => break,
+                read => len += read
+            }
+        }
+
+        let status = String::from_utf8_lossy(&buf[..len]);
+        match status.split_whitespace().nth(1).and_then(|code| code.parse::<u16>().ok()) {
+            Some(code) if (200..400).contains(&code) => Ok(()),
+            Some(code) => Err(io::Error::other(format!("HTTP status {}", code))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP response"))
+        }
+    }
+
+    /// Counts the result of a check towards the rise / fall thresholds and schedules the next one.
+    pub fn record(&mut self, passed: bool, now: u64) {
+        self.next_check = now + self.interval;
+
+        if passed == self.healthy {
+            self.streak = 0;
+            return;
+        }
+
+        self.streak += 1;
+        let threshold = match self.healthy {
+            true => self.fall,
+            false => self.rise
+        };
+
+        if self.streak >= threshold {
+            self.healthy = passed;
+            self.streak = 0;
+        }
+    }
+}
+
+/// Health state for A and AAAA records, keyed by owner name and address. Records without a check are
+/// always served, records that are down are left out of answers and once every record of an RRset is
+/// down the backup addresses for the name are served instead.
+#[derive(Default)]
+pub struct HealthChecks {
+    checks: HashMap<(String, IpAddr), HealthCheck>,
+    backups: HashMap<String, Vec<IpAddr>>
+}
+
+impl HealthChecks {
+
+    pub fn new() -> Self {
+        Self {
+            checks: HashMap::new(),
+            backups: HashMap::new()
+        }
+    }
+
+    pub fn add_check(&mut self, name: &str, address: IpAddr, check: HealthCheck) {
+        self.checks.insert((normalize(name), address), check);
+    }
+
+    pub fn remove_check(&mut self, name: &str, address: IpAddr) {
+        self.checks.remove(&(normalize(name), address));
+    }
+
+    pub fn get_check(&self, name: &str, address: IpAddr) -> Option<&HealthCheck> {
+        self.checks.get(&(normalize(name), address))
+    }
+
+    pub fn add_backup(&mut self, name: &str, address: IpAddr) {
+        let backups = self.backups.entry(normalize(name)).or_default();
+        if !backups.contains(&address) {
+            backups.push(address);
+        }
+    }
+
+    pub fn get_backups(&self, name: &str) -> Option<&Vec<IpAddr>> {
+        self.backups.get(&normalize(name))
+    }
+
+    /// The checks that should run now, they are run without holding the lock and the results handed
+    /// back through `record`.
+    pub fn get_due_checks(&self, now: u64) -> Vec<((String, IpAddr), HealthCheck)> {
+        self.checks.iter()
+            .filter(|(_, check)| check.is_due(now))
+            .map(|(key, check)| (key.clone(), check.clone()))
+            .collect()
+    }
+
+    pub fn record(&mut self, key: &(String, IpAddr), passed: bool, now: u64) {
+        if let Some(check) = self.checks.get_mut(key) {
+            check.record(passed, now);
+        }
+    }
+
+    pub fn is_healthy(&self, name: &str, record: &dyn RecordBase) -> bool {
+        match get_record_address(record) {
+            Some(address) => self.get_check(name, address).map(|check| check.is_healthy()).unwrap_or(true),
+            None => true
+        }
+    }
+
+    /// Leaves the records that are down out of an A or AAAA RRset held at the name. With every record
+    /// down the backups are served with the TTL of the RRset, or the RRset as it is if there are none
+    /// so the name never goes dark.
+    pub fn filter(&self, name: &str, records: Vec<Box<dyn RecordBase>>) -> Vec<Box<dyn RecordBase>> {
+        let _type = match records.first() {
+            Some(record) if matches!(record.get_type(), RRTypes::A | RRTypes::Aaaa) => record.get_type(),
+            _ => return records
+        };
+
+        if records.iter().all(|record| self.is_healthy(name, record.as_ref())) {
+            return records;
+        }
+
+        let healthy: Vec<Box<dyn RecordBase>> = records.iter()
+            .filter(|record| self.is_healthy(name, record.as_ref()))
+            .cloned()
+            .collect();
+
+        if !healthy.is_empty() {
+            return healthy;
+        }
+
+        let (ttl, class) = (records[0].get_ttl(), records[0].get_class());
+
+        let backups: Vec<Box<dyn RecordBase>> = self.get_backups(name).map(|backups| {
+            backups.iter().filter_map(|address| match (_type, address) {
+                (RRTypes::A, IpAddr::V4(address)) => {
+                    let mut record = ARecord::new(ttl, class);
+                    record.set_address(*address);
+                    Some(Box::new(record) as Box<dyn RecordBase>)
+                }
+                (RRTypes::Aaaa, IpAddr::V6(address)) => {
+                    let mut record = AaaaRecord::new(ttl, class);
+                    record.set_address(*address);
+                    Some(Box::new(record) as Box<dyn RecordBase>)
+                }
+                _ => None
+            }).collect()
+        }).unwrap_or_default();
+
+        match backups.is_empty() {
+            true => records,
+            false => backups
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use super::*;
+
+    /// A backend on a free loopback port answering each connection with the status line, IE a closed
+    /// connection without one when empty.
+    fn serve(status: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break
+                };
+
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(status.as_bytes());
+            }
+        });
+
+        address
+    }
+
+    fn get_closed_address() -> SocketAddr {
+        TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap()
+    }
+
+    fn get_record(address: [u8; 4]) -> Box<dyn RecordBase> {
+        let mut record = ARecord::new(300, RRClasses::In);
+        record.set_address(Ipv4Addr::from(address));
+        record.upcast()
+    }
+
+    #[test]
+    fn tcp() {
+        assert!(HealthCheck::new(CheckTypes::Tcp, serve("")).run().is_ok());
+        assert!(HealthCheck::new(CheckTypes::Tcp, get_closed_address()).run().is_err());
+    }
+
+    #[test]
+    fn http() {
+        let check = |status| HealthCheck::new(CheckTypes::Http("/health".to_string()), serve(status)).run();
+
+        assert!(check("HTTP/1.1 200 OK\r\n\r\n").is_ok());
+        assert!(check("HTTP/1.0 301 Moved Permanently\r\nLocation: /\r\n\r\n").is_ok());
+        assert_eq!(check("HTTP/1.1 503 Service Unavailable\r\n\r\n").unwrap_err().kind(), io::ErrorKind::Other);
+        assert_eq!(check("").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(HealthCheck::new(CheckTypes::Http("/".to_string()), get_closed_address()).run().is_err());
+    }
+
+    #[test]
+    fn rise_fall() {
+        let mut check = HealthCheck::new(CheckTypes::Tcp, get_closed_address());
+        check.set_rise(2);
+        check.set_fall(3);
+
+        //A PASS BETWEEN FAILURES STARTS THE COUNT AGAIN
+        for passed in [false, false, true, false, false] {
+            check.record(passed, 0);
+            assert!(check.is_healthy());
+        }
+
+        check.record(false, 0);
+        assert!(!check.is_healthy());
+
+        check.record(true, 0);
+        assert!(!check.is_healthy());
+        check.record(true, 10);
+        assert!(check.is_healthy());
+
+        assert!(!check.is_due(19));
+        assert!(check.is_due(20));
+    }
+
+    #[test]
+    fn filter() {
+        let mut checks = HealthChecks::new();
+
+        let mut down = HealthCheck::new(CheckTypes::Tcp, get_closed_address());
+        down.set_fall(1);
+        down.record(false, 0);
+        checks.add_check("www.test", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), down.clone());
+
+        let records = vec![get_record([192, 0, 2, 1]), get_record([192, 0, 2, 2])];
+        let filtered = checks.filter("www.test", records);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(get_record_address(filtered[0].as_ref()), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))));
+
+        //EVERY RECORD DOWN WITHOUT BACKUPS SERVES THE RRSET AS IT IS
+        checks.add_check("www.test", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)), down);
+        let records = vec![get_record([192, 0, 2, 1]), get_record([192, 0, 2, 2])];
+        assert_eq!(checks.filter("www.test", records).len(), 2);
+
+        checks.add_backup("www.test", IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)));
+        let records = vec![get_record([192, 0, 2, 1]), get_record([192, 0, 2, 2])];
+        let filtered = checks.filter("www.test", records);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].get_ttl(), 300);
+        assert_eq!(get_record_address(filtered[0].as_ref()), Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))));
+    }
+}
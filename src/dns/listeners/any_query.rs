@@ -5,7 +5,7 @@ use rlibdns::records::hinfo_record::HInfoRecord;
 use rlibdns::records::inter::record_base::RecordBase;
 use rlibdns::zone::zone_store::ZoneStore;
 use crate::dns::dns::ResponseResult;
-use crate::{ANY_QUERY_ALLOWED, MAX_ANSWERS};
+use crate::ANY_QUERY_ALLOWED;
 use crate::rpc::events::request_event::RequestEvent;
 
 pub fn on_any_query(store: &Arc<RwLock<ZoneStore>>) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
@@ -20,7 +20,7 @@ pub fn on_any_query(store: &Arc<RwLock<ZoneStore>>) -> impl Fn(&mut RequestEvent
 
                 if ANY_QUERY_ALLOWED {
                     for (_type, records) in zone.get_all_records() {
-                        for record in records.iter().take(MAX_ANSWERS) {
+                        for record in records.iter() {
                             event.add_answer(&name, record.clone());
                         }
                     }
@@ -1,46 +1,123 @@
-use std::sync::{Arc, RwLock};
+use rlibdns::journal::journal::Journal;
 use rlibdns::messages::inter::response_codes::ResponseCodes;
 use rlibdns::messages::inter::rr_types::RRTypes;
-use rlibdns::zone::zone_store::ZoneStore;
+use rlibdns::zone::zone::Zone;
 use crate::dns::dns::ResponseResult;
+use crate::dns::listeners::query::QueryContext;
 use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::normalize;
+use crate::utils::zone_utils::to_fqdn;
 
-pub fn on_axfr_query(store: &Arc<RwLock<ZoneStore>>) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
-    let store = store.clone();
+pub fn on_axfr_query(context: &QueryContext) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
+    let context = context.clone();
 
     move |event| {
-        let name = event.get_query().get_name().to_string();
+        let apex = normalize(event.get_query().get_fqdn());
+        with_transfer_zone(&context, event, &apex, |event, zone, _| add_zone_transfer(event, zone, &apex))
+    }
+}
+
+/// Hands the zone the client asked to transfer to the callback along with its journal. The zone comes
+/// from the view of the client like any other answer, or the policy zones, and the client has to be
+/// in the transfer ACL of that view or the one for the zones outside any view.
+pub(crate) fn with_transfer_zone<F>(context: &QueryContext, event: &mut RequestEvent, apex: &str, f: F) -> ResponseResult<()>
+where
+    F: FnOnce(&mut RequestEvent, &Zone, Option<&Journal>) -> ResponseResult<()>
+{
+    let store = context.store.read().unwrap();
+    let views = context.views.read().unwrap();
+    let policies = context.policies.read().unwrap();
 
-        match store.read().unwrap().get_zone_exact(&name) {
-            Some(zone) => {
-                event.set_authoritative(zone.is_authority());
+    let origin = event.get_origin();
+    let view = views.find_view(origin);
+    event.set_view(view.map(|view| view.get_name()));
 
-                match zone.get_records(&RRTypes::Soa) {
-                    Some(records) => {
-                        let record = records.first().unwrap();
-                        event.add_answer(&name, record.clone());
+    let (zone, journal) = match view {
+        Some(view) => {
+            if !view.is_transfer_allowed(origin) {
+                return Err(ResponseCodes::Refused);
+            }
+
+            match view.get_store().get_zone_exact(apex) {
+                Some(zone) => (zone, view.get_journal(apex)),
+                //POLICY ZONES SIT OUTSIDE ANY VIEW AND APPLY TO EVERY CLIENT, SO THEY TRANSFER TO EVERY VIEW
+                None if policies.is_policy_zone(apex) => {
+                    let zone = store.get_zone_exact(apex).ok_or(ResponseCodes::NotAuth)?;
+                    (zone, zone.get_journal())
+                }
+                None => return Err(ResponseCodes::NotAuth)
+            }
+        }
+        None => {
+            if !views.is_transfer_allowed(origin) {
+                return Err(ResponseCodes::Refused);
+            }
 
+            let zone = store.get_zone_exact(apex).ok_or(ResponseCodes::NotAuth)?;
+            (zone, zone.get_journal())
+        }
+    };
 
-                        /*
-                        for (n, records) in zone.get_all_records_recursive() {
-                            for record in records {
-                                event.add_answer(&format!("{n}{name}"), record.clone());
+    event.set_authoritative(zone.is_authority());
+    f(event, zone, journal)
+}
 
-                                //for i in 0..26 {
-                                //    event.add_answer(&format!("{n}{name}"), record.clone());
-                                //}
-                            }
-                        }
-                        */
+/// RFC 5936 section 2.2 - the SOA, every other record of the zone and the SOA again to close it.
+pub(crate) fn add_zone_transfer(event: &mut RequestEvent, zone: &Zone, apex: &str) -> ResponseResult<()> {
+    let soa = zone.get_records("", &RRTypes::Soa)
+        .and_then(|records| records.first())
+        .ok_or(ResponseCodes::ServFail)?;
 
-                        event.add_answer(&name, record.clone());
-                    }
-                    None => return Err(ResponseCodes::ServFail)
-                }
+    event.add_answer(apex, soa.clone());
+
+    for (sub, records) in zone.get_all_records_recursive() {
+        let name = to_fqdn(&sub, apex);
+        for record in records {
+            if name == apex && record.get_type() == RRTypes::Soa {
+                continue;
             }
-            None => return Err(ResponseCodes::Refused) //KILL CONNECTION
+
+            event.add_answer(&name, record.clone());
         }
+    }
+
+    event.add_answer(apex, soa.clone());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::a_record::ARecord;
+    use rlibdns::records::inter::record_base::RecordBase;
+    use rlibdns::records::soa_record::SoaRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    #[test]
+    fn zone_transfer() {
+        let mut zone = Zone::new(ZoneTypes::Master);
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.test.");
+        soa.set_mailbox("hostmaster.test.");
+        soa.set_serial(7);
+        zone.add_record("", soa.upcast());
+        zone.add_record("www", ARecord::new(300, RRClasses::In).upcast());
+        zone.add_record("a.b", ARecord::new(300, RRClasses::In).upcast());
+
+        let mut event = RequestEvent::new();
+        add_zone_transfer(&mut event, &zone, "test").unwrap();
+
+        let answers = event.get_answers();
+        assert_eq!(answers.len(), 4);
+        assert_eq!((answers[0].0.as_str(), answers[0].1.get_type()), ("test", RRTypes::Soa));
+        assert_eq!((answers[3].0.as_str(), answers[3].1.get_type()), ("test", RRTypes::Soa));
+        assert!(answers[1..3].iter().all(|(_, record)| record.get_type() == RRTypes::A));
+        assert!(answers.iter().any(|(name, _)| name == "www.test"));
+        assert!(answers.iter().any(|(name, _)| name == "a.b.test"));
 
-        Ok(())
+        assert_eq!(add_zone_transfer(&mut RequestEvent::new(), &Zone::new(ZoneTypes::Master), "test"), Err(ResponseCodes::ServFail));
     }
 }
@@ -1,135 +1,128 @@
-use std::sync::{Arc, RwLock};
 use rlibdns::journal::inter::txn_op_codes::TxnOpCodes;
 use rlibdns::journal::journal::Journal;
 use rlibdns::messages::inter::response_codes::ResponseCodes;
-use rlibdns::messages::inter::rr_types::RRTypes;
 use rlibdns::records::inter::record_base::RecordBase;
 use rlibdns::records::soa_record::SoaRecord;
-use rlibdns::zone::zone_store::ZoneStore;
+use rlibdns::zone::zone::Zone;
 use crate::dns::dns::ResponseResult;
+use crate::dns::listeners::axfr_query::{add_zone_transfer, with_transfer_zone};
+use crate::dns::listeners::query::QueryContext;
 use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::normalize;
+use crate::utils::zone_utils::get_soa;
 
-pub fn on_ixfr_query(store: &Arc<RwLock<ZoneStore>>) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
-    let store = store.clone();
+pub fn on_ixfr_query(context: &QueryContext) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
+    let context = context.clone();
 
     move |event| {
-        let name = event.get_query().get_name().to_string();
-
-        match store.read().unwrap().get_zone_exact(&name) {
-            Some(zone) => {
-                event.set_authoritative(zone.is_authority());
-
-                match zone.get_records(&RRTypes::Soa) {
-                    Some(records) => {
-                        let record = records.first().unwrap();
-                        event.add_answer(&name, record.clone());
-
-                        //GET JOURNALS
-                        //CONVERT JOURNALS TO BYTES
-                        //THIS WOULD ENTAIL TAKING THE SOA AND GENERATING OTHER SOA WITH THE SERIAL_0 & SERIAL_1
-
-                        //WE NEED THE AUTHORITATIVE RECORD FROM THE REQUEST TO GET THE IXFR NUMBER
-                        //IF IXFR=2 then WE NEED TO START FROM JOURNAL 2 -> END
-
-                        //EXAMPLE OF RESPONSE
-                        //SOA(2)
-                        // ... deletes for 2→3 ...
-                        // SOA(3)
-                        // ... adds for 2→3 ...
-                        // SOA(3)
-                        // ... deletes for 3→4 ...
-                        // SOA(4)
-                        // ... adds for 3→4 ...
-                        // SOA(4)
-                        // ... deletes for 4→5 ...
-                        // SOA(5)
-                        // ... adds for 4→5 ...
-
-                        //ADD CONDENSING...
-                        //Client has serial 10, server is at 13. Valid IXFR replies include:
-                        //
-                        // Three deltas: (10→11), then (11→12), then (12→13), oldest first.
-                        // IETF Datatracker
-                        //
-                        // One condensed delta: (10→13) with all necessary deletes/adds to transform 10 into 13.
-
-                        let mut current_soa = record.as_any().downcast_ref::<SoaRecord>().unwrap().clone();
-                        let current_serial = current_soa.get_serial();
-
-
-
-                        /*
-
-                        match event
-                            .get_request_authority_records()
-                            .iter()
-                            .find_map(|(q, r)| {
-                                if q == &name {
-                                    r.as_any()
-                                        .downcast_ref::<SoaRecord>()
-                                        .map(|soa| soa.get_serial())
-                                } else {
-                                    None
-                                }
-                            }) {
-                            Some(client_serial) => {
-                                if client_serial >= current_serial {
-                                    return Ok(());
-                                }
-
-                                match zone.get_journal() {
-                                    Some(journal) => {
-                                        let mut it = journal.get_txns_from(client_serial).peekable();
-
-                                        match it.peek() {
-                                            Some(_) => {
-                                                for (_, txn) in it {
-                                                    current_soa.set_serial(txn.get_serial_0());
-                                                    event.add_answer(&name, current_soa.clone().upcast());
-
-                                                    //DELETES
-                                                    for (name, record) in txn.get_records(TxnOpCodes::Delete) {
-                                                        event.add_answer(name, record.clone());
-                                                    }
-
-                                                    current_soa.set_serial(txn.get_serial_1());
-                                                    event.add_answer(&name, current_soa.clone().upcast());
-
-                                                    //ADDS
-                                                    for (name, record) in txn.get_records(TxnOpCodes::Add) {
-                                                        event.add_answer(name, record.clone());
-                                                    }
-                                                }
-
-                                                event.add_answer(&name, current_soa.upcast());
-                                                return Ok(());
-                                            }
-                                            None => {}
-                                        }
-                                    }
-                                    None => {}
-                                }
-                            }
-                            None => {}
-                        }
-
-                        //AXFR
-                        for (n, records) in zone.get_all_records_recursive() {
-                            for record in records {
-                                event.add_answer(&format!("{n}{name}"), record.clone());
-                            }
-                        }
-
-                        */
-
-                        event.add_answer(&name, current_soa.upcast());
-                    }
-                    None => return Err(ResponseCodes::ServFail)
-                }
-            }
-            None => return Err(ResponseCodes::Refused) //KILL CONNECTION
+        let apex = normalize(event.get_query().get_fqdn());
+
+        //RFC 1995 section 3 - THE SOA OF THE CLIENT IN THE AUTHORITY SECTION CARRIES THE SERIAL IT HAS
+        let serial = event.get_request_authority_records().iter()
+            .filter(|(name, _)| normalize(name) == apex)
+            .find_map(|(_, record)| record.as_any().downcast_ref::<SoaRecord>().map(|soa| soa.get_serial()))
+            .ok_or(ResponseCodes::FormErr)?;
+
+        with_transfer_zone(&context, event, &apex, |event, zone, journal| add_incremental_transfer(event, zone, &apex, serial, journal))
+    }
+}
+
+/// RFC 1995 section 4 - the changes since the serial of the client, oldest first. A client that is up
+/// to date gets the SOA alone and one the journal can't bring up to date gets the whole zone.
+pub(crate) fn add_incremental_transfer(event: &mut RequestEvent, zone: &Zone, apex: &str, serial: u32, journal: Option<&Journal>) -> ResponseResult<()> {
+    let mut soa = get_soa(zone).ok_or(ResponseCodes::ServFail)?.clone();
+    let current = soa.get_serial();
+
+    //RFC 1982 - SERIALS WRAP, THE CLIENT IS UP TO DATE UNLESS CURRENT IS AHEAD OF IT
+    if (current.wrapping_sub(serial) as i32) <= 0 {
+        event.add_answer(apex, soa.upcast());
+        return Ok(());
+    }
+
+    let txns: Vec<_> = match journal {
+        Some(journal) => journal.get_txns_from(serial).map(|(_, txn)| txn).collect(),
+        None => Vec::new()
+    };
+
+    //THE JOURNAL HAS TO CHAIN FROM THE SERIAL OF THE CLIENT TO OURS WITHOUT GAPS
+    let mut next = serial;
+    for txn in &txns {
+        if txn.get_serial_0() != next {
+            break;
+        }
+        next = txn.get_serial_1();
+    }
+
+    if txns.is_empty() || next != current {
+        return add_zone_transfer(event, zone, apex);
+    }
+
+    event.add_answer(apex, soa.clone().upcast());
+
+    for txn in txns {
+        soa.set_serial(txn.get_serial_0());
+        event.add_answer(apex, soa.clone().upcast());
+
+        for (name, record) in txn.get_records(TxnOpCodes::Delete) {
+            event.add_answer(name, record.clone());
+        }
+
+        soa.set_serial(txn.get_serial_1());
+        event.add_answer(apex, soa.clone().upcast());
+
+        for (name, record) in txn.get_records(TxnOpCodes::Add) {
+            event.add_answer(name, record.clone());
         }
+    }
+
+    event.add_answer(apex, soa.upcast());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::messages::inter::rr_types::RRTypes;
+    use rlibdns::records::a_record::ARecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    fn get_zone(serial: u32) -> Zone {
+        let mut zone = Zone::new(ZoneTypes::Master);
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.test.");
+        soa.set_mailbox("hostmaster.test.");
+        soa.set_serial(serial);
+        zone.add_record("", soa.upcast());
+        zone.add_record("www", ARecord::new(300, RRClasses::In).upcast());
+        zone
+    }
+
+    #[test]
+    fn up_to_date() {
+        let zone = get_zone(5);
+
+        for serial in [5, 6, 1000] {
+            let mut event = RequestEvent::new();
+            add_incremental_transfer(&mut event, &zone, "test", serial, None).unwrap();
+            assert_eq!(event.get_answers().len(), 1);
+        }
+    }
+
+    #[test]
+    fn without_journal() {
+        let zone = get_zone(5);
+
+        //NOTHING TO BRING THE CLIENT UP TO DATE WITH, THE WHOLE ZONE IS SENT INSTEAD
+        let mut event = RequestEvent::new();
+        add_incremental_transfer(&mut event, &zone, "test", 3, None).unwrap();
 
-        Ok(())
+        let answers = event.get_answers();
+        assert_eq!(answers.len(), 3);
+        assert_eq!(answers[0].1.get_type(), RRTypes::Soa);
+        assert_eq!(answers[1].1.get_type(), RRTypes::A);
+        assert_eq!(answers[2].1.get_type(), RRTypes::Soa);
     }
 }
@@ -1,20 +1,5 @@
-pub mod a_query;
-pub mod aaaa_query;
-//pub mod ns_query;
-//pub mod txt_query;
-//pub mod soa_query;
-//pub mod mx_query;
-//pub mod ptr_query;
-//pub mod cname_query;
-//pub mod srv_query;
-//pub mod naptr_query;
-//pub mod sshfp_query;
-//pub mod smimea_query;
-//pub mod svcb_query;
-//pub mod https_query;
-//pub mod uri_query;
-//pub mod loc_query;
-//pub mod axfr_query;
-//pub mod ixfr_query;
+pub mod query;
+pub mod axfr_query;
+pub mod ixfr_query;
 //pub mod any_query;
 //pub mod opt_record;
@@ -0,0 +1,149 @@
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::zone::zone_store::ZoneStore;
+use crate::dns::alias::Aliases;
+use crate::dns::cache::Cache;
+use crate::dns::answer_order::AnswerOrder;
+use crate::dns::client_subnet::SubnetAnswers;
+use crate::dns::geo::GeoAnswers;
+use crate::dns::forward::{forward, Forwarders};
+use crate::dns::health::HealthChecks;
+use crate::dns::dns::ResponseResult;
+use crate::dns::lookup::{lookup, resolve_for_client, LookupContext, SourceTypes};
+use crate::dns::resolver::Resolver;
+use crate::dns::rpz::{apply_policies, ResponsePolicies};
+use crate::dns::view::Views;
+use crate::dns::zone_names::ZoneNamesCache;
+use crate::dnssec::signer::Signer;
+use crate::dnssec::validator::Validator;
+use crate::rpc::events::request_event::RequestEvent;
+
+/// The state shared by the query listeners and the server configuration, each part behind its own
+/// lock.
+#[derive(Clone)]
+pub struct QueryContext {
+    pub(crate) store: Arc<RwLock<ZoneStore>>,
+    pub(crate) views: Arc<RwLock<Views>>,
+    pub(crate) signer: Arc<RwLock<Signer>>,
+    pub(crate) aliases: Arc<RwLock<Aliases>>,
+    pub(crate) answer_order: Arc<RwLock<AnswerOrder>>,
+    pub(crate) health: Arc<RwLock<HealthChecks>>,
+    pub(crate) subnets: Arc<RwLock<SubnetAnswers>>,
+    pub(crate) geo: Arc<RwLock<GeoAnswers>>,
+    pub(crate) policies: Arc<RwLock<ResponsePolicies>>,
+    pub(crate) forwarders: Arc<RwLock<Forwarders>>,
+    pub(crate) resolver: Arc<RwLock<Resolver>>,
+    pub(crate) validator: Arc<RwLock<Validator>>,
+    pub(crate) cache: Arc<Cache>,
+    pub(crate) names: Arc<ZoneNamesCache>,
+    pub(crate) minimal_responses: Arc<AtomicBool>
+}
+
+impl QueryContext {
+
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new(RwLock::new(ZoneStore::new())),
+            views: Arc::new(RwLock::new(Views::new())),
+            signer: Arc::new(RwLock::new(Signer::new())),
+            aliases: Arc::new(RwLock::new(Aliases::new())),
+            answer_order: Arc::new(RwLock::new(AnswerOrder::new())),
+            health: Arc::new(RwLock::new(HealthChecks::new())),
+            subnets: Arc::new(RwLock::new(SubnetAnswers::new())),
+            geo: Arc::new(RwLock::new(GeoAnswers::new())),
+            policies: Arc::new(RwLock::new(ResponsePolicies::new())),
+            forwarders: Arc::new(RwLock::new(Forwarders::new())),
+            resolver: Arc::new(RwLock::new(Resolver::new())),
+            validator: Arc::new(RwLock::new(Validator::new())),
+            cache: Arc::new(Cache::new()),
+            names: Arc::new(ZoneNamesCache::new()),
+            minimal_responses: Arc::new(AtomicBool::new(false))
+        }
+    }
+}
+
+impl Default for QueryContext {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn on_query(context: &QueryContext) -> impl Fn(&mut RequestEvent) -> ResponseResult<()> {
+    let context = context.clone();
+
+    move |event| {
+        //POLICY ZONES ARE REGISTERED OUTSIDE ANY VIEW AND APPLY TO EVERY CLIENT
+        apply_policies(&context.policies, &context.store, event, &|event| answer(&context, event))
+    }
+}
+
+/// Answers the query from the zones of the client. The zone locks are released before anything is
+/// sent upstream so a slow server doesn't hold up health checks, key rollovers or GeoIP reloads.
+fn answer(context: &QueryContext, event: &mut RequestEvent) -> ResponseResult<()> {
+    //THE LOOKUP RUNS AGAIN ONCE THE ALIAS TARGET IT WAS MISSING IS CACHED
+    for _ in 0..2 {
+        match lookup_local(context, event) {
+            SourceTypes::Local(response) => return response,
+            //THE CACHE MAY RESOLVE THE QUERY AGAIN ON ITS OWN THREAD, THE LOCKS ARE TAKEN EACH TIME
+            SourceTypes::Forward(upstream) => {
+                let validator = context.validator.clone();
+                return resolve_for_client(&context.cache, event, move |event| {
+                    validator.read().unwrap().validate(event, &|event| forward(&upstream, event))
+                });
+            }
+            SourceTypes::Recurse => {
+                let (resolver, validator) = (context.resolver.clone(), context.validator.clone());
+                return resolve_for_client(&context.cache, event, move |event| {
+                    let resolver = resolver.read().unwrap();
+                    validator.read().unwrap().validate(event, &|event| resolver.resolve(event))
+                });
+            }
+            SourceTypes::Alias(target, _type) => {
+                context.aliases.read().unwrap().resolve_external(&target, _type)?;
+                for records in event.response_records.iter_mut() {
+                    records.clear();
+                }
+            }
+        }
+    }
+
+    Err(ResponseCodes::ServFail)
+}
+
+fn lookup_local(context: &QueryContext, event: &mut RequestEvent) -> SourceTypes {
+    let store = context.store.read().unwrap();
+    let views = context.views.read().unwrap();
+    let signer = context.signer.read().unwrap();
+    let aliases = context.aliases.read().unwrap();
+    let answer_order = context.answer_order.read().unwrap();
+    let health = context.health.read().unwrap();
+    let subnets = context.subnets.read().unwrap();
+    let geo = context.geo.read().unwrap();
+    let forwarders = context.forwarders.read().unwrap();
+    let resolver = context.resolver.read().unwrap();
+
+    //THE ZONES OF THE FIRST VIEW THE CLIENT MATCHES, OR THOSE OUTSIDE ANY VIEW
+    let view = views.find_view(event.get_origin());
+    event.set_view(view.map(|view| view.get_name()));
+
+    let zones = match view {
+        Some(view) => view.get_store(),
+        None => &*store
+    };
+
+    lookup(&LookupContext {
+        store: zones,
+        signer: &signer,
+        aliases: Some(&aliases),
+        answer_order: &answer_order,
+        health: &health,
+        subnets: Some(&subnets),
+        geo: Some(&geo),
+        forwarders: &forwarders,
+        resolver: &resolver,
+        names: &context.names,
+        minimal_responses: context.minimal_responses.load(Ordering::Relaxed)
+    }, event)
+}
@@ -0,0 +1,744 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use rlibdns::messages::dns_query::DnsQuery;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::a_record::ARecord;
+use rlibdns::records::aaaa_record::AaaaRecord;
+use rlibdns::records::cname_record::CNameRecord;
+use rlibdns::records::dname_record::DNameRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::https_record::HttpsRecord;
+use rlibdns::records::mx_record::MxRecord;
+use rlibdns::records::ns_record::NsRecord;
+use rlibdns::records::srv_record::SrvRecord;
+use rlibdns::records::svcb_record::SvcbRecord;
+use rlibdns::utils::fqdn_utils::fqdn_to_relative;
+use rlibdns::zone::inter::zone_types::ZoneTypes;
+use rlibdns::zone::zone::Zone;
+use rlibdns::zone::zone_store::ZoneStore;
+use crate::MAX_CNAME_CHAIN_SIZE;
+use crate::dns::alias::Aliases;
+use crate::dns::cache::Cache;
+use crate::dns::answer_order::AnswerOrder;
+use crate::dns::client_subnet::SubnetAnswers;
+use crate::dns::geo::GeoAnswers;
+use crate::dns::forward::Forwarders;
+use crate::dns::health::HealthChecks;
+use crate::dns::resolver::Resolver;
+use crate::dns::upstream::Upstream;
+use crate::dns::dns::ResponseResult;
+use crate::dnssec::signer::Signer;
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::{get_labels, get_parent, get_wire_length, is_subdomain, normalize, MAX_NAME_LENGTH};
+use crate::utils::query_utils::{add_delegation_proof, add_glue, add_negative_answer, add_wildcard_answer, add_wildcard_no_data_answer, get_ds_records, get_response_size, sign_response};
+use crate::dns::zone_names::ZoneNamesCache;
+use crate::utils::zone_utils::to_fqdn;
+
+/// Types answered by the lookup, everything the zone parser understands plus the DNSSEC types the
+/// signer publishes.
+pub const QUERY_TYPES: [RRTypes; 23] = [
+    RRTypes::A,
+    RRTypes::Aaaa,
+    RRTypes::Ns,
+    RRTypes::CName,
+    RRTypes::Soa,
+    RRTypes::Ptr,
+    RRTypes::HInfo,
+    RRTypes::Mx,
+    RRTypes::Txt,
+    RRTypes::Srv,
+    RRTypes::Naptr,
+    RRTypes::SshFp,
+    RRTypes::Smimea,
+    RRTypes::Svcb,
+    RRTypes::Https,
+    RRTypes::Uri,
+    RRTypes::Loc,
+    RRTypes::DName,
+    RRTypes::Ds,
+    RRTypes::DnsKey,
+    RRTypes::Cds,
+    RRTypes::CDnsKey,
+    RRTypes::NSec3Param
+];
+
+/// The state a lookup answers from, borrowed for one query. ALIAS, subnet and region answers are
+/// left out when resolving the target of an ALIAS. Minimal responses leave the additional section
+/// out of answers, referrals keep their glue.
+#[derive(Clone, Copy)]
+pub struct LookupContext<'a> {
+    pub store: &'a ZoneStore,
+    pub signer: &'a Signer,
+    pub aliases: Option<&'a Aliases>,
+    pub answer_order: &'a AnswerOrder,
+    pub health: &'a HealthChecks,
+    pub subnets: Option<&'a SubnetAnswers>,
+    pub geo: Option<&'a GeoAnswers>,
+    pub forwarders: &'a Forwarders,
+    pub resolver: &'a Resolver,
+    pub names: &'a ZoneNamesCache,
+    pub minimal_responses: bool
+}
+
+/// Where the answer comes from. Zones we host are answered while their locks are held, anything
+/// needing the network is handed back to be resolved once they are released.
+pub enum SourceTypes {
+    Local(ResponseResult<()>),
+    Forward(Upstream),
+    Recurse,
+    Alias(String, RRTypes)
+}
+
+/// The addresses for an ALIAS, or the external target still to be resolved for them.
+enum AliasTypes {
+    Records(Vec<Box<dyn RecordBase>>),
+    External(String)
+}
+
+/// RFC 1034 section 4.3.2 - answers a query of any type from the zones we hold, following CNAMEs
+/// and DNAMEs into any zone we are authoritative for, flattening ALIASes, leaving out unhealthy addresses, expanding wildcards, returning referrals at zone cuts and
+/// NXDOMAIN / NODATA otherwise. Address RRsets with subnet or region answers are picked by the client
+/// subnet or where the client is. Names we don't host are left to the resolver for clients allowed to
+/// recurse, as is the target of an ALIAS that isn't cached yet, and names in a forward zone to its
+/// upstreams for the clients it forwards for.
+pub fn lookup(context: &LookupContext, event: &mut RequestEvent) -> SourceTypes {
+    let name = normalize(event.get_query().get_fqdn());
+    let _type = event.get_query().get_type();
+
+    //RFC 7871 section 7.1.1 - AN ECS OPTION WE CAN'T MAKE SENSE OF IS A FORMAT ERROR
+    if event.has_client_subnet() && event.get_client_subnet().is_none() {
+        return SourceTypes::Local(Err(ResponseCodes::FormErr));
+    }
+
+    //RA TELLS THE CLIENT WHETHER WE WOULD RECURSE FOR IT, WHETHER OR NOT THIS ANSWER NEEDED IT
+    let recursion = context.resolver.is_allowed(event.get_origin());
+    event.set_recursion_available(recursion);
+
+    //A FORWARD ZONE TAKES THE SUBTREE UNLESS WE HOST A ZONE DEEPER INSIDE IT
+    if let Some((forward_apex, upstream)) = context.forwarders.find_zone(&name) {
+        let hosted = find_zone(context.store, &name, &_type).map(|(apex, _)| is_subdomain(&normalize(&apex), &forward_apex)).unwrap_or(false);
+        if !hosted {
+            return match context.forwarders.is_allowed(&forward_apex, event.get_origin(), recursion) {
+                true => SourceTypes::Forward(upstream.clone()),
+                false => SourceTypes::Local(Err(ResponseCodes::Refused))
+            };
+        }
+    }
+
+    let (apex, zone) = match find_zone(context.store, &name, &_type) {
+        Some(found) => found,
+        None => return match recursion && event.is_recursion_desired() {
+            true => SourceTypes::Recurse,
+            false => SourceTypes::Local(Err(ResponseCodes::Refused))
+        }
+    };
+
+    //AA DESCRIBES THE QUERY NAME, THE REST OF THE CHAIN ONLY COMES FROM ZONES WE OWN AS WELL
+    event.set_authoritative(zone.is_authority());
+
+    let mut zones = vec![(apex, zone)];
+    let response = match lookup_in_zones(context, event, &name, &_type, &mut zones) {
+        Ok(Some(target)) => return SourceTypes::Alias(target, _type),
+        Ok(None) => Ok(()),
+        Err(code) => Err(code)
+    };
+
+    //ONLY THE ORDER WITHIN EACH RRSET CHANGES, THE CHAIN AND ANY SIGNATURES ALREADY ADDED KEEP THEIR PLACE
+    context.answer_order.apply_to_section(event.get_answers_mut());
+
+    if event.is_dnssec_ok() {
+        for (apex, zone) in &zones {
+            sign_response(context.signer, zone, apex, event);
+        }
+    }
+
+    SourceTypes::Local(response)
+}
+
+/// Resolves the query through the cache, `resolve` validates what comes back and may be run again on
+/// another thread to prefetch the entry. The signatures and denial proofs we ask upstream for are
+/// left out for clients that didn't set DO, RFC 4035 section 3.2.1.
+pub fn resolve_for_client<F>(cache: &Arc<Cache>, event: &mut RequestEvent, resolve: F) -> ResponseResult<()>
+where
+    F: Fn(&mut RequestEvent) -> ResponseResult<()> + Send + 'static
+{
+    let response = cache.resolve(event, resolve);
+
+    if !event.is_dnssec_ok() {
+        let _type = event.get_query().get_type();
+        for section in event.response_records.iter_mut() {
+            section.retain(|(_, record)| record.get_type() == _type || !matches!(record.get_type(), RRTypes::RRSig | RRTypes::NSec | RRTypes::NSec3));
+        }
+    }
+
+    response
+}
+
+/// Runs the lookup starting in the last of the zones, every zone the CNAME chain moves into is added
+/// so the response can be signed for each of them. An ALIAS target that has to be resolved upstream
+/// and isn't cached is returned instead of an answer.
+fn lookup_in_zones<'a>(context: &LookupContext<'a>, event: &mut RequestEvent, name: &str, _type: &RRTypes, zones: &mut Vec<(String, &'a Zone)>) -> ResponseResult<Option<String>> {
+    let (store, signer, health) = (context.store, context.signer, context.health);
+    let (mut current_apex, mut zone) = match zones.last() {
+        Some((apex, zone)) => (apex.clone(), *zone),
+        None => return Err(ResponseCodes::Refused)
+    };
+
+    let mut target = name.to_string();
+    let mut visited = vec![target.clone()];
+    let mut depth = 0;
+
+    loop {
+        let apex = current_apex.as_str();
+        let sub = match fqdn_to_relative(apex, &target) {
+            Some(sub) => sub,
+            None => return Ok(None)
+        };
+
+        let dname = find_dname(zone, apex, &target);
+
+        if let Some(cut) = find_cut(zone, apex, &target, _type) {
+            //A DNAME ABOVE THE CUT REDIRECTS THE WHOLE SUBTREE, THE DELEGATION IS NEVER REACHED
+            let redirected = dname.as_ref().map(|(owner, _)| *owner != cut && is_subdomain(&cut, owner)).unwrap_or(false);
+
+            if !redirected {
+                //RFC 1034 section 4.3.2 step 3b - A REFERRAL ISN'T AUTHORITATIVE, EVEN AT THE END OF A CNAME CHAIN WE OWN
+                event.set_authoritative(false);
+
+                add_referral(store, signer, zone, apex, event, &cut);
+                return Ok(None);
+            }
+        }
+
+        //RFC 6672 section 3.1 - THE DNAME GOES IN THE ANSWER ALONG WITH A CNAME SYNTHESIZED FOR THE NAME
+        if let Some((owner, record)) = dname {
+            depth += 1;
+            if depth >= MAX_CNAME_CHAIN_SIZE {
+                return Err(ResponseCodes::ServFail);
+            }
+
+            let next = match record.as_any().downcast_ref::<DNameRecord>().and_then(|record| record.get_target()) {
+                Some(next) => rewrite_name(&target, &owner, &normalize(&next)),
+                None => return Err(ResponseCodes::ServFail)
+            };
+
+            let mut cname = CNameRecord::new(record.get_ttl(), record.get_class());
+            cname.set_target(&format!("{}.", next));
+
+            event.add_answer(&owner, record);
+
+            //RFC 6672 section 2.2 - THE REWRITTEN NAME DOESN'T FIT IN A DOMAIN NAME
+            if get_wire_length(&next) > MAX_NAME_LENGTH {
+                return Err(ResponseCodes::YxDomain);
+            }
+
+            event.add_answer(&target, cname.upcast());
+            target = next;
+
+            match follow(store, &target, _type, &mut visited, zones) {
+                Some((next_apex, next_zone)) => {
+                    current_apex = next_apex;
+                    zone = next_zone;
+                }
+                None => return Ok(None)
+            }
+            continue;
+        }
+
+        //ALIAS - THE ADDRESSES OF THE TARGET ARE ANSWERED AS IF THEY WERE HELD AT THE NAME, ADDRESSES IN THE
+        //ZONE ITSELF TAKE PRIORITY
+        if matches!(_type, RRTypes::A | RRTypes::Aaaa) && zone.get_records(&sub, _type).is_none() {
+            if let Some((aliases, alias)) = context.aliases.and_then(|aliases| aliases.get_alias(&target).map(|alias| (aliases, alias))) {
+                let records = match resolve_alias(context, aliases, event.get_view(), alias, _type)? {
+                    AliasTypes::Records(records) => records,
+                    AliasTypes::External(target) => return Ok(Some(target))
+                };
+                if records.is_empty() {
+                    let names = context.names.get(event.get_view(), apex, zone);
+                    return add_negative_answer(signer, &names, zone, apex, event, &target).map(|_| None);
+                }
+
+                for record in records {
+                    event.add_answer(&target, record);
+                }
+                return Ok(None);
+            }
+        }
+
+        //RFC 4592 - A NAME THAT DOESN'T EXIST IS ANSWERED FROM THE WILDCARD AT ITS CLOSEST ENCLOSER
+        let names = context.names.get(event.get_view(), apex, zone);
+        let source = match names.exists(&target) {
+            true => target.clone(),
+            false => {
+                let closest_encloser = names.get_closest_encloser(&target);
+                let wildcard = format!("*.{}", closest_encloser).trim_end_matches('.').to_string();
+                match names.exists(&wildcard) {
+                    true => wildcard,
+                    false => return add_negative_answer(signer, &names, zone, apex, event, &target).map(|_| None)
+                }
+            }
+        };
+
+        let synthesized = source != target;
+        let source_sub = fqdn_to_relative(apex, &source).unwrap_or(sub);
+
+        //BACKENDS THAT FAILED THEIR HEALTH CHECKS ARE LEFT OUT BEFORE THE RRSET IS SIGNED
+        let records = health.filter(&source, get_rrset(signer, zone, apex, &source, &source_sub, _type));
+
+        //ECS / GEOIP - THE ADDRESSES PICKED FOR THE CLIENT TAKE THE PLACE OF THE ZONE RRSET, KEEPING ITS TTL
+        let records = match records.is_empty() {
+            true => records,
+            false => get_client_rrset(context.subnets, context.geo, event, &target, _type, records)
+        };
+
+        if !records.is_empty() {
+            match synthesized {
+                true => add_wildcard_answer(signer, zone, apex, event, &target, &source, records),
+                false => {
+                    for record in records {
+                        event.add_answer(&target, record);
+                    }
+                }
+            }
+
+            if !context.minimal_responses {
+                add_additional_records(store, zone, apex, event);
+            }
+            return Ok(None);
+        }
+
+        if *_type != RRTypes::CName {
+            if let Some(record) = zone.get_records(&source_sub, &RRTypes::CName).and_then(|records| records.first()) {
+                depth += 1;
+                if depth >= MAX_CNAME_CHAIN_SIZE {
+                    return Err(ResponseCodes::ServFail);
+                }
+
+                match synthesized {
+                    true => add_wildcard_answer(signer, zone, apex, event, &target, &source, vec![record.clone()]),
+                    false => event.add_answer(&target, record.clone())
+                }
+
+                target = match record.as_any().downcast_ref::<CNameRecord>().and_then(|record| record.get_target()) {
+                    Some(next) => normalize(&next),
+                    None => return Err(ResponseCodes::ServFail)
+                };
+
+                match follow(store, &target, _type, &mut visited, zones) {
+                    Some((next_apex, next_zone)) => {
+                        current_apex = next_apex;
+                        zone = next_zone;
+                    }
+                    None => return Ok(None)
+                }
+                continue;
+            }
+        }
+
+        if synthesized {
+            add_wildcard_no_data_answer(signer, zone, apex, event, &target);
+            return Ok(None);
+        }
+
+        //RFC 6604 - THE RCODE DESCRIBES THE LAST NAME IN THE CHAIN
+        return add_negative_answer(signer, &names, zone, apex, event, &target).map(|_| None);
+    }
+}
+
+/// The RRset for the client, an answer for its subnet wins over one for its region. The scope is set
+/// to the network the answer was picked by, clients without one get the zone RRset which only holds
+/// for their own subnet.
+fn get_client_rrset(subnets: Option<&SubnetAnswers>, geo: Option<&GeoAnswers>, event: &mut RequestEvent, name: &str, _type: &RRTypes, records: Vec<Box<dyn RecordBase>>) -> Vec<Box<dyn RecordBase>> {
+    let subnets = subnets.filter(|subnets| subnets.has_answers(name, *_type));
+    let geo = geo.filter(|geo| geo.has_answers(name, *_type));
+    if subnets.is_none() && geo.is_none() {
+        return records;
+    }
+
+    let address = event.get_client_address();
+    let found = subnets.and_then(|subnets| subnets.find(name, *_type, address?))
+        .or_else(|| geo.and_then(|geo| geo.find(name, *_type, address)));
+
+    let (scope, addresses) = match found {
+        Some(found) => found,
+        None => {
+            let scope = event.get_client_subnet().map(|subnet| subnet.get_source_prefix()).unwrap_or(0);
+            event.set_subnet_scope(scope);
+            return records;
+        }
+    };
+
+    event.set_subnet_scope(scope);
+    let (ttl, class) = (records[0].get_ttl(), records[0].get_class());
+
+    addresses.into_iter().filter_map(|address| match address {
+        IpAddr::V4(address) if *_type == RRTypes::A => {
+            let mut record = ARecord::new(ttl, class);
+            record.set_address(address);
+            Some(Box::new(record) as Box<dyn RecordBase>)
+        }
+        IpAddr::V6(address) if *_type == RRTypes::Aaaa => {
+            let mut record = AaaaRecord::new(ttl, class);
+            record.set_address(address);
+            Some(Box::new(record) as Box<dyn RecordBase>)
+        }
+        _ => None
+    }).collect()
+}
+
+/// The address records of an ALIAS target. Targets in zones we host are looked up locally without
+/// following any further aliases so they can't loop, anything else is answered from the cache of
+/// the upstream answers or handed back to be resolved.
+fn resolve_alias(context: &LookupContext, aliases: &Aliases, view: Option<&str>, alias: &str, _type: &RRTypes) -> ResponseResult<AliasTypes> {
+    let mut target = alias.to_string();
+
+    if let Some((apex, zone)) = find_zone(context.store, &target, _type) {
+        let mut event = RequestEvent::new();
+        event.query = Some(DnsQuery::new(&target, *_type, RRClasses::In));
+        event.set_view(view);
+
+        let mut zones = vec![(apex, zone)];
+        let context = LookupContext { aliases: None, subnets: None, geo: None, ..*context };
+        let _ = lookup_in_zones(&context, &mut event, &target, _type, &mut zones);
+
+        let records: Vec<Box<dyn RecordBase>> = event.get_answers().iter()
+            .filter(|(_, record)| record.get_type() == *_type)
+            .map(|(_, record)| record.clone())
+            .collect();
+
+        if !records.is_empty() {
+            return Ok(AliasTypes::Records(records));
+        }
+
+        //THE CHAIN MAY LEAVE THE ZONES WE HOST, THE REST OF IT IS RESOLVED UPSTREAM
+        let next = event.get_answers().iter()
+            .rev()
+            .find_map(|(_, record)| record.as_any().downcast_ref::<CNameRecord>())
+            .and_then(|record| record.get_target())
+            .map(|next| normalize(&next));
+
+        match next {
+            Some(next) if context.store.get_deepest_zone_with_name(&next).is_none() => target = next,
+            _ => return Ok(AliasTypes::Records(Vec::new()))
+        }
+    }
+
+    if !aliases.get_upstream().has_servers() {
+        return Err(ResponseCodes::ServFail);
+    }
+
+    match aliases.get_cached(&target, *_type) {
+        Some(records) => Ok(AliasTypes::Records(records)),
+        None => Ok(AliasTypes::External(target))
+    }
+}
+
+/// Moves the chain on to the target of a CNAME or DNAME, returning the zone to continue in. A loop
+/// is answered as far as it goes so the resolver sees the repeated name, and the chain only continues
+/// in zones we are authoritative for, names we don't host are left for the resolver to follow.
+fn follow<'a>(store: &'a ZoneStore, target: &str, _type: &RRTypes, visited: &mut Vec<String>, zones: &mut Vec<(String, &'a Zone)>) -> Option<(String, &'a Zone)> {
+    if visited.iter().any(|name| name == target) {
+        return None;
+    }
+    visited.push(target.to_string());
+
+    let current = zones.last().map(|(apex, _)| apex.clone());
+
+    match find_zone(store, target, _type) {
+        Some((apex, zone)) if Some(&apex) == current.as_ref() || zone.is_authority() => {
+            if !zones.iter().any(|(a, _)| *a == apex) {
+                zones.push((apex.clone(), zone));
+            }
+
+            Some((apex, zone))
+        }
+        _ => None
+    }
+}
+
+/// The zone to answer from, the DS RRset is answered from the parent side of the cut if we host it
+/// (RFC 4035 section 3.1.4.1).
+fn find_zone<'a>(store: &'a ZoneStore, name: &str, _type: &RRTypes) -> Option<(String, &'a Zone)> {
+    let (apex, zone) = store.get_deepest_zone_with_name(name)?;
+
+    //ROOT HINTS ONLY SEED THE RESOLVER, NOTHING IS ANSWERED FROM THEM
+    if zone.get_type() == ZoneTypes::Hint {
+        return None;
+    }
+
+    if *_type == RRTypes::Ds && normalize(&apex) == name {
+        if let Some(parent) = get_parent(name).and_then(|parent| store.get_deepest_zone_with_name(&parent)) {
+            return Some(parent);
+        }
+    }
+
+    Some((apex, zone))
+}
+
+/// RFC 6672 section 2.3 - returns the DNAME at the highest ancestor of the name, the owner of a
+/// DNAME isn't redirected, only the names below it.
+fn find_dname(zone: &Zone, apex: &str, name: &str) -> Option<(String, Box<dyn RecordBase>)> {
+    let apex = normalize(apex);
+    let sub = fqdn_to_relative(&apex, name)?;
+    let labels = get_labels(&sub);
+
+    for i in (1..=labels.len()).rev() {
+        let owner_sub = labels[i..].join(".");
+        if let Some(record) = zone.get_records(&owner_sub, &RRTypes::DName).and_then(|records| records.first()) {
+            return Some((to_fqdn(&owner_sub, &apex), record.clone()));
+        }
+    }
+
+    None
+}
+
+/// Replaces the owner suffix of the name with the DNAME target.
+fn rewrite_name(name: &str, owner: &str, target: &str) -> String {
+    let prefix = match owner.is_empty() {
+        true => name,
+        false => name.strip_suffix(owner).map(|prefix| prefix.trim_end_matches('.')).unwrap_or(name)
+    };
+
+    match target.is_empty() {
+        true => prefix.to_string(),
+        false => format!("{}.{}", prefix, target)
+    }
+}
+
+/// RFC 1034 section 4.3.2 step 3b - returns the highest zone cut between the apex and the name,
+/// everything below it belongs to the child and is occluded. The DS at a cut belongs to the parent
+/// so it is answered rather than referred.
+fn find_cut(zone: &Zone, apex: &str, name: &str, _type: &RRTypes) -> Option<String> {
+    let apex = normalize(apex);
+    let sub = fqdn_to_relative(&apex, name)?;
+    let labels = get_labels(&sub);
+
+    for i in (0..labels.len()).rev() {
+        if i == 0 && *_type == RRTypes::Ds {
+            break;
+        }
+
+        let cut_sub = labels[i..].join(".");
+        if zone.get_records(&cut_sub, &RRTypes::Ns).is_some() {
+            return Some(to_fqdn(&cut_sub, &apex));
+        }
+    }
+
+    None
+}
+
+/// The RRset for the name, the DNSSEC records at the apex of a zone we sign come from the signer
+/// rather than the zone file.
+fn get_rrset(signer: &Signer, zone: &Zone, apex: &str, name: &str, sub: &str, _type: &RRTypes) -> Vec<Box<dyn RecordBase>> {
+    if sub.is_empty() && signer.is_signed(apex) {
+        match _type {
+            RRTypes::DnsKey => return signer.get_dnskey_records(apex),
+            RRTypes::Cds => return signer.get_cds_records(apex),
+            RRTypes::CDnsKey => return signer.get_cdnskey_records(apex),
+            RRTypes::NSec3Param => return signer.get_nsec3param_records(apex, zone),
+            _ => {}
+        }
+    }
+
+    if *_type == RRTypes::Ds {
+        //ONLY THE CHILD IS HOSTED, THERE IS NO DS AT ITS OWN APEX
+        return match sub.is_empty() {
+            true => Vec::new(),
+            false => get_ds_records(signer, zone, apex, name)
+        };
+    }
+
+    match zone.get_records(sub, _type) {
+        Some(records) => records.clone(),
+        None => Vec::new()
+    }
+}
+
+fn add_referral(store: &ZoneStore, signer: &Signer, zone: &Zone, apex: &str, event: &mut RequestEvent, cut: &str) {
+    let sub = match fqdn_to_relative(apex, cut) {
+        Some(sub) => sub,
+        None => return
+    };
+
+    if let Some(records) = zone.get_records(&sub, &RRTypes::Ns) {
+        for record in records {
+            event.add_authority_record(cut, record.clone());
+        }
+    }
+
+    add_delegation_proof(signer, zone, apex, event, cut);
+
+    //GLUE IS PART OF THE REFERRAL, IT'S GIVEN EVEN WITH MINIMAL RESPONSES
+    add_additional_records(store, zone, apex, event);
+}
+
+/// RFC 1034 section 4.3.2 step 6 - adds the addresses of the hosts named by records in the answer
+/// and authority sections, from any zone we are authoritative for, for as long as they fit.
+fn add_additional_records(store: &ZoneStore, zone: &Zone, apex: &str, event: &mut RequestEvent) {
+    let mut targets: Vec<String> = Vec::new();
+
+    for (owner, record) in event.get_answers().iter().chain(event.get_authority_records().iter()) {
+        if let Some(target) = get_additional_target(owner, record.as_ref()) {
+            if !target.is_empty() && !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        return;
+    }
+
+    let mut size = get_response_size(event);
+
+    for target in targets {
+        let (target_apex, target_zone) = match fqdn_to_relative(apex, &target) {
+            Some(_) => (apex.to_string(), zone),
+            None => {
+                match store.get_deepest_zone_with_name(&target) {
+                    Some((target_apex, target_zone)) if target_zone.is_authority() => (target_apex, target_zone),
+                    _ => continue
+                }
+            }
+        };
+
+        //OCCLUDED ADDRESSES ARE ONLY GIVEN AS GLUE FOR THE DELEGATION BEING REFERRED TO
+        if let Some(cut) = find_cut(target_zone, &target_apex, &target, &RRTypes::A) {
+            let referred = event.get_authority_records().iter()
+                .any(|(owner, record)| record.get_type() == RRTypes::Ns && normalize(owner) == cut);
+
+            if !referred {
+                continue;
+            }
+        }
+
+        if !add_glue(target_zone, &target_apex, event, &target, &mut size) {
+            break;
+        }
+    }
+}
+
+/// Type specific hook naming the host whose addresses belong in the additional section.
+fn get_additional_target(owner: &str, record: &dyn RecordBase) -> Option<String> {
+    let any = record.as_any();
+
+    let target = match record.get_type() {
+        RRTypes::Ns => any.downcast_ref::<NsRecord>()?.get_server(),
+        RRTypes::Mx => any.downcast_ref::<MxRecord>()?.get_server(),
+        RRTypes::Srv => any.downcast_ref::<SrvRecord>()?.get_target(),
+        RRTypes::Svcb => {
+            let record = any.downcast_ref::<SvcbRecord>()?;
+            get_service_target(owner, record.get_priority(), record.get_target()?)
+        }
+        RRTypes::Https => {
+            let record = any.downcast_ref::<HttpsRecord>()?;
+            get_service_target(owner, record.get_priority(), record.get_target()?)
+        }
+        _ => None
+    }?;
+
+    Some(normalize(&target))
+}
+
+//RFC 9460 section 2.5.2 - A TARGET OF "." IN SERVICE MODE IS THE OWNER ITSELF
+fn get_service_target(owner: &str, priority: u16, target: String) -> Option<String> {
+    match priority != 0 && normalize(&target).is_empty() {
+        true => Some(owner.to_string()),
+        false => Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::{Ipv4Addr, SocketAddr};
+    use super::*;
+
+    struct Fixture {
+        store: ZoneStore,
+        signer: Signer,
+        answer_order: AnswerOrder,
+        health: HealthChecks,
+        forwarders: Forwarders,
+        resolver: Resolver,
+        names: ZoneNamesCache
+    }
+
+    impl Fixture {
+
+        fn new() -> Self {
+            Self {
+                store: ZoneStore::new(),
+                signer: Signer::new(),
+                answer_order: AnswerOrder::new(),
+                health: HealthChecks::new(),
+                forwarders: Forwarders::new(),
+                resolver: Resolver::new(),
+                names: ZoneNamesCache::new()
+            }
+        }
+
+        fn get_context(&self, minimal_responses: bool) -> LookupContext<'_> {
+            LookupContext {
+                store: &self.store,
+                signer: &self.signer,
+                aliases: None,
+                answer_order: &self.answer_order,
+                health: &self.health,
+                subnets: None,
+                geo: None,
+                forwarders: &self.forwarders,
+                resolver: &self.resolver,
+                names: &self.names,
+                minimal_responses
+            }
+        }
+    }
+
+    fn new_event(name: &str, _type: RRTypes, origin: Ipv4Addr) -> RequestEvent {
+        let mut event = RequestEvent::new();
+        event.query = Some(DnsQuery::new(name, _type, RRClasses::In));
+        event.set_origin(SocketAddr::new(IpAddr::V4(origin), 5353));
+        event
+    }
+
+    #[test]
+    fn forward_acl() {
+        let mut fixture = Fixture::new();
+        fixture.forwarders.add_zone("corp.local", Upstream::new());
+
+        let lookup_from = |fixture: &Fixture, origin: Ipv4Addr| {
+            lookup(&fixture.get_context(false), &mut new_event("host.corp.local", RRTypes::A, origin))
+        };
+
+        //WITHOUT AN ACL OF ITS OWN THE ZONE IS FORWARDED FOR CLIENTS ALLOWED TO RECURSE, HERE NOBODY
+        assert!(matches!(lookup_from(&fixture, Ipv4Addr::new(192, 168, 0, 5)), SourceTypes::Local(Err(ResponseCodes::Refused))));
+
+        fixture.forwarders.allow("corp.local", IpAddr::V4(Ipv4Addr::new(192, 168, 0, 0)), 16).unwrap();
+        assert!(matches!(lookup_from(&fixture, Ipv4Addr::new(192, 168, 0, 5)), SourceTypes::Forward(_)));
+        assert!(matches!(lookup_from(&fixture, Ipv4Addr::new(10, 0, 0, 1)), SourceTypes::Local(Err(ResponseCodes::Refused))));
+    }
+
+    #[test]
+    fn minimal_responses() {
+        let fixture = Fixture::new();
+
+        let mut zone = Zone::new(ZoneTypes::Master);
+        let mut mx = MxRecord::new(300, RRClasses::In);
+        mx.set_priority(10);
+        mx.set_server("mail.test.");
+        zone.add_record("", mx.upcast());
+        zone.add_record("mail", ARecord::new(300, RRClasses::In).upcast());
+
+        for minimal_responses in [false, true] {
+            let mut event = new_event("test", RRTypes::Mx, Ipv4Addr::LOCALHOST);
+            let mut zones = vec![("test".to_string(), &zone)];
+
+            assert!(matches!(lookup_in_zones(&fixture.get_context(minimal_responses), &mut event, "test", &RRTypes::Mx, &mut zones), Ok(None)));
+            assert_eq!(event.get_answers().len(), 1);
+            assert_eq!(event.get_additional_records().is_empty(), minimal_responses);
+        }
+    }
+}
@@ -1,4 +1,18 @@
 pub mod listeners;
+pub mod lookup;
+pub mod alias;
+pub mod answer_order;
+pub mod health;
+pub mod client_subnet;
+pub mod geo;
+pub mod rpz;
+pub mod upstream;
+pub mod coalescer;
+pub mod forward;
+pub mod resolver;
+pub mod cache;
+pub mod view;
+pub mod zone_names;
 pub mod dns;
 pub mod server;
 pub mod udp_server;
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::{io, slice};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::messages::message_base::MessageBase;
+use rlibdns::records::cname_record::CNameRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::ns_record::NsRecord;
+use rlibdns::records::rrsig_record::RRSigRecord;
+use rlibdns::zone::zone_store::ZoneStore;
+use crate::dns::coalescer::Coalescer;
+use crate::dns::dns::ResponseResult;
+use crate::dns::upstream::query_server;
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::crypto::random::fill_random;
+use crate::utils::domain_utils::{get_labels, get_parent, is_subdomain, normalize};
+use crate::utils::net::net_mask::NetMask;
+use crate::utils::time_utils::get_now;
+use crate::utils::zone_utils::get_record_address;
+use crate::{MAX_CACHE_TTL, MAX_CNAME_CHAIN_SIZE};
+
+const DEFAULT_PORT: u16 = 53;
+const MAX_REFERRALS: usize = 30;
+const MAX_NS_DEPTH: usize = 3;
+const MAX_CACHED_DELEGATIONS: usize = 4096;
+
+//RFC 9156 section 2.3 - LIMITS THE QUERIES A NAME WITH MANY LABELS CAN COST, THE REST GO IN ONE STEP
+const MAX_MINIMISE_COUNT: usize = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinimisationTypes {
+    Off,
+    Strict,
+    #[default]
+    Relaxed
+}
+
+/// Iterative resolver for clients in the ACL, starting at the root servers from the hint zone and
+/// following referrals down to the servers that are authoritative for the name. Off until a client
+/// range is allowed so we aren't an open resolver.
+///
+/// The names are minimised, RFC 9156, so each server only sees one label more than the zone it is
+/// authoritative for. Relaxed minimisation falls back to the full name when a server fails on the
+/// shortened one, strict takes its NXDOMAIN as the answer.
+///
+/// The servers of every zone cut we are referred to are kept for the TTL of its NS RRset, so later
+/// names in the zone start there instead of at the root.
+pub struct Resolver {
+    hints: Vec<SocketAddr>,
+    roots: RwLock<Vec<SocketAddr>>,
+    acl: Vec<NetMask>,
+    port: u16,
+    minimisation: MinimisationTypes,
+    in_flight: Coalescer<(String, String, RRTypes), ResponseResult<Arc<MessageBase>>>,
+    delegations: Mutex<HashMap<String, (u64, Vec<SocketAddr>)>>
+}
+
+impl Resolver {
+
+    pub fn new() -> Self {
+        Self {
+            hints: Vec::new(),
+            roots: RwLock::new(Vec::new()),
+            acl: Vec::new(),
+            port: DEFAULT_PORT,
+            minimisation: MinimisationTypes::Relaxed,
+            in_flight: Coalescer::new(),
+            delegations: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn set_minimisation(&mut self, minimisation: MinimisationTypes) {
+        self.minimisation = minimisation;
+    }
+
+    pub fn get_minimisation(&self) -> MinimisationTypes {
+        self.minimisation
+    }
+
+    /// The port the name servers found through referrals are queried on, 53 unless testing against
+    /// local instances.
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn add_hint(&mut self, address: SocketAddr) {
+        if !self.hints.contains(&address) {
+            self.hints.push(address);
+        }
+    }
+
+    /// Reads the root servers from a hint zone, IE named.root, the NS records at the root and the
+    /// addresses held for them.
+    pub fn open_hints(&mut self, file_path: &str) -> io::Result<()> {
+        let mut store = ZoneStore::new();
+        store.open(file_path, ".")?;
+
+        let zone = store.get_zone_exact("")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Hint zone has no root"))?;
+
+        let servers: Vec<String> = zone.get_records("", &RRTypes::Ns)
+            .map(|records| records.iter().filter_map(|record| get_server(record.as_ref())).collect())
+            .unwrap_or_default();
+
+        //THE APEX IS THE ROOT SO OWNER NAMES ARE ALREADY RELATIVE TO IT
+        for server in servers {
+            for _type in [RRTypes::A, RRTypes::Aaaa] {
+                for record in zone.get_records(&server, &_type).into_iter().flatten() {
+                    if let Some(address) = get_record_address(record.as_ref()) {
+                        self.add_hint(SocketAddr::new(address, self.port));
+                    }
+                }
+            }
+        }
+
+        match self.hints.is_empty() {
+            true => Err(io::Error::new(io::ErrorKind::InvalidData, "Hint zone has no root server addresses")),
+            false => Ok(())
+        }
+    }
+
+    pub fn get_hints(&self) -> &Vec<SocketAddr> {
+        &self.hints
+    }
+
+    pub fn allow(&mut self, address: IpAddr, mask: u32) -> io::Result<()> {
+        let mask = NetMask::new(address, mask)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid network mask"))?;
+        self.acl.push(mask);
+        Ok(())
+    }
+
+    pub fn is_allowed(&self, address: Option<SocketAddr>) -> bool {
+        match address {
+            Some(address) => !self.hints.is_empty() && self.acl.iter().any(|mask| mask.contains(address.ip())),
+            None => false
+        }
+    }
+
+    /// RFC 8109 - asks the hints for the current root servers, the hints stay in use if none of them
+    /// answer.
+    pub fn prime(&self) -> io::Result<()> {
+        let response = self.query_servers(&self.hints, "", "", RRTypes::Ns)
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "No root hint answered the priming query"))?;
+
+        let servers: Vec<String> = response.get_answers().iter()
+            .filter(|(owner, _)| normalize(owner).is_empty())
+            .filter_map(|(_, record)| get_server(record.as_ref()))
+            .collect();
+
+        let roots = self.get_glue(&response, &servers, "");
+        if roots.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Priming response has no root server addresses"));
+        }
+
+        *self.roots.write().unwrap() = roots;
+        Ok(())
+    }
+
+    /// Forgets the zone cuts learned so far, names are resolved from the root again.
+    pub fn flush(&self) {
+        self.delegations.lock().unwrap().clear();
+    }
+
+    fn get_roots(&self) -> Vec<SocketAddr> {
+        let roots = self.roots.read().unwrap();
+        match roots.is_empty() {
+            true => self.hints.clone(),
+            false => roots.clone()
+        }
+    }
+
+    /// Resolves the query of the event, following CNAMEs wherever they lead. The rcode of the last
+    /// name in the chain is returned along with its records.
+    pub fn resolve(&self, event: &mut RequestEvent) -> ResponseResult<()> {
+        let _type = event.get_query().get_type();
+        let mut target = normalize(event.get_query().get_fqdn());
+        let mut visited = vec![target.clone()];
+
+        event.set_authoritative(false);
+        event.set_recursion_available(true);
+
+        loop {
+            let (cut, response) = self.iterate(&target, _type, 0)?;
+
+            //ONLY RECORDS INSIDE THE ZONE OF THE SERVER THAT SENT THEM ARE TRUSTED
+            let mut current = target.clone();
+            loop {
+                if !is_subdomain(&current, &cut) {
+                    break;
+                }
+
+                let records: Vec<&Box<dyn RecordBase>> = response.get_answers().iter()
+                    .filter(|(owner, record)| normalize(owner) == current && record.get_type() == _type)
+                    .map(|(_, record)| record)
+                    .collect();
+
+                if !records.is_empty() {
+                    for record in records.into_iter().cloned().chain(get_signatures(&response, &current, _type)) {
+                        event.add_answer(&current, record);
+                    }
+
+                    //A WILDCARD ANSWER COMES WITH THE PROOF THAT THE NAME ITSELF DOESN'T EXIST
+                    for (owner, record) in response.get_authority_records() {
+                        if is_subdomain(owner, &cut) && matches!(record.get_type(), RRTypes::NSec | RRTypes::NSec3 | RRTypes::RRSig) {
+                            event.add_authority_record(owner, record.clone());
+                        }
+                    }
+                    return Ok(());
+                }
+
+                let next = response.get_answers().iter()
+                    .filter(|(owner, _)| normalize(owner) == current)
+                    .find_map(|(_, record)| record.as_any().downcast_ref::<CNameRecord>().map(|cname| (record, cname)));
+
+                match next.and_then(|(record, cname)| cname.get_target().map(|next| (record, normalize(&next)))) {
+                    Some((record, next)) => {
+                        if visited.contains(&next) || visited.len() > MAX_CNAME_CHAIN_SIZE as usize {
+                            return Err(ResponseCodes::ServFail);
+                        }
+
+                        event.add_answer(&current, record.clone());
+                        for signature in get_signatures(&response, &current, RRTypes::CName) {
+                            event.add_answer(&current, signature);
+                        }
+                        visited.push(next.clone());
+                        current = next;
+                    }
+                    None => break
+                }
+            }
+
+            //THE CHAIN WENT ON WITHOUT AN ANSWER, THE REST OF IT IS RESOLVED FROM THE ROOT
+            if current != target {
+                target = current;
+                continue;
+            }
+
+            //NXDOMAIN / NODATA, THE SOA AND ANY DENIAL PROOF FROM THE ZONE GO BACK TO THE CLIENT
+            for (owner, record) in response.get_authority_records() {
+                if is_subdomain(owner, &cut) {
+                    event.add_authority_record(owner, record.clone());
+                }
+            }
+
+            return match response.get_response_code() {
+                ResponseCodes::NoError => Ok(()),
+                code => Err(code)
+            };
+        }
+    }
+
+    /// Follows referrals down from the deepest zone cut we know the servers of until a server answers
+    /// for the name, returning the zone cut that server was found at along with its response.
+    fn iterate(&self, name: &str, _type: RRTypes, depth: usize) -> ResponseResult<(String, Arc<MessageBase>)> {
+        if let Some((cut, servers)) = self.get_delegation(name) {
+            match self.iterate_from(name, _type, depth, &cut, servers) {
+                Ok(response) => return Ok(response),
+                //THE SERVERS MAY HAVE MOVED SINCE, THE CUT IS FOUND AGAIN FROM THE ROOT
+                Err(_) => self.remove_delegation(&cut)
+            }
+        }
+
+        self.iterate_from(name, _type, depth, "", self.get_roots())
+    }
+
+    fn iterate_from(&self, name: &str, _type: RRTypes, depth: usize, cut: &str, servers: Vec<SocketAddr>) -> ResponseResult<(String, Arc<MessageBase>)> {
+        let mut cut = cut.to_string();
+        let mut servers = servers;
+
+        //THE DEEPEST NAME KNOWN TO BE IN THE ZONE OF THE SERVERS, WITHOUT MINIMISATION THE FULL NAME
+        let mut known = match self.minimisation {
+            MinimisationTypes::Off => None,
+            _ => Some(cut.clone())
+        };
+        let mut steps = 0;
+
+        for _ in 0..MAX_REFERRALS + MAX_MINIMISE_COUNT {
+            let (qname, qtype) = match &known {
+                Some(known) if steps < MAX_MINIMISE_COUNT => match get_child(name, known) {
+                    //RFC 9156 section 3 - A IS ASKED FOR THE SHORTENED NAMES AS SOME SERVERS GET NS WRONG
+                    Some(child) if child != name => (child, RRTypes::A),
+                    _ => (name.to_string(), _type)
+                },
+                _ => (name.to_string(), _type)
+            };
+            let minimised = qname != name;
+
+            let response = match self.query_servers(&servers, &cut, &qname, qtype) {
+                Ok(response) => response,
+                Err(_) if minimised && self.minimisation == MinimisationTypes::Relaxed => {
+                    known = None;
+                    continue;
+                }
+                Err(code) => return Err(code)
+            };
+
+            if response.get_response_code() == ResponseCodes::NxDomain && minimised {
+                //RFC 8020 - NOTHING EXISTS BELOW A NAME THAT DOESN'T, UNLESS THE SERVER GETS EMPTY NON-TERMINALS WRONG
+                match self.minimisation {
+                    MinimisationTypes::Strict => return Ok((cut, response)),
+                    _ => {
+                        known = None;
+                        continue;
+                    }
+                }
+            }
+
+            if response.is_authoritative() || !response.get_answers().is_empty() || response.get_response_code() == ResponseCodes::NxDomain {
+                //THE SHORTENED NAME ISN'T A ZONE CUT, ONE MORE LABEL IS ASKED FROM THE SAME SERVERS
+                if minimised {
+                    known = Some(qname);
+                    steps += 1;
+                    continue;
+                }
+
+                return Ok((cut, response));
+            }
+
+            //A REFERRAL HAS TO MOVE CLOSER TO THE NAME AND STAY INSIDE THE ZONE THAT SENT IT
+            let zone = match response.get_authority_records().iter()
+                    .filter(|(_, record)| record.get_type() == RRTypes::Ns)
+                    .map(|(owner, _)| normalize(owner))
+                    .find(|zone| *zone != cut && is_subdomain(zone, &cut) && is_subdomain(&qname, zone)) {
+                Some(zone) => zone,
+                None if minimised && self.minimisation == MinimisationTypes::Relaxed => {
+                    known = None;
+                    continue;
+                }
+                None => return Err(ResponseCodes::ServFail)
+            };
+
+            let ns_names: Vec<String> = response.get_authority_records().iter()
+                .filter(|(owner, _)| normalize(owner) == zone)
+                .filter_map(|(_, record)| get_server(record.as_ref()))
+                .collect();
+
+            let mut next = self.get_glue(&response, &ns_names, &cut);
+
+            //NO USABLE GLUE, THE SERVERS ARE OUT OF BAILIWICK AND ARE LOOKED UP ON THEIR OWN
+            if next.is_empty() && depth < MAX_NS_DEPTH {
+                for ns in &ns_names {
+                    if is_subdomain(ns, &zone) {
+                        continue;
+                    }
+
+                    if let Ok((ns_cut, response)) = self.iterate(ns, RRTypes::A, depth + 1) {
+                        next = self.get_glue(&response, slice::from_ref(ns), &ns_cut);
+                        if !next.is_empty() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                return Err(ResponseCodes::ServFail);
+            }
+
+            let ttl = response.get_authority_records().iter()
+                .filter(|(owner, record)| normalize(owner) == zone && record.get_type() == RRTypes::Ns)
+                .map(|(_, record)| record.get_ttl())
+                .min()
+                .unwrap_or(0);
+            self.add_delegation(&zone, next.clone(), ttl);
+
+            if known.is_some() {
+                known = Some(zone.clone());
+            }
+            cut = zone;
+            servers = next;
+        }
+
+        Err(ResponseCodes::ServFail)
+    }
+
+    /// The deepest zone cut at or above the name whose servers haven't expired.
+    fn get_delegation(&self, name: &str) -> Option<(String, Vec<SocketAddr>)> {
+        let now = get_now();
+        let delegations = self.delegations.lock().unwrap();
+
+        let mut current = normalize(name);
+        while !current.is_empty() {
+            if let Some((expires, servers)) = delegations.get(&current) {
+                if *expires > now {
+                    return Some((current, servers.clone()));
+                }
+            }
+
+            current = get_parent(&current)?;
+        }
+
+        None
+    }
+
+    fn add_delegation(&self, cut: &str, servers: Vec<SocketAddr>, ttl: u32) {
+        if ttl == 0 {
+            return;
+        }
+
+        let now = get_now();
+        let mut delegations = self.delegations.lock().unwrap();
+        if delegations.len() >= MAX_CACHED_DELEGATIONS {
+            delegations.retain(|_, (expires, _)| *expires > now);
+            if delegations.len() >= MAX_CACHED_DELEGATIONS {
+                delegations.clear();
+            }
+        }
+        delegations.insert(cut.to_string(), (now + ttl.min(MAX_CACHE_TTL) as u64, servers));
+    }
+
+    fn remove_delegation(&self, cut: &str) {
+        self.delegations.lock().unwrap().remove(cut);
+    }
+
+    /// The addresses of the name servers found in the response, only for names inside the zone that
+    /// sent it.
+    fn get_glue(&self, response: &MessageBase, servers: &[String], cut: &str) -> Vec<SocketAddr> {
+        response.get_answers().iter()
+            .chain(response.get_additional_records().iter())
+            .filter(|(owner, _)| is_subdomain(owner, cut) && servers.contains(&normalize(owner)))
+            .filter_map(|(_, record)| get_record_address(record.as_ref()))
+            .map(|address| SocketAddr::new(address, self.port))
+            .collect()
+    }
+
+    /// Tries the servers starting from a random one, the first response that is an answer, referral
+    /// or NXDOMAIN is returned. The same question to the servers of a zone cut is only sent once at a
+    /// time, concurrent lookups wait for it.
+    fn query_servers(&self, servers: &[SocketAddr], cut: &str, name: &str, _type: RRTypes) -> ResponseResult<Arc<MessageBase>> {
+        self.in_flight.run((cut.to_string(), normalize(name), _type), || self.query_any(servers, name, _type).map(Arc::new))
+    }
+
+    fn query_any(&self, servers: &[SocketAddr], name: &str, _type: RRTypes) -> ResponseResult<MessageBase> {
+        if servers.is_empty() {
+            return Err(ResponseCodes::ServFail);
+        }
+
+        let mut offset = [0u8; 1];
+        fill_random(&mut offset).map_err(|_| ResponseCodes::ServFail)?;
+
+        for i in 0..servers.len() {
+            let server = servers[(offset[0] as usize + i) % servers.len()];
+
+            if let Ok(response) = query_server(server, name, _type, false, None) {
+                if matches!(response.get_response_code(), ResponseCodes::NoError | ResponseCodes::NxDomain) {
+                    return Ok(response);
+                }
+            }
+        }
+
+        Err(ResponseCodes::ServFail)
+    }
+}
+
+impl Default for Resolver {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The RRSIGs covering an RRset of the response, passed on with it so the answer can be validated.
+fn get_signatures(response: &MessageBase, owner: &str, _type: RRTypes) -> Vec<Box<dyn RecordBase>> {
+    response.get_answers().iter()
+        .filter(|(name, record)| normalize(name) == owner && record.as_any().downcast_ref::<RRSigRecord>()
+            .map(|signature| signature.get_type_covered() == Some(_type))
+            .unwrap_or(false))
+        .map(|(_, record)| record.clone())
+        .collect()
+}
+
+/// The name one label below the ancestor on the way to the name, none once the ancestor is the name.
+fn get_child(name: &str, ancestor: &str) -> Option<String> {
+    let labels = get_labels(name);
+    let depth = get_labels(ancestor).len();
+
+    match labels.len() > depth {
+        true => Some(labels[labels.len() - depth - 1..].join(".")),
+        false => None
+    }
+}
+
+fn get_server(record: &dyn RecordBase) -> Option<String> {
+    record.as_any().downcast_ref::<NsRecord>()?.get_server().map(|server| normalize(&server))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn delegations() {
+        let resolver = Resolver::new();
+        let tld = vec![SocketAddr::from(([192, 0, 2, 1], 53))];
+        let leaf = vec![SocketAddr::from(([192, 0, 2, 2], 53))];
+
+        resolver.add_delegation("test", tld.clone(), 300);
+        resolver.add_delegation("example.test", leaf.clone(), 300);
+        resolver.add_delegation("other.test", leaf.clone(), 0);
+
+        assert_eq!(resolver.get_delegation("www.example.test"), Some(("example.test".to_string(), leaf)));
+        assert_eq!(resolver.get_delegation("www.other.test"), Some(("test".to_string(), tld.clone())));
+        assert_eq!(resolver.get_delegation("test"), Some(("test".to_string(), tld)));
+        assert_eq!(resolver.get_delegation("www.example"), None);
+
+        resolver.remove_delegation("test");
+        assert_eq!(resolver.get_delegation("www.other.test"), None);
+
+        resolver.flush();
+        assert_eq!(resolver.get_delegation("www.example.test"), None);
+    }
+
+    #[test]
+    fn child() {
+        assert_eq!(get_child("www.example.test", ""), Some("test".to_string()));
+        assert_eq!(get_child("www.example.test", "test"), Some("example.test".to_string()));
+        assert_eq!(get_child("www.example.test", "www.example.test"), None);
+    }
+}
@@ -0,0 +1,637 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::sync::{Mutex, RwLock};
+use rlibdns::messages::dns_query::DnsQuery;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::cname_record::CNameRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::ns_record::NsRecord;
+use rlibdns::utils::fqdn_utils::fqdn_to_relative;
+use rlibdns::zone::zone::Zone;
+use rlibdns::zone::zone_store::ZoneStore;
+use crate::dns::client_subnet::mask_address;
+use crate::dns::dns::ResponseResult;
+use crate::rpc::events::inter::event::Event;
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::{get_parent, is_subdomain, normalize};
+use crate::utils::time_utils::get_now;
+use crate::utils::zone_utils::{get_all_names, get_negative_soa, get_record_address, get_soa, to_fqdn};
+
+//RFC 8914 section 4.16 - THE ANSWER WAS BLOCKED BY A POLICY OF THE SERVER THE CLIENT TALKS TO
+pub const EDE_BLOCKED: u16 = 15;
+
+//TRIGGER NAMES ARE PLACED UNDER THESE LABELS OF THE POLICY ZONE, ANYTHING ELSE IS A QNAME TRIGGER
+const CLIENT_IP_LABEL: &str = "rpz-client-ip";
+const RESPONSE_IP_LABEL: &str = "rpz-ip";
+const NSDNAME_LABEL: &str = "rpz-nsdname";
+const NSIP_LABEL: &str = "rpz-nsip";
+
+const MAX_CACHED_NAME_SERVERS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerTypes {
+    ClientIp,
+    QName,
+    ResponseIp,
+    NsDName
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyTypes {
+    NxDomain,
+    NoData,
+    PassThru,
+    Drop,
+    TcpOnly,
+    LocalData
+}
+
+struct PolicyZone {
+    apex: String,
+    //THE KINDS OF TRIGGER IN THE ZONE AT THE SERIAL, LOOKED FOR AGAIN WHEN THE ZONE CHANGES
+    triggers: Mutex<(u32, Vec<TriggerTypes>)>
+}
+
+impl PolicyZone {
+
+    fn new(zone: &Zone, apex: String) -> Self {
+        let triggers = Mutex::new((get_serial(zone), get_triggers(zone, &apex)));
+
+        Self {
+            apex,
+            triggers
+        }
+    }
+
+    fn get_triggers(&self, zone: &Zone) -> Vec<TriggerTypes> {
+        let serial = get_serial(zone);
+
+        let mut triggers = self.triggers.lock().unwrap();
+        if triggers.0 != serial {
+            *triggers = (serial, get_triggers(zone, &self.apex));
+        }
+        triggers.1.clone()
+    }
+}
+
+struct Policy {
+    zone: String,
+    trigger: TriggerTypes,
+    owner: String,
+    action: PolicyTypes,
+    records: Vec<Box<dyn RecordBase>>,
+    soa: Option<Box<dyn RecordBase>>
+}
+
+/// Response policy zones (draft-vixie-dnsop-dns-rpz), IE blocking malware and tracking domains. The
+/// policy zones are ordinary zones in the store, checked in the order they were added with the first
+/// zone that matches deciding the answer. Within a zone client IP triggers win over QNAME, then
+/// response IP and NSDNAME.
+#[derive(Default)]
+pub struct ResponsePolicies {
+    zones: Vec<PolicyZone>,
+    name_servers: Mutex<HashMap<String, (u64, Vec<String>)>>
+}
+
+impl ResponsePolicies {
+
+    pub fn new() -> Self {
+        Self {
+            zones: Vec::new(),
+            name_servers: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Adds a zone of the store as a policy zone after the existing ones, the kinds of trigger it
+    /// holds are noted so queries only look for those.
+    pub fn add_zone(&mut self, store: &ZoneStore, domain: &str) -> io::Result<()> {
+        let apex = normalize(domain);
+        let zone = store.get_zone_exact(&apex)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No zone {} to use as a policy zone", apex)))?;
+
+        self.zones.retain(|zone| zone.apex != apex);
+        self.zones.push(PolicyZone::new(zone, apex));
+        Ok(())
+    }
+
+    /// Looks for the kinds of trigger again in a policy zone that was loaded again, the file may have
+    /// changed without the serial. Zones that aren't policy zones are left alone.
+    pub fn update_zone(&mut self, store: &ZoneStore, domain: &str) {
+        let apex = normalize(domain);
+        if let Some(zone) = store.get_zone_exact(&apex) {
+            if let Some(policy_zone) = self.zones.iter_mut().find(|zone| zone.apex == apex) {
+                *policy_zone = PolicyZone::new(zone, apex);
+            }
+        }
+    }
+
+    pub fn remove_zone(&mut self, domain: &str) {
+        let apex = normalize(domain);
+        self.zones.retain(|zone| zone.apex != apex);
+    }
+
+    pub fn get_zones(&self) -> Vec<&String> {
+        self.zones.iter().map(|zone| &zone.apex).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn is_policy_zone(&self, domain: &str) -> bool {
+        let apex = normalize(domain);
+        self.zones.iter().any(|zone| zone.apex == apex)
+    }
+
+    /// The policy for the client IP and QNAME triggers, checked before the query is resolved so a
+    /// blocked name is never looked up.
+    fn find_for_query(&self, store: &ZoneStore, event: &RequestEvent) -> Option<Policy> {
+        let name = normalize(event.get_query().get_fqdn());
+        let client = event.get_origin().map(|origin| origin.ip());
+
+        self.find(store, &[TriggerTypes::ClientIp, TriggerTypes::QName], |zone, apex, trigger| match trigger {
+            TriggerTypes::ClientIp => find_address(zone, apex, CLIENT_IP_LABEL, client.iter()),
+            _ => find_name(zone, apex, None, &name)
+        })
+    }
+
+    /// The policy for the response IP and NSDNAME triggers, checked once the answer and the name
+    /// servers of its zone are known.
+    fn find_for_response(&self, store: &ZoneStore, event: &RequestEvent, servers: &[String]) -> Option<Policy> {
+        let addresses: Vec<IpAddr> = event.get_answers().iter().filter_map(|(_, record)| get_record_address(record.as_ref())).collect();
+
+        self.find(store, &[TriggerTypes::ResponseIp, TriggerTypes::NsDName], |zone, apex, trigger| match trigger {
+            TriggerTypes::ResponseIp => find_address(zone, apex, RESPONSE_IP_LABEL, addresses.iter()),
+            _ => servers.iter().find_map(|server| find_name(zone, apex, Some(NSDNAME_LABEL), server))
+        })
+    }
+
+    fn has_trigger(&self, store: &ZoneStore, trigger: TriggerTypes) -> bool {
+        self.zones.iter().any(|policy_zone| {
+            store.get_zone_exact(&policy_zone.apex)
+                .map(|zone| policy_zone.get_triggers(zone).contains(&trigger))
+                .unwrap_or(false)
+        })
+    }
+
+    /// The name servers found for the name by an earlier query, until their TTL runs out.
+    fn get_name_servers(&self, name: &str) -> Option<Vec<String>> {
+        match self.name_servers.lock().unwrap().get(name) {
+            Some((expires, servers)) if *expires > get_now() => Some(servers.clone()),
+            _ => None
+        }
+    }
+
+    fn cache_name_servers(&self, name: &str, servers: Vec<String>, ttl: u32) {
+        if ttl == 0 {
+            return;
+        }
+
+        let now = get_now();
+        let mut name_servers = self.name_servers.lock().unwrap();
+        if name_servers.len() >= MAX_CACHED_NAME_SERVERS {
+            name_servers.retain(|_, (expires, _)| *expires > now);
+            if name_servers.len() >= MAX_CACHED_NAME_SERVERS {
+                name_servers.clear();
+            }
+        }
+        name_servers.insert(name.to_string(), (now + ttl as u64, servers));
+    }
+
+    /// The first policy zone holding a trigger of the kinds given that matches, the kinds are checked
+    /// in order within each zone.
+    fn find<F>(&self, store: &ZoneStore, triggers: &[TriggerTypes], matches: F) -> Option<Policy>
+    where
+        F: Fn(&Zone, &str, TriggerTypes) -> Option<String>
+    {
+        for policy_zone in &self.zones {
+            let zone = match store.get_zone_exact(&policy_zone.apex) {
+                Some(zone) => zone,
+                None => continue
+            };
+
+            let zone_triggers = policy_zone.get_triggers(zone);
+            for trigger in triggers.iter().filter(|trigger| zone_triggers.contains(trigger)) {
+                if let Some(owner) = matches(zone, &policy_zone.apex, *trigger) {
+                    if let Some(policy) = get_policy(zone, &policy_zone.apex, *trigger, owner) {
+                        return Some(policy);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Answers the query through the policies. Client IP and QNAME triggers are checked before the query
+/// is resolved, response IP and NSDNAME triggers once the answer is known. The policy zones are only
+/// locked while their triggers are looked up, never while the query is resolved.
+pub fn apply_policies(policies: &RwLock<ResponsePolicies>, store: &RwLock<ZoneStore>, event: &mut RequestEvent, resolve: &dyn Fn(&mut RequestEvent) -> ResponseResult<()>) -> ResponseResult<()> {
+    if policies.read().unwrap().is_empty() {
+        return resolve(event);
+    }
+
+    //THE STORE IS LOCKED BEFORE THE POLICIES, THE ORDER REGISTERING A POLICY ZONE TAKES THEM IN
+    let (policy, nsdname) = {
+        let store = store.read().unwrap();
+        let policies = policies.read().unwrap();
+        (policies.find_for_query(&store, event), policies.has_trigger(&store, TriggerTypes::NsDName))
+    };
+
+    if let Some(policy) = policy {
+        return rewrite(policy, event, resolve);
+    }
+
+    let response = resolve(event);
+
+    //THE NAME SERVERS TAKE A QUERY OR TWO OF THEIR OWN, THEY ARE KEPT FOR THEIR TTL
+    let servers = match nsdname {
+        true => {
+            let name = normalize(event.get_query().get_fqdn());
+            let cached = policies.read().unwrap().get_name_servers(&name);
+            match cached {
+                Some(servers) => servers,
+                None => {
+                    let (servers, ttl) = get_name_servers(event, &name, resolve);
+                    policies.read().unwrap().cache_name_servers(&name, servers.clone(), ttl);
+                    servers
+                }
+            }
+        }
+        false => Vec::new()
+    };
+
+    let policy = {
+        let store = store.read().unwrap();
+        let policies = policies.read().unwrap();
+        policies.find_for_response(&store, event, &servers)
+    };
+
+    match policy {
+        Some(policy) => {
+            clear_response(event);
+            rewrite(policy, event, resolve)
+        }
+        None => response
+    }
+}
+
+/// The kinds of trigger held in the policy zone, by the label the owner names sit under.
+fn get_triggers(zone: &Zone, apex: &str) -> Vec<TriggerTypes> {
+    let mut triggers = Vec::new();
+    for (name, _) in get_all_names(zone, apex) {
+        let trigger = match name {
+            name if name == apex => continue,
+            name if is_subdomain(&name, &to_fqdn(CLIENT_IP_LABEL, apex)) => TriggerTypes::ClientIp,
+            name if is_subdomain(&name, &to_fqdn(RESPONSE_IP_LABEL, apex)) => TriggerTypes::ResponseIp,
+            name if is_subdomain(&name, &to_fqdn(NSDNAME_LABEL, apex)) => TriggerTypes::NsDName,
+            //NSIP TRIGGERS AREN'T APPLIED
+            name if is_subdomain(&name, &to_fqdn(NSIP_LABEL, apex)) => continue,
+            _ => TriggerTypes::QName
+        };
+
+        if !triggers.contains(&trigger) {
+            triggers.push(trigger);
+        }
+    }
+
+    triggers
+}
+
+fn get_serial(zone: &Zone) -> u32 {
+    get_soa(zone).map(|soa| soa.get_serial()).unwrap_or(0)
+}
+
+/// Reads the action of a trigger from the records at its owner, a CNAME to one of the special
+/// targets picks the action and anything else is local data.
+fn get_policy(zone: &Zone, apex: &str, trigger: TriggerTypes, owner: String) -> Option<Policy> {
+    let records: Vec<Box<dyn RecordBase>> = zone.get_all_records(&fqdn_to_relative(apex, &owner)?)?.into_iter()
+        .filter(|record| !matches!(record.get_type(), RRTypes::RRSig | RRTypes::NSec | RRTypes::NSec3))
+        .cloned()
+        .collect();
+
+    if records.is_empty() {
+        return None;
+    }
+
+    let action = match get_cname_target(&records).as_deref() {
+        Some("") => PolicyTypes::NxDomain,
+        Some("*") => PolicyTypes::NoData,
+        Some("rpz-passthru") => PolicyTypes::PassThru,
+        Some("rpz-drop") => PolicyTypes::Drop,
+        Some("rpz-tcp-only") => PolicyTypes::TcpOnly,
+        //OLDER POLICY ZONES PASS A NAME THROUGH BY POINTING IT AT ITSELF
+        Some(target) if trigger == TriggerTypes::QName && fqdn_to_relative(apex, &owner).as_deref() == Some(target) => PolicyTypes::PassThru,
+        _ => PolicyTypes::LocalData
+    };
+
+    Some(Policy {
+        zone: apex.to_string(),
+        trigger,
+        owner,
+        action,
+        records,
+        soa: get_negative_soa(zone)
+    })
+}
+
+fn rewrite(policy: Policy, event: &mut RequestEvent, resolve: &dyn Fn(&mut RequestEvent) -> ResponseResult<()>) -> ResponseResult<()> {
+    let name = normalize(event.get_query().get_fqdn());
+    let _type = event.get_query().get_type();
+
+    println!("RPZ {} matched {:?} trigger {} for {} asking {} {:?}, {:?}", policy.zone, policy.trigger, policy.owner,
+        event.get_origin().map(|origin| origin.ip().to_string()).unwrap_or_else(|| "-".to_string()), name, _type, policy.action);
+
+    match policy.action {
+        PolicyTypes::PassThru => resolve(event),
+        PolicyTypes::Drop => {
+            event.prevent_default();
+            Ok(())
+        }
+        //TCP ANSWERS AS USUAL, OVER UDP THE CLIENT IS SENT AN EMPTY TRUNCATED RESPONSE TO RETRY WITH
+        PolicyTypes::TcpOnly => match event.is_tcp() {
+            true => resolve(event),
+            false => {
+                event.set_truncated(true);
+                Ok(())
+            }
+        },
+        PolicyTypes::NxDomain => {
+            add_policy_soa(&policy, event);
+            event.set_extended_error(EDE_BLOCKED);
+            Err(ResponseCodes::NxDomain)
+        }
+        PolicyTypes::NoData => {
+            add_policy_soa(&policy, event);
+            event.set_extended_error(EDE_BLOCKED);
+            Ok(())
+        }
+        PolicyTypes::LocalData => {
+            let cname = policy.records.iter().find(|record| record.get_type() == RRTypes::CName);
+            match cname {
+                Some(record) if _type != RRTypes::CName => {
+                    let target = match get_cname_target(&policy.records) {
+                        //A WILDCARD TARGET IS THE QUERY NAME PLACED UNDER THE REST OF IT, IE *.walled.garden.
+                        Some(target) if target.starts_with("*.") => to_fqdn(&name, &target[2..]),
+                        Some(target) => target,
+                        None => return Err(ResponseCodes::ServFail)
+                    };
+
+                    let mut cname = CNameRecord::new(record.get_ttl(), record.get_class());
+                    cname.set_target(&target);
+                    event.add_answer(&name, cname.upcast());
+
+                    //THE TARGET IS LOOKED UP WITHOUT THE POLICIES SO TWO TRIGGERS CAN'T POINT AT EACH OTHER
+                    let mut target_event = new_event(event, &target, _type);
+                    let response = resolve(&mut target_event);
+                    for i in 0..3 {
+                        let records = std::mem::take(&mut target_event.response_records[i]);
+                        event.response_records[i].extend(records);
+                    }
+                    response
+                }
+                _ => {
+                    let answers: Vec<&Box<dyn RecordBase>> = policy.records.iter().filter(|record| record.get_type() == _type).collect();
+                    match answers.is_empty() {
+                        true => add_policy_soa(&policy, event),
+                        false => {
+                            for record in answers {
+                                event.add_answer(&name, record.clone());
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// The owner of the most specific trigger under the label holding any of the addresses, IE
+/// `24.0.2.0.192.rpz-client-ip` for `192.0.2.0/24`.
+fn find_address<'a>(zone: &Zone, apex: &str, label: &str, addresses: impl Iterator<Item = &'a IpAddr>) -> Option<String> {
+    let base = to_fqdn(label, apex);
+
+    addresses.filter_map(|address| {
+        let max_prefix = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128
+        };
+
+        (0..=max_prefix).rev().find_map(|prefix| {
+            let owner = to_fqdn(&get_address_label(mask_address(*address, prefix), prefix), &base);
+            has_records(zone, apex, &owner).then_some((prefix, owner))
+        })
+    }).max_by_key(|(prefix, _)| *prefix).map(|(_, owner)| owner)
+}
+
+/// The owner of the trigger for the name, an exact match wins over the closest wildcard, IE
+/// `*.example.com` covers everything below `example.com` but not the name itself.
+fn find_name(zone: &Zone, apex: &str, label: Option<&str>, name: &str) -> Option<String> {
+    let base = match label {
+        Some(label) => to_fqdn(label, apex),
+        None => apex.to_string()
+    };
+
+    let name = normalize(name);
+    if name.is_empty() {
+        return None;
+    }
+
+    let owner = to_fqdn(&name, &base);
+    if has_records(zone, apex, &owner) {
+        return Some(owner);
+    }
+
+    let mut current = name;
+    while let Some(parent) = get_parent(&current) {
+        let owner = to_fqdn(&to_fqdn("*", &parent), &base);
+        if has_records(zone, apex, &owner) {
+            return Some(owner);
+        }
+        current = parent;
+    }
+
+    None
+}
+
+/// The labels of an address trigger, the prefix and then the address in reverse. IPv6 addresses
+/// are written in groups with the longest run of zero groups as `zz`, IE `48.zz.1.db8.2001`.
+fn get_address_label(address: IpAddr, prefix: u8) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let octets = address.octets();
+            format!("{}.{}.{}.{}.{}", prefix, octets[3], octets[2], octets[1], octets[0])
+        }
+        IpAddr::V6(address) => {
+            let groups = address.segments();
+
+            //RFC 5952 section 4.2 - ONLY RUNS OF 2 OR MORE ARE SHORTENED, THE FIRST ONE ON A TIE
+            let (mut start, mut length) = (0, 0);
+            let mut i = 0;
+            while i < groups.len() {
+                let end = (i..groups.len()).find(|j| groups[*j] != 0).unwrap_or(groups.len());
+                if end - i > length {
+                    start = i;
+                    length = end - i;
+                }
+                i = end.max(i + 1);
+            }
+
+            let mut labels = Vec::new();
+            let mut i = 0;
+            while i < groups.len() {
+                match length >= 2 && i == start {
+                    true => {
+                        labels.push("zz".to_string());
+                        i += length;
+                    }
+                    false => {
+                        labels.push(format!("{:x}", groups[i]));
+                        i += 1;
+                    }
+                }
+            }
+
+            labels.reverse();
+            format!("{}.{}", prefix, labels.join("."))
+        }
+    }
+}
+
+/// The name servers of the zone the name is in and how long they can be kept, from its NS RRset or
+/// that of the apex named by the SOA of a negative answer.
+fn get_name_servers(event: &RequestEvent, name: &str, resolve: &dyn Fn(&mut RequestEvent) -> ResponseResult<()>) -> (Vec<String>, u32) {
+    let mut ns_event = new_event(event, name, RRTypes::Ns);
+    let _ = resolve(&mut ns_event);
+
+    let mut servers = get_ns_targets(ns_event.get_answers());
+    let mut ttl = get_min_ttl(ns_event.get_answers());
+    if servers.is_empty() {
+        let soa = ns_event.get_authority_records().iter()
+            .find(|(_, record)| record.get_type() == RRTypes::Soa)
+            .map(|(owner, record)| (normalize(owner), record.get_ttl()));
+
+        if let Some((apex, soa_ttl)) = soa {
+            ttl = soa_ttl;
+
+            if apex != normalize(name) {
+                let mut ns_event = new_event(event, &apex, RRTypes::Ns);
+                let _ = resolve(&mut ns_event);
+                servers = get_ns_targets(ns_event.get_answers());
+                if !servers.is_empty() {
+                    ttl = ttl.min(get_min_ttl(ns_event.get_answers()));
+                }
+            }
+        }
+    }
+
+    (servers, ttl)
+}
+
+fn get_ns_targets(records: &[(String, Box<dyn RecordBase>)]) -> Vec<String> {
+    records.iter()
+        .filter_map(|(_, record)| record.as_any().downcast_ref::<NsRecord>()?.get_server())
+        .map(|server| normalize(&server))
+        .collect()
+}
+
+fn get_min_ttl(records: &[(String, Box<dyn RecordBase>)]) -> u32 {
+    records.iter()
+        .filter(|(_, record)| record.get_type() == RRTypes::Ns)
+        .map(|(_, record)| record.get_ttl())
+        .min()
+        .unwrap_or(0)
+}
+
+fn get_cname_target(records: &[Box<dyn RecordBase>]) -> Option<String> {
+    records.iter().find_map(|record| record.as_any().downcast_ref::<CNameRecord>()?.get_target()).map(|target| normalize(&target))
+}
+
+fn has_records(zone: &Zone, apex: &str, owner: &str) -> bool {
+    fqdn_to_relative(apex, owner)
+        .and_then(|sub| zone.get_all_records(&sub))
+        .map(|records| !records.is_empty())
+        .unwrap_or(false)
+}
+
+/// The SOA of the policy zone goes in the authority section of the NXDOMAIN / NODATA it answers with.
+fn add_policy_soa(policy: &Policy, event: &mut RequestEvent) {
+    if let Some(record) = &policy.soa {
+        event.add_authority_record(&policy.zone, record.clone());
+    }
+}
+
+/// A query of our own made for the client, keeping its address and EDNS options.
+fn new_event(event: &RequestEvent, name: &str, _type: RRTypes) -> RequestEvent {
+    let mut new_event = event.clone();
+    new_event.query = Some(DnsQuery::new(name, _type, event.get_query().get_class()));
+    clear_response(&mut new_event);
+    new_event
+}
+
+/// Drops what the resolved answer left in the event before the policy answer replaces it.
+fn clear_response(event: &mut RequestEvent) {
+    for records in event.response_records.iter_mut() {
+        records.clear();
+    }
+    event.set_authoritative(false);
+    event.set_authenticated_data(false);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::soa_record::SoaRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    fn get_zone(serial: u32, names: &[&str]) -> Zone {
+        let mut zone = Zone::new(ZoneTypes::Master);
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.rpz.");
+        soa.set_mailbox("hostmaster.rpz.");
+        soa.set_serial(serial);
+        zone.add_record("", soa.upcast());
+
+        for name in names {
+            let mut cname = CNameRecord::new(300, RRClasses::In);
+            cname.set_target(".");
+            zone.add_record(name, cname.upcast());
+        }
+        zone
+    }
+
+    #[test]
+    fn triggers() {
+        let zone = get_zone(1, &["bad.example", "32.1.2.0.192.rpz-client-ip", "ns.example.rpz-nsdname", "32.1.2.0.192.rpz-nsip"]);
+        assert_eq!(get_triggers(&zone, "rpz"), vec![TriggerTypes::QName, TriggerTypes::ClientIp, TriggerTypes::NsDName]);
+
+        //A NEW SERIAL MEANS THE ZONE CHANGED, THE TRIGGERS ARE LOOKED FOR AGAIN
+        let policy_zone = PolicyZone::new(&zone, "rpz".to_string());
+        assert_eq!(policy_zone.get_triggers(&get_zone(1, &["24.0.2.0.192.rpz-ip"])).len(), 3);
+        assert_eq!(policy_zone.get_triggers(&get_zone(2, &["24.0.2.0.192.rpz-ip"])), vec![TriggerTypes::ResponseIp]);
+    }
+
+    #[test]
+    fn address_label() {
+        assert_eq!(get_address_label("192.0.2.1".parse().unwrap(), 32), "32.1.2.0.192");
+        assert_eq!(get_address_label("2001:db8::1".parse().unwrap(), 128), "128.1.zz.db8.2001");
+        assert_eq!(get_address_label("2001:db8:0:1::".parse().unwrap(), 64), "64.zz.1.0.db8.2001");
+    }
+
+    #[test]
+    fn name_servers() {
+        let policies = ResponsePolicies::new();
+        policies.cache_name_servers("www.example", vec!["ns.example".to_string()], 300);
+        policies.cache_name_servers("mail.example", Vec::new(), 0);
+
+        assert_eq!(policies.get_name_servers("www.example"), Some(vec!["ns.example".to_string()]));
+        assert_eq!(policies.get_name_servers("mail.example"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io;
+use std::net::SocketAddr;
 use std::thread::JoinHandle;
 use rlibdns::messages::inter::op_codes::OpCodes;
 use rlibdns::messages::inter::rr_types::RRTypes;
@@ -7,7 +8,7 @@ use crate::rpc::events::request_event::RequestEvent;
 
 pub trait Server {
 
-    fn run(&mut self, port: u16) -> io::Result<JoinHandle<()>>;
+    fn run(&mut self, address: SocketAddr) -> io::Result<JoinHandle<()>>;
 
     fn is_running(&self) -> bool;
 
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::{io, thread};
 use std::io::{Read, Write};
-use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
 use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
@@ -11,11 +11,14 @@ use rlibdns::messages::inter::response_codes::ResponseCodes;
 use rlibdns::messages::inter::rr_classes::RRClasses;
 use rlibdns::messages::inter::rr_types::RRTypes;
 use rlibdns::messages::message_base::MessageBase;
+use rlibdns::records::inter::opt_codes::OptCodes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::opt_record::OptRecord;
 use crate::dns::dns::{RequestMap, ResponseResult};
 use crate::{BOGON_ALLOWED, COOKIE_SECRET, MAX_QUERIES};
 use crate::dns::server::Server;
 use crate::rpc::events::inter::event::Event;
-use crate::rpc::events::request_event::RequestEvent;
+use crate::rpc::events::request_event::{RequestEvent, DNSSEC_OK_FLAG};
 use crate::utils::net::address_utils::is_bogon;
 use crate::utils::spam_throttle::SpamThrottle;
 
@@ -78,6 +81,10 @@ impl TcpServer {
                     }
 
                     let mut event = RequestEvent::new();
+                    event.set_max_response_size(MAX_TCP_MESSAGE_SIZE);
+                    event.set_tcp(true);
+                    event.set_origin(src_addr);
+                    event.set_recursion_desired(message.is_recursion_desired());
                     if message.has_answers() {
                         event.request_records[0] = std::mem::take(message.get_answers_mut());
                     }
@@ -102,6 +109,9 @@ impl TcpServer {
 
                             response.add_query(query.clone());
                             response.set_authoritative(event.is_authoritative());
+                            response.set_recursion_available(event.is_recursion_available());
+                            //RFC 6840 section 5.8 - AD ONLY GOES TO CLIENTS THAT ASKED FOR IT WITH DO OR AD
+                            response.set_authenticated_data(event.is_authenticated_data() && (event.is_dnssec_ok() || message.is_authenticated_data()));
 
                             if event.has_answers() {
                                 for (query, record) in event.response_records[0].drain(..) {
@@ -135,6 +145,22 @@ impl TcpServer {
                         }
                     }
 
+                    if let Some(record) = event.get_opt_record() {
+                        let mut record = OptRecord::new(MAX_TCP_MESSAGE_SIZE as u16, 0, 0, record.get_flags() & DNSSEC_OK_FLAG);
+                        if let Some(code) = event.get_extended_error() {
+                            record.insert_option(OptCodes::EDnsError, code.to_be_bytes().to_vec());
+                        }
+                        //RFC 7871 section 7.2.1 - ECS GOES BACK AS IT CAME WITH THE SCOPE OF THE ANSWER, 0 IF IT DIDN'T MATTER
+                        if let Some(mut subnet) = event.get_client_subnet() {
+                            subnet.set_scope_prefix(match subnet.get_source_prefix() {
+                                0 => 0,
+                                _ => event.get_subnet_scope().unwrap_or(0)
+                            });
+                            record.insert_option(OptCodes::Ecs, subnet.to_option());
+                        }
+                        response.add_additional_record("", record.upcast());
+                    }
+
                     println!("{}", message);
 /*
                     if message.has_additional_records() {
@@ -270,12 +296,12 @@ impl TcpServer {
 
 impl Server for TcpServer {
 
-    fn run(&mut self, port: u16) -> io::Result<JoinHandle<()>> {
+    fn run(&mut self, address: SocketAddr) -> io::Result<JoinHandle<()>> {
         if self.is_running() {
             return Err(io::Error::new(io::ErrorKind::Unsupported, "Dns is already running"));
         }
 
-        self.socket = Some(TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))?);
+        self.socket = Some(TcpListener::bind(address)?);
 
         self.running.store(true, Ordering::Relaxed);
 
@@ -1,6 +1,6 @@
 use std::{io, thread};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
@@ -17,13 +17,16 @@ use crate::dns::dns::{RequestMap, ResponseResult};
 use crate::{BOGON_ALLOWED, COOKIE_SECRET, MAX_QUERIES};
 use crate::dns::server::Server;
 use crate::rpc::events::inter::event::Event;
-use crate::rpc::events::request_event::RequestEvent;
+use crate::rpc::events::request_event::{RequestEvent, DNSSEC_OK_FLAG};
 use crate::utils::hash::hmac::hmac;
 use crate::utils::hash::sha256::Sha256;
 use crate::utils::net::address_utils::is_bogon;
+use crate::utils::message_utils::fit_response;
 use crate::utils::spam_throttle::SpamThrottle;
 
 pub const MAX_UDP_MESSAGE_SIZE: usize = 512;
+//DNS FLAG DAY 2020 - LARGE ENOUGH FOR DNSSEC WITHOUT RELYING ON IP FRAGMENTATION
+pub const MAX_EDNS_UDP_MESSAGE_SIZE: usize = 1232;
 
 pub struct UdpServer {
     running: Arc<AtomicBool>,
@@ -53,7 +56,7 @@ impl UdpServer {
             }
 
             if !sender_throttle.add_and_test(message.get_destination().unwrap().ip()) {
-                socket.send_to(message.to_bytes(MAX_UDP_MESSAGE_SIZE).as_slice(), message.get_destination().unwrap())?;
+                socket.send_to(message.to_bytes(MAX_EDNS_UDP_MESSAGE_SIZE).as_slice(), message.get_destination().unwrap())?;
             }
 
             Err(io::Error::new(io::ErrorKind::TooManyLinks, "Too many outgoing messages to ip"))
@@ -89,6 +92,8 @@ impl UdpServer {
                     }
 
                     let mut event = RequestEvent::new();
+                    event.set_origin(src_addr);
+                    event.set_recursion_desired(message.is_recursion_desired());
                     if message.has_answers() {
                         event.request_records[0] = std::mem::take(message.get_answers_mut());
                     }
@@ -101,6 +106,13 @@ impl UdpServer {
                         event.request_records[2] = std::mem::take(message.get_additional_records_mut());
                     }
 
+                    //RFC 6891 section 6.2.5 - THE SMALLER OF THE CLIENTS PAYLOAD SIZE AND OURS, NEVER BELOW 512
+                    let max_response_size = match event.get_opt_record() {
+                        Some(record) => (record.get_payload_size() as usize).clamp(MAX_UDP_MESSAGE_SIZE, MAX_EDNS_UDP_MESSAGE_SIZE),
+                        None => MAX_UDP_MESSAGE_SIZE
+                    };
+                    event.set_max_response_size(max_response_size);
+
                     for (i, query) in message.get_queries().iter().enumerate() {
                         if i >= MAX_QUERIES {
                             break;
@@ -113,6 +125,9 @@ impl UdpServer {
 
                             response.add_query(query.clone());
                             response.set_authoritative(event.is_authoritative());
+                            response.set_recursion_available(event.is_recursion_available());
+                            //RFC 6840 section 5.8 - AD ONLY GOES TO CLIENTS THAT ASKED FOR IT WITH DO OR AD
+                            response.set_authenticated_data(event.is_authenticated_data() && (event.is_dnssec_ok() || message.is_authenticated_data()));
 
                             if event.has_answers() {
                                 for (query, record) in event.response_records[0].drain(..) {
@@ -146,6 +161,28 @@ impl UdpServer {
                         }
                     }
 
+                    if let Some(record) = event.get_opt_record() {
+                        let mut record = OptRecord::new(MAX_EDNS_UDP_MESSAGE_SIZE as u16, 0, 0, record.get_flags() & DNSSEC_OK_FLAG);
+                        if let Some(code) = event.get_extended_error() {
+                            record.insert_option(OptCodes::EDnsError, code.to_be_bytes().to_vec());
+                        }
+                        //RFC 7871 section 7.2.1 - ECS GOES BACK AS IT CAME WITH THE SCOPE OF THE ANSWER, 0 IF IT DIDN'T MATTER
+                        if let Some(mut subnet) = event.get_client_subnet() {
+                            subnet.set_scope_prefix(match subnet.get_source_prefix() {
+                                0 => 0,
+                                _ => event.get_subnet_scope().unwrap_or(0)
+                            });
+                            record.insert_option(OptCodes::Ecs, subnet.to_option());
+                        }
+                        response.add_additional_record("", record.upcast());
+                    }
+
+                    if event.is_truncated() {
+                        response.set_truncated(true);
+                    }
+
+                    fit_response(&mut response, max_response_size);
+
 
 /*
                     if message.has_additional_records() {
@@ -255,7 +292,7 @@ impl UdpServer {
         }
 
         if !self.sender_throttle.add_and_test(message.get_destination().unwrap().ip()) {
-            self.socket.as_ref().unwrap().send_to(message.to_bytes(MAX_UDP_MESSAGE_SIZE).as_slice(), message.get_destination().unwrap())?;
+            self.socket.as_ref().unwrap().send_to(message.to_bytes(MAX_EDNS_UDP_MESSAGE_SIZE).as_slice(), message.get_destination().unwrap())?;
         }
 
         Err(io::Error::new(io::ErrorKind::TooManyLinks, "Too many outgoing messages to ip"))
@@ -268,12 +305,12 @@ impl UdpServer {
 
 impl Server for UdpServer {
     
-    fn run(&mut self, port: u16) -> io::Result<JoinHandle<()>> {
+    fn run(&mut self, address: SocketAddr) -> io::Result<JoinHandle<()>> {
         if self.is_running() {
             return Err(io::Error::new(io::ErrorKind::Unsupported, "Dns is already running"));
         }
 
-        self.socket = Some(UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))?);
+        self.socket = Some(UdpSocket::bind(address)?);
 
         self.running.store(true, Ordering::Relaxed);
 
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rlibdns::messages::dns_query::DnsQuery;
+use rlibdns::messages::inter::op_codes::OpCodes;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::messages::message_base::MessageBase;
+use rlibdns::records::inter::opt_codes::OptCodes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::opt_record::OptRecord;
+use crate::dns::client_subnet::ClientSubnet;
+use crate::dns::coalescer::Coalescer;
+use crate::dns::tcp_server::MAX_TCP_MESSAGE_SIZE;
+use crate::dns::udp_server::{MAX_EDNS_UDP_MESSAGE_SIZE, MAX_UDP_MESSAGE_SIZE};
+use crate::rpc::events::request_event::DNSSEC_OK_FLAG;
+use crate::utils::crypto::random::fill_random;
+use crate::utils::domain_utils::normalize;
+use crate::UPSTREAM_TIMEOUT;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionTypes {
+    #[default]
+    Ordered,
+    Latency
+}
+
+type InFlight = Coalescer<(String, RRTypes, Option<ClientSubnet>), Result<Arc<MessageBase>, io::ErrorKind>>;
+
+/// Stub client for the name servers we send queries on to, each server is tried in turn until one
+/// answers. The servers are tried in the order they were added, or fastest first by their measured
+/// round trip time. A question already on its way upstream isn't sent again, callers asking it in
+/// the meantime share the response.
+#[derive(Clone, Debug, Default)]
+pub struct Upstream {
+    servers: Vec<SocketAddr>,
+    selection: SelectionTypes,
+    client_subnet: bool,
+    latencies: Arc<Mutex<HashMap<SocketAddr, u64>>>,
+    in_flight: Arc<InFlight>
+}
+
+impl Upstream {
+
+    pub fn new() -> Self {
+        Self {
+            servers: Vec::new(),
+            selection: SelectionTypes::Ordered,
+            client_subnet: false,
+            latencies: Arc::new(Mutex::new(HashMap::new())),
+            in_flight: Arc::new(Coalescer::new())
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: SelectionTypes) {
+        self.selection = selection;
+    }
+
+    pub fn get_selection(&self) -> SelectionTypes {
+        self.selection
+    }
+
+    /// RFC 7871 - passes the subnet of the client on to the servers so they can pick the answer for it,
+    /// off by default as it tells the servers who is asking.
+    pub fn set_client_subnet(&mut self, client_subnet: bool) {
+        self.client_subnet = client_subnet;
+    }
+
+    pub fn is_client_subnet(&self) -> bool {
+        self.client_subnet
+    }
+
+    pub fn add_server(&mut self, address: SocketAddr) {
+        if !self.servers.contains(&address) {
+            self.servers.push(address);
+        }
+    }
+
+    pub fn get_servers(&self) -> &Vec<SocketAddr> {
+        &self.servers
+    }
+
+    pub fn has_servers(&self) -> bool {
+        !self.servers.is_empty()
+    }
+
+    /// Smoothed round trip time of the server in milliseconds, servers that haven't been measured yet
+    /// have none and are tried first so they get one.
+    pub fn get_latency(&self, server: &SocketAddr) -> Option<u64> {
+        self.latencies.lock().unwrap().get(server).copied()
+    }
+
+    /// The servers in the order they should be tried.
+    pub fn get_ordered_servers(&self) -> Vec<SocketAddr> {
+        let mut servers = self.servers.clone();
+
+        if self.selection == SelectionTypes::Latency {
+            let latencies = self.latencies.lock().unwrap();
+            servers.sort_by_key(|server| latencies.get(server).copied().unwrap_or(0));
+        }
+
+        servers
+    }
+
+    /// Sends a recursive query to the servers in turn, the first response that matches the question
+    /// is returned whatever its rcode.
+    pub fn query(&self, name: &str, _type: RRTypes, subnet: Option<ClientSubnet>) -> io::Result<Arc<MessageBase>> {
+        self.in_flight.run((normalize(name), _type, subnet), || self.query_servers(name, _type, subnet.as_ref()).map(Arc::new).map_err(|e| e.kind()))
+            .map_err(|kind| io::Error::new(kind, "Upstream query failed"))
+    }
+
+    fn query_servers(&self, name: &str, _type: RRTypes, subnet: Option<&ClientSubnet>) -> io::Result<MessageBase> {
+        let mut error = io::Error::new(io::ErrorKind::NotFound, "No upstream servers configured");
+
+        for server in self.get_ordered_servers() {
+            let start = Instant::now();
+
+            match query_server(server, name, _type, true, subnet) {
+                Ok(response) => {
+                    self.record_latency(server, start.elapsed().as_millis() as u64);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    //A SERVER THAT DIDN'T ANSWER COUNTS AS TAKING THE WHOLE TIMEOUT
+                    self.record_latency(server, UPSTREAM_TIMEOUT * 1000);
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    fn record_latency(&self, server: SocketAddr, sample: u64) {
+        let mut latencies = self.latencies.lock().unwrap();
+        let latency = latencies.entry(server).or_insert(sample);
+        *latency = (*latency * 7 + sample) / 8;
+    }
+}
+
+/// Queries a single server over UDP, retrying over TCP if the response was truncated. The socket is
+/// bound to a port picked by the OS and the ID is random so responses are hard to spoof.
+pub fn query_server(server: SocketAddr, name: &str, _type: RRTypes, recursion_desired: bool, subnet: Option<&ClientSubnet>) -> io::Result<MessageBase> {
+    let mut id = [0u8; 2];
+    fill_random(&mut id)?;
+    let id = u16::from_be_bytes(id);
+
+    let mut message = MessageBase::new(id);
+    message.set_op_code(OpCodes::Query);
+    message.set_recursion_desired(recursion_desired);
+    message.add_query(DnsQuery::new(name, _type, RRClasses::In));
+
+    //RFC 3225 - DO ASKS FOR THE SIGNATURES SO THE ANSWER CAN BE VALIDATED, THE PAYLOAD SIZE MAKES ROOM FOR THEM
+    let mut record = OptRecord::new(MAX_EDNS_UDP_MESSAGE_SIZE as u16, 0, 0, DNSSEC_OK_FLAG);
+    if let Some(subnet) = subnet {
+        record.insert_option(OptCodes::Ecs, subnet.to_option());
+    }
+    message.add_additional_record("", record.upcast());
+
+    let response = query_udp(server, &message)?;
+    if !response.is_truncated() {
+        return Ok(response);
+    }
+
+    query_tcp(server, &message)
+}
+
+fn query_udp(server: SocketAddr, message: &MessageBase) -> io::Result<MessageBase> {
+    let local = match server.ip() {
+        IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0)
+    };
+
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(server)?;
+    socket.send(&message.to_bytes(MAX_UDP_MESSAGE_SIZE))?;
+
+    let mut buf = [0u8; MAX_EDNS_UDP_MESSAGE_SIZE];
+
+    //ANYTHING THAT ISN'T THE RESPONSE TO OUR QUESTION IS DROPPED, IE SPOOFING ATTEMPTS. THE TIMEOUT
+    //COVERS THE WHOLE WAIT SO A STREAM OF JUNK CAN'T KEEP THE QUERY OPEN
+    let deadline = Instant::now() + Duration::from_secs(UPSTREAM_TIMEOUT);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "No response from upstream"));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let len = socket.recv(&mut buf)?;
+        if let Ok(response) = MessageBase::from_bytes(&buf[..len]) {
+            if is_response_to(message, &response) {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+fn query_tcp(server: SocketAddr, message: &MessageBase) -> io::Result<MessageBase> {
+    let mut stream = TcpStream::connect_timeout(&server, Duration::from_secs(UPSTREAM_TIMEOUT))?;
+    stream.set_read_timeout(Some(Duration::from_secs(UPSTREAM_TIMEOUT)))?;
+
+    let buf = message.to_bytes(MAX_TCP_MESSAGE_SIZE);
+    stream.write_all(&(buf.len() as u16).to_be_bytes())?;
+    stream.write_all(&buf)?;
+    stream.flush()?;
+
+    let mut len_buf = [0u8; 2];
+    stream.read_exact(&mut len_buf)?;
+
+    let mut buf = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+    stream.read_exact(&mut buf)?;
+
+    let response = MessageBase::from_bytes(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match is_response_to(message, &response) {
+        true => Ok(response),
+        false => Err(io::Error::new(io::ErrorKind::InvalidData, "Response doesn't match the query"))
+    }
+}
+
+/// The ECS option of a response, none if the server left it out.
+pub fn get_client_subnet(response: &MessageBase) -> Option<ClientSubnet> {
+    response.get_additional_records().iter()
+        .find_map(|(_, record)| record.as_any().downcast_ref::<OptRecord>())
+        .and_then(|record| ClientSubnet::from_option(record.get_option(&OptCodes::Ecs)?))
+}
+
+fn is_response_to(query: &MessageBase, response: &MessageBase) -> bool {
+    if !response.is_qr() || response.get_id() != query.get_id() {
+        return false;
+    }
+
+    match (query.get_queries().first(), response.get_queries().first()) {
+        (Some(q), Some(r)) => normalize(q.get_fqdn()) == normalize(r.get_fqdn()) && q.get_type() == r.get_type(),
+        _ => false
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use rlibdns::journal::journal::Journal;
+use rlibdns::zone::zone_store::ZoneStore;
+use crate::utils::domain_utils::normalize;
+use crate::utils::net::net_mask::NetMask;
+
+/// A named set of zones served to the clients in its ACL, IE internal addresses for the LAN and
+/// public ones for everyone else under the same zone name. Journals and zone transfers are kept per
+/// view so the data of one never leaks into another.
+pub struct View {
+    name: String,
+    clients: Vec<NetMask>,
+    store: ZoneStore,
+    journals: HashMap<String, Journal>,
+    transfer_acl: Vec<NetMask>
+}
+
+impl View {
+
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            clients: Vec::new(),
+            store: ZoneStore::new(),
+            journals: HashMap::new(),
+            transfer_acl: Vec::new()
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add_client(&mut self, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.clients.push(new_mask(address, mask)?);
+        Ok(())
+    }
+
+    pub fn matches(&self, address: Option<SocketAddr>) -> bool {
+        match address {
+            Some(address) => self.clients.iter().any(|mask| mask.contains(address.ip())),
+            None => false
+        }
+    }
+
+    pub fn open_zone(&mut self, file_path: &str, domain: &str) -> io::Result<()> {
+        self.store.open(file_path, domain)
+    }
+
+    pub fn get_store(&self) -> &ZoneStore {
+        &self.store
+    }
+
+    pub fn get_store_mut(&mut self) -> &mut ZoneStore {
+        &mut self.store
+    }
+
+    /// Opens the journal for a zone of this view, the zone has to be opened first.
+    pub fn open_journal(&mut self, file_path: &str, domain: &str) -> io::Result<()> {
+        let domain = normalize(domain);
+        if self.store.get_zone_exact(&domain).is_none() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("View {} has no zone {}", self.name, domain)));
+        }
+
+        self.journals.insert(domain, Journal::open(file_path)?);
+        Ok(())
+    }
+
+    pub fn get_journal(&self, domain: &str) -> Option<&Journal> {
+        self.journals.get(&normalize(domain))
+    }
+
+    pub fn allow_transfer(&mut self, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.transfer_acl.push(new_mask(address, mask)?);
+        Ok(())
+    }
+
+    pub fn is_transfer_allowed(&self, address: Option<SocketAddr>) -> bool {
+        match address {
+            Some(address) => self.transfer_acl.iter().any(|mask| mask.contains(address.ip())),
+            None => false
+        }
+    }
+}
+
+/// The views in the order they were added, a client gets the first one it matches. Clients that
+/// match none are served the zones registered outside of any view.
+#[derive(Default)]
+pub struct Views {
+    views: Vec<View>,
+    transfer_acl: Vec<NetMask>
+}
+
+impl Views {
+
+    pub fn new() -> Self {
+        Self {
+            views: Vec::new(),
+            transfer_acl: Vec::new()
+        }
+    }
+
+    /// Adds the view at the end, a view with the same name is replaced in place.
+    pub fn add_view(&mut self, view: View) {
+        match self.views.iter_mut().find(|v| v.get_name() == view.get_name()) {
+            Some(v) => *v = view,
+            None => self.views.push(view)
+        }
+    }
+
+    pub fn remove_view(&mut self, name: &str) {
+        self.views.retain(|view| view.get_name() != name);
+    }
+
+    pub fn get_view(&self, name: &str) -> Option<&View> {
+        self.views.iter().find(|view| view.get_name() == name)
+    }
+
+    pub fn get_view_mut(&mut self, name: &str) -> Option<&mut View> {
+        self.views.iter_mut().find(|view| view.get_name() == name)
+    }
+
+    pub fn get_views(&self) -> &[View] {
+        &self.views
+    }
+
+    pub fn find_view(&self, address: Option<SocketAddr>) -> Option<&View> {
+        self.views.iter().find(|view| view.matches(address))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Lets the range transfer the zones registered outside of any view.
+    pub fn allow_transfer(&mut self, address: IpAddr, mask: u32) -> io::Result<()> {
+        self.transfer_acl.push(new_mask(address, mask)?);
+        Ok(())
+    }
+
+    pub fn is_transfer_allowed(&self, address: Option<SocketAddr>) -> bool {
+        match address {
+            Some(address) => self.transfer_acl.iter().any(|mask| mask.contains(address.ip())),
+            None => false
+        }
+    }
+}
+
+fn new_mask(address: IpAddr, mask: u32) -> io::Result<NetMask> {
+    NetMask::new(address, mask)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid network mask"))
+}
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use rlibdns::zone::zone::Zone;
+use crate::utils::domain_utils::{get_parent, is_subdomain, normalize};
+use crate::utils::zone_utils::{get_soa, to_fqdn};
+
+type ZoneNamesMap = HashMap<(String, String), (u32, Arc<ZoneNames>)>;
+
+/// The owner names of a zone and the empty non-terminals between them and the apex, so whether a
+/// name exists is a set lookup rather than a walk of the zone.
+pub struct ZoneNames {
+    apex: String,
+    names: HashSet<String>,
+    ents: HashSet<String>
+}
+
+impl ZoneNames {
+
+    pub fn new(zone: &Zone, apex: &str) -> Self {
+        let apex = normalize(apex);
+
+        let names: HashSet<String> = zone.get_all_records_recursive().into_iter()
+            .filter(|(_, records)| !records.is_empty())
+            .map(|(sub, _)| to_fqdn(&sub, &apex))
+            .collect();
+
+        let mut ents = HashSet::new();
+        for name in &names {
+            let mut current = get_parent(name);
+            while let Some(parent) = current {
+                if parent == apex || !is_subdomain(&parent, &apex) || names.contains(&parent) || !ents.insert(parent.clone()) {
+                    break;
+                }
+                current = get_parent(&parent);
+            }
+        }
+
+        Self {
+            apex,
+            names,
+            ents
+        }
+    }
+
+    /// True if the name owns records or is an empty non-terminal, IE `b.c` when only `a.b.c` has records.
+    pub fn exists(&self, name: &str) -> bool {
+        let name = normalize(name);
+        name == self.apex || self.names.contains(&name) || self.ents.contains(&name)
+    }
+
+    /// RFC 4592 section 3.3.1 - the longest ancestor of a name that doesn't exist which does, either
+    /// owning records or as an empty non-terminal.
+    pub fn get_closest_encloser(&self, name: &str) -> String {
+        let mut current = normalize(name);
+
+        while current != self.apex {
+            current = match get_parent(&current) {
+                Some(parent) => parent,
+                None => break
+            };
+
+            if self.exists(&current) {
+                return current;
+            }
+        }
+
+        self.apex.clone()
+    }
+}
+
+/// The names of the zones we answer from, built again whenever the SOA serial changes. Views can hold
+/// different data under the same apex so each view keeps its own.
+#[derive(Default)]
+pub struct ZoneNamesCache {
+    zones: Mutex<ZoneNamesMap>
+}
+
+impl ZoneNamesCache {
+
+    pub fn new() -> Self {
+        Self {
+            zones: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn get(&self, view: Option<&str>, apex: &str, zone: &Zone) -> Arc<ZoneNames> {
+        let key = (normalize(apex), view.unwrap_or_default().to_string());
+        let serial = get_soa(zone).map(|soa| soa.get_serial()).unwrap_or(0);
+
+        if let Some((s, names)) = self.zones.lock().unwrap().get(&key) {
+            if *s == serial {
+                return names.clone();
+            }
+        }
+
+        //BUILT WITHOUT THE LOCK, TWO QUERIES RACING ON A NEW SERIAL BUILD THE SAME NAMES
+        let names = Arc::new(ZoneNames::new(zone, apex));
+        self.zones.lock().unwrap().insert(key, (serial, names.clone()));
+        names
+    }
+
+    /// Drops the names of a zone that was loaded again, the file may have changed without the serial.
+    pub fn remove(&self, view: Option<&str>, apex: &str) {
+        self.zones.lock().unwrap().remove(&(normalize(apex), view.unwrap_or_default().to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::a_record::ARecord;
+    use rlibdns::records::inter::record_base::RecordBase;
+    use rlibdns::records::soa_record::SoaRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    fn get_zone(serial: u32, names: &[&str]) -> Zone {
+        let mut zone = Zone::new(ZoneTypes::Master);
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.test.");
+        soa.set_mailbox("hostmaster.test.");
+        soa.set_serial(serial);
+        zone.add_record("", soa.upcast());
+
+        for name in names {
+            zone.add_record(name, ARecord::new(300, RRClasses::In).upcast());
+        }
+        zone
+    }
+
+    #[test]
+    fn exists() {
+        let names = ZoneNames::new(&get_zone(1, &["www", "a.b.c", "*.w"]), "test");
+
+        assert!(names.exists("test"));
+        assert!(names.exists("WWW.test."));
+        assert!(names.exists("a.b.c.test"));
+        assert!(names.exists("b.c.test"));
+        assert!(names.exists("c.test"));
+        assert!(names.exists("w.test"));
+        assert!(!names.exists("x.test"));
+        assert!(!names.exists("z.a.b.c.test"));
+        assert!(!names.exists("www.other"));
+    }
+
+    #[test]
+    fn closest_encloser() {
+        let names = ZoneNames::new(&get_zone(1, &["www", "a.b.c"]), "test");
+
+        assert_eq!(names.get_closest_encloser("x.y.b.c.test"), "b.c.test");
+        assert_eq!(names.get_closest_encloser("x.www.test"), "www.test");
+        assert_eq!(names.get_closest_encloser("x.test"), "test");
+        assert_eq!(names.get_closest_encloser("x.y.z.test"), "test");
+    }
+
+    #[test]
+    fn cache() {
+        let cache = ZoneNamesCache::new();
+
+        let zone = get_zone(1, &["www"]);
+        assert!(cache.get(None, "test", &zone).exists("www.test"));
+        assert!(!cache.get(Some("internal"), "test", &get_zone(1, &["mail"])).exists("www.test"));
+
+        //THE SAME SERIAL IS ANSWERED FROM THE CACHE UNTIL THE ZONE IS LOADED AGAIN
+        let changed = get_zone(1, &["mail"]);
+        assert!(cache.get(None, "test", &changed).exists("www.test"));
+        cache.remove(None, "test");
+        assert!(cache.get(None, "test", &changed).exists("mail.test"));
+
+        assert!(cache.get(None, "test", &get_zone(2, &["ftp"])).exists("ftp.test"));
+        assert!(cache.get(Some("internal"), "test", &zone).exists("mail.test"));
+    }
+}
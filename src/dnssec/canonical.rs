@@ -0,0 +1,470 @@
+use std::cmp::Ordering;
+use std::io;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::a_record::ARecord;
+use rlibdns::records::aaaa_record::AaaaRecord;
+use rlibdns::records::cdnskey_record::CDnsKeyRecord;
+use rlibdns::records::cds_record::CdsRecord;
+use rlibdns::records::cname_record::CNameRecord;
+use rlibdns::records::dname_record::DNameRecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
+use rlibdns::records::ds_record::DsRecord;
+use rlibdns::records::hinfo_record::HInfoRecord;
+use rlibdns::records::https_record::HttpsRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::loc_record::LocRecord;
+use rlibdns::records::mx_record::MxRecord;
+use rlibdns::records::naptr_record::NaptrRecord;
+use rlibdns::records::ns_record::NsRecord;
+use rlibdns::records::nsec3_record::NSec3Record;
+use rlibdns::records::nsec3param_record::NSec3ParamRecord;
+use rlibdns::records::nsec_record::NSecRecord;
+use rlibdns::records::ptr_record::PtrRecord;
+use rlibdns::records::smimea_record::SmimeaRecord;
+use rlibdns::records::soa_record::SoaRecord;
+use rlibdns::records::srv_record::SrvRecord;
+use rlibdns::records::sshfp_record::SshFpRecord;
+use rlibdns::records::svcb_record::SvcbRecord;
+use rlibdns::records::txt_record::TxtRecord;
+use rlibdns::records::uri_record::UriRecord;
+use rlibdns::zone::zone::Zone;
+use crate::dnssec::presentation::type_to_string;
+use crate::utils::domain_utils::get_labels;
+use crate::utils::zone_utils::to_fqdn;
+
+//RFC 4034 section 6 - canonical form and ordering of resource records
+
+pub fn name_to_wire(name: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    for label in get_labels(name) {
+        let label = label.to_lowercase();
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+
+    buf.push(0);
+    buf
+}
+
+/// The uncompressed wire form of a name with its case kept, for the names RFC 4034 section 6.2 doesn't
+/// lowercase.
+fn name_to_wire_as_is(name: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    for label in get_labels(name) {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+
+    buf.push(0);
+    buf
+}
+
+/// Canonical DNS name order, names are compared label by label starting from the root.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    let a = get_labels(a);
+    let b = get_labels(b);
+
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        match x.to_lowercase().as_bytes().cmp(y.to_lowercase().as_bytes()) {
+            Ordering::Equal => continue,
+            ordering => return ordering
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
+//RFC 4034 section 4.1.2
+pub fn type_bitmap(types: &[RRTypes]) -> Vec<u8> {
+    let mut codes: Vec<u16> = types.iter().map(|t| t.get_code()).collect();
+    codes.sort();
+    codes.dedup();
+
+    let mut buf = Vec::new();
+    let mut i = 0;
+
+    while i < codes.len() {
+        let window = (codes[i] >> 8) as u8;
+        let mut bitmap = [0u8; 32];
+        let mut len = 0;
+
+        while i < codes.len() && (codes[i] >> 8) as u8 == window {
+            let low = (codes[i] & 0xff) as usize;
+            bitmap[low / 8] |= 0x80 >> (low % 8);
+            len = low / 8 + 1;
+            i += 1;
+        }
+
+        buf.push(window);
+        buf.push(len as u8);
+        buf.extend_from_slice(&bitmap[..len]);
+    }
+
+    buf
+}
+
+pub fn label_count(name: &str) -> u8 {
+    let labels = get_labels(name);
+
+    match labels.first() {
+        Some(&"*") => (labels.len() - 1) as u8,
+        _ => labels.len() as u8
+    }
+}
+
+fn character_string(buf: &mut Vec<u8>, s: &str) {
+    let bytes = s.as_bytes();
+    let len = bytes.len().min(255);
+    buf.push(len as u8);
+    buf.extend_from_slice(&bytes[..len]);
+}
+
+//RFC 9460 section 2.2 - THE KEYS ARE WRITTEN IN STRICTLY INCREASING ORDER
+fn svc_params(buf: &mut Vec<u8>, mut params: Vec<(u16, Vec<u8>)>) {
+    params.sort_by_key(|(key, _)| *key);
+
+    for (key, value) in params {
+        buf.extend_from_slice(&key.to_be_bytes());
+        buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        buf.extend(value);
+    }
+}
+
+pub fn rdata_to_wire(record: &dyn RecordBase) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    match record.get_type() {
+        RRTypes::A => {
+            let record = record.as_any().downcast_ref::<ARecord>()?;
+            buf.extend_from_slice(&record.get_address()?.octets());
+        }
+        RRTypes::Aaaa => {
+            let record = record.as_any().downcast_ref::<AaaaRecord>()?;
+            buf.extend_from_slice(&record.get_address()?.octets());
+        }
+        RRTypes::Ns => {
+            let record = record.as_any().downcast_ref::<NsRecord>()?;
+            buf.extend(name_to_wire(&record.get_server()?));
+        }
+        RRTypes::CName => {
+            let record = record.as_any().downcast_ref::<CNameRecord>()?;
+            buf.extend(name_to_wire(&record.get_target()?));
+        }
+        RRTypes::DName => {
+            let record = record.as_any().downcast_ref::<DNameRecord>()?;
+            buf.extend(name_to_wire(&record.get_target()?));
+        }
+        RRTypes::Ptr => {
+            let record = record.as_any().downcast_ref::<PtrRecord>()?;
+            buf.extend(name_to_wire(&record.get_domain()?));
+        }
+        RRTypes::Mx => {
+            let record = record.as_any().downcast_ref::<MxRecord>()?;
+            buf.extend_from_slice(&record.get_priority().to_be_bytes());
+            buf.extend(name_to_wire(&record.get_server()?));
+        }
+        RRTypes::Soa => {
+            let record = record.as_any().downcast_ref::<SoaRecord>()?;
+            buf.extend(name_to_wire(&record.get_fqdn()?));
+            buf.extend(name_to_wire(&record.get_mailbox()?));
+            buf.extend_from_slice(&record.get_serial().to_be_bytes());
+            buf.extend_from_slice(&record.get_refresh().to_be_bytes());
+            buf.extend_from_slice(&record.get_retry().to_be_bytes());
+            buf.extend_from_slice(&record.get_expire().to_be_bytes());
+            buf.extend_from_slice(&record.get_minimum_ttl().to_be_bytes());
+        }
+        RRTypes::Txt => {
+            let record = record.as_any().downcast_ref::<TxtRecord>()?;
+            for data in record.get_data() {
+                character_string(&mut buf, data);
+            }
+        }
+        RRTypes::Srv => {
+            let record = record.as_any().downcast_ref::<SrvRecord>()?;
+            buf.extend_from_slice(&record.get_priority().to_be_bytes());
+            buf.extend_from_slice(&record.get_weight().to_be_bytes());
+            buf.extend_from_slice(&record.get_port().to_be_bytes());
+            buf.extend(name_to_wire(&record.get_target()?));
+        }
+        RRTypes::Naptr => {
+            let record = record.as_any().downcast_ref::<NaptrRecord>()?;
+            buf.extend_from_slice(&record.get_order().to_be_bytes());
+            buf.extend_from_slice(&record.get_preference().to_be_bytes());
+            character_string(&mut buf, &record.get_flags().unwrap_or_default());
+            character_string(&mut buf, &record.get_service().unwrap_or_default());
+            character_string(&mut buf, &record.get_regex().unwrap_or_default());
+            buf.extend(name_to_wire(&record.get_replacement()?));
+        }
+        RRTypes::SshFp => {
+            let record = record.as_any().downcast_ref::<SshFpRecord>()?;
+            buf.push(record.get_algorithm());
+            buf.push(record.get_fingerprint_type());
+            buf.extend_from_slice(record.get_fingerprint());
+        }
+        RRTypes::Smimea => {
+            let record = record.as_any().downcast_ref::<SmimeaRecord>()?;
+            buf.push(record.get_usage());
+            buf.push(record.get_selector());
+            buf.push(record.get_matching_type());
+            buf.extend_from_slice(record.get_certificate());
+        }
+        //RFC 9460 section 2.2 - THE TARGET ISN'T ONE OF THE NAMES RFC 4034 LOWERCASES
+        RRTypes::Svcb => {
+            let record = record.as_any().downcast_ref::<SvcbRecord>()?;
+            buf.extend_from_slice(&record.get_priority().to_be_bytes());
+            buf.extend(name_to_wire_as_is(&record.get_target()?));
+            svc_params(&mut buf, record.get_params().iter().map(|(key, param)| (key.get_code(), param.to_bytes())).collect());
+        }
+        RRTypes::Https => {
+            let record = record.as_any().downcast_ref::<HttpsRecord>()?;
+            buf.extend_from_slice(&record.get_priority().to_be_bytes());
+            buf.extend(name_to_wire_as_is(&record.get_target()?));
+            svc_params(&mut buf, record.get_params().iter().map(|(key, param)| (key.get_code(), param.to_bytes())).collect());
+        }
+        //RFC 7553 section 4.5 - THE TARGET IS THE REST OF THE RDATA, NOT A CHARACTER STRING
+        RRTypes::Uri => {
+            let record = record.as_any().downcast_ref::<UriRecord>()?;
+            buf.extend_from_slice(&record.get_priority().to_be_bytes());
+            buf.extend_from_slice(&record.get_weight().to_be_bytes());
+            buf.extend_from_slice(record.get_target()?.as_bytes());
+        }
+        RRTypes::Loc => {
+            let record = record.as_any().downcast_ref::<LocRecord>()?;
+            buf.push(record.get_version());
+            buf.push(record.get_size());
+            buf.push(record.get_horizontal_precision());
+            buf.push(record.get_vertical_precision());
+            buf.extend_from_slice(&record.get_latitude().to_be_bytes());
+            buf.extend_from_slice(&record.get_longitude().to_be_bytes());
+            buf.extend_from_slice(&record.get_altitude().to_be_bytes());
+        }
+        RRTypes::HInfo => {
+            let record = record.as_any().downcast_ref::<HInfoRecord>()?;
+            character_string(&mut buf, &record.get_cpu().unwrap_or_default());
+            character_string(&mut buf, &record.get_os().unwrap_or_default());
+        }
+        RRTypes::DnsKey => {
+            let record = record.as_any().downcast_ref::<DnsKeyRecord>()?;
+            buf.extend_from_slice(&record.get_flags().to_be_bytes());
+            buf.push(record.get_protocol());
+            buf.push(record.get_algorithm());
+            buf.extend_from_slice(record.get_public_key());
+        }
+        RRTypes::CDnsKey => {
+            let record = record.as_any().downcast_ref::<CDnsKeyRecord>()?;
+            buf.extend_from_slice(&record.get_flags().to_be_bytes());
+            buf.push(record.get_protocol());
+            buf.push(record.get_algorithm());
+            buf.extend_from_slice(record.get_public_key());
+        }
+        RRTypes::Ds => {
+            let record = record.as_any().downcast_ref::<DsRecord>()?;
+            buf.extend_from_slice(&record.get_key_tag().to_be_bytes());
+            buf.push(record.get_algorithm());
+            buf.push(record.get_digest_type());
+            buf.extend_from_slice(record.get_digest());
+        }
+        RRTypes::Cds => {
+            let record = record.as_any().downcast_ref::<CdsRecord>()?;
+            buf.extend_from_slice(&record.get_key_tag().to_be_bytes());
+            buf.push(record.get_algorithm());
+            buf.push(record.get_digest_type());
+            buf.extend_from_slice(record.get_digest());
+        }
+        RRTypes::NSec => {
+            let record = record.as_any().downcast_ref::<NSecRecord>()?;
+            //RFC 6840 section 5.1 - the next domain name is not converted to lowercase
+            buf.extend(name_to_wire_as_is(&record.get_next_domain()?));
+            buf.extend(type_bitmap(record.get_types()));
+        }
+        RRTypes::NSec3 => {
+            let record = record.as_any().downcast_ref::<NSec3Record>()?;
+            buf.push(record.get_algorithm());
+            buf.push(record.get_flags());
+            buf.extend_from_slice(&record.get_iterations().to_be_bytes());
+            buf.push(record.get_salt().len() as u8);
+            buf.extend_from_slice(record.get_salt());
+            buf.push(record.get_next_hash().len() as u8);
+            buf.extend_from_slice(record.get_next_hash());
+            buf.extend(type_bitmap(record.get_types()));
+        }
+        RRTypes::NSec3Param => {
+            let record = record.as_any().downcast_ref::<NSec3ParamRecord>()?;
+            buf.push(record.get_algorithm());
+            buf.push(record.get_flags());
+            buf.extend_from_slice(&record.get_iterations().to_be_bytes());
+            buf.push(record.get_salt().len() as u8);
+            buf.extend_from_slice(record.get_salt());
+        }
+        _ => return None
+    }
+
+    Some(buf)
+}
+
+/// Fails on the first record of the zone that has no canonical form, a zone holding one can't be
+/// signed. RRSIGs are never signed themselves so they are left out.
+pub fn check_encodable(zone: &Zone, apex: &str) -> io::Result<()> {
+    for (sub, records) in zone.get_all_records_recursive() {
+        for record in records {
+            if record.get_type() != RRTypes::RRSig && rdata_to_wire(record.as_ref()).is_none() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} {} has no canonical form and can't be signed", to_fqdn(&sub, apex), type_to_string(&record.get_type()))));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the canonical wire form of an RRset, records are ordered by their RDATA and duplicates
+/// are removed. Returns None if any member of the set can't be encoded.
+pub fn rrset_to_wire(owner: &str, records: &[&dyn RecordBase], ttl: u32) -> Option<Vec<u8>> {
+    let owner = name_to_wire(owner);
+
+    let mut rdatas = Vec::with_capacity(records.len());
+    for record in records {
+        rdatas.push((record.get_type(), record.get_class(), rdata_to_wire(*record)?));
+    }
+
+    rdatas.sort_by(|a, b| a.2.cmp(&b.2));
+    rdatas.dedup_by(|a, b| a.2 == b.2);
+
+    let mut buf = Vec::new();
+    for (_type, class, rdata) in rdatas {
+        buf.extend_from_slice(&owner);
+        buf.extend_from_slice(&_type.get_code().to_be_bytes());
+        buf.extend_from_slice(&class.get_code().to_be_bytes());
+        buf.extend_from_slice(&ttl.to_be_bytes());
+        buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buf.extend(rdata);
+    }
+
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::Ipv4Addr;
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    #[test]
+    fn naptr() {
+        let mut record = NaptrRecord::new(300, RRClasses::In);
+        record.set_order(100);
+        record.set_preference(10);
+        record.set_flags("S");
+        record.set_service("SIP+D2U");
+        record.set_regex("");
+        record.set_replacement("_SIP._udp.Example.com");
+
+        let mut expected = vec![0, 100, 0, 10, 1, b'S', 7];
+        expected.extend_from_slice(b"SIP+D2U");
+        expected.push(0);
+        //RFC 4034 section 6.2 - ONLY THE REPLACEMENT IS LOWERCASED, THE CHARACTER STRINGS ARE KEPT
+        expected.extend_from_slice(b"\x04_sip\x04_udp\x07example\x03com\x00");
+        assert_eq!(rdata_to_wire(&record), Some(expected));
+    }
+
+    #[test]
+    fn sshfp() {
+        let mut record = SshFpRecord::new(300, RRClasses::In);
+        record.set_algorithm(4);
+        record.set_fingerprint_type(2);
+        record.set_fingerprint(&[0xde, 0xad, 0xbe, 0xef]);
+
+        assert_eq!(rdata_to_wire(&record), Some(vec![4, 2, 0xde, 0xad, 0xbe, 0xef]));
+    }
+
+    #[test]
+    fn smimea() {
+        let mut record = SmimeaRecord::new(300, RRClasses::In);
+        record.set_usage(3);
+        record.set_selector(1);
+        record.set_matching_type(1);
+        record.set_certificate(&[0x01, 0x02, 0x03]);
+
+        assert_eq!(rdata_to_wire(&record), Some(vec![3, 1, 1, 0x01, 0x02, 0x03]));
+    }
+
+    #[test]
+    fn svcb() {
+        let mut record = SvcbRecord::new(300, RRClasses::In);
+        record.set_priority(1);
+        record.set_target("Svc.Example.com");
+
+        //THE TARGET KEEPS ITS CASE, SVCB ISN'T ONE OF THE TYPES RFC 4034 LOWERCASES
+        let mut expected = vec![0, 1];
+        expected.extend_from_slice(b"\x03Svc\x07Example\x03com\x00");
+        assert_eq!(rdata_to_wire(&record), Some(expected));
+    }
+
+    #[test]
+    fn https() {
+        let mut record = HttpsRecord::new(300, RRClasses::In);
+        record.set_priority(0);
+        record.set_target("Example.com");
+
+        let mut expected = vec![0, 0];
+        expected.extend_from_slice(b"\x07Example\x03com\x00");
+        assert_eq!(rdata_to_wire(&record), Some(expected));
+    }
+
+    #[test]
+    fn uri() {
+        let mut record = UriRecord::new(300, RRClasses::In);
+        record.set_priority(10);
+        record.set_weight(1);
+        record.set_target("ftp://ftp1.example.com/public");
+
+        let mut expected = vec![0, 10, 0, 1];
+        expected.extend_from_slice(b"ftp://ftp1.example.com/public");
+        assert_eq!(rdata_to_wire(&record), Some(expected));
+    }
+
+    #[test]
+    fn loc() {
+        let mut record = LocRecord::new(300, RRClasses::In);
+        record.set_version(0);
+        record.set_size(0x12);
+        record.set_horizontal_precision(0x16);
+        record.set_vertical_precision(0x13);
+        record.set_latitude(0x8b3d4f20);
+        record.set_longitude(0x7f2c7c6c);
+        record.set_altitude(0x00989680);
+
+        assert_eq!(rdata_to_wire(&record), Some(vec![
+            0, 0x12, 0x16, 0x13,
+            0x8b, 0x3d, 0x4f, 0x20,
+            0x7f, 0x2c, 0x7c, 0x6c,
+            0x00, 0x98, 0x96, 0x80
+        ]));
+    }
+
+    #[test]
+    fn encodable() {
+        let mut address = ARecord::new(300, RRClasses::In);
+        address.set_address(Ipv4Addr::new(192, 0, 2, 1));
+
+        let mut zone = Zone::new(ZoneTypes::Master);
+        zone.add_record("www", address.upcast());
+        assert!(check_encodable(&zone, "example.com").is_ok());
+
+        //AN NS RECORD WITHOUT A SERVER HAS NO WIRE FORM, THE ZONE CAN'T BE SIGNED
+        zone.add_record("sub", NsRecord::new(300, RRClasses::In).upcast());
+        assert!(check_encodable(&zone, "example.com").is_err());
+    }
+
+    #[test]
+    fn dname() {
+        let mut record = DNameRecord::new(300, RRClasses::In);
+        record.set_target("Example.NET");
+
+        //RFC 4034 section 6.2 - THE TARGET OF A DNAME IS LOWERCASED
+        assert_eq!(rdata_to_wire(&record), Some(b"\x07example\x03net\x00".to_vec()));
+    }
+}
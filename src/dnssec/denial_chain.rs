@@ -0,0 +1,468 @@
+use std::collections::HashSet;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::nsec3_record::NSec3Record;
+use rlibdns::records::nsec3param_record::NSec3ParamRecord;
+use rlibdns::records::nsec_record::NSecRecord;
+use rlibdns::zone::zone::Zone;
+use crate::dnssec::nsec3::{Nsec3Params, NSEC3_HASH_SHA1, OPT_OUT_FLAG};
+use crate::utils::base32::{decode_hex, encode_hex};
+use crate::utils::domain_utils::{get_labels, get_parent, is_subdomain, normalize};
+use crate::utils::zone_utils::{get_all_names, get_all_records_of_type};
+
+type ZoneNameSets = (Vec<(String, Vec<RRTypes>)>, Vec<String>, HashSet<String>, HashSet<String>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DenialTypes {
+    NSec,
+    NSec3(Nsec3Params)
+}
+
+/// The NSEC or NSEC3 chain for a single zone, generated from the zone contents. Entries are kept in
+/// canonical order (NSEC) or hash order (NSEC3) so proofs can be found with a binary search.
+pub struct DenialChain {
+    apex: String,
+    denial: DenialTypes,
+    names: HashSet<String>,
+    ents: HashSet<String>,
+    entries: Vec<(Vec<u8>, String, Box<dyn RecordBase>)>
+}
+
+impl DenialChain {
+
+    /// `extra_types` are the types the signer serves on top of the zone data, IE DNSKEY at the apex,
+    /// they are only added to names that already exist in the zone.
+    pub fn build(apex: &str, zone: &Zone, denial: &DenialTypes, extra_types: &[(String, RRTypes)], ttl: u32) -> Self {
+        let apex = normalize(apex);
+        let (all, cuts, names, ents) = collect_names(&apex, zone, extra_types);
+
+        let mut bitmaps: Vec<(String, Vec<RRTypes>, bool)> = Vec::new();
+        for (name, types) in all {
+            let delegation = cuts.contains(&name);
+            let secure = !delegation || types.contains(&RRTypes::Ds);
+
+            let mut bitmap: Vec<RRTypes> = match delegation {
+                true => types.into_iter().filter(|t| matches!(t, RRTypes::Ns | RRTypes::Ds)).collect(),
+                false => types
+            };
+
+            //RFC 4035 section 2.3 - THE NSEC OF AN INSECURE DELEGATION IS SIGNED LIKE ANY OTHER, SO EVERY
+            //OWNER WITH AN NSEC HAS AN RRSIG. AN NSEC3 DESCRIBES THE NAME, WHICH HAS NONE AT THE CUT
+            match denial {
+                DenialTypes::NSec => {
+                    bitmap.push(RRTypes::RRSig);
+                    bitmap.push(RRTypes::NSec);
+                }
+                DenialTypes::NSec3(_) => {
+                    if secure {
+                        bitmap.push(RRTypes::RRSig);
+                    }
+                }
+            }
+
+            bitmaps.push((name, bitmap, secure));
+        }
+
+        let mut chain = Self {
+            apex: apex.clone(),
+            denial: denial.clone(),
+            names,
+            ents,
+            entries: Vec::new()
+        };
+
+        match denial {
+            DenialTypes::NSec => {
+                let mut keyed: Vec<(Vec<u8>, String, Vec<RRTypes>)> = bitmaps.into_iter()
+                    .map(|(name, bitmap, _)| (canonical_key(&name), name, bitmap))
+                    .collect();
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+                for i in 0..keyed.len() {
+                    let next = &keyed[(i + 1) % keyed.len()].1;
+
+                    let mut record = NSecRecord::new(ttl, RRClasses::In);
+                    record.set_next_domain(next);
+                    for _type in &keyed[i].2 {
+                        record.add_type(*_type);
+                    }
+
+                    chain.entries.push((keyed[i].0.clone(), keyed[i].1.clone(), record.upcast()));
+                }
+            }
+            DenialTypes::NSec3(params) => {
+                let mut keyed: Vec<(Vec<u8>, Vec<RRTypes>)> = Vec::new();
+
+                for (name, bitmap, secure) in bitmaps {
+                    //RFC 5155 section 6 - insecure delegations can be left out of an opt-out chain
+                    if params.is_opt_out() && !secure {
+                        continue;
+                    }
+                    keyed.push((params.hash_name(&name), bitmap));
+                }
+
+                for ent in &chain.ents {
+                    keyed.push((params.hash_name(ent), Vec::new()));
+                }
+
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                keyed.dedup_by(|a, b| a.0 == b.0);
+
+                for i in 0..keyed.len() {
+                    let mut record = NSec3Record::new(ttl, RRClasses::In);
+                    record.set_algorithm(NSEC3_HASH_SHA1);
+                    record.set_flags(params.get_flags());
+                    record.set_iterations(params.get_iterations());
+                    record.set_salt(params.get_salt());
+                    record.set_next_hash(&keyed[(i + 1) % keyed.len()].0);
+                    for _type in &keyed[i].1 {
+                        record.add_type(*_type);
+                    }
+
+                    let owner = format!("{}.{}", encode_hex(&keyed[i].0), apex);
+                    chain.entries.push((keyed[i].0.clone(), owner, record.upcast()));
+                }
+            }
+        }
+
+        chain
+    }
+
+    /// Loads the chain of a zone that was signed offline from the NSEC / NSEC3 records it already
+    /// holds, returns None if the zone has neither.
+    pub fn from_zone(apex: &str, zone: &Zone) -> Option<Self> {
+        let apex = normalize(apex);
+        let (_, _, names, ents) = collect_names(&apex, zone, &[]);
+
+        let nsec3 = get_all_records_of_type(zone, &apex, RRTypes::NSec3);
+        let param = zone.get_records("", &RRTypes::NSec3Param)
+            .and_then(|records| records.first())
+            .and_then(|record| record.as_any().downcast_ref::<NSec3ParamRecord>());
+
+        let (denial, records) = match param {
+            Some(param) if !nsec3.is_empty() => {
+                let opt_out = nsec3.iter()
+                    .filter_map(|(_, record)| record.as_any().downcast_ref::<NSec3Record>())
+                    .any(|record| record.get_flags() & OPT_OUT_FLAG != 0);
+                (DenialTypes::NSec3(Nsec3Params::from_record(param, opt_out)), nsec3)
+            }
+            _ => (DenialTypes::NSec, get_all_records_of_type(zone, &apex, RRTypes::NSec))
+        };
+
+        let mut entries = Vec::with_capacity(records.len());
+        for (owner, record) in records {
+            let key = match denial {
+                DenialTypes::NSec => canonical_key(&owner),
+                DenialTypes::NSec3(_) => match get_labels(&owner).first().and_then(|label| decode_hex(label)) {
+                    Some(key) => key,
+                    None => continue
+                }
+            };
+            entries.push((key, owner, record));
+        }
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Some(Self {
+            apex,
+            denial,
+            names,
+            ents,
+            entries
+        })
+    }
+
+    pub fn get_apex(&self) -> &str {
+        &self.apex
+    }
+
+    pub fn get_denial(&self) -> &DenialTypes {
+        &self.denial
+    }
+
+    /// Every record in the chain with its owner, in chain order.
+    pub fn get_records(&self) -> Vec<(String, Box<dyn RecordBase>)> {
+        self.entries.iter().map(|(_, owner, record)| (owner.clone(), record.clone())).collect()
+    }
+
+    /// The names in the zone that are part of the chain, delegations included.
+    pub fn get_names(&self) -> &HashSet<String> {
+        &self.names
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        let name = normalize(name);
+        self.names.contains(&name) || self.ents.contains(&name)
+    }
+
+    pub fn is_empty_non_terminal(&self, name: &str) -> bool {
+        self.ents.contains(&normalize(name))
+    }
+
+    pub fn get_closest_encloser(&self, name: &str) -> String {
+        let mut current = normalize(name);
+
+        while current != self.apex && !self.exists(&current) {
+            current = match get_parent(&current) {
+                Some(parent) => parent,
+                None => return self.apex.clone()
+            };
+        }
+
+        current
+    }
+
+    /// Adds the proof for a name with no data of the queried type (NODATA) or no data at all
+    /// (NXDOMAIN). Returns true if the name doesn't exist.
+    pub fn add_negative_proof(&self, name: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>) -> bool {
+        let name = normalize(name);
+
+        if let Some(i) = self.get_matching(&name) {
+            self.push(i, records);
+            return false;
+        }
+
+        //INSECURE DELEGATIONS LEFT OUT OF AN OPT-OUT CHAIN STILL EXIST
+        if self.names.contains(&name) {
+            self.add_delegation_proof(&name, records);
+            return false;
+        }
+
+        match self.denial {
+            DenialTypes::NSec => {
+                self.push(self.get_covering(&name), records);
+
+                //THE NEXT NAME OF THE COVERING NSEC IS A DESCENDANT, THE NAME IS AN EMPTY NON-TERMINAL
+                if self.ents.contains(&name) {
+                    return false;
+                }
+
+                let wildcard = format!("*.{}", self.get_closest_encloser(&name));
+                match self.get_matching(&wildcard) {
+                    Some(i) => self.push(i, records),
+                    None => self.push(self.get_covering(&wildcard), records)
+                }
+            }
+            DenialTypes::NSec3(_) => {
+                let closest_encloser = self.add_closest_encloser_proof(&name, records);
+                self.push(self.get_covering(&format!("*.{}", closest_encloser)), records);
+            }
+        }
+
+        true
+    }
+
+    /// Proves that the query name didn't exist so the answer had to be synthesized from a wildcard.
+    pub fn add_wildcard_proof(&self, name: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        let name = normalize(name);
+
+        match self.denial {
+            DenialTypes::NSec => self.push(self.get_covering(&name), records),
+            DenialTypes::NSec3(_) => {
+                let closest_encloser = self.get_closest_encloser(&name);
+                self.push(self.get_covering(&next_closer(&name, &closest_encloser)), records);
+            }
+        }
+    }
+
+    /// Proves the query name doesn't exist and the matching wildcard has no data of the queried type.
+    pub fn add_wildcard_no_data_proof(&self, name: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        let name = normalize(name);
+
+        match self.denial {
+            DenialTypes::NSec => self.push(self.get_covering(&name), records),
+            DenialTypes::NSec3(_) => {
+                self.add_closest_encloser_proof(&name, records);
+            }
+        }
+
+        let wildcard = format!("*.{}", self.get_closest_encloser(&name));
+        if let Some(i) = self.get_matching(&wildcard) {
+            self.push(i, records);
+        }
+    }
+
+    /// Proves there is no DS at a delegation, making the child zone insecure.
+    pub fn add_delegation_proof(&self, cut: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        let cut = normalize(cut);
+
+        if let Some(i) = self.get_matching(&cut) {
+            self.push(i, records);
+            return;
+        }
+
+        //OPT-OUT - PROVE THE CLOSEST PROVABLE ENCLOSER AND THAT THE NEXT CLOSER NAME FALLS IN AN OPT-OUT SPAN
+        let mut closest_encloser = cut.clone();
+        loop {
+            closest_encloser = match get_parent(&closest_encloser) {
+                Some(parent) => parent,
+                None => return
+            };
+
+            if let Some(i) = self.get_matching(&closest_encloser) {
+                self.push(i, records);
+                break;
+            }
+
+            if closest_encloser == self.apex {
+                return;
+            }
+        }
+
+        self.push(self.get_covering(&next_closer(&cut, &closest_encloser)), records);
+    }
+
+    fn add_closest_encloser_proof(&self, name: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>) -> String {
+        let closest_encloser = self.get_closest_encloser(name);
+
+        if let Some(i) = self.get_matching(&closest_encloser) {
+            self.push(i, records);
+        }
+
+        self.push(self.get_covering(&next_closer(name, &closest_encloser)), records);
+        closest_encloser
+    }
+
+    fn get_key(&self, name: &str) -> Vec<u8> {
+        match &self.denial {
+            DenialTypes::NSec => canonical_key(name),
+            DenialTypes::NSec3(params) => params.hash_name(name)
+        }
+    }
+
+    fn get_matching(&self, name: &str) -> Option<usize> {
+        let key = self.get_key(name);
+        self.entries.binary_search_by(|(k, _, _)| k.cmp(&key)).ok()
+    }
+
+    fn get_covering(&self, name: &str) -> usize {
+        let key = self.get_key(name);
+
+        match self.entries.binary_search_by(|(k, _, _)| k.cmp(&key)) {
+            Ok(i) => i,
+            Err(0) => self.entries.len() - 1,
+            Err(i) => i - 1
+        }
+    }
+
+    fn push(&self, i: usize, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        let (_, owner, record) = &self.entries[i];
+
+        if !records.iter().any(|(name, r)| name == owner && r.get_type() == record.get_type()) {
+            records.push((owner.clone(), record.clone()));
+        }
+    }
+}
+
+/// Lists the names of the zone with their types along with the zone cuts, the names that are part
+/// of the chain and the empty non-terminals between them and the apex.
+fn collect_names(apex: &str, zone: &Zone, extra_types: &[(String, RRTypes)]) -> ZoneNameSets {
+    let mut all = get_all_names(zone, apex);
+    for (name, types) in all.iter_mut() {
+        types.retain(|t| !matches!(t, RRTypes::RRSig | RRTypes::NSec | RRTypes::NSec3 | RRTypes::NSec3Param));
+
+        for (_, _type) in extra_types.iter().filter(|(n, _)| n == name) {
+            if !types.contains(_type) {
+                types.push(*_type);
+            }
+        }
+    }
+    all.retain(|(name, types)| (!types.is_empty() || name == apex) && is_subdomain(name, apex));
+
+    //ANYTHING BELOW A ZONE CUT IS GLUE OR OCCLUDED AND ISN'T PART OF THE CHAIN
+    let cuts: Vec<String> = all.iter()
+        .filter(|(name, types)| name != apex && types.contains(&RRTypes::Ns))
+        .map(|(name, _)| name.clone())
+        .collect();
+    all.retain(|(name, _)| !cuts.iter().any(|cut| name != cut && is_subdomain(name, cut)));
+
+    let names: HashSet<String> = all.iter().map(|(name, _)| name.clone()).collect();
+
+    let mut ents = HashSet::new();
+    for name in &names {
+        let mut current = get_parent(name);
+        while let Some(parent) = current {
+            if parent == apex || !is_subdomain(&parent, apex) {
+                break;
+            }
+
+            if !names.contains(&parent) {
+                ents.insert(parent.clone());
+            }
+            current = get_parent(&parent);
+        }
+    }
+
+    (all, cuts, names, ents)
+}
+
+/// Sort key matching canonical name order, labels from the root down each terminated by 0.
+fn canonical_key(name: &str) -> Vec<u8> {
+    let mut key = Vec::new();
+
+    for label in get_labels(name).iter().rev() {
+        key.extend_from_slice(label.to_lowercase().as_bytes());
+        key.push(0);
+    }
+
+    key
+}
+
+/// The name one label longer than the closest encloser on the way to the query name.
+fn next_closer(name: &str, closest_encloser: &str) -> String {
+    let labels = get_labels(name);
+    let depth = get_labels(closest_encloser).len() + 1;
+
+    match labels.len() >= depth {
+        true => labels[labels.len() - depth..].join("."),
+        false => name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::records::a_record::ARecord;
+    use rlibdns::records::ns_record::NsRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+
+    /// `test` with an address and an insecure delegation to `sub.test`.
+    fn get_zone() -> Zone {
+        let mut ns = NsRecord::new(300, RRClasses::In);
+        ns.set_server("ns.example.");
+
+        let mut zone = Zone::new(ZoneTypes::Master);
+        zone.add_record("www", ARecord::new(300, RRClasses::In).upcast());
+        zone.add_record("sub", ns.upcast());
+        zone
+    }
+
+    /// The type bitmap of the NSEC or NSEC3 record at the owner.
+    fn get_types(chain: &DenialChain, owner: &str) -> Vec<RRTypes> {
+        let (_, record) = chain.get_records().into_iter().find(|(name, _)| name == owner).unwrap();
+
+        match record.as_any().downcast_ref::<NSecRecord>() {
+            Some(record) => record.get_types().clone(),
+            None => record.as_any().downcast_ref::<NSec3Record>().unwrap().get_types().clone()
+        }
+    }
+
+    #[test]
+    fn insecure_delegation_bitmap() {
+        let chain = DenialChain::build("test", &get_zone(), &DenialTypes::NSec, &[], 300);
+        assert_eq!(get_types(&chain, "sub.test"), vec![RRTypes::Ns, RRTypes::RRSig, RRTypes::NSec]);
+        assert_eq!(get_types(&chain, "www.test"), vec![RRTypes::A, RRTypes::RRSig, RRTypes::NSec]);
+
+        //THE NSEC3 BITMAP OF THE CUT HAS NO RRSIG, NOTHING AT THE NAME ITSELF IS SIGNED
+        let params = Nsec3Params::new(0, &[], false);
+        let chain = DenialChain::build("test", &get_zone(), &DenialTypes::NSec3(params.clone()), &[], 300);
+        let owner = format!("{}.test", encode_hex(&params.hash_name("sub.test")));
+        assert_eq!(get_types(&chain, &owner), vec![RRTypes::Ns]);
+    }
+}
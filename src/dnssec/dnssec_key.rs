@@ -0,0 +1,361 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::records::cdnskey_record::CDnsKeyRecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use crate::dnssec::ds::{to_cds_record, to_ds_record, to_ds_string};
+use crate::dnssec::inter::dnssec_algorithms::DnssecAlgorithms;
+use crate::dnssec::key_timing::KeyTiming;
+use crate::utils::base64;
+use crate::utils::crypto::ed25519::Ed25519PrivateKey;
+use crate::utils::crypto::p256::P256PrivateKey;
+use crate::utils::crypto::random::fill_random;
+use crate::utils::time_utils::{to_date_time, to_readable};
+use crate::utils::domain_utils::normalize;
+
+pub const ZONE_KEY_FLAG: u16 = 0x0100;
+pub const SECURE_ENTRY_POINT_FLAG: u16 = 0x0001;
+pub const DNSKEY_PROTOCOL: u8 = 3;
+pub const DEFAULT_DNSKEY_TTL: u32 = 3600;
+
+/// Owner, TTL, flags, algorithm and public key of a DNSKEY line.
+type PublicKeyLine = (String, Option<u32>, u16, u8, Vec<u8>);
+
+#[derive(Clone)]
+pub enum PrivateKey {
+    EcdsaP256(P256PrivateKey),
+    Ed25519(Ed25519PrivateKey)
+}
+
+impl PrivateKey {
+
+    pub fn generate(algorithm: DnssecAlgorithms) -> io::Result<Self> {
+        let mut buf = [0u8; 32];
+
+        loop {
+            fill_random(&mut buf)?;
+
+            //P-256 SCALARS OUT OF RANGE ARE SO UNLIKELY A RETRY IS SIMPLER THAN REDUCING
+            let key = match algorithm {
+                DnssecAlgorithms::EcdsaP256Sha256 => P256PrivateKey::from_bytes(&buf).map(Self::EcdsaP256),
+                DnssecAlgorithms::Ed25519 => Ed25519PrivateKey::from_bytes(&buf).map(Self::Ed25519)
+            };
+
+            if let Some(key) = key {
+                return Ok(key);
+            }
+        }
+    }
+
+    pub fn get_algorithm(&self) -> DnssecAlgorithms {
+        match self {
+            Self::EcdsaP256(_) => DnssecAlgorithms::EcdsaP256Sha256,
+            Self::Ed25519(_) => DnssecAlgorithms::Ed25519
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        match self {
+            Self::EcdsaP256(key) => key.to_bytes(),
+            Self::Ed25519(key) => key.to_bytes()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DnssecKey {
+    owner: String,
+    ttl: u32,
+    flags: u16,
+    algorithm: DnssecAlgorithms,
+    private_key: PrivateKey,
+    public_key: Vec<u8>,
+    key_tag: u16,
+    timing: KeyTiming
+}
+
+impl DnssecKey {
+
+    pub fn new(owner: &str, flags: u16, private_key: PrivateKey) -> Self {
+        let algorithm = private_key.get_algorithm();
+        let public_key = match &private_key {
+            PrivateKey::EcdsaP256(key) => key.get_public_key().to_vec(),
+            PrivateKey::Ed25519(key) => key.get_public_key().to_vec()
+        };
+
+        let mut key = Self {
+            owner: normalize(owner),
+            ttl: DEFAULT_DNSKEY_TTL,
+            flags,
+            algorithm,
+            private_key,
+            public_key,
+            key_tag: 0,
+            timing: KeyTiming::default()
+        };
+        key.key_tag = calculate_key_tag(&key.get_rdata());
+        key
+    }
+
+    /// Generates a new key, `now` is recorded as its creation time.
+    pub fn generate(owner: &str, flags: u16, algorithm: DnssecAlgorithms, now: u64) -> io::Result<Self> {
+        let mut key = Self::new(owner, flags, PrivateKey::generate(algorithm)?);
+        key.timing.set_created(Some(now));
+        Ok(key)
+    }
+
+    /// Opens a BIND style key pair, `file_path` is the shared prefix of the `.key` and `.private`
+    /// files IE `/etc/find9/keys/Kfind9.net.+013+38518`.
+    pub fn open(file_path: &str) -> io::Result<Self> {
+        let public = fs::read_to_string(format!("{}.key", file_path))?;
+        let private = fs::read_to_string(format!("{}.private", file_path))?;
+
+        let (owner, ttl, flags, algorithm, public_key) = parse_public_key(&public)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid DNSKEY in key file"))?;
+
+        let mut private_algorithm = None;
+        let mut private_bytes = None;
+        let mut timing = KeyTiming::default();
+
+        for line in private.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some(pair) => pair,
+                None => continue
+            };
+
+            match key.trim() {
+                "Algorithm" => {
+                    private_algorithm = value.split_whitespace().next().and_then(|v| v.parse::<u8>().ok());
+                }
+                "PrivateKey" => {
+                    private_bytes = base64::decode(value.trim());
+                }
+                key => {
+                    timing.parse_line(key, value);
+                }
+            }
+        }
+
+        //THE STATE FILE IS WRITTEN BY THE KEY MANAGER AND WINS OVER THE TIMES IN THE PRIVATE FILE
+        if let Ok(state) = fs::read_to_string(format!("{}.state", file_path)) {
+            for line in state.lines() {
+                if let Some((key, value)) = line.split_once(':') {
+                    timing.parse_line(key.trim(), value);
+                }
+            }
+        }
+
+        if private_algorithm != Some(algorithm) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Private key algorithm doesn't match DNSKEY"));
+        }
+
+        let private_key = match DnssecAlgorithms::from_code(algorithm) {
+            Some(DnssecAlgorithms::EcdsaP256Sha256) => {
+                PrivateKey::EcdsaP256(private_bytes.as_deref().and_then(P256PrivateKey::from_bytes)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid ECDSA P-256 private key"))?)
+            }
+            Some(DnssecAlgorithms::Ed25519) => {
+                PrivateKey::Ed25519(private_bytes.as_deref().and_then(Ed25519PrivateKey::from_bytes)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid Ed25519 private key"))?)
+            }
+            None => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("Unsupported DNSSEC algorithm {}", algorithm)))
+        };
+
+        let mut key = Self::new(&owner, flags, private_key);
+        key.ttl = ttl.unwrap_or(DEFAULT_DNSKEY_TTL);
+        key.timing = timing;
+
+        if key.public_key != public_key {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Private key doesn't match DNSKEY public key"));
+        }
+
+        Ok(key)
+    }
+
+    /// BIND file name prefix for the key IE `Kfind9.net.+013+37601`.
+    pub fn get_file_name(&self) -> String {
+        format!("K{}.+{:03}+{:05}", self.owner, self.algorithm.get_code(), self.key_tag)
+    }
+
+    /// Writes the `.key`, `.private` and `.state` files into the directory. Each file is written
+    /// to a temporary file first and renamed so a crash never leaves a half written key behind.
+    pub fn write(&self, directory: &str) -> io::Result<()> {
+        let prefix = Path::new(directory).join(self.get_file_name());
+        let prefix = prefix.to_string_lossy();
+
+        let kind = match self.is_ksk() {
+            true => "key-signing",
+            false => "zone-signing"
+        };
+
+        let mut public = format!("; This is a {} key, keyid {}, for {}.\n", kind, self.key_tag, self.owner);
+        let mut private = format!("Private-key-format: v1.3\nAlgorithm: {} ({})\nPrivateKey: {}\n",
+                                  self.algorithm.get_code(), self.algorithm.get_mnemonic(), base64::encode(&self.private_key.to_bytes()));
+        let mut state = format!("; This is the state of key {}, for {}.\nAlgorithm: {}\nKSK: {}\nZSK: {}\n",
+                                self.key_tag, self.owner, self.algorithm.get_code(),
+                                if self.is_ksk() { "yes" } else { "no" }, if self.is_ksk() { "no" } else { "yes" });
+
+        let times = [
+            ("Created", "Generated", self.timing.get_created()),
+            ("Publish", "Published", self.timing.get_publish()),
+            ("Activate", "Active", self.timing.get_activate()),
+            ("Inactive", "Retired", self.timing.get_inactive()),
+            ("Delete", "Removed", self.timing.get_delete())
+        ];
+
+        for (name, state_name, time) in times {
+            if let Some(time) = time {
+                public.push_str(&format!("; {}: {} ({})\n", name, to_date_time(time), to_readable(time)));
+                private.push_str(&format!("{}: {}\n", name, to_date_time(time)));
+                state.push_str(&format!("{}: {}\n", state_name, to_date_time(time)));
+            }
+        }
+
+        public.push_str(&format!("{}. {} IN DNSKEY {} {} {} {}\n", self.owner, self.ttl, self.flags, DNSKEY_PROTOCOL,
+                                 self.algorithm.get_code(), base64::encode(&self.public_key)));
+
+        for (extension, data) in [("key", public), ("private", private), ("state", state)] {
+            let path = format!("{}.{}", prefix, extension);
+            let tmp = format!("{}.tmp", path);
+            fs::write(&tmp, data)?;
+            fs::rename(&tmp, &path)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_owner(&self) -> &str {
+        &self.owner
+    }
+
+    pub fn get_ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    pub fn set_ttl(&mut self, ttl: u32) {
+        self.ttl = ttl;
+    }
+
+    pub fn get_flags(&self) -> u16 {
+        self.flags
+    }
+
+    pub fn is_ksk(&self) -> bool {
+        self.flags & SECURE_ENTRY_POINT_FLAG != 0
+    }
+
+    pub fn get_algorithm(&self) -> DnssecAlgorithms {
+        self.algorithm
+    }
+
+    pub fn get_public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn get_key_tag(&self) -> u16 {
+        self.key_tag
+    }
+
+    pub fn get_timing(&self) -> &KeyTiming {
+        &self.timing
+    }
+
+    pub fn get_timing_mut(&mut self) -> &mut KeyTiming {
+        &mut self.timing
+    }
+
+    pub fn is_published(&self, now: u64) -> bool {
+        self.timing.is_published(now)
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.timing.is_active(now)
+    }
+
+    pub fn get_rdata(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.public_key.len());
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        buf.push(DNSKEY_PROTOCOL);
+        buf.push(self.algorithm.get_code());
+        buf.extend_from_slice(&self.public_key);
+        buf
+    }
+
+    pub fn to_record(&self) -> Box<dyn RecordBase> {
+        let mut record = DnsKeyRecord::new(self.ttl, RRClasses::In);
+        record.set_flags(self.flags);
+        record.set_protocol(DNSKEY_PROTOCOL);
+        record.set_algorithm(self.algorithm.get_code());
+        record.set_public_key(&self.public_key);
+        record.upcast()
+    }
+
+    pub fn to_cdnskey_record(&self) -> Box<dyn RecordBase> {
+        let mut record = CDnsKeyRecord::new(self.ttl, RRClasses::In);
+        record.set_flags(self.flags);
+        record.set_protocol(DNSKEY_PROTOCOL);
+        record.set_algorithm(self.algorithm.get_code());
+        record.set_public_key(&self.public_key);
+        record.upcast()
+    }
+
+    pub fn to_ds_record(&self) -> Box<dyn RecordBase> {
+        to_ds_record(&self.owner, &self.get_rdata(), self.ttl)
+    }
+
+    pub fn to_cds_record(&self) -> Box<dyn RecordBase> {
+        to_cds_record(&self.owner, &self.get_rdata(), self.ttl)
+    }
+
+    pub fn to_ds_string(&self) -> String {
+        to_ds_string(&self.owner, &self.get_rdata(), self.ttl)
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        match &self.private_key {
+            PrivateKey::EcdsaP256(key) => key.sign(data).to_vec(),
+            PrivateKey::Ed25519(key) => key.sign(data).to_vec()
+        }
+    }
+}
+
+//RFC 4034 Appendix B
+pub fn calculate_key_tag(rdata: &[u8]) -> u16 {
+    let mut ac = 0u32;
+
+    for (i, b) in rdata.iter().enumerate() {
+        ac += if i & 1 == 1 {
+            *b as u32
+        } else {
+            (*b as u32) << 8
+        };
+    }
+
+    ac += (ac >> 16) & 0xffff;
+    (ac & 0xffff) as u16
+}
+
+fn parse_public_key(buf: &str) -> Option<PublicKeyLine> {
+    for line in buf.lines() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let i = tokens.iter().position(|t| t.eq_ignore_ascii_case("DNSKEY"))?;
+
+        let owner = tokens.first()?.to_string();
+        let ttl = tokens[1..i].iter().find_map(|t| t.parse::<u32>().ok());
+
+        let flags = tokens.get(i + 1)?.parse::<u16>().ok()?;
+        let algorithm = tokens.get(i + 3)?.parse::<u8>().ok()?;
+        let public_key = base64::decode(&tokens[i + 4..].concat())?;
+
+        return Some((owner, ttl, flags, algorithm, public_key));
+    }
+
+    None
+}
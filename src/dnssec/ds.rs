@@ -0,0 +1,56 @@
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::records::cds_record::CdsRecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
+use rlibdns::records::ds_record::DsRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use crate::dnssec::canonical::name_to_wire;
+use crate::dnssec::dnssec_key::calculate_key_tag;
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha256::Sha256;
+
+pub const DS_DIGEST_SHA256: u8 = 2;
+
+//RFC 4509 section 2.1 - digest = SHA-256(owner name | DNSKEY RDATA)
+pub fn calculate_digest(owner: &str, dnskey_rdata: &[u8]) -> [u8; 32] {
+    let owner = name_to_wire(owner);
+
+    let mut hasher = Sha256::new();
+    hasher.update(&owner, 0, owner.len());
+    hasher.update(dnskey_rdata, 0, dnskey_rdata.len());
+    hasher.get_value()
+}
+
+pub fn to_ds_record(owner: &str, dnskey_rdata: &[u8], ttl: u32) -> Box<dyn RecordBase> {
+    let mut record = DsRecord::new(ttl, RRClasses::In);
+    record.set_key_tag(calculate_key_tag(dnskey_rdata));
+    record.set_algorithm(dnskey_rdata[3]);
+    record.set_digest_type(DS_DIGEST_SHA256);
+    record.set_digest(&calculate_digest(owner, dnskey_rdata));
+    record.upcast()
+}
+
+pub fn to_cds_record(owner: &str, dnskey_rdata: &[u8], ttl: u32) -> Box<dyn RecordBase> {
+    let mut record = CdsRecord::new(ttl, RRClasses::In);
+    record.set_key_tag(calculate_key_tag(dnskey_rdata));
+    record.set_algorithm(dnskey_rdata[3]);
+    record.set_digest_type(DS_DIGEST_SHA256);
+    record.set_digest(&calculate_digest(owner, dnskey_rdata));
+    record.upcast()
+}
+
+/// Rebuilds the RDATA of a DNSKEY read from a zone file so a DS can be computed for it.
+pub fn get_dnskey_rdata(record: &DnsKeyRecord) -> Vec<u8> {
+    let mut rdata = Vec::with_capacity(4 + record.get_public_key().len());
+    rdata.extend_from_slice(&record.get_flags().to_be_bytes());
+    rdata.push(record.get_protocol());
+    rdata.push(record.get_algorithm());
+    rdata.extend_from_slice(record.get_public_key());
+    rdata
+}
+
+/// Presentation format of the DS as it would be handed to a registrar IE
+/// `find9.net. 3600 IN DS 37601 13 2 9A3F...`.
+pub fn to_ds_string(owner: &str, dnskey_rdata: &[u8], ttl: u32) -> String {
+    let digest: String = calculate_digest(owner, dnskey_rdata).iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{}. {} IN DS {} {} {} {}", owner, ttl, calculate_key_tag(dnskey_rdata), dnskey_rdata[3], DS_DIGEST_SHA256, digest)
+}
@@ -0,0 +1,26 @@
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DnssecAlgorithms {
+    EcdsaP256Sha256,
+    Ed25519
+}
+
+impl DnssecAlgorithms {
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        [Self::EcdsaP256Sha256, Self::Ed25519].into_iter().find(|a| a.get_code() == code)
+    }
+
+    pub fn get_code(&self) -> u8 {
+        match self {
+            Self::EcdsaP256Sha256 => 13,
+            Self::Ed25519 => 15
+        }
+    }
+
+    pub fn get_mnemonic(&self) -> &'static str {
+        match self {
+            Self::EcdsaP256Sha256 => "ECDSAP256SHA256",
+            Self::Ed25519 => "ED25519"
+        }
+    }
+}
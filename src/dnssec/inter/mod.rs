@@ -0,0 +1 @@
+pub mod dnssec_algorithms;
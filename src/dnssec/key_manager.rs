@@ -0,0 +1,258 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::dnssec::dnssec_key::{DnssecKey, SECURE_ENTRY_POINT_FLAG, ZONE_KEY_FLAG};
+use crate::dnssec::inter::dnssec_algorithms::DnssecAlgorithms;
+use crate::utils::domain_utils::normalize;
+
+pub const DEFAULT_ZSK_LIFETIME: u64 = 2592000;
+pub const DEFAULT_KSK_LIFETIME: u64 = 31536000;
+pub const DEFAULT_PROPAGATION_DELAY: u64 = 3600;
+pub const DEFAULT_MAX_ZONE_TTL: u64 = 86400;
+pub const DEFAULT_DS_DELAY: u64 = 172800;
+
+/// How often keys are rolled and how long each step of a rollover waits for caches to catch up.
+/// A lifetime of 0 disables rollovers for that key type.
+#[derive(Clone, Debug)]
+pub struct KeyPolicy {
+    algorithm: DnssecAlgorithms,
+    zsk_lifetime: u64,
+    ksk_lifetime: u64,
+    propagation_delay: u64,
+    max_zone_ttl: u64,
+    ds_delay: u64
+}
+
+impl KeyPolicy {
+
+    pub fn new(algorithm: DnssecAlgorithms) -> Self {
+        Self {
+            algorithm,
+            zsk_lifetime: DEFAULT_ZSK_LIFETIME,
+            ksk_lifetime: DEFAULT_KSK_LIFETIME,
+            propagation_delay: DEFAULT_PROPAGATION_DELAY,
+            max_zone_ttl: DEFAULT_MAX_ZONE_TTL,
+            ds_delay: DEFAULT_DS_DELAY
+        }
+    }
+
+    pub fn get_algorithm(&self) -> DnssecAlgorithms {
+        self.algorithm
+    }
+
+    pub fn set_zsk_lifetime(&mut self, zsk_lifetime: u64) {
+        self.zsk_lifetime = zsk_lifetime;
+    }
+
+    pub fn get_zsk_lifetime(&self) -> u64 {
+        self.zsk_lifetime
+    }
+
+    pub fn set_ksk_lifetime(&mut self, ksk_lifetime: u64) {
+        self.ksk_lifetime = ksk_lifetime;
+    }
+
+    pub fn get_ksk_lifetime(&self) -> u64 {
+        self.ksk_lifetime
+    }
+
+    pub fn set_propagation_delay(&mut self, propagation_delay: u64) {
+        self.propagation_delay = propagation_delay;
+    }
+
+    pub fn get_propagation_delay(&self) -> u64 {
+        self.propagation_delay
+    }
+
+    pub fn set_max_zone_ttl(&mut self, max_zone_ttl: u64) {
+        self.max_zone_ttl = max_zone_ttl;
+    }
+
+    pub fn get_max_zone_ttl(&self) -> u64 {
+        self.max_zone_ttl
+    }
+
+    /// Time allowed for the parent to publish the new DS and for its old DS to expire from caches.
+    pub fn set_ds_delay(&mut self, ds_delay: u64) {
+        self.ds_delay = ds_delay;
+    }
+
+    pub fn get_ds_delay(&self) -> u64 {
+        self.ds_delay
+    }
+}
+
+/// Keeps the keys for one zone in a directory of BIND key files, generating the first keys and
+/// scheduling rollovers. ZSKs use pre-publish, KSKs use double-signature.
+pub struct KeyManager {
+    apex: String,
+    directory: String,
+    policy: KeyPolicy
+}
+
+impl KeyManager {
+
+    pub fn new(directory: &str, apex: &str, policy: KeyPolicy) -> Self {
+        Self {
+            apex: normalize(apex),
+            directory: directory.to_string(),
+            policy
+        }
+    }
+
+    pub fn get_apex(&self) -> &str {
+        &self.apex
+    }
+
+    pub fn get_directory(&self) -> &str {
+        &self.directory
+    }
+
+    pub fn get_policy(&self) -> &KeyPolicy {
+        &self.policy
+    }
+
+    pub fn load_keys(&self) -> io::Result<Vec<DnssecKey>> {
+        let prefix = format!("K{}.+", self.apex);
+        let mut keys = Vec::new();
+
+        //THE DIRECTORY IS MADE ON THE FIRST RUN SO THE KEYS CAN BE GENERATED WHERE THE ZONE IS DEPLOYED
+        fs::create_dir_all(&self.directory)?;
+
+        for entry in fs::read_dir(&self.directory)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+
+            let stem = match file_name.strip_suffix(".key") {
+                Some(stem) if stem.starts_with(&prefix) => stem.to_string(),
+                _ => continue
+            };
+
+            let key = DnssecKey::open(&Path::new(&self.directory).join(stem).to_string_lossy())?;
+            if key.get_owner() == self.apex {
+                keys.push(key);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Brings the key set up to date for `now`, writing any generated or changed keys back to the
+    /// directory. Returns every key that hasn't been deleted yet so the signer can be swapped over
+    /// to the new set in one step.
+    pub fn run(&self, now: u64) -> io::Result<Vec<DnssecKey>> {
+        let mut keys = self.load_keys()?;
+        let mut changed = Vec::new();
+
+        //KEYS FROM OTHER TOOLS WITHOUT TIMING ARE TREATED AS ACTIVE FROM THE FIRST TIME WE SEE THEM
+        for (i, key) in keys.iter_mut().enumerate() {
+            if key.get_timing().get_activate().is_none() {
+                let activate = key.get_timing().get_created().unwrap_or(now);
+                key.get_timing_mut().set_activate(Some(activate));
+                changed.push(i);
+            }
+        }
+
+        for ksk in [true, false] {
+            if let Some(i) = self.roll(&mut keys, ksk, now)? {
+                changed.push(i);
+            }
+        }
+
+        changed.sort();
+        changed.dedup();
+
+        //SUCCESSORS ARE WRITTEN BY roll BEFORE THE RETIRING KEY SO A CRASH CAN ONLY LEAVE AN EXTRA KEY
+        for i in changed {
+            keys[i].write(&self.directory)?;
+        }
+
+        keys.retain(|key| !key.get_timing().is_deleted(now));
+        Ok(keys)
+    }
+
+    /// Generates a successor when the current key of the type is due to roll, returns the index of
+    /// the retiring key if its timing changed. The successor is pushed onto the end of the list.
+    fn roll(&self, keys: &mut Vec<DnssecKey>, ksk: bool, now: u64) -> io::Result<Option<usize>> {
+        let algorithm = self.policy.algorithm;
+        let flags = match ksk {
+            true => ZONE_KEY_FLAG | SECURE_ENTRY_POINT_FLAG,
+            false => ZONE_KEY_FLAG
+        };
+
+        let current = keys.iter()
+            .enumerate()
+            .filter(|(_, key)| key.is_ksk() == ksk && key.get_algorithm() == algorithm && !key.get_timing().is_inactive(now))
+            .max_by_key(|(_, key)| key.get_timing().get_activate().unwrap_or(0))
+            .map(|(i, _)| i);
+
+        let i = match current {
+            Some(i) => i,
+            None => {
+                let mut key = self.generate(keys, flags, now)?;
+                key.get_timing_mut().set_publish(Some(now));
+                key.get_timing_mut().set_activate(Some(now));
+                key.write(&self.directory)?;
+                keys.push(key);
+                return Ok(None);
+            }
+        };
+
+        let lifetime = match ksk {
+            true => self.policy.ksk_lifetime,
+            false => self.policy.zsk_lifetime
+        };
+
+        //ALREADY ROLLING OR ROLLOVERS DISABLED
+        if lifetime == 0 || keys[i].get_timing().get_inactive().is_some() {
+            return Ok(None);
+        }
+
+        let dnskey_ttl = keys[i].get_ttl() as u64;
+        let prepublish = dnskey_ttl + self.policy.propagation_delay;
+        let roll_at = keys[i].get_timing().get_activate().unwrap_or(now) + lifetime;
+
+        let (activate, inactive, delete) = match ksk {
+            //DOUBLE-SIGNATURE - BOTH KSKS SIGN THE DNSKEY RRSET UNTIL THE PARENT HAS SWAPPED THE DS
+            true => {
+                if now < roll_at {
+                    return Ok(None);
+                }
+
+                let retire = now + prepublish + self.policy.ds_delay;
+                (now, retire, retire)
+            }
+            //PRE-PUBLISH - THE NEW ZSK IS IN THE DNSKEY RRSET A FULL TTL BEFORE IT SIGNS ANYTHING AND THE
+            //OLD ONE STAYS PUBLISHED UNTIL ITS SIGNATURES HAVE EXPIRED FROM CACHES
+            false => {
+                if now + prepublish < roll_at {
+                    return Ok(None);
+                }
+
+                let activate = roll_at.max(now + prepublish);
+                (activate, activate, activate + self.policy.max_zone_ttl + self.policy.propagation_delay)
+            }
+        };
+
+        let mut successor = self.generate(keys, flags, now)?;
+        successor.set_ttl(keys[i].get_ttl());
+        successor.get_timing_mut().set_publish(Some(now));
+        successor.get_timing_mut().set_activate(Some(activate));
+        successor.write(&self.directory)?;
+        keys.push(successor);
+
+        keys[i].get_timing_mut().set_inactive(Some(inactive));
+        keys[i].get_timing_mut().set_delete(Some(delete));
+
+        Ok(Some(i))
+    }
+
+    /// Generates a key whose tag isn't already used by the zone so its files can't overwrite another key.
+    fn generate(&self, keys: &[DnssecKey], flags: u16, now: u64) -> io::Result<DnssecKey> {
+        loop {
+            let key = DnssecKey::generate(&self.apex, flags, self.policy.algorithm, now)?;
+            if !keys.iter().any(|k| k.get_key_tag() == key.get_key_tag()) {
+                return Ok(key);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::utils::time_utils::from_date_time;
+
+/// Key timing metadata as BIND stores it, missing times mean the event has always happened for
+/// publish / activate and never happens for inactive / delete.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyTiming {
+    created: Option<u64>,
+    publish: Option<u64>,
+    activate: Option<u64>,
+    inactive: Option<u64>,
+    delete: Option<u64>
+}
+
+impl KeyTiming {
+
+    pub fn new(created: u64, publish: u64, activate: u64) -> Self {
+        Self {
+            created: Some(created),
+            publish: Some(publish),
+            activate: Some(activate),
+            inactive: None,
+            delete: None
+        }
+    }
+
+    pub fn get_created(&self) -> Option<u64> {
+        self.created
+    }
+
+    pub fn set_created(&mut self, created: Option<u64>) {
+        self.created = created;
+    }
+
+    pub fn get_publish(&self) -> Option<u64> {
+        self.publish
+    }
+
+    pub fn set_publish(&mut self, publish: Option<u64>) {
+        self.publish = publish;
+    }
+
+    pub fn get_activate(&self) -> Option<u64> {
+        self.activate
+    }
+
+    pub fn set_activate(&mut self, activate: Option<u64>) {
+        self.activate = activate;
+    }
+
+    pub fn get_inactive(&self) -> Option<u64> {
+        self.inactive
+    }
+
+    pub fn set_inactive(&mut self, inactive: Option<u64>) {
+        self.inactive = inactive;
+    }
+
+    pub fn get_delete(&self) -> Option<u64> {
+        self.delete
+    }
+
+    pub fn set_delete(&mut self, delete: Option<u64>) {
+        self.delete = delete;
+    }
+
+    /// The key belongs in the DNSKEY RRset.
+    pub fn is_published(&self, now: u64) -> bool {
+        self.publish.map(|t| now >= t).unwrap_or(true) && !self.is_deleted(now)
+    }
+
+    /// The key signs records.
+    pub fn is_active(&self, now: u64) -> bool {
+        self.activate.map(|t| now >= t).unwrap_or(true) && !self.is_inactive(now) && self.is_published(now)
+    }
+
+    pub fn is_inactive(&self, now: u64) -> bool {
+        self.inactive.map(|t| now >= t).unwrap_or(false)
+    }
+
+    pub fn is_deleted(&self, now: u64) -> bool {
+        self.delete.map(|t| now >= t).unwrap_or(false)
+    }
+
+    /// Reads a `Key: YYYYMMDDHHmmSS` line from a `.private` or `.state` file, returns false if the
+    /// key isn't a timing field.
+    pub fn parse_line(&mut self, key: &str, value: &str) -> bool {
+        let time = value.split_whitespace().next().and_then(from_date_time);
+
+        match key {
+            "Created" | "Generated" => self.created = time,
+            "Publish" | "Published" => self.publish = time,
+            "Activate" | "Active" => self.activate = time,
+            "Inactive" | "Retired" => self.inactive = time,
+            "Delete" | "Removed" => self.delete = time,
+            _ => return false
+        }
+
+        true
+    }
+}
@@ -0,0 +1,13 @@
+pub mod inter;
+pub mod canonical;
+pub mod dnssec_key;
+pub mod ds;
+pub mod key_timing;
+pub mod key_manager;
+pub mod signer;
+pub mod nsec3;
+pub mod denial_chain;
+pub mod presentation;
+pub mod zone_signer;
+pub mod trust_anchor;
+pub mod validator;
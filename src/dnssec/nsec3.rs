@@ -0,0 +1,83 @@
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::nsec3param_record::NSec3ParamRecord;
+use crate::dnssec::canonical::name_to_wire;
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha1::Sha1;
+
+pub const NSEC3_HASH_SHA1: u8 = 1;
+pub const OPT_OUT_FLAG: u8 = 0x01;
+
+//RFC 9276 section 3.2 - validators may treat anything above this as insecure
+pub const MAX_ITERATIONS: u16 = 150;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Nsec3Params {
+    iterations: u16,
+    salt: Vec<u8>,
+    opt_out: bool
+}
+
+impl Nsec3Params {
+
+    pub fn new(iterations: u16, salt: &[u8], opt_out: bool) -> Self {
+        Self {
+            iterations: iterations.min(MAX_ITERATIONS),
+            salt: salt.to_vec(),
+            opt_out
+        }
+    }
+
+    /// Parameters of a zone signed elsewhere, the iterations aren't capped as the hashes have to
+    /// match the NSEC3 records already in the zone.
+    pub fn from_record(record: &NSec3ParamRecord, opt_out: bool) -> Self {
+        Self {
+            iterations: record.get_iterations(),
+            salt: record.get_salt().to_vec(),
+            opt_out
+        }
+    }
+
+    pub fn get_iterations(&self) -> u16 {
+        self.iterations
+    }
+
+    pub fn get_salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    pub fn is_opt_out(&self) -> bool {
+        self.opt_out
+    }
+
+    pub fn get_flags(&self) -> u8 {
+        match self.opt_out {
+            true => OPT_OUT_FLAG,
+            false => 0
+        }
+    }
+
+    //RFC 5155 section 5
+    pub fn hash_name(&self, name: &str) -> Vec<u8> {
+        let mut digest = name_to_wire(name);
+
+        for _ in 0..=self.iterations {
+            let mut hasher = Sha1::new();
+            hasher.update(&digest, 0, digest.len());
+            hasher.update(&self.salt, 0, self.salt.len());
+            digest = hasher.get_value().to_vec();
+        }
+
+        digest
+    }
+
+    pub fn to_record(&self, ttl: u32) -> Box<dyn RecordBase> {
+        //THE NSEC3PARAM FLAGS FIELD IS ALWAYS 0, OPT-OUT ONLY APPEARS ON THE NSEC3 RECORDS
+        let mut record = NSec3ParamRecord::new(ttl, RRClasses::In);
+        record.set_algorithm(NSEC3_HASH_SHA1);
+        record.set_flags(0);
+        record.set_iterations(self.iterations);
+        record.set_salt(&self.salt);
+        record.upcast()
+    }
+}
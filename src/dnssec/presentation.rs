@@ -0,0 +1,125 @@
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::cdnskey_record::CDnsKeyRecord;
+use rlibdns::records::cds_record::CdsRecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
+use rlibdns::records::ds_record::DsRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::nsec3_record::NSec3Record;
+use rlibdns::records::nsec3param_record::NSec3ParamRecord;
+use rlibdns::records::nsec_record::NSecRecord;
+use rlibdns::records::rrsig_record::RRSigRecord;
+use crate::utils::base32::encode_hex;
+use crate::utils::base64;
+use crate::utils::domain_utils::normalize;
+use crate::utils::hex::to_hex;
+use crate::utils::time_utils::to_date_time;
+
+/// Zone file mnemonic for the type, types without one use the RFC 3597 `TYPEnnn` form.
+pub fn type_to_string(_type: &RRTypes) -> String {
+    match _type {
+        RRTypes::A => "A",
+        RRTypes::Aaaa => "AAAA",
+        RRTypes::Ns => "NS",
+        RRTypes::CName => "CNAME",
+        RRTypes::Soa => "SOA",
+        RRTypes::Ptr => "PTR",
+        RRTypes::HInfo => "HINFO",
+        RRTypes::Mx => "MX",
+        RRTypes::Txt => "TXT",
+        RRTypes::Srv => "SRV",
+        RRTypes::Naptr => "NAPTR",
+        RRTypes::SshFp => "SSHFP",
+        RRTypes::Smimea => "SMIMEA",
+        RRTypes::Svcb => "SVCB",
+        RRTypes::Https => "HTTPS",
+        RRTypes::Uri => "URI",
+        RRTypes::Loc => "LOC",
+        RRTypes::DName => "DNAME",
+        RRTypes::Ds => "DS",
+        RRTypes::DnsKey => "DNSKEY",
+        RRTypes::RRSig => "RRSIG",
+        RRTypes::NSec => "NSEC",
+        RRTypes::NSec3 => "NSEC3",
+        RRTypes::NSec3Param => "NSEC3PARAM",
+        RRTypes::Cds => "CDS",
+        RRTypes::CDnsKey => "CDNSKEY",
+        _ => return format!("TYPE{}", _type.get_code())
+    }.to_string()
+}
+
+/// Writes a DNSSEC record as a zone file line with an absolute owner, returns None for types that
+/// aren't generated by the signer.
+pub fn to_zone_line(owner: &str, record: &dyn RecordBase) -> Option<String> {
+    let any = record.as_any();
+
+    let rdata = match record.get_type() {
+        RRTypes::DnsKey => {
+            let record = any.downcast_ref::<DnsKeyRecord>()?;
+            format!("{} {} {} {}", record.get_flags(), record.get_protocol(), record.get_algorithm(), base64::encode(record.get_public_key()))
+        }
+        RRTypes::CDnsKey => {
+            let record = any.downcast_ref::<CDnsKeyRecord>()?;
+            format!("{} {} {} {}", record.get_flags(), record.get_protocol(), record.get_algorithm(), base64::encode(record.get_public_key()))
+        }
+        RRTypes::Ds => {
+            let record = any.downcast_ref::<DsRecord>()?;
+            format!("{} {} {} {}", record.get_key_tag(), record.get_algorithm(), record.get_digest_type(), to_hex(record.get_digest()))
+        }
+        RRTypes::Cds => {
+            let record = any.downcast_ref::<CdsRecord>()?;
+            format!("{} {} {} {}", record.get_key_tag(), record.get_algorithm(), record.get_digest_type(), to_hex(record.get_digest()))
+        }
+        RRTypes::RRSig => {
+            let record = any.downcast_ref::<RRSigRecord>()?;
+            format!("{} {} {} {} {} {} {} {} {}",
+                    type_to_string(&record.get_type_covered()?),
+                    record.get_algorithm(),
+                    record.get_labels(),
+                    record.get_original_ttl(),
+                    to_date_time(record.get_expiration() as u64),
+                    to_date_time(record.get_inception() as u64),
+                    record.get_key_tag(),
+                    to_absolute(&record.get_signer_name()?),
+                    base64::encode(record.get_signature()))
+        }
+        RRTypes::NSec => {
+            let record = any.downcast_ref::<NSecRecord>()?;
+            format!("{} {}", to_absolute(&record.get_next_domain()?), types_to_string(record.get_types()))
+        }
+        RRTypes::NSec3 => {
+            let record = any.downcast_ref::<NSec3Record>()?;
+            format!("{} {} {} {} {} {}",
+                    record.get_algorithm(),
+                    record.get_flags(),
+                    record.get_iterations(),
+                    salt_to_string(record.get_salt()),
+                    encode_hex(record.get_next_hash()).to_uppercase(),
+                    types_to_string(record.get_types()))
+        }
+        RRTypes::NSec3Param => {
+            let record = any.downcast_ref::<NSec3ParamRecord>()?;
+            format!("{} {} {} {}", record.get_algorithm(), record.get_flags(), record.get_iterations(), salt_to_string(record.get_salt()))
+        }
+        _ => return None
+    };
+
+    Some(format!("{} {} IN {} {}", to_absolute(owner), record.get_ttl(), type_to_string(&record.get_type()), rdata))
+}
+
+fn to_absolute(name: &str) -> String {
+    format!("{}.", normalize(name))
+}
+
+//RFC 5155 section 3.3 - AN EMPTY SALT IS WRITTEN AS A SINGLE DASH
+fn salt_to_string(salt: &[u8]) -> String {
+    match salt.is_empty() {
+        true => "-".to_string(),
+        false => to_hex(salt)
+    }
+}
+
+fn types_to_string(types: &[RRTypes]) -> String {
+    let mut types = types.to_vec();
+    types.sort_by_key(|_type| _type.get_code());
+    types.iter().map(type_to_string).collect::<Vec<String>>().join(" ")
+}
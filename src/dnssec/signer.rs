@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::rrsig_record::RRSigRecord;
+use rlibdns::utils::fqdn_utils::fqdn_to_relative;
+use rlibdns::zone::zone::Zone;
+use crate::{SIGNATURE_REFRESH, SIGNATURE_VALIDITY};
+use crate::dnssec::canonical::{label_count, name_to_wire, rrset_to_wire};
+use crate::dnssec::denial_chain::{DenialChain, DenialTypes};
+use crate::dnssec::dnssec_key::DnssecKey;
+use crate::dnssec::nsec3::Nsec3Params;
+use crate::utils::domain_utils::{get_labels, is_subdomain, normalize};
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha256::Sha256;
+use crate::utils::zone_utils::{get_negative_ttl, get_soa, is_presigned};
+use crate::utils::time_utils::get_now;
+
+const INCEPTION_OFFSET: u32 = 3600;
+const MAX_CACHED_SIGNATURES: usize = 65536;
+
+type Records = Vec<(String, Box<dyn RecordBase>)>;
+type DenialChains = HashMap<(String, String), (u32, Arc<DenialChain>)>;
+
+#[derive(Default)]
+pub struct Signer {
+    keys: HashMap<String, Vec<DnssecKey>>,
+    denial: HashMap<String, DenialTypes>,
+    cache: Mutex<HashMap<[u8; 32], RRSigRecord>>,
+    chains: Mutex<DenialChains>
+}
+
+impl Signer {
+
+    pub fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+            denial: HashMap::new(),
+            cache: Mutex::new(HashMap::new()),
+            chains: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn add_key(&mut self, apex: &str, key: DnssecKey) {
+        let keys = self.keys.entry(normalize(apex)).or_default();
+        keys.retain(|k| k.get_key_tag() != key.get_key_tag() || k.get_public_key() != key.get_public_key());
+        keys.push(key);
+    }
+
+    /// Replaces every key for the zone at once, used by the key manager so a rollover step never
+    /// exposes a partial key set.
+    pub fn set_keys(&mut self, apex: &str, keys: Vec<DnssecKey>) {
+        self.keys.insert(normalize(apex), keys);
+    }
+
+    pub fn is_signed(&self, apex: &str) -> bool {
+        self.keys.get(&normalize(apex)).map(|keys| !keys.is_empty()).unwrap_or(false)
+    }
+
+    pub fn get_keys(&self, apex: &str) -> Option<&Vec<DnssecKey>> {
+        self.keys.get(&normalize(apex))
+    }
+
+    /// The DNSKEY records of the keys published for the apex, empty if we hold no keys for it.
+    pub fn get_dnskey_records(&self, apex: &str) -> Vec<Box<dyn RecordBase>> {
+        let now = get_now();
+
+        match self.keys.get(&normalize(apex)) {
+            Some(keys) => keys.iter().filter(|key| key.is_published(now)).map(|key| key.to_record()).collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// The active keys the parent should point to, the KSKs or every key if the zone only has ZSKs.
+    fn get_entry_keys(&self, apex: &str) -> Vec<&DnssecKey> {
+        let now = get_now();
+        let keys: Vec<&DnssecKey> = match self.keys.get(&normalize(apex)) {
+            Some(keys) => keys.iter().filter(|key| key.is_active(now)).collect(),
+            None => return Vec::new()
+        };
+
+        let entry_keys: Vec<&DnssecKey> = keys.iter().filter(|key| key.is_ksk()).cloned().collect();
+        match entry_keys.is_empty() {
+            true => keys,
+            false => entry_keys
+        }
+    }
+
+    pub fn get_ds_records(&self, apex: &str) -> Vec<Box<dyn RecordBase>> {
+        self.get_entry_keys(apex).iter().map(|key| key.to_ds_record()).collect()
+    }
+
+    //RFC 7344 - CDS / CDNSKEY mirror what the parent should publish so it can update the DS itself
+    pub fn get_cds_records(&self, apex: &str) -> Vec<Box<dyn RecordBase>> {
+        self.get_entry_keys(apex).iter().map(|key| key.to_cds_record()).collect()
+    }
+
+    pub fn get_cdnskey_records(&self, apex: &str) -> Vec<Box<dyn RecordBase>> {
+        self.get_entry_keys(apex).iter().map(|key| key.to_cdnskey_record()).collect()
+    }
+
+    pub fn get_ds_strings(&self, apex: &str) -> Vec<String> {
+        self.get_entry_keys(apex).iter().map(|key| key.to_ds_string()).collect()
+    }
+
+    /// Switches the zone from NSEC to NSEC3 denial of existence.
+    pub fn set_nsec3(&mut self, apex: &str, params: Nsec3Params) {
+        let apex = normalize(apex);
+        self.chains.lock().unwrap().retain(|(owner, _), _| *owner != apex);
+        self.denial.insert(apex, DenialTypes::NSec3(params));
+    }
+
+    /// Drops the chains of a zone that was loaded again, the file may have changed without the serial.
+    pub fn remove_denial_chain(&self, view: Option<&str>, apex: &str) {
+        self.chains.lock().unwrap().remove(&(normalize(apex), view.unwrap_or_default().to_string()));
+    }
+
+    pub fn get_denial(&self, apex: &str) -> DenialTypes {
+        self.denial.get(&normalize(apex)).cloned().unwrap_or(DenialTypes::NSec)
+    }
+
+    pub fn get_nsec3param_records(&self, apex: &str, zone: &Zone) -> Vec<Box<dyn RecordBase>> {
+        if !self.is_signed(apex) {
+            return Vec::new();
+        }
+
+        match (self.get_denial(apex), get_negative_ttl(zone)) {
+            (DenialTypes::NSec3(params), Some(ttl)) => vec![params.to_record(ttl)],
+            _ => Vec::new()
+        }
+    }
+
+    /// Returns the NSEC/NSEC3 chain for a signed zone, the chain is rebuilt whenever the SOA serial
+    /// changes. Zones signed offline use the chain already in the zone. Views can hold different data
+    /// under the same apex so each view keeps its own chain.
+    pub fn get_denial_chain(&self, view: Option<&str>, apex: &str, zone: &Zone) -> Option<Arc<DenialChain>> {
+        let apex = normalize(apex);
+        let signed = self.is_signed(&apex);
+        if !signed && !is_presigned(zone) {
+            return None;
+        }
+
+        let serial = get_soa(zone)?.get_serial();
+
+        let key = (apex.clone(), view.unwrap_or_default().to_string());
+
+        if let Some((s, chain)) = self.chains.lock().unwrap().get(&key) {
+            if *s == serial {
+                return Some(chain.clone());
+            }
+        }
+
+        if !signed {
+            let chain = Arc::new(DenialChain::from_zone(&apex, zone)?);
+            self.chains.lock().unwrap().insert(key, (serial, chain.clone()));
+            return Some(chain);
+        }
+
+        let denial = self.get_denial(&apex);
+        let mut extra_types = vec![
+            (apex.clone(), RRTypes::DnsKey),
+            (apex.clone(), RRTypes::Cds),
+            (apex.clone(), RRTypes::CDnsKey)
+        ];
+        if let DenialTypes::NSec3(_) = denial {
+            extra_types.push((apex.clone(), RRTypes::NSec3Param));
+        }
+
+        //CHILD ZONES WE SIGN GET A DS AT THE CUT EVEN IF THE PARENT ZONE FILE DOESN'T HAVE ONE
+        for child in self.keys.keys() {
+            if *child == apex || !is_subdomain(child, &apex) {
+                continue;
+            }
+
+            if let Some(sub) = fqdn_to_relative(&apex, child) {
+                if zone.get_records(&sub, &RRTypes::Ns).is_some() {
+                    extra_types.push((child.clone(), RRTypes::Ds));
+                }
+            }
+        }
+
+        //RFC 4034 section 4 - NSEC records use the negative caching TTL of the zone
+        let ttl = get_negative_ttl(zone)?;
+        let chain = Arc::new(DenialChain::build(&apex, zone, &denial, &extra_types, ttl));
+        self.chains.lock().unwrap().insert(key, (serial, chain.clone()));
+
+        Some(chain)
+    }
+
+    /// Signs a single RRset, the DNSKEY RRset is signed by the KSKs and everything else by the
+    /// ZSKs. Zones with only one type of key use it for everything (CSK).
+    pub fn sign_rrset(&self, apex: &str, owner: &str, records: &[&dyn RecordBase]) -> Vec<Box<dyn RecordBase>> {
+        let keys = match self.keys.get(&normalize(apex)) {
+            Some(keys) if !records.is_empty() => keys,
+            _ => return Vec::new()
+        };
+
+        let now = get_now();
+        let keys: Vec<&DnssecKey> = keys.iter().filter(|k| k.is_active(now)).collect();
+
+        let _type = records[0].get_type();
+        let want_ksk = _type == RRTypes::DnsKey;
+        let mut signing_keys: Vec<&DnssecKey> = keys.iter().filter(|k| k.is_ksk() == want_ksk).cloned().collect();
+        if signing_keys.is_empty() {
+            signing_keys = keys;
+        }
+
+        let ttl = records[0].get_ttl();
+        let rrset = match rrset_to_wire(owner, records, ttl) {
+            Some(rrset) => rrset,
+            None => return Vec::new()
+        };
+
+        let now = now as u32;
+
+        let mut signatures = Vec::with_capacity(signing_keys.len());
+
+        for key in signing_keys {
+            //RRSIG RDATA without the signature, expiration and inception are left out of the cache key
+            let mut fields = Vec::new();
+            fields.extend_from_slice(&_type.get_code().to_be_bytes());
+            fields.push(key.get_algorithm().get_code());
+            fields.push(label_count(owner));
+            fields.extend_from_slice(&ttl.to_be_bytes());
+
+            let mut hasher = Sha256::new();
+            hasher.update(&fields, 0, fields.len());
+            hasher.update(&key.get_key_tag().to_be_bytes(), 0, 2);
+            hasher.update(key.get_public_key(), 0, key.get_public_key().len());
+            hasher.update(&rrset, 0, rrset.len());
+            let cache_key = hasher.get_value();
+
+            if let Some(record) = self.cache.lock().unwrap().get(&cache_key) {
+                if record.get_expiration().wrapping_sub(now) > SIGNATURE_REFRESH {
+                    signatures.push(record.clone().upcast());
+                    continue;
+                }
+            }
+
+            let inception = now.wrapping_sub(INCEPTION_OFFSET);
+            let expiration = now.wrapping_add(SIGNATURE_VALIDITY);
+
+            let mut data = fields.clone();
+            data.extend_from_slice(&expiration.to_be_bytes());
+            data.extend_from_slice(&inception.to_be_bytes());
+            data.extend_from_slice(&key.get_key_tag().to_be_bytes());
+            data.extend(name_to_wire(key.get_owner()));
+            data.extend_from_slice(&rrset);
+
+            let mut record = RRSigRecord::new(ttl, records[0].get_class());
+            record.set_type_covered(_type);
+            record.set_algorithm(key.get_algorithm().get_code());
+            record.set_labels(label_count(owner));
+            record.set_original_ttl(ttl);
+            record.set_expiration(expiration);
+            record.set_inception(inception);
+            record.set_key_tag(key.get_key_tag());
+            record.set_signer_name(key.get_owner());
+            record.set_signature(&key.sign(&data));
+
+            let mut cache = self.cache.lock().unwrap();
+            if cache.len() >= MAX_CACHED_SIGNATURES {
+                cache.retain(|_, r| r.get_expiration().wrapping_sub(now) > SIGNATURE_REFRESH);
+                if cache.len() >= MAX_CACHED_SIGNATURES {
+                    cache.clear();
+                }
+            }
+            cache.insert(cache_key, record.clone());
+
+            signatures.push(record.upcast());
+        }
+
+        signatures
+    }
+
+    /// Groups a response section into RRsets and places the RRSIGs for every RRset of the zone
+    /// directly after it, signed with the keys of the zone. RRsets of other zones are left to be
+    /// signed with their own keys.
+    pub fn sign_records(&self, apex: &str, zone: &Zone, records: &mut Vec<(String, Box<dyn RecordBase>)>) {
+        self.sign_records_filtered(apex, zone, records, |_| true);
+    }
+
+    /// Same as `sign_records` but only RRsets whose type passes the filter are signed. RRsets that
+    /// already carry RRSIGs in the section, IE wildcard expansions, are left as they are.
+    pub fn sign_records_filtered<F: Fn(&RRTypes) -> bool>(&self, apex: &str, zone: &Zone, records: &mut Vec<(String, Box<dyn RecordBase>)>, filter: F) {
+        let signed: Vec<(String, RRTypes)> = records.iter()
+            .filter_map(|(name, record)| {
+                let covered = record.as_any().downcast_ref::<RRSigRecord>()?.get_type_covered()?;
+                Some((normalize(name), covered))
+            })
+            .collect();
+
+        let mut sets: Vec<((String, RRTypes), Records)> = Vec::new();
+
+        for (name, record) in records.drain(..) {
+            let key = (normalize(&name), record.get_type());
+            match sets.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, set)) => set.push((name, record)),
+                None => sets.push((key, vec![(name, record)]))
+            }
+        }
+
+        for ((owner, _type), set) in sets {
+            let signatures = match _type {
+                RRTypes::RRSig | RRTypes::Opt => Vec::new(),
+                _ if !filter(&_type) || signed.contains(&(owner.clone(), _type)) => Vec::new(),
+                _ if !is_in_zone(zone, apex, &owner, &_type) => Vec::new(),
+                _ => {
+                    let rrset: Vec<&dyn RecordBase> = set.iter().map(|(_, r)| r.as_ref()).collect();
+                    self.sign_rrset(apex, &owner, &rrset)
+                }
+            };
+
+            let name = set[0].0.clone();
+            records.extend(set);
+
+            for signature in signatures {
+                records.push((name.clone(), signature));
+            }
+        }
+    }
+}
+
+/// True if the RRset is part of the zone. The DS and NSEC at a cut belong to the parent, everything
+/// else at or below it to the child, and the DS at the apex to the zone above.
+fn is_in_zone(zone: &Zone, apex: &str, owner: &str, _type: &RRTypes) -> bool {
+    let sub = match fqdn_to_relative(apex, owner) {
+        Some(sub) => sub,
+        None => return false
+    };
+
+    if sub.is_empty() {
+        return *_type != RRTypes::Ds;
+    }
+
+    let labels = get_labels(&sub);
+    for i in (0..labels.len()).rev() {
+        if zone.get_records(&labels[i..].join("."), &RRTypes::Ns).is_some() {
+            return i == 0 && matches!(_type, RRTypes::Ds | RRTypes::NSec);
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::Ipv4Addr;
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::a_record::ARecord;
+    use rlibdns::records::ds_record::DsRecord;
+    use rlibdns::records::ns_record::NsRecord;
+    use rlibdns::records::soa_record::SoaRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+    use crate::dnssec::dnssec_key::{PrivateKey, SECURE_ENTRY_POINT_FLAG, ZONE_KEY_FLAG};
+    use crate::utils::crypto::p256::P256PrivateKey;
+
+    fn new_key(owner: &str, seed: u8) -> DnssecKey {
+        let private_key = P256PrivateKey::from_bytes(&[seed; 32]).unwrap();
+        DnssecKey::new(owner, ZONE_KEY_FLAG | SECURE_ENTRY_POINT_FLAG, PrivateKey::EcdsaP256(private_key))
+    }
+
+    /// `test` delegating `sub.test`, with glue for its name server below the cut.
+    fn get_parent_zone() -> Zone {
+        let mut ns = NsRecord::new(300, RRClasses::In);
+        ns.set_server("ns.sub.test.");
+
+        let mut zone = Zone::new(ZoneTypes::Master);
+        zone.add_record("sub", ns.upcast());
+        zone
+    }
+
+    fn new_address() -> Box<dyn RecordBase> {
+        let mut record = ARecord::new(300, RRClasses::In);
+        record.set_address(Ipv4Addr::new(192, 0, 2, 1));
+        record.upcast()
+    }
+
+    fn new_section(records: &[(&str, Box<dyn RecordBase>)]) -> Vec<(String, Box<dyn RecordBase>)> {
+        records.iter().map(|(owner, record)| (owner.to_string(), record.clone())).collect()
+    }
+
+    /// The owner, type covered and signer name of every RRSIG in the section.
+    fn get_signatures(records: &[(String, Box<dyn RecordBase>)]) -> Vec<(String, RRTypes, String)> {
+        records.iter()
+            .filter_map(|(owner, record)| {
+                let record = record.as_any().downcast_ref::<RRSigRecord>()?;
+                Some((owner.clone(), record.get_type_covered()?, normalize(&record.get_signer_name()?)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cut() {
+        let mut signer = Signer::new();
+        signer.add_key("test", new_key("test", 1));
+        signer.add_key("sub.test", new_key("sub.test", 2));
+
+        let mut ns = NsRecord::new(300, RRClasses::In);
+        ns.set_server("ns.sub.test.");
+
+        let mut records = new_section(&[
+            ("www.test", new_address()),
+            ("sub.test", ns.upcast()),
+            ("sub.test", DsRecord::new(300, RRClasses::In).upcast()),
+            ("ns.sub.test", new_address())
+        ]);
+        signer.sign_records("test", &get_parent_zone(), &mut records);
+
+        //THE NS RRSET AND GLUE BELONG TO THE CHILD, ONLY ITS OWN ANSWERS ARE SIGNED WITH ITS KEYS
+        assert_eq!(get_signatures(&records), vec![
+            ("www.test".to_string(), RRTypes::A, "test".to_string()),
+            ("sub.test".to_string(), RRTypes::Ds, "test".to_string())
+        ]);
+    }
+
+    #[test]
+    fn answering_zone() {
+        let mut signer = Signer::new();
+        signer.add_key("test", new_key("test", 1));
+
+        //WE HOST THE CHILD AS WELL BUT ONLY THE PARENT IS SIGNED, ITS KEYS DON'T COVER THE CHILD
+        let mut records = new_section(&[("www.sub.test", new_address())]);
+        signer.sign_records("sub.test", &Zone::new(ZoneTypes::Master), &mut records);
+        assert!(get_signatures(&records).is_empty());
+
+        signer.add_key("sub.test", new_key("sub.test", 2));
+        signer.sign_records("sub.test", &Zone::new(ZoneTypes::Master), &mut records);
+        assert_eq!(get_signatures(&records), vec![("www.sub.test".to_string(), RRTypes::A, "sub.test".to_string())]);
+
+        //RRSETS OUTSIDE THE ZONE ARE LEFT FOR THEIR OWN ZONE TO SIGN
+        let mut records = new_section(&[("www.other", new_address())]);
+        signer.sign_records("test", &Zone::new(ZoneTypes::Master), &mut records);
+        assert!(get_signatures(&records).is_empty());
+    }
+
+    #[test]
+    fn apex_ds() {
+        let mut signer = Signer::new();
+        signer.add_key("sub.test", new_key("sub.test", 2));
+
+        let mut records = new_section(&[("sub.test", DsRecord::new(300, RRClasses::In).upcast())]);
+        signer.sign_records("sub.test", &Zone::new(ZoneTypes::Master), &mut records);
+        assert!(get_signatures(&records).is_empty());
+    }
+
+    #[test]
+    fn view_chains() {
+        let mut signer = Signer::new();
+        signer.add_key("test", new_key("test", 1));
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.test.");
+        soa.set_mailbox("hostmaster.test.");
+        soa.set_serial(1);
+
+        let mut zone = Zone::new(ZoneTypes::Master);
+        zone.add_record("", soa.upcast());
+        zone.add_record("www", new_address());
+
+        let chain = signer.get_denial_chain(None, "test", &zone).unwrap();
+        let internal = signer.get_denial_chain(Some("internal"), "test", &zone).unwrap();
+        assert!(!Arc::ptr_eq(&chain, &internal));
+        assert!(Arc::ptr_eq(&chain, &signer.get_denial_chain(None, "test", &zone).unwrap()));
+
+        //A ZONE LOADED AGAIN GETS A NEW CHAIN EVEN WITH THE SAME SERIAL, THE OTHER VIEWS KEEP THEIRS
+        signer.remove_denial_chain(None, "test");
+        assert!(!Arc::ptr_eq(&chain, &signer.get_denial_chain(None, "test", &zone).unwrap()));
+        assert!(Arc::ptr_eq(&internal, &signer.get_denial_chain(Some("internal"), "test", &zone).unwrap()));
+    }
+}
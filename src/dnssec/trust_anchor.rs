@@ -0,0 +1,280 @@
+use std::fs;
+use std::io;
+use crate::dnssec::canonical::name_to_wire;
+use crate::dnssec::dnssec_key::{calculate_key_tag, SECURE_ENTRY_POINT_FLAG};
+use crate::dnssec::ds::{calculate_digest, DS_DIGEST_SHA256};
+use crate::utils::base64;
+use crate::utils::domain_utils::{is_subdomain, normalize};
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha1::Sha1;
+use crate::utils::hex::from_hex;
+use crate::utils::time_utils::{from_date_time, to_date_time};
+
+pub const REVOKE_FLAG: u16 = 0x0080;
+pub const DS_DIGEST_SHA1: u8 = 1;
+
+//RFC 5011 section 2.4.1 - A NEW KEY HAS TO BE SEEN FOR 30 DAYS BEFORE IT IS TRUSTED
+pub const ADD_HOLD_DOWN: u64 = 2592000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnchorTypes {
+    Ds {
+        key_tag: u16,
+        algorithm: u8,
+        digest_type: u8,
+        digest: Vec<u8>
+    },
+    DnsKey(Vec<u8>)
+}
+
+/// A key we trust without a chain above it, IE the root KSK. Keys learned through RFC 5011 stay
+/// pending until the add hold-down has passed.
+#[derive(Clone, Debug)]
+pub struct TrustAnchor {
+    owner: String,
+    _type: AnchorTypes,
+    pending: Option<u64>
+}
+
+impl TrustAnchor {
+
+    pub fn new(owner: &str, _type: AnchorTypes) -> Self {
+        Self {
+            owner: normalize(owner),
+            _type,
+            pending: None
+        }
+    }
+
+    /// Reads a DS or DNSKEY line in zone file format, IE
+    /// `. IN DS 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D`. Keys still
+    /// in their hold-down carry a `; PENDING <time>` comment.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(2, ';');
+        let record = parts.next().unwrap_or("").trim();
+        let comment = parts.next().unwrap_or("").trim();
+
+        let tokens: Vec<&str> = record.split_whitespace().collect();
+        let owner = tokens.first()?;
+        let i = tokens.iter().position(|t| t.eq_ignore_ascii_case("DS") || t.eq_ignore_ascii_case("DNSKEY"))?;
+
+        let _type = match tokens[i].eq_ignore_ascii_case("DS") {
+            true => AnchorTypes::Ds {
+                key_tag: tokens.get(i + 1)?.parse().ok()?,
+                algorithm: tokens.get(i + 2)?.parse().ok()?,
+                digest_type: tokens.get(i + 3)?.parse().ok()?,
+                digest: from_hex(&tokens[i + 4..].concat())?
+            },
+            false => {
+                let mut rdata = Vec::new();
+                rdata.extend_from_slice(&tokens.get(i + 1)?.parse::<u16>().ok()?.to_be_bytes());
+                rdata.push(tokens.get(i + 2)?.parse().ok()?);
+                rdata.push(tokens.get(i + 3)?.parse().ok()?);
+                rdata.extend(base64::decode(&tokens[i + 4..].concat())?);
+                AnchorTypes::DnsKey(rdata)
+            }
+        };
+
+        let mut anchor = Self::new(owner, _type);
+        if let Some(since) = comment.strip_prefix("PENDING") {
+            anchor.pending = Some(from_date_time(since)?);
+        }
+
+        Some(anchor)
+    }
+
+    pub fn to_line(&self) -> String {
+        let line = match &self._type {
+            AnchorTypes::Ds { key_tag, algorithm, digest_type, digest } => {
+                let digest: String = digest.iter().map(|b| format!("{:02X}", b)).collect();
+                format!("{}. IN DS {} {} {} {}", self.owner, key_tag, algorithm, digest_type, digest)
+            }
+            AnchorTypes::DnsKey(rdata) => format!("{}. IN DNSKEY {} {} {} {}", self.owner,
+                                                  u16::from_be_bytes([rdata[0], rdata[1]]), rdata[2], rdata[3], base64::encode(&rdata[4..]))
+        };
+
+        match self.pending {
+            Some(since) => format!("{} ; PENDING {}", line, to_date_time(since)),
+            None => line
+        }
+    }
+
+    pub fn get_owner(&self) -> &str {
+        &self.owner
+    }
+
+    pub fn get_type(&self) -> &AnchorTypes {
+        &self._type
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Whether the DNSKEY, given as its RDATA, is the key this anchor describes.
+    pub fn matches(&self, owner: &str, rdata: &[u8]) -> bool {
+        if normalize(owner) != self.owner || rdata.len() < 4 {
+            return false;
+        }
+
+        match &self._type {
+            AnchorTypes::Ds { key_tag, algorithm, digest_type, digest } => {
+                if *key_tag != calculate_key_tag(rdata) || *algorithm != rdata[3] {
+                    return false;
+                }
+
+                match *digest_type {
+                    DS_DIGEST_SHA1 => *digest == calculate_sha1_digest(owner, rdata),
+                    DS_DIGEST_SHA256 => *digest == calculate_digest(owner, rdata),
+                    _ => false
+                }
+            }
+            AnchorTypes::DnsKey(key) => key == rdata
+        }
+    }
+}
+
+/// The trust anchors the validator starts its chains from. When opened from a file the anchors are
+/// written back whenever RFC 5011 adds or revokes a key, so a restart doesn't lose a rollover.
+#[derive(Default)]
+pub struct TrustAnchors {
+    anchors: Vec<TrustAnchor>,
+    file_path: Option<String>
+}
+
+impl TrustAnchors {
+
+    pub fn new() -> Self {
+        Self {
+            anchors: Vec::new(),
+            file_path: None
+        }
+    }
+
+    pub fn open(file_path: &str) -> io::Result<Self> {
+        let mut anchors = Vec::new();
+
+        for line in fs::read_to_string(file_path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            anchors.push(TrustAnchor::from_line(line)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid trust anchor: {}", line)))?);
+        }
+
+        if anchors.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Trust anchor file has no anchors"));
+        }
+
+        Ok(Self {
+            anchors,
+            file_path: Some(file_path.to_string())
+        })
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        let file_path = match &self.file_path {
+            Some(file_path) => file_path,
+            None => return Ok(())
+        };
+
+        let mut buf = String::from("; Trust anchors, kept up to date following RFC 5011\n");
+        for anchor in &self.anchors {
+            buf.push_str(&anchor.to_line());
+            buf.push('\n');
+        }
+
+        let tmp = format!("{}.tmp", file_path);
+        fs::write(&tmp, buf)?;
+        fs::rename(&tmp, file_path)
+    }
+
+    pub fn add(&mut self, anchor: TrustAnchor) {
+        self.anchors.push(anchor);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.anchors.iter().any(|anchor| !anchor.is_pending())
+    }
+
+    pub fn get_anchors(&self) -> &Vec<TrustAnchor> {
+        &self.anchors
+    }
+
+    /// The deepest domain with a trusted anchor that the name falls under.
+    pub fn find_anchor(&self, name: &str) -> Option<String> {
+        self.anchors.iter()
+            .filter(|anchor| !anchor.is_pending() && is_subdomain(name, anchor.get_owner()))
+            .map(|anchor| anchor.get_owner().to_string())
+            .max_by_key(|owner| owner.len())
+    }
+
+    pub fn is_trusted(&self, owner: &str, rdata: &[u8]) -> bool {
+        self.anchors.iter().any(|anchor| !anchor.is_pending() && anchor.matches(owner, rdata))
+    }
+
+    /// RFC 5011 section 2 - takes in a DNSKEY RRset that was validated against the anchors. Revoked
+    /// keys that signed the RRset are dropped, new SEP keys start their hold-down and become trusted
+    /// once it has passed. Returns whether anything changed.
+    pub fn update(&mut self, owner: &str, keys: &[Vec<u8>], revoked: &[Vec<u8>], now: u64) -> bool {
+        let owner = normalize(owner);
+        let len = self.anchors.len();
+        let mut changed = false;
+
+        for key in revoked {
+            let mut original = key.clone();
+            original[..2].copy_from_slice(&(u16::from_be_bytes([key[0], key[1]]) & !REVOKE_FLAG).to_be_bytes());
+            self.anchors.retain(|anchor| !anchor.matches(&owner, &original) && !anchor.matches(&owner, key));
+        }
+
+        for key in keys {
+            let flags = u16::from_be_bytes([key[0], key[1]]);
+            if flags & SECURE_ENTRY_POINT_FLAG == 0 || flags & REVOKE_FLAG != 0 {
+                continue;
+            }
+
+            let anchor = self.anchors.iter_mut()
+                .find(|anchor| matches!(anchor.get_type(), AnchorTypes::DnsKey(_)) && anchor.matches(&owner, key));
+
+            match anchor {
+                Some(anchor) => {
+                    if let Some(since) = anchor.pending {
+                        if now >= since + ADD_HOLD_DOWN {
+                            anchor.pending = None;
+                            changed = true;
+                        }
+                    }
+                }
+                None => {
+                    //A KEY WE ALREADY TRUST THROUGH A DS IS TRACKED AS A KEY SO IT CAN BE REVOKED
+                    let mut anchor = TrustAnchor::new(&owner, AnchorTypes::DnsKey(key.clone()));
+                    if !self.is_trusted(&owner, key) {
+                        anchor.pending = Some(now);
+                    }
+                    self.anchors.push(anchor);
+                    changed = true;
+                }
+            }
+        }
+
+        //A PENDING KEY THAT LEAVES THE RRSET HAS TO START ITS HOLD-DOWN OVER
+        self.anchors.retain(|anchor| match (anchor.is_pending(), anchor.get_type()) {
+            (true, AnchorTypes::DnsKey(rdata)) if anchor.get_owner() == owner => keys.contains(rdata),
+            _ => true
+        });
+
+        changed || self.anchors.len() != len
+    }
+}
+
+//RFC 4034 section 5.1.4 - SHA-1 DIGESTS ARE STILL FOUND IN OLDER ANCHORS
+fn calculate_sha1_digest(owner: &str, rdata: &[u8]) -> Vec<u8> {
+    let owner = name_to_wire(owner);
+
+    let mut hasher = Sha1::new();
+    hasher.update(&owner, 0, owner.len());
+    hasher.update(rdata, 0, rdata.len());
+    hasher.get_value().to_vec()
+}
@@ -0,0 +1,1252 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use rlibdns::messages::dns_query::DnsQuery;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_classes::RRClasses;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::cname_record::CNameRecord;
+use rlibdns::records::dname_record::DNameRecord;
+use rlibdns::records::dnskey_record::DnsKeyRecord;
+use rlibdns::records::ds_record::DsRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::nsec3_record::NSec3Record;
+use rlibdns::records::nsec_record::NSecRecord;
+use rlibdns::records::rrsig_record::RRSigRecord;
+use crate::dns::dns::ResponseResult;
+use crate::dnssec::canonical::{compare_names, label_count, name_to_wire, rrset_to_wire};
+use crate::dnssec::dnssec_key::{calculate_key_tag, DNSKEY_PROTOCOL, ZONE_KEY_FLAG};
+use crate::dnssec::ds::{get_dnskey_rdata, DS_DIGEST_SHA256};
+use crate::dnssec::inter::dnssec_algorithms::DnssecAlgorithms;
+use crate::dnssec::nsec3::{Nsec3Params, MAX_ITERATIONS, NSEC3_HASH_SHA1, OPT_OUT_FLAG};
+use crate::dnssec::trust_anchor::{AnchorTypes, TrustAnchor, TrustAnchors, DS_DIGEST_SHA1, REVOKE_FLAG};
+use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::base32::decode_hex;
+use crate::utils::crypto::{ed25519, p256, rsa};
+use crate::utils::domain_utils::{get_labels, get_parent, is_subdomain, normalize};
+use crate::{MAX_CACHE_TTL, MAX_CNAME_CHAIN_SIZE};
+use crate::utils::time_utils::get_now;
+
+//RFC 8914 section 4
+pub const EDE_DNSSEC_BOGUS: u16 = 6;
+pub const EDE_SIGNATURE_EXPIRED: u16 = 7;
+pub const EDE_SIGNATURE_NOT_YET_VALID: u16 = 8;
+pub const EDE_DNSKEY_MISSING: u16 = 9;
+pub const EDE_RRSIGS_MISSING: u16 = 10;
+pub const EDE_NSEC_MISSING: u16 = 12;
+
+//RFC 5702 - THE SIGNER ONLY USES THE CURVES, OLDER ZONES ARE STILL SIGNED WITH RSA
+pub const ALGORITHM_RSASHA256: u8 = 8;
+
+const MAX_CUTS: usize = 10000;
+
+/// RFC 4033 section 5 - what a response or zone was found to be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SecurityStates {
+    Secure,
+    Insecure,
+    Bogus(u16),
+    Indeterminate
+}
+
+#[derive(Clone, Debug)]
+enum CutStates {
+    Secure(Vec<Vec<u8>>),
+    Insecure,
+    NotCut,
+    NoName
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Proofs {
+    NoName,
+    NoData(bool),
+    OptOut,
+    Insecure,
+    Missing
+}
+
+type Resolve<'a> = &'a dyn Fn(&mut RequestEvent) -> ResponseResult<()>;
+type ZoneKeys = (String, Vec<Vec<u8>>);
+
+/// Validates the responses we resolve for clients, forwarded or recursive, RFC 4035 section 5. The
+/// chain of trust is built from the trust anchors down through each DS and DNSKEY, secure answers get
+/// the AD bit and bogus ones become SERVFAIL with an Extended DNS Error. Names under a negative trust
+/// anchor are treated as unsigned, RFC 7646.
+pub struct Validator {
+    anchors: Mutex<TrustAnchors>,
+    negative_anchors: Vec<String>,
+    cuts: Mutex<HashMap<String, (CutStates, u64)>>
+}
+
+impl Validator {
+
+    pub fn new() -> Self {
+        Self {
+            anchors: Mutex::new(TrustAnchors::new()),
+            negative_anchors: Vec::new(),
+            cuts: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn set_trust_anchors(&mut self, anchors: TrustAnchors) {
+        *self.anchors.get_mut().unwrap() = anchors;
+        self.cuts.get_mut().unwrap().clear();
+    }
+
+    /// Validation is off until a trust anchor is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.anchors.lock().unwrap().is_empty()
+    }
+
+    pub fn add_negative_anchor(&mut self, domain: &str) {
+        let domain = normalize(domain);
+        if !self.negative_anchors.contains(&domain) {
+            self.negative_anchors.push(domain);
+        }
+    }
+
+    pub fn remove_negative_anchor(&mut self, domain: &str) {
+        let domain = normalize(domain);
+        self.negative_anchors.retain(|anchor| *anchor != domain);
+    }
+
+    pub fn get_negative_anchors(&self) -> &Vec<String> {
+        &self.negative_anchors
+    }
+
+    pub fn is_negative(&self, name: &str) -> bool {
+        self.negative_anchors.iter().any(|domain| is_subdomain(name, domain))
+    }
+
+    /// Drops the zone cuts and keys learned so far, they are fetched again when next needed.
+    pub fn flush(&self) {
+        self.cuts.lock().unwrap().clear();
+    }
+
+    /// Resolves the query and validates the response. Secure answers are marked authenticated, bogus
+    /// ones are replaced by SERVFAIL with the reason as an Extended DNS Error.
+    pub fn validate(&self, event: &mut RequestEvent, resolve: Resolve) -> ResponseResult<()> {
+        let response = resolve(event);
+
+        if !self.is_enabled() || self.is_negative(event.get_query().get_fqdn()) {
+            return response;
+        }
+
+        let nxdomain = match response {
+            Ok(_) => false,
+            Err(ResponseCodes::NxDomain) => true,
+            Err(_) => return response
+        };
+
+        let state = self.check_response(event, nxdomain, resolve);
+        if matches!(state, SecurityStates::Bogus(_) | SecurityStates::Indeterminate) {
+            for section in event.response_records.iter_mut() {
+                section.clear();
+            }
+        }
+
+        match state {
+            SecurityStates::Secure => {
+                event.set_authenticated_data(true);
+                response
+            }
+            SecurityStates::Insecure => response,
+            SecurityStates::Bogus(code) => {
+                event.set_extended_error(code);
+                Err(ResponseCodes::ServFail)
+            }
+            SecurityStates::Indeterminate => Err(ResponseCodes::ServFail)
+        }
+    }
+
+    fn check_response(&self, event: &RequestEvent, nxdomain: bool, resolve: Resolve) -> SecurityStates {
+        let _type = event.get_query().get_type();
+        let answers = event.get_answers();
+        let authority = event.get_authority_records();
+        let mut state = SecurityStates::Secure;
+
+        for (owner, rr_type) in get_rrsets(answers) {
+            let records = get_rrset(answers, &owner, rr_type);
+            let signatures = get_signatures(answers, &owner, rr_type);
+
+            //RFC 6672 section 5.3.3 - THE CNAME SYNTHESIZED FROM A DNAME ISN'T SIGNED, THE DNAME IS
+            if rr_type == RRTypes::CName && signatures.is_empty() && is_synthesized(answers, &owner, &records) {
+                continue;
+            }
+
+            let signer = signatures.first()
+                .and_then(|signature| signature.get_signer_name())
+                .map(|signer| normalize(&signer));
+
+            let (zone, keys) = match self.get_zone(signer.as_deref().unwrap_or(&owner), resolve) {
+                Ok(Some(zone)) => zone,
+                Ok(None) => {
+                    state = SecurityStates::Insecure;
+                    continue;
+                }
+                Err(state) => return state
+            };
+
+            //THE SIGNER HAS TO BE THE ZONE THE RECORDS ARE IN
+            if let Some(signer) = &signer {
+                if *signer != zone || !is_subdomain(&owner, signer) {
+                    return SecurityStates::Bogus(EDE_DNSKEY_MISSING);
+                }
+            }
+
+            match self.verify_rrset(&owner, &records, &signatures, &zone, &keys) {
+                SecurityStates::Secure => {}
+                SecurityStates::Insecure => state = SecurityStates::Insecure,
+                state => return state
+            }
+
+            //RFC 4035 section 5.3.4 - AN EXPANDED WILDCARD NEEDS PROOF THAT THE NAME ITSELF DOESN'T EXIST
+            let labels = signatures.iter().map(|signature| signature.get_labels()).min().unwrap_or(0);
+            if labels < label_count(&owner) {
+                match self.check_wildcard(authority, &owner, labels, &zone, &keys) {
+                    Proofs::NoName | Proofs::OptOut => {}
+                    Proofs::Insecure => state = SecurityStates::Insecure,
+                    _ => return SecurityStates::Bogus(EDE_NSEC_MISSING)
+                }
+            }
+        }
+
+        let target = get_target(answers, &normalize(event.get_query().get_fqdn()), _type);
+        let answered = answers.iter().any(|(owner, record)| normalize(owner) == target && record.get_type() == _type);
+
+        if nxdomain || !answered {
+            //THE SIGNER OF THE PROOF TELLS US WHICH ZONE TO LOOK FOR, A WRONG ONE FAILS VERIFICATION
+            let signer = authority.iter()
+                .filter_map(|(_, record)| record.as_any().downcast_ref::<RRSigRecord>())
+                .filter_map(|signature| signature.get_signer_name())
+                .map(|signer| normalize(&signer))
+                .find(|signer| is_subdomain(&target, signer));
+
+            let (zone, keys) = match self.get_zone(signer.as_deref().unwrap_or(&target), resolve) {
+                Ok(Some(zone)) => zone,
+                Ok(None) => return SecurityStates::Insecure,
+                Err(state) => return state
+            };
+
+            match (self.check_denial(authority, &target, _type, &zone, &keys), nxdomain) {
+                (Proofs::NoName, true) | (Proofs::NoData(_), false) => {}
+                (Proofs::OptOut, _) | (Proofs::Insecure, _) => state = SecurityStates::Insecure,
+                _ => return SecurityStates::Bogus(EDE_NSEC_MISSING)
+            }
+        }
+
+        state
+    }
+
+    /// Walks down from the closest trust anchor to the zone the name is in and returns it along with
+    /// its keys, None if the chain of trust ends in an unsigned delegation on the way.
+    fn get_zone(&self, name: &str, resolve: Resolve) -> Result<Option<ZoneKeys>, SecurityStates> {
+        let name = normalize(name);
+        if self.is_negative(&name) {
+            return Ok(None);
+        }
+
+        let anchor = self.anchors.lock().unwrap().find_anchor(&name);
+        let mut apex = match anchor {
+            Some(anchor) => anchor,
+            None => return Ok(None)
+        };
+
+        let mut keys = self.get_anchor_keys(&apex, resolve)?;
+
+        //EVERY NAME BETWEEN THE ANCHOR AND THE NAME MAY BE A ZONE CUT, IE com THEN example.com
+        let labels = get_labels(&name);
+        let depth = get_labels(&apex).len();
+
+        for i in (0..labels.len() - depth).rev() {
+            let child = labels[i..].join(".");
+
+            match self.get_cut(&apex, &keys, &child, resolve)? {
+                CutStates::Secure(child_keys) => {
+                    apex = child;
+                    keys = child_keys;
+                }
+                CutStates::Insecure => return Ok(None),
+                CutStates::NotCut => {}
+                CutStates::NoName => break
+            }
+        }
+
+        Ok(Some((apex, keys)))
+    }
+
+    /// Validates the DNSKEY RRset at a trust anchor and follows RFC 5011 for any keys that were added
+    /// or revoked since.
+    fn get_anchor_keys(&self, anchor: &str, resolve: Resolve) -> Result<Vec<Vec<u8>>, SecurityStates> {
+        if let Some(CutStates::Secure(keys)) = self.get_cached(anchor) {
+            return Ok(keys);
+        }
+
+        let (event, _) = fetch(resolve, anchor, RRTypes::DnsKey)?;
+        let mut anchors = self.anchors.lock().unwrap();
+        let keys = self.verify_keys(&event, anchor, |rdata| anchors.is_trusted(anchor, rdata))?;
+
+        //RFC 5011 section 2.1 - A KEY IS REVOKED BY SIGNING THE RRSET WITH THE REVOKE BIT SET
+        let records = get_rrset(event.get_answers(), anchor, RRTypes::DnsKey);
+        let signatures = get_signatures(event.get_answers(), anchor, RRTypes::DnsKey);
+        let rdatas = get_key_rdatas(&records);
+
+        let revoked: Vec<Vec<u8>> = rdatas.iter()
+            .filter(|rdata| u16::from_be_bytes([rdata[0], rdata[1]]) & REVOKE_FLAG != 0)
+            .filter(|rdata| self.verify_rrset(anchor, &records, &signatures, anchor, &[rdata.to_vec()]) == SecurityStates::Secure)
+            .cloned()
+            .collect();
+
+        if anchors.update(anchor, &rdatas, &revoked, get_now()) {
+            if let Err(e) = anchors.write() {
+                println!("Writing the trust anchors for {} failed: {}", anchor, e);
+            }
+        }
+
+        self.set_cached(anchor, CutStates::Secure(keys.clone()), get_ttl(&event));
+        Ok(keys)
+    }
+
+    /// Whether the child is a zone cut below the apex, and if it is a signed one its keys. The DS is
+    /// asked for and either validated or its absence proven.
+    fn get_cut(&self, apex: &str, keys: &[Vec<u8>], child: &str, resolve: Resolve) -> Result<CutStates, SecurityStates> {
+        if self.is_negative(child) {
+            return Ok(CutStates::Insecure);
+        }
+
+        if let Some(state) = self.get_cached(child) {
+            return Ok(state);
+        }
+
+        let (event, nxdomain) = fetch(resolve, child, RRTypes::Ds)?;
+        let answers = event.get_answers();
+        let records = get_rrset(answers, child, RRTypes::Ds);
+
+        if records.is_empty() {
+            let state = match answers.iter().any(|(owner, record)| normalize(owner) == child && record.get_type() == RRTypes::CName) {
+                true => CutStates::NotCut,
+                false => match self.check_denial(event.get_authority_records(), child, RRTypes::Ds, apex, keys) {
+                    Proofs::NoName if nxdomain => CutStates::NoName,
+                    Proofs::NoData(true) | Proofs::OptOut | Proofs::Insecure => CutStates::Insecure,
+                    Proofs::NoData(false) => CutStates::NotCut,
+                    _ => return Err(SecurityStates::Bogus(EDE_NSEC_MISSING))
+                }
+            };
+
+            self.set_cached(child, state.clone(), get_ttl(&event));
+            return Ok(state);
+        }
+
+        match self.verify_rrset(child, &records, &get_signatures(answers, child, RRTypes::Ds), apex, keys) {
+            SecurityStates::Secure => {}
+            SecurityStates::Insecure => return Ok(CutStates::Insecure),
+            state => return Err(state)
+        }
+
+        //RFC 4035 section 5.2 - A DS RRSET WITHOUT AN ALGORITHM AND DIGEST WE SUPPORT MAKES THE ZONE INSECURE
+        let anchors: Vec<TrustAnchor> = records.iter()
+            .filter_map(|record| record.as_any().downcast_ref::<DsRecord>())
+            .filter(|record| is_supported_algorithm(record.get_algorithm()) && matches!(record.get_digest_type(), DS_DIGEST_SHA1 | DS_DIGEST_SHA256))
+            .map(|record| TrustAnchor::new(child, AnchorTypes::Ds {
+                key_tag: record.get_key_tag(),
+                algorithm: record.get_algorithm(),
+                digest_type: record.get_digest_type(),
+                digest: record.get_digest().to_vec()
+            }))
+            .collect();
+
+        if anchors.is_empty() {
+            self.set_cached(child, CutStates::Insecure, get_ttl(&event));
+            return Ok(CutStates::Insecure);
+        }
+
+        let (key_event, _) = fetch(resolve, child, RRTypes::DnsKey)?;
+        let keys = self.verify_keys(&key_event, child, |rdata| anchors.iter().any(|anchor| anchor.matches(child, rdata)))?;
+
+        let state = CutStates::Secure(keys);
+        self.set_cached(child, state.clone(), get_ttl(&event).min(get_ttl(&key_event)));
+        Ok(state)
+    }
+
+    /// Validates the DNSKEY RRset of the zone with the keys that are trusted, returning the zone keys
+    /// that can be used for the rest of the zone.
+    fn verify_keys<F>(&self, event: &RequestEvent, apex: &str, trusted: F) -> Result<Vec<Vec<u8>>, SecurityStates>
+    where
+        F: Fn(&[u8]) -> bool
+    {
+        let records = get_rrset(event.get_answers(), apex, RRTypes::DnsKey);
+        let rdatas: Vec<Vec<u8>> = get_key_rdatas(&records).into_iter().filter(|rdata| is_zone_key(rdata)).collect();
+
+        let trusted: Vec<Vec<u8>> = rdatas.iter().filter(|rdata| trusted(rdata)).cloned().collect();
+        if trusted.is_empty() {
+            return Err(SecurityStates::Bogus(EDE_DNSKEY_MISSING));
+        }
+
+        match self.verify_rrset(apex, &records, &get_signatures(event.get_answers(), apex, RRTypes::DnsKey), apex, &trusted) {
+            SecurityStates::Secure => Ok(rdatas),
+            SecurityStates::Insecure => Err(SecurityStates::Bogus(EDE_DNSSEC_BOGUS)),
+            state => Err(state)
+        }
+    }
+
+    /// RFC 4035 section 5.3 - checks the RRSIGs of the RRset against the keys of the zone, any one
+    /// that verifies within its validity period is enough. An RRset we can't put in canonical form is
+    /// insecure rather than bogus.
+    fn verify_rrset(&self, owner: &str, records: &[&dyn RecordBase], signatures: &[&RRSigRecord], zone: &str, keys: &[Vec<u8>]) -> SecurityStates {
+        if records.is_empty() || signatures.is_empty() {
+            return SecurityStates::Bogus(EDE_RRSIGS_MISSING);
+        }
+
+        let _type = records[0].get_type();
+        let labels = get_labels(owner);
+        let owner_labels = label_count(owner);
+        let now = get_now() as u32;
+        let mut error = EDE_DNSSEC_BOGUS;
+
+        for signature in signatures {
+            if signature.get_signer_name().map(|signer| normalize(&signer)).as_deref() != Some(zone) || signature.get_labels() > owner_labels {
+                continue;
+            }
+
+            //RFC 4034 section 3.1.5 - SERIAL NUMBER ARITHMETIC, THE TIMES WRAP AROUND IN 2106
+            if now.wrapping_sub(signature.get_expiration()) as i32 > 0 {
+                error = EDE_SIGNATURE_EXPIRED;
+                continue;
+            }
+
+            if signature.get_inception().wrapping_sub(now) as i32 > 0 {
+                error = EDE_SIGNATURE_NOT_YET_VALID;
+                continue;
+            }
+
+            //A WILDCARD EXPANSION IS SIGNED AS THE WILDCARD, RFC 4035 section 5.3.2
+            let signed_owner = match signature.get_labels() < owner_labels {
+                true => to_wildcard(&labels[labels.len() - signature.get_labels() as usize..].join(".")),
+                false => owner.to_string()
+            };
+
+            let rrset = match rrset_to_wire(&signed_owner, records, signature.get_original_ttl()) {
+                Some(rrset) => rrset,
+                None => return SecurityStates::Insecure
+            };
+
+            let mut data = Vec::new();
+            data.extend_from_slice(&_type.get_code().to_be_bytes());
+            data.push(signature.get_algorithm());
+            data.push(signature.get_labels());
+            data.extend_from_slice(&signature.get_original_ttl().to_be_bytes());
+            data.extend_from_slice(&signature.get_expiration().to_be_bytes());
+            data.extend_from_slice(&signature.get_inception().to_be_bytes());
+            data.extend_from_slice(&signature.get_key_tag().to_be_bytes());
+            data.extend(name_to_wire(zone));
+            data.extend(rrset);
+
+            let candidates: Vec<&Vec<u8>> = keys.iter()
+                .filter(|key| key[3] == signature.get_algorithm() && calculate_key_tag(key) == signature.get_key_tag())
+                .collect();
+
+            if candidates.is_empty() {
+                error = EDE_DNSKEY_MISSING;
+                continue;
+            }
+
+            if candidates.iter().any(|key| verify_signature(signature.get_algorithm(), &key[4..], &data, signature.get_signature())) {
+                return SecurityStates::Secure;
+            }
+
+            error = EDE_DNSSEC_BOGUS;
+        }
+
+        SecurityStates::Bogus(error)
+    }
+
+    /// What the NSEC or NSEC3 records of the zone prove about the name, only records that verify are
+    /// taken into account.
+    fn check_denial(&self, records: &[(String, Box<dyn RecordBase>)], name: &str, _type: RRTypes, zone: &str, keys: &[Vec<u8>]) -> Proofs {
+        let nsecs: Vec<(String, &NSecRecord)> = self.get_verified(records, RRTypes::NSec, zone, keys).into_iter()
+            .filter_map(|(owner, record)| record.as_any().downcast_ref::<NSecRecord>().map(|record| (owner, record)))
+            .collect();
+
+        if !nsecs.is_empty() {
+            return check_nsec(&nsecs, name, _type);
+        }
+
+        let nsec3s: Vec<(String, &NSec3Record)> = self.get_verified(records, RRTypes::NSec3, zone, keys).into_iter()
+            .filter_map(|(owner, record)| record.as_any().downcast_ref::<NSec3Record>().map(|record| (owner, record)))
+            .collect();
+
+        match Nsec3Chain::new(&nsec3s, zone) {
+            Some(chain) if !chain.is_empty() => chain.check(name, _type, zone),
+            Some(_) => Proofs::Missing,
+            None => Proofs::Insecure
+        }
+    }
+
+    /// The name a wildcard was expanded for must not exist, its next closer name has to be covered.
+    fn check_wildcard(&self, records: &[(String, Box<dyn RecordBase>)], owner: &str, labels: u8, zone: &str, keys: &[Vec<u8>]) -> Proofs {
+        let nsecs: Vec<(String, &NSecRecord)> = self.get_verified(records, RRTypes::NSec, zone, keys).into_iter()
+            .filter_map(|(owner, record)| record.as_any().downcast_ref::<NSecRecord>().map(|record| (owner, record)))
+            .collect();
+
+        if !nsecs.is_empty() {
+            return match nsecs.iter().any(|(nsec_owner, nsec)| covers_name(nsec_owner, nsec, owner)) {
+                true => Proofs::NoName,
+                false => Proofs::Missing
+            };
+        }
+
+        let nsec3s: Vec<(String, &NSec3Record)> = self.get_verified(records, RRTypes::NSec3, zone, keys).into_iter()
+            .filter_map(|(owner, record)| record.as_any().downcast_ref::<NSec3Record>().map(|record| (owner, record)))
+            .collect();
+
+        let owner_labels = get_labels(owner);
+        let next_closer = owner_labels[owner_labels.len() - labels as usize - 1..].join(".");
+
+        match Nsec3Chain::new(&nsec3s, zone) {
+            Some(chain) => match chain.find_cover(&next_closer) {
+                Some(_) => Proofs::NoName,
+                None => Proofs::Missing
+            },
+            None => Proofs::Insecure
+        }
+    }
+
+    fn get_verified<'a>(&self, records: &'a [(String, Box<dyn RecordBase>)], _type: RRTypes, zone: &str, keys: &[Vec<u8>]) -> Vec<(String, &'a dyn RecordBase)> {
+        let mut verified = Vec::new();
+
+        for (owner, rr_type) in get_rrsets(records) {
+            if rr_type != _type || !is_subdomain(&owner, zone) {
+                continue;
+            }
+
+            let rrset = get_rrset(records, &owner, _type);
+            if self.verify_rrset(&owner, &rrset, &get_signatures(records, &owner, _type), zone, keys) == SecurityStates::Secure {
+                for record in rrset {
+                    verified.push((owner.clone(), record));
+                }
+            }
+        }
+
+        verified
+    }
+
+    fn get_cached(&self, name: &str) -> Option<CutStates> {
+        match self.cuts.lock().unwrap().get(name) {
+            Some((state, expires)) if *expires > get_now() => Some(state.clone()),
+            _ => None
+        }
+    }
+
+    fn set_cached(&self, name: &str, state: CutStates, ttl: u64) {
+        if ttl == 0 {
+            return;
+        }
+
+        let now = get_now();
+        let mut cuts = self.cuts.lock().unwrap();
+        if cuts.len() >= MAX_CUTS {
+            cuts.retain(|_, (_, expires)| *expires > now);
+            if cuts.len() >= MAX_CUTS {
+                cuts.clear();
+            }
+        }
+
+        cuts.insert(name.to_string(), (state, now + ttl));
+    }
+}
+
+impl Default for Validator {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The NSEC3 records of a zone with their owner names decoded back into hashes.
+struct Nsec3Chain<'a> {
+    params: Nsec3Params,
+    records: Vec<(Vec<u8>, &'a NSec3Record)>
+}
+
+impl<'a> Nsec3Chain<'a> {
+
+    /// None if the chain uses a hash we don't know or more iterations than RFC 9276 allows, the
+    /// zone is treated as unsigned in that case.
+    fn new(nsec3s: &[(String, &'a NSec3Record)], zone: &str) -> Option<Self> {
+        let first = match nsec3s.first() {
+            Some((_, first)) => *first,
+            None => return Some(Self {
+                params: Nsec3Params::new(0, &[], false),
+                records: Vec::new()
+            })
+        };
+
+        if first.get_algorithm() != NSEC3_HASH_SHA1 || first.get_iterations() > MAX_ITERATIONS {
+            return None;
+        }
+
+        let records = nsec3s.iter()
+            .filter(|(_, record)| record.get_iterations() == first.get_iterations() && record.get_salt() == first.get_salt())
+            .filter_map(|(owner, record)| {
+                let labels = get_labels(owner);
+                if labels.is_empty() || labels[1..].join(".") != zone {
+                    return None;
+                }
+
+                Some((decode_hex(labels[0])?, *record))
+            })
+            .collect();
+
+        Some(Self {
+            params: Nsec3Params::new(first.get_iterations(), first.get_salt(), false),
+            records
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn find_match(&self, name: &str) -> Option<&'a NSec3Record> {
+        let hash = self.params.hash_name(name);
+        self.records.iter().find(|(owner, _)| *owner == hash).map(|(_, record)| *record)
+    }
+
+    fn find_cover(&self, name: &str) -> Option<&'a NSec3Record> {
+        let hash = self.params.hash_name(name);
+        self.records.iter().find(|(owner, record)| covers_hash(owner, record.get_next_hash(), &hash)).map(|(_, record)| *record)
+    }
+
+    //RFC 5155 section 8
+    fn check(&self, name: &str, _type: RRTypes, zone: &str) -> Proofs {
+        if let Some(record) = self.find_match(name) {
+            return get_no_data(record.get_types(), _type);
+        }
+
+        //CLOSEST ENCLOSER PROOF, AN ANCESTOR THAT EXISTS AND THE NAME JUST BELOW IT THAT DOESN'T
+        let mut next_closer = name.to_string();
+        let encloser = loop {
+            let parent = match get_parent(&next_closer) {
+                Some(parent) if is_subdomain(&parent, zone) => parent,
+                _ => return Proofs::Missing
+            };
+
+            if self.find_match(&parent).is_some() {
+                break parent;
+            }
+
+            next_closer = parent;
+        };
+
+        let cover = match self.find_cover(&next_closer) {
+            Some(cover) => cover,
+            None => return Proofs::Missing
+        };
+
+        //RFC 5155 section 6 - UNDER OPT-OUT THE NAME MAY STILL BE AN UNSIGNED DELEGATION
+        if cover.get_flags() & OPT_OUT_FLAG != 0 {
+            return Proofs::OptOut;
+        }
+
+        let wildcard = to_wildcard(&encloser);
+        if let Some(record) = self.find_match(&wildcard) {
+            return match get_no_data(record.get_types(), _type) {
+                Proofs::NoData(_) => Proofs::NoData(false),
+                proof => proof
+            };
+        }
+
+        match self.find_cover(&wildcard) {
+            Some(_) => Proofs::NoName,
+            None => Proofs::Missing
+        }
+    }
+}
+
+//RFC 4035 section 5.4
+fn check_nsec(nsecs: &[(String, &NSecRecord)], name: &str, _type: RRTypes) -> Proofs {
+    if let Some((_, nsec)) = nsecs.iter().find(|(owner, _)| owner == name) {
+        return get_no_data(nsec.get_types(), _type);
+    }
+
+    let (owner, nsec) = match nsecs.iter().find(|(owner, nsec)| covers_name(owner, nsec, name)) {
+        Some(covering) => covering,
+        None => return Proofs::Missing
+    };
+
+    //THE CLOSEST ENCLOSER IS THE LONGEST ANCESTOR THE NAME SHARES WITH EITHER END OF THE NSEC
+    let next = nsec.get_next_domain().map(|next| normalize(&next)).unwrap_or_default();
+    let encloser = [common_ancestor(name, owner), common_ancestor(name, &next)].into_iter()
+        .max_by_key(|ancestor| get_labels(ancestor).len())
+        .unwrap_or_default();
+
+    let wildcard = to_wildcard(&encloser);
+    if let Some((_, nsec)) = nsecs.iter().find(|(owner, _)| *owner == wildcard) {
+        return match get_no_data(nsec.get_types(), _type) {
+            Proofs::NoData(_) => Proofs::NoData(false),
+            proof => proof
+        };
+    }
+
+    match nsecs.iter().any(|(owner, nsec)| covers_name(owner, nsec, &wildcard)) {
+        true => Proofs::NoName,
+        false => Proofs::Missing
+    }
+}
+
+/// What an NSEC or NSEC3 at the name proves, neither the type nor a CNAME may be in its bitmap. NS
+/// without SOA is a delegation and without a DS an unsigned one.
+fn get_no_data(types: &[RRTypes], _type: RRTypes) -> Proofs {
+    if types.contains(&_type) || types.contains(&RRTypes::CName) {
+        return Proofs::Missing;
+    }
+
+    Proofs::NoData(types.contains(&RRTypes::Ns) && !types.contains(&RRTypes::Soa) && !types.contains(&RRTypes::Ds))
+}
+
+fn covers_name(owner: &str, nsec: &NSecRecord, name: &str) -> bool {
+    let next = match nsec.get_next_domain() {
+        Some(next) => normalize(&next),
+        None => return false
+    };
+
+    let after_owner = compare_names(owner, name) == Ordering::Less;
+    let before_next = compare_names(name, &next) == Ordering::Less;
+
+    //THE LAST NSEC OF THE ZONE POINTS BACK TO THE APEX
+    match compare_names(owner, &next) {
+        Ordering::Less => after_owner && before_next,
+        _ => after_owner || before_next
+    }
+}
+
+fn covers_hash(owner: &[u8], next: &[u8], hash: &[u8]) -> bool {
+    match owner < next {
+        true => owner < hash && hash < next,
+        false => owner < hash || hash < next
+    }
+}
+
+fn common_ancestor(a: &str, b: &str) -> String {
+    let a = get_labels(a);
+    let b = get_labels(b);
+    let count = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x.eq_ignore_ascii_case(y)).count();
+    a[a.len() - count..].join(".")
+}
+
+fn to_wildcard(name: &str) -> String {
+    match name.is_empty() {
+        true => "*".to_string(),
+        false => format!("*.{}", name)
+    }
+}
+
+fn is_supported_algorithm(algorithm: u8) -> bool {
+    algorithm == ALGORITHM_RSASHA256 || DnssecAlgorithms::from_code(algorithm).is_some()
+}
+
+fn is_zone_key(rdata: &[u8]) -> bool {
+    let flags = u16::from_be_bytes([rdata[0], rdata[1]]);
+    flags & ZONE_KEY_FLAG != 0 && flags & REVOKE_FLAG == 0 && rdata[2] == DNSKEY_PROTOCOL
+}
+
+fn verify_signature(algorithm: u8, public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+    if algorithm == ALGORITHM_RSASHA256 {
+        return rsa::verify(public_key, data, signature);
+    }
+
+    match DnssecAlgorithms::from_code(algorithm) {
+        Some(DnssecAlgorithms::EcdsaP256Sha256) => p256::verify(public_key, data, signature),
+        Some(DnssecAlgorithms::Ed25519) => ed25519::verify(public_key, data, signature),
+        None => false
+    }
+}
+
+/// Resolves a name the validator needs on its own, IE the DS and DNSKEY of each zone in the chain.
+fn fetch(resolve: Resolve, name: &str, _type: RRTypes) -> Result<(RequestEvent, bool), SecurityStates> {
+    let mut event = RequestEvent::new();
+    event.query = Some(DnsQuery::new(name, _type, RRClasses::In));
+
+    match resolve(&mut event) {
+        Ok(_) => Ok((event, false)),
+        Err(ResponseCodes::NxDomain) => Ok((event, true)),
+        Err(_) => Err(SecurityStates::Indeterminate)
+    }
+}
+
+/// The distinct RRsets of a section in the order they first appear, the RRSIGs are kept apart.
+fn get_rrsets(records: &[(String, Box<dyn RecordBase>)]) -> Vec<(String, RRTypes)> {
+    let mut rrsets = Vec::new();
+
+    for (owner, record) in records {
+        let rrset = (normalize(owner), record.get_type());
+        if !matches!(rrset.1, RRTypes::RRSig | RRTypes::Opt) && !rrsets.contains(&rrset) {
+            rrsets.push(rrset);
+        }
+    }
+
+    rrsets
+}
+
+fn get_rrset<'a>(records: &'a [(String, Box<dyn RecordBase>)], owner: &str, _type: RRTypes) -> Vec<&'a dyn RecordBase> {
+    records.iter()
+        .filter(|(name, record)| record.get_type() == _type && normalize(name) == owner)
+        .map(|(_, record)| record.as_ref())
+        .collect()
+}
+
+fn get_signatures<'a>(records: &'a [(String, Box<dyn RecordBase>)], owner: &str, _type: RRTypes) -> Vec<&'a RRSigRecord> {
+    records.iter()
+        .filter(|(name, _)| normalize(name) == owner)
+        .filter_map(|(_, record)| record.as_any().downcast_ref::<RRSigRecord>())
+        .filter(|signature| signature.get_type_covered() == Some(_type))
+        .collect()
+}
+
+fn get_key_rdatas(records: &[&dyn RecordBase]) -> Vec<Vec<u8>> {
+    records.iter()
+        .filter_map(|record| record.as_any().downcast_ref::<DnsKeyRecord>())
+        .map(get_dnskey_rdata)
+        .filter(|rdata| rdata.len() > 4)
+        .collect()
+}
+
+/// Follows the CNAMEs in the answer from the query name, the last name is the one the answer or
+/// the denial is for.
+fn get_target(answers: &[(String, Box<dyn RecordBase>)], name: &str, _type: RRTypes) -> String {
+    let mut target = name.to_string();
+    if _type == RRTypes::CName {
+        return target;
+    }
+
+    for _ in 0..MAX_CNAME_CHAIN_SIZE {
+        let next = answers.iter()
+            .filter(|(owner, _)| normalize(owner) == target)
+            .find_map(|(_, record)| record.as_any().downcast_ref::<CNameRecord>())
+            .and_then(|record| record.get_target());
+
+        match next {
+            Some(next) => target = normalize(&next),
+            None => break
+        }
+    }
+
+    target
+}
+
+/// Whether the CNAME is the one a DNAME in the answer rewrites the owner to.
+fn is_synthesized(answers: &[(String, Box<dyn RecordBase>)], owner: &str, records: &[&dyn RecordBase]) -> bool {
+    let target = match records.first().and_then(|record| record.as_any().downcast_ref::<CNameRecord>()).and_then(|record| record.get_target()) {
+        Some(target) => normalize(&target),
+        None => return false
+    };
+
+    answers.iter()
+        .filter_map(|(dname_owner, record)| record.as_any().downcast_ref::<DNameRecord>().map(|record| (normalize(dname_owner), record)))
+        .any(|(dname_owner, record)| {
+            if dname_owner == owner || !is_subdomain(owner, &dname_owner) {
+                return false;
+            }
+
+            let prefix = match dname_owner.is_empty() {
+                true => owner,
+                false => &owner[..owner.len() - dname_owner.len() - 1]
+            };
+
+            match record.get_target().map(|next| normalize(&next)) {
+                Some(next) if next.is_empty() => prefix == target,
+                Some(next) => format!("{}.{}", prefix, next) == target,
+                None => false
+            }
+        })
+}
+
+/// The lowest TTL in the response, how long what we learned from it can be kept.
+fn get_ttl(event: &RequestEvent) -> u64 {
+    event.get_answers().iter()
+        .chain(event.get_authority_records().iter())
+        .map(|(_, record)| record.get_ttl())
+        .min()
+        .unwrap_or(0)
+        .min(MAX_CACHE_TTL) as u64
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::Ipv4Addr;
+    use rlibdns::records::a_record::ARecord;
+    use super::*;
+    use crate::dnssec::dnssec_key::{DnssecKey, PrivateKey, SECURE_ENTRY_POINT_FLAG};
+    use crate::utils::base32::encode_hex;
+    use crate::utils::crypto::p256::P256PrivateKey;
+
+    const ZONE: &str = "test";
+
+    /// A signed zone with `www.test A` and nothing else, answering the queries the validator makes
+    /// with NSEC or NSEC3 proofs for everything it doesn't hold.
+    struct Fixture {
+        key: DnssecKey,
+        nsec3: Option<Nsec3Params>
+    }
+
+    impl Fixture {
+
+        fn new(nsec3: Option<Nsec3Params>) -> Self {
+            let private_key = P256PrivateKey::from_bytes(&[7u8; 32]).unwrap();
+            Self {
+                key: DnssecKey::new(ZONE, ZONE_KEY_FLAG | SECURE_ENTRY_POINT_FLAG, PrivateKey::EcdsaP256(private_key)),
+                nsec3
+            }
+        }
+
+        fn get_validator(&self) -> Validator {
+            let mut anchors = TrustAnchors::new();
+            anchors.add(TrustAnchor::new(ZONE, AnchorTypes::DnsKey(self.key.get_rdata())));
+
+            let mut validator = Validator::new();
+            validator.set_trust_anchors(anchors);
+            validator
+        }
+
+        fn get_types(name: &str) -> Vec<RRTypes> {
+            match name {
+                ZONE => vec![RRTypes::DnsKey, RRTypes::RRSig],
+                "www.test" => vec![RRTypes::A, RRTypes::RRSig],
+                _ => Vec::new()
+            }
+        }
+
+        fn sign(&self, owner: &str, records: &[Box<dyn RecordBase>], expiration: u32) -> Box<dyn RecordBase> {
+            sign_with(&self.key, owner, records, get_now() as u32 - 3600, expiration)
+        }
+
+        fn add_rrset(&self, section: &mut Vec<(String, Box<dyn RecordBase>)>, owner: &str, records: Vec<Box<dyn RecordBase>>) {
+            let signature = self.sign(owner, &records, get_now() as u32 + 3600);
+            for record in records {
+                section.push((owner.to_string(), record));
+            }
+            section.push((owner.to_string(), signature));
+        }
+
+        fn add_nsec(&self, event: &mut RequestEvent, owner: &str, next: &str) {
+            let mut record = NSecRecord::new(300, RRClasses::In);
+            record.set_next_domain(next);
+            for _type in Self::get_types(owner) {
+                record.add_type(_type);
+            }
+            record.add_type(RRTypes::NSec);
+            self.add_rrset(event.get_authority_records_mut(), owner, vec![record.upcast()]);
+        }
+
+        fn add_nsec3(&self, event: &mut RequestEvent, params: &Nsec3Params, hash: &[u8]) {
+            let mut chain: Vec<(Vec<u8>, &str)> = [ZONE, "www.test"].iter().map(|name| (params.hash_name(name), *name)).collect();
+            chain.sort();
+
+            let i = chain.iter().position(|(h, _)| h == hash).unwrap();
+            let mut record = NSec3Record::new(300, RRClasses::In);
+            record.set_algorithm(NSEC3_HASH_SHA1);
+            record.set_flags(params.get_flags());
+            record.set_iterations(params.get_iterations());
+            record.set_salt(params.get_salt());
+            record.set_next_hash(&chain[(i + 1) % chain.len()].0);
+            for _type in Self::get_types(chain[i].1) {
+                record.add_type(_type);
+            }
+
+            let owner = format!("{}.{}", encode_hex(hash), ZONE);
+            self.add_rrset(event.get_authority_records_mut(), &owner, vec![record.upcast()]);
+        }
+
+        /// The NSEC3 that matches the name, or the one whose span covers its hash.
+        fn get_nsec3_hash(&self, params: &Nsec3Params, name: &str) -> Vec<u8> {
+            let mut hashes: Vec<Vec<u8>> = [ZONE, "www.test"].iter().map(|name| params.hash_name(name)).collect();
+            hashes.sort();
+
+            let hash = params.hash_name(name);
+            hashes.iter().rev().find(|h| **h <= hash).unwrap_or(hashes.last().unwrap()).clone()
+        }
+
+        fn resolve(&self, event: &mut RequestEvent) -> ResponseResult<()> {
+            let name = normalize(event.get_query().get_fqdn());
+            let _type = event.get_query().get_type();
+
+            match (name.as_str(), _type) {
+                (ZONE, RRTypes::DnsKey) => {
+                    self.add_rrset(event.get_answers_mut(), ZONE, vec![self.key.to_record()]);
+                    return Ok(());
+                }
+                ("www.test", RRTypes::A) => {
+                    let mut record = ARecord::new(300, RRClasses::In);
+                    record.set_address(Ipv4Addr::new(192, 0, 2, 1));
+                    self.add_rrset(event.get_answers_mut(), "www.test", vec![record.upcast()]);
+                    return Ok(());
+                }
+                _ => {}
+            }
+
+            let exists = !Self::get_types(&name).is_empty();
+
+            match (&self.nsec3, exists) {
+                (None, true) => self.add_nsec(event, &name, if name == ZONE { "www.test" } else { ZONE }),
+                //test < *.test < nx.test < www.test, THE APEX NSEC COVERS BOTH THE NAME AND THE WILDCARD
+                (None, false) => self.add_nsec(event, ZONE, "www.test"),
+                (Some(params), true) => self.add_nsec3(event, params, &params.hash_name(&name)),
+                (Some(params), false) => {
+                    let mut hashes = vec![params.hash_name(ZONE), self.get_nsec3_hash(params, &name), self.get_nsec3_hash(params, "*.test")];
+                    hashes.sort();
+                    hashes.dedup();
+                    for hash in hashes {
+                        self.add_nsec3(event, params, &hash);
+                    }
+                }
+            }
+
+            match exists {
+                true => Ok(()),
+                false => Err(ResponseCodes::NxDomain)
+            }
+        }
+    }
+
+    fn sign_with(key: &DnssecKey, owner: &str, records: &[Box<dyn RecordBase>], inception: u32, expiration: u32) -> Box<dyn RecordBase> {
+        let records: Vec<&dyn RecordBase> = records.iter().map(|record| record.as_ref()).collect();
+        let _type = records[0].get_type();
+        let ttl = records[0].get_ttl();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&_type.get_code().to_be_bytes());
+        data.push(key.get_algorithm().get_code());
+        data.push(label_count(owner));
+        data.extend_from_slice(&ttl.to_be_bytes());
+        data.extend_from_slice(&expiration.to_be_bytes());
+        data.extend_from_slice(&inception.to_be_bytes());
+        data.extend_from_slice(&key.get_key_tag().to_be_bytes());
+        data.extend(name_to_wire(key.get_owner()));
+        data.extend(rrset_to_wire(owner, &records, ttl).unwrap());
+
+        let mut record = RRSigRecord::new(ttl, RRClasses::In);
+        record.set_type_covered(_type);
+        record.set_algorithm(key.get_algorithm().get_code());
+        record.set_labels(label_count(owner));
+        record.set_original_ttl(ttl);
+        record.set_expiration(expiration);
+        record.set_inception(inception);
+        record.set_key_tag(key.get_key_tag());
+        record.set_signer_name(key.get_owner());
+        record.set_signature(&key.sign(&data));
+        record.upcast()
+    }
+
+    fn query(validator: &Validator, resolve: Resolve, name: &str, _type: RRTypes) -> (ResponseResult<()>, RequestEvent) {
+        let mut event = RequestEvent::new();
+        event.query = Some(DnsQuery::new(name, _type, RRClasses::In));
+        let response = validator.validate(&mut event, resolve);
+        (response, event)
+    }
+
+    fn get_nsec3_params() -> Nsec3Params {
+        Nsec3Params::new(0, &[0xaa, 0xbb], false)
+    }
+
+    #[test]
+    fn secure_answer() {
+        let fixture = Fixture::new(None);
+        let (response, event) = query(&fixture.get_validator(), &|event| fixture.resolve(event), "www.test", RRTypes::A);
+        assert_eq!(response, Ok(()));
+        assert!(event.is_authenticated_data());
+        assert_eq!(event.get_answers().len(), 2);
+    }
+
+    #[test]
+    fn bogus_signature() {
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            if event.get_query().get_type() == RRTypes::A {
+                for (_, record) in event.get_answers_mut().iter_mut() {
+                    if let Some(signature) = record.as_any_mut().downcast_mut::<RRSigRecord>() {
+                        let mut tampered = signature.get_signature().to_vec();
+                        tampered[10] ^= 0x01;
+                        signature.set_signature(&tampered);
+                    }
+                }
+            }
+            response
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "www.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_DNSSEC_BOGUS));
+        assert!(event.get_answers().is_empty());
+    }
+
+    #[test]
+    fn expired_signature() {
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            if event.get_query().get_type() == RRTypes::A {
+                let records: Vec<Box<dyn RecordBase>> = event.get_answers().iter()
+                    .filter(|(_, record)| record.get_type() == RRTypes::A)
+                    .map(|(_, record)| record.clone())
+                    .collect();
+                let now = get_now() as u32;
+                let signature = sign_with(&fixture.key, "www.test", &records, now - 7200, now - 3600);
+
+                let answers = event.get_answers_mut();
+                answers.retain(|(_, record)| record.get_type() != RRTypes::RRSig);
+                answers.push(("www.test".to_string(), signature));
+            }
+            response
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "www.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_SIGNATURE_EXPIRED));
+    }
+
+    #[test]
+    fn missing_signatures() {
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            if event.get_query().get_type() == RRTypes::A {
+                event.get_answers_mut().retain(|(_, record)| record.get_type() != RRTypes::RRSig);
+            }
+            response
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "www.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_RRSIGS_MISSING));
+    }
+
+    #[test]
+    fn nsec_no_name() {
+        let fixture = Fixture::new(None);
+        let (response, event) = query(&fixture.get_validator(), &|event| fixture.resolve(event), "nx.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::NxDomain));
+        assert!(event.is_authenticated_data());
+    }
+
+    #[test]
+    fn nsec_no_data() {
+        let fixture = Fixture::new(None);
+        let (response, event) = query(&fixture.get_validator(), &|event| fixture.resolve(event), "www.test", RRTypes::Aaaa);
+        assert_eq!(response, Ok(()));
+        assert!(event.is_authenticated_data());
+    }
+
+    #[test]
+    fn nsec_missing() {
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            if event.get_query().get_type() == RRTypes::A {
+                event.get_authority_records_mut().clear();
+            }
+            response
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "nx.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_NSEC_MISSING));
+    }
+
+    #[test]
+    fn nsec_forged_no_data() {
+        //A NODATA FOR www.test A, THE NSEC THAT COMES WITH IT SHOWS A IS THERE
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            match (normalize(event.get_query().get_fqdn()).as_str(), event.get_query().get_type()) {
+                ("www.test", RRTypes::A) => {
+                    fixture.add_nsec(event, "www.test", ZONE);
+                    Ok(())
+                }
+                _ => fixture.resolve(event)
+            }
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "www.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_NSEC_MISSING));
+    }
+
+    #[test]
+    fn nsec3_no_name() {
+        let fixture = Fixture::new(Some(get_nsec3_params()));
+        let (response, event) = query(&fixture.get_validator(), &|event| fixture.resolve(event), "nx.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::NxDomain));
+        assert!(event.is_authenticated_data());
+    }
+
+    #[test]
+    fn nsec3_no_data() {
+        let fixture = Fixture::new(Some(get_nsec3_params()));
+        let (response, event) = query(&fixture.get_validator(), &|event| fixture.resolve(event), "www.test", RRTypes::Aaaa);
+        assert_eq!(response, Ok(()));
+        assert!(event.is_authenticated_data());
+    }
+
+    #[test]
+    fn nsec3_missing() {
+        let fixture = Fixture::new(Some(get_nsec3_params()));
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            if event.get_query().get_type() == RRTypes::A {
+                //WITHOUT THE APEX NSEC3 THERE IS NO CLOSEST ENCLOSER TO PROVE
+                let apex = format!("{}.{}", encode_hex(&get_nsec3_params().hash_name(ZONE)), ZONE);
+                event.get_authority_records_mut().retain(|(owner, _)| *owner != apex);
+            }
+            response
+        };
+
+        let (response, event) = query(&fixture.get_validator(), &resolve, "nx.test", RRTypes::A);
+        assert_eq!(response, Err(ResponseCodes::ServFail));
+        assert_eq!(event.get_extended_error(), Some(EDE_NSEC_MISSING));
+    }
+
+    #[test]
+    fn negative_anchor() {
+        let fixture = Fixture::new(None);
+        let resolve = |event: &mut RequestEvent| {
+            let response = fixture.resolve(event);
+            event.get_answers_mut().retain(|(_, record)| record.get_type() != RRTypes::RRSig);
+            response
+        };
+
+        let mut validator = fixture.get_validator();
+        validator.add_negative_anchor("test.");
+        let (response, event) = query(&validator, &resolve, "www.test", RRTypes::A);
+        assert_eq!(response, Ok(()));
+        assert!(!event.is_authenticated_data());
+        assert_eq!(event.get_answers().len(), 1);
+    }
+
+    #[test]
+    fn covers() {
+        assert!(covers_hash(&[1], &[5], &[3]));
+        assert!(!covers_hash(&[1], &[5], &[5]));
+        //THE LAST NSEC3 WRAPS AROUND TO THE FIRST
+        assert!(covers_hash(&[9], &[1], &[0]));
+        assert!(covers_hash(&[9], &[1], &[10]));
+        assert!(!covers_hash(&[9], &[1], &[5]));
+
+        assert_eq!(common_ancestor("a.b.find9.net", "c.find9.net"), "find9.net");
+        assert_eq!(common_ancestor("find9.net", "find9.com"), "");
+        assert_eq!(to_wildcard("find9.net"), "*.find9.net");
+        assert_eq!(to_wildcard(""), "*");
+    }
+
+    #[test]
+    fn no_data_bitmaps() {
+        assert_eq!(get_no_data(&[RRTypes::A, RRTypes::NSec], RRTypes::Aaaa), Proofs::NoData(false));
+        assert_eq!(get_no_data(&[RRTypes::A, RRTypes::NSec], RRTypes::A), Proofs::Missing);
+        assert_eq!(get_no_data(&[RRTypes::CName, RRTypes::NSec], RRTypes::A), Proofs::Missing);
+        //AN UNSIGNED DELEGATION
+        assert_eq!(get_no_data(&[RRTypes::Ns, RRTypes::NSec], RRTypes::Ds), Proofs::NoData(true));
+        assert_eq!(get_no_data(&[RRTypes::Ns, RRTypes::Ds, RRTypes::NSec], RRTypes::A), Proofs::NoData(false));
+    }
+}
@@ -0,0 +1,190 @@
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::utils::fqdn_utils::fqdn_to_relative;
+use rlibdns::zone::zone::Zone;
+use crate::dnssec::canonical::compare_names;
+use crate::dnssec::signer::Signer;
+use crate::utils::domain_utils::normalize;
+
+//TYPES THE SIGNER GENERATES ITSELF, ANY COPIES IN THE INPUT ZONE ARE REPLACED
+const GENERATED_TYPES: [&str; 7] = ["RRSIG", "NSEC", "NSEC3", "NSEC3PARAM", "DNSKEY", "CDS", "CDNSKEY"];
+
+type SignedZone = (Vec<(String, Box<dyn RecordBase>)>, Vec<(String, RRTypes)>);
+
+/// Signs a whole zone for serving as-is, returns the DNSSEC records to add to it along with the
+/// RRsets that couldn't be signed.
+pub fn sign_zone(signer: &Signer, apex: &str, zone: &Zone) -> SignedZone {
+    let apex = normalize(apex);
+    let mut records = Vec::new();
+    let mut unsigned = Vec::new();
+
+    let mut apex_records = vec![signer.get_dnskey_records(&apex), signer.get_cds_records(&apex), signer.get_cdnskey_records(&apex)];
+    apex_records.push(signer.get_nsec3param_records(&apex, zone));
+
+    for set in apex_records {
+        if set.is_empty() {
+            continue;
+        }
+
+        let signatures = signer.sign_rrset(&apex, &apex, &set.iter().map(|record| record.as_ref()).collect::<Vec<_>>());
+        records.extend(set.into_iter().map(|record| (apex.clone(), record)));
+        records.extend(signatures.into_iter().map(|record| (apex.clone(), record)));
+    }
+
+    let chain = match signer.get_denial_chain(None, &apex, zone) {
+        Some(chain) => chain,
+        None => return (records, unsigned)
+    };
+
+    let mut names: Vec<&String> = chain.get_names().iter().collect();
+    names.sort_by(|a, b| compare_names(a, b));
+
+    for name in names {
+        let sub = match fqdn_to_relative(&apex, name) {
+            Some(sub) => sub,
+            None => continue
+        };
+
+        let set = match zone.get_all_records(&sub) {
+            Some(set) => set,
+            None => continue
+        };
+
+        //ONLY THE DS IS AUTHORITATIVE AT A DELEGATION, THE NS RRSET BELONGS TO THE CHILD
+        let delegation = *name != apex && zone.get_records(&sub, &RRTypes::Ns).is_some();
+
+        let mut types: Vec<RRTypes> = Vec::new();
+        for record in &set {
+            let _type = record.get_type();
+            if !types.contains(&_type) {
+                types.push(_type);
+            }
+        }
+
+        for _type in types {
+            if matches!(_type, RRTypes::RRSig | RRTypes::NSec | RRTypes::NSec3 | RRTypes::NSec3Param | RRTypes::DnsKey | RRTypes::Cds | RRTypes::CDnsKey) {
+                continue;
+            }
+
+            if delegation && _type != RRTypes::Ds {
+                continue;
+            }
+
+            let rrset: Vec<&dyn RecordBase> = set.iter().filter(|record| record.get_type() == _type).map(|record| record.as_ref()).collect();
+            let signatures = signer.sign_rrset(&apex, name, &rrset);
+
+            if signatures.is_empty() {
+                unsigned.push((name.clone(), _type));
+            }
+
+            records.extend(signatures.into_iter().map(|record| (name.clone(), record)));
+        }
+    }
+
+    for (owner, record) in chain.get_records() {
+        let signatures = signer.sign_rrset(&apex, &owner, &[record.as_ref()]);
+        records.push((owner.clone(), record));
+        records.extend(signatures.into_iter().map(|record| (owner.clone(), record)));
+    }
+
+    (records, unsigned)
+}
+
+/// Removes the records the signer generates from zone file text so a zone can be signed again.
+/// Entries spanning several lines with parentheses are removed whole, and when a removed entry
+/// named the owner the next entry that relied on it has the owner written out.
+pub fn strip_generated_records(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut entry: Vec<&str> = Vec::new();
+    let mut depth = 0i32;
+    let mut pending_owner: Option<String> = None;
+
+    for line in text.lines() {
+        entry.push(line);
+        depth += paren_depth(line);
+        if depth > 0 {
+            continue;
+        }
+        depth = 0;
+
+        let first = entry[0];
+        let content = strip_comment(first);
+        let owner = match first.starts_with(char::is_whitespace) {
+            true => None,
+            false => content.split_whitespace().next().map(|owner| owner.to_string())
+        };
+
+        if content.trim().is_empty() || content.starts_with('$') {
+            for line in entry.drain(..) {
+                out.push_str(line);
+                out.push('\n');
+            }
+            continue;
+        }
+
+        if is_generated(&content, owner.is_some()) {
+            if owner.is_some() {
+                pending_owner = owner;
+            }
+            entry.clear();
+            continue;
+        }
+
+        for (i, line) in entry.drain(..).enumerate() {
+            match (i, &pending_owner, owner.is_none()) {
+                (0, Some(owner), true) => out.push_str(&format!("{}{}", owner, line)),
+                _ => out.push_str(line)
+            }
+            out.push('\n');
+        }
+        pending_owner = None;
+    }
+
+    for line in entry {
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    out
+}
+
+fn is_generated(content: &str, has_owner: bool) -> bool {
+    let tokens = content.split_whitespace().skip(if has_owner { 1 } else { 0 });
+
+    //THE TYPE IS THE FIRST TOKEN THAT ISN'T A TTL OR A CLASS
+    for token in tokens {
+        let upper = token.to_uppercase();
+        if matches!(upper.as_str(), "IN" | "CH" | "HS" | "CS") || upper.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+
+        return GENERATED_TYPES.contains(&upper.as_str());
+    }
+
+    false
+}
+
+fn strip_comment(line: &str) -> String {
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return line[..i].to_string(),
+            _ => {}
+        }
+    }
+
+    line.to_string()
+}
+
+fn paren_depth(line: &str) -> i32 {
+    strip_comment(line).chars().fold((0, false), |(depth, quoted), c| {
+        match c {
+            '"' => (depth, !quoted),
+            '(' if !quoted => (depth + 1, quoted),
+            ')' if !quoted => (depth - 1, quoted),
+            _ => (depth, quoted)
+        }
+    }).0
+}
@@ -0,0 +1,21 @@
+pub mod rpc;
+pub mod utils;
+pub mod dns;
+pub mod dnssec;
+pub mod unix_rpc;
+
+pub const BOGON_ALLOWED: bool = true;
+pub const ANY_QUERY_ALLOWED: bool = true;
+pub const MAX_CNAME_CHAIN_SIZE: u8 = 10;
+pub const MAX_QUERIES: usize = 1;
+pub const COOKIE_SECRET: &[u8] = b"HELLO WORLD";
+pub const SIGNATURE_VALIDITY: u32 = 1209600;
+pub const SIGNATURE_REFRESH: u32 = 259200;
+pub const KEY_CHECK_INTERVAL: u64 = 3600;
+pub const GEOIP_RELOAD_INTERVAL: u64 = 60;
+pub const UPSTREAM_TIMEOUT: u64 = 2;
+pub const MIN_ALIAS_CACHE_TTL: u32 = 30;
+pub const MAX_ALIAS_CACHE_TTL: u32 = 3600;
+pub const MAX_CACHE_TTL: u32 = 86400;
+pub const STALE_ANSWER_TTL: u32 = 30;
+pub const MAX_STALE_TTL: u32 = 86400;
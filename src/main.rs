@@ -1,17 +1,8 @@
-mod rpc;
-mod utils;
-mod dns;
-
 use std::{io, thread};
-use crate::dns::dns::Dns;
-
-pub const BOGON_ALLOWED: bool = true;
-pub const ANY_QUERY_ALLOWED: bool = true;
-pub const MAX_CNAME_CHAIN_SIZE: u8 = 10;
-pub const MAX_QUERIES: usize = 1;
-pub const MAX_ANSWERS: usize = 3;
-pub const COOKIE_SECRET: &[u8] = b"HELLO WORLD";
-
+use find9::dns::dns::Dns;
+use find9::dnssec::inter::dnssec_algorithms::DnssecAlgorithms;
+use find9::dnssec::key_manager::KeyPolicy;
+use find9::unix_rpc::UnixRpc;
 
 //dig @127.0.0.1 -p 6767 find9.net
 //NS
@@ -146,8 +137,20 @@ fn main() -> io::Result<()> {
     dns.register_zone("res/find9.net.zone", "find9.net")?;
     dns.register_zone("res/sub.find9.net.zone", "sub.find9.net")?;
     dns.register_zone("res/192.168.0.zone", "0.168.192.in-addr.arpa")?;
+    //KEYS ARE GENERATED ON THE FIRST RUN, THE DIRECTORY IS NEVER COMMITTED
+    dns.register_key_directory("keys/find9.net", "find9.net", KeyPolicy::new(DnssecAlgorithms::EcdsaP256Sha256))?;
+
+    //DS TO GIVE THE REGISTRAR
+    for ds in dns.get_ds_records("find9.net") {
+        println!("{}", ds);
+    }
+
     //dns.register_journal("res/find9.net.zone.jnl", "find9.net")?;
-    //dns.get_server().add_fallback(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53));
+    //dns.set_trust_anchor("res/root.key")?;
+    //dns.set_geoip_database("res/geo-test.mmdb")?;
+    //dns.register_response_policy_zone("res/rpz.local.zone", "rpz.local")?;
+    //dns.add_forward_zone("corp.local", &[SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 2)), 53)], SelectionTypes::Latency);
+    //dns.allow_forward("corp.local", IpAddr::V4(Ipv4Addr::new(192, 168, 0, 0)), 16)?;
     dns.start(6767)?;
 
     println!();
@@ -159,9 +162,11 @@ fn main() -> io::Result<()> {
     //println!("UDP Server started on port: {}", dns.get_udp().socket.as_ref().unwrap().local_addr().unwrap().port());
     //println!("TCP Server started on port: {}", dns.get_tcp().socket.as_ref().unwrap().local_addr().unwrap().port());
 
-    //let mut unix_rpc = UnixRpc::new()?;
-    //unix_rpc.set_database(database.clone());
-    //unix_rpc.start()?.join().unwrap();
+    //THE RPC LISTENS ON ITS OWN THREAD, FLUSHING THE CACHE AND SETTING ANSWER WEIGHTS
+    let mut unix_rpc = UnixRpc::new()?;
+    unix_rpc.set_cache(dns.get_cache().clone());
+    unix_rpc.set_answer_order(dns.get_answer_order().clone());
+    unix_rpc.start()?;
 
     thread::park();
     Ok(())
@@ -1,14 +1,31 @@
+use std::net::{IpAddr, SocketAddr};
 use rlibdns::messages::dns_query::DnsQuery;
+use rlibdns::records::inter::opt_codes::OptCodes;
 use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::opt_record::OptRecord;
+use crate::dns::client_subnet::{ClientSubnet, DEFAULT_IPV4_SOURCE_PREFIX, DEFAULT_IPV6_SOURCE_PREFIX};
+use crate::dns::udp_server::MAX_UDP_MESSAGE_SIZE;
 use crate::rpc::events::inter::event::Event;
 
+pub const DNSSEC_OK_FLAG: u16 = 0x8000;
+
 #[derive(Default, Clone, Debug)]
 pub struct RequestEvent {
     prevent_default: bool,
     pub(crate) query: Option<DnsQuery>,
+    origin: Option<SocketAddr>,
+    tcp: bool,
+    truncated: bool,
+    recursion_desired: bool,
     authoritative: bool,
+    recursion_available: bool,
+    authenticated_data: bool,
+    extended_error: Option<u16>,
+    subnet_scope: Option<u8>,
+    view: Option<String>,
     pub(crate) request_records: [Vec<(String, Box<dyn RecordBase>)>; 3],
     pub(crate) response_records: [Vec<(String, Box<dyn RecordBase>)>; 3],
+    max_response_size: usize,
     //answers: Vec<(String, Box<dyn RecordBase>)>, //WE HAVE TO SWITCH FROM ORDERED_MAP TO VEC FOR RFC 5936
     //authority_records: Vec<(String, Box<dyn RecordBase>)>,
     //additional_records: Vec<(String, Box<dyn RecordBase>)>,
@@ -21,9 +38,19 @@ impl RequestEvent {
         Self {
             prevent_default: false,
             query: None,
+            origin: None,
+            tcp: false,
+            truncated: false,
+            recursion_desired: false,
             authoritative: false,
+            recursion_available: false,
+            authenticated_data: false,
+            extended_error: None,
+            subnet_scope: None,
+            view: None,
             request_records: Default::default(),
             response_records: Default::default(),
+            max_response_size: MAX_UDP_MESSAGE_SIZE,
             received_time: 0
         }
     }
@@ -36,6 +63,95 @@ impl RequestEvent {
         self.authoritative
     }
 
+    pub fn set_origin(&mut self, origin: SocketAddr) {
+        self.origin = Some(origin);
+    }
+
+    pub fn get_origin(&self) -> Option<SocketAddr> {
+        self.origin
+    }
+
+    pub fn set_tcp(&mut self, tcp: bool) {
+        self.tcp = tcp;
+    }
+
+    pub fn is_tcp(&self) -> bool {
+        self.tcp
+    }
+
+    /// Set to make the client retry over TCP, the response goes out with TC even if it fits.
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn set_recursion_desired(&mut self, recursion_desired: bool) {
+        self.recursion_desired = recursion_desired;
+    }
+
+    pub fn is_recursion_desired(&self) -> bool {
+        self.recursion_desired
+    }
+
+    /// Set when the answer was resolved for the client rather than from our own zones, IE forwarded.
+    pub fn set_recursion_available(&mut self, recursion_available: bool) {
+        self.recursion_available = recursion_available;
+    }
+
+    pub fn is_recursion_available(&self) -> bool {
+        self.recursion_available
+    }
+
+    /// Set when every part of a resolved answer was validated, the AD bit.
+    pub fn set_authenticated_data(&mut self, authenticated_data: bool) {
+        self.authenticated_data = authenticated_data;
+    }
+
+    pub fn is_authenticated_data(&self) -> bool {
+        self.authenticated_data
+    }
+
+    /// RFC 8914 - the reason a query failed, sent back in the OPT record of the response.
+    pub fn set_extended_error(&mut self, code: u16) {
+        self.extended_error = Some(code);
+    }
+
+    pub fn get_extended_error(&self) -> Option<u16> {
+        self.extended_error
+    }
+
+    /// RFC 7871 - the prefix of the client subnet the answer was picked by, echoed back in the ECS
+    /// option as the scope.
+    pub fn set_subnet_scope(&mut self, scope_prefix: u8) {
+        self.subnet_scope = Some(scope_prefix);
+    }
+
+    pub fn get_subnet_scope(&self) -> Option<u8> {
+        self.subnet_scope
+    }
+
+    /// The view the client was matched to, zones under the same apex in different views are told
+    /// apart by it.
+    pub fn set_view(&mut self, view: Option<&str>) {
+        self.view = view.map(|view| view.to_string());
+    }
+
+    pub fn get_view(&self) -> Option<&str> {
+        self.view.as_deref()
+    }
+
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    /// The largest response the transport can carry, IE 512 for UDP without EDNS.
+    pub fn get_max_response_size(&self) -> usize {
+        self.max_response_size
+    }
+
     pub fn get_query(&self) -> &DnsQuery {
         self.query.as_ref().unwrap()
     }
@@ -56,6 +172,55 @@ impl RequestEvent {
         self.request_records[1].as_mut()
     }
 
+    pub fn get_request_additional_records(&self) -> &Vec<(String, Box<dyn RecordBase>)> {
+        self.request_records[2].as_ref()
+    }
+
+    pub fn get_opt_record(&self) -> Option<&OptRecord> {
+        self.request_records[2].iter().find_map(|(_, record)| record.as_any().downcast_ref::<OptRecord>())
+    }
+
+    pub fn is_dnssec_ok(&self) -> bool {
+        match self.get_opt_record() {
+            Some(record) => record.get_flags() & DNSSEC_OK_FLAG != 0,
+            None => false
+        }
+    }
+
+    /// Whether the query carried an ECS option, valid or not.
+    pub fn has_client_subnet(&self) -> bool {
+        self.get_opt_record().map(|record| record.get_option(&OptCodes::Ecs).is_some()).unwrap_or(false)
+    }
+
+    pub fn get_client_subnet(&self) -> Option<ClientSubnet> {
+        ClientSubnet::from_option(self.get_opt_record()?.get_option(&OptCodes::Ecs)?)
+    }
+
+    /// The address answers are picked for, the one in the ECS option if the client passed one,
+    /// otherwise where the query came from.
+    pub fn get_client_address(&self) -> Option<IpAddr> {
+        match self.get_client_subnet() {
+            Some(subnet) => Some(subnet.get_address()),
+            None => self.origin.map(|origin| origin.ip())
+        }
+    }
+
+    /// The subnet passed on when we resolve for the client, no longer than our defaults and none if the
+    /// client opted out with a source prefix of 0.
+    pub fn get_upstream_subnet(&self) -> Option<ClientSubnet> {
+        match self.get_client_subnet() {
+            Some(subnet) if subnet.get_source_prefix() == 0 => None,
+            Some(subnet) => {
+                let max_prefix = match subnet.get_address() {
+                    IpAddr::V4(_) => DEFAULT_IPV4_SOURCE_PREFIX,
+                    IpAddr::V6(_) => DEFAULT_IPV6_SOURCE_PREFIX
+                };
+                Some(ClientSubnet::new(subnet.get_address(), subnet.get_source_prefix().min(max_prefix)))
+            }
+            None => self.origin.map(|origin| ClientSubnet::from_address(origin.ip()))
+        }
+    }
+
 
 
 
@@ -1,25 +1,25 @@
 use std::{fs, io, thread};
-use std::collections::HashMap;
+use std::net::IpAddr;
 use std::os::unix::net::UnixDatagram;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
+use std::time::Duration;
 use rlibbencode::bencode;
-use rlibbencode::variables::bencode_array::{AddArray, BencodeArray};
 use rlibbencode::variables::bencode_bytes::BencodeBytes;
 use rlibbencode::variables::bencode_number::BencodeNumber;
 use rlibbencode::variables::bencode_object::{BencodeObject, GetObject, PutObject};
 use rlibbencode::variables::inter::bencode_variable::{BencodeVariable, FromBencode, ToBencode};
-use rlibdns::messages::inter::dns_classes::DnsClasses;
-use crate::database::sqlite::Database;
-use crate::dns_ext::messages::inter::dns_classes_ext::DnsClassesExt;
+use crate::dns::answer_order::AnswerOrder;
+use crate::dns::cache::Cache;
 
 const UNIX_RPC_PATH: &str = "/tmp/find9.sock";
 
 pub struct UnixRpc {
     server: Option<UnixDatagram>,
-    database: Option<Database>,
+    cache: Option<Arc<Cache>>,
+    answer_order: Option<Arc<RwLock<AnswerOrder>>>,
     running: Arc<AtomicBool>
 }
 
@@ -28,14 +28,15 @@ impl UnixRpc {
     pub fn new() -> io::Result<Self> {
         Ok(Self {
             server: None,
-            database: None,
+            cache: None,
+            answer_order: None,
             running: Arc::new(AtomicBool::new(false))
         })
     }
 
     pub fn start(&mut self) -> io::Result<JoinHandle<()>> {
         if self.is_running() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Server is already running"));
+            return Err(io::Error::other("Server is already running"));
         }
 
         self.running.store(true, Ordering::Relaxed);
@@ -44,40 +45,38 @@ impl UnixRpc {
         }
 
         self.server = Some(UnixDatagram::bind(UNIX_RPC_PATH)?);
-        self.server.as_ref().unwrap().set_nonblocking(true)?;
+        //WAKE UP NOW AND THEN TO SEE IF WE WERE STOPPED RATHER THAN SPINNING ON A NON-BLOCKING SOCKET
+        self.server.as_ref().unwrap().set_read_timeout(Some(Duration::from_secs(1)))?;
 
         Ok(thread::spawn({
             let server = self.server.as_ref().unwrap().try_clone()?;
-            let database = self.database.clone();
+            let cache = self.cache.clone();
+            let answer_order = self.answer_order.clone();
             let running = Arc::clone(&self.running);
             move || {
                 let mut buf = [0u8; 65535];
 
                 while running.load(Ordering::Relaxed) {
-                    match server.recv_from(&mut buf) {
-                        Ok((size, src_addr)) => {
-                            if let Ok(bencode) = BencodeObject::from_bencode(&buf[..size]) {
-                                //let response = on_request(&database.as_ref().unwrap(), bencode);
-
-                                let bencode = match on_request(&database.as_ref().unwrap(), bencode) {
-                                    Ok(bencode) => {
-                                        bencode
-                                    }
-                                    Err(e) => {
-                                        let m = e.to_string();
-                                        let v = env!("CARGO_PKG_VERSION");
-                                        bencode!({
-                                            "s": 100,
-                                            "m": m,
-                                            "v": v
-                                        })
-                                    }
-                                };
-
-                                server.send_to_addr(&bencode.to_bencode(), &src_addr).unwrap();
-                            }
+                    if let Ok((size, src_addr)) = server.recv_from(&mut buf) {
+                        if let Ok(bencode) = BencodeObject::from_bencode(&buf[..size]) {
+                            let bencode = match on_request(cache.as_ref(), answer_order.as_ref(), bencode) {
+                                Ok(bencode) => {
+                                    bencode
+                                }
+                                Err(e) => {
+                                    let m = e.to_string();
+                                    let v = env!("CARGO_PKG_VERSION");
+                                    bencode!({
+                                        "s": 100,
+                                        "m": m,
+                                        "v": v
+                                    })
+                                }
+                            };
+
+                            //THE CLIENT MAY HAVE GONE AWAY, THAT SHOULDN'T TAKE THE LISTENER DOWN WITH IT
+                            let _ = server.send_to_addr(&bencode.to_bencode(), &src_addr);
                         }
-                        Err(_) => {}
                     }
                 }
             }
@@ -92,15 +91,19 @@ impl UnixRpc {
         self.running.load(Ordering::Relaxed)
     }
 
-    pub fn set_database(&mut self, database: Database) {
-        self.database = Some(database);
+    pub fn set_cache(&mut self, cache: Arc<Cache>) {
+        self.cache = Some(cache);
+    }
+
+    pub fn set_answer_order(&mut self, answer_order: Arc<RwLock<AnswerOrder>>) {
+        self.answer_order = Some(answer_order);
     }
 }
 
-fn on_request(database: &Database, bencode: BencodeObject) -> io::Result<BencodeObject> {
+fn on_request(cache: Option<&Arc<Cache>>, answer_order: Option<&Arc<RwLock<AnswerOrder>>>, bencode: BencodeObject) -> io::Result<BencodeObject> {
     Ok(match bencode.get::<BencodeBytes>("t").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Type not found"))?.as_str() {
-        "create" => {
-            on_create_record(database, bencode.get::<BencodeObject>("q").unwrap())?;
+        "flush" => {
+            on_flush_cache(cache.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Cache not set"))?, bencode.get::<BencodeObject>("q"))?;
 
             let v = env!("CARGO_PKG_VERSION");
             bencode!({
@@ -108,367 +111,36 @@ fn on_request(database: &Database, bencode: BencodeObject) -> io::Result<Bencode
                 "v": v
             })
         }
-        "get" => {
-            let r = on_get_record(database, bencode.get::<BencodeObject>("q").unwrap())?;
-
-            let v = env!("CARGO_PKG_VERSION");
-            bencode!({
-                "s": 0,
-                "v": v,
-                "r": r
-            })
-        }
-        "remove" => {
-            on_remove_record(database, bencode.get::<BencodeObject>("q").unwrap())?;
+        "weight" => {
+            on_set_weight(answer_order.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Answer order not set"))?, bencode.get::<BencodeObject>("q").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Query not found"))?)?;
 
             let v = env!("CARGO_PKG_VERSION");
             bencode!({
                 "s": 0,
-                "v": v,
-                //"r": r
+                "v": v
             })
         }
-        _ => unreachable!()
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Unknown type"))
     })
 }
 
-fn on_create_record(database: &Database, bencode: &BencodeObject) -> io::Result<()> {
-    let record = bencode.get::<BencodeBytes>("record").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Record not found"))?.to_string();
-    let class = DnsClasses::from_str(bencode.get::<BencodeBytes>("class").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Class not found"))?.as_str())?;
-
-    let name = bencode.get::<BencodeBytes>("name").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Name not found"))?.to_string();
-    let ttl = bencode.get::<BencodeNumber>("ttl").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "TTL not found"))?.parse::<u32>().unwrap();
-
-    let network = {
-        let local = match bencode.get::<BencodeNumber>("local") {
-            Some(b) => b.parse::<u8>()? != 0,
-            None => false
-        };
-        let external = match bencode.get::<BencodeNumber>("external") {
-            Some(b) => b.parse::<u8>()? != 0,
-            None => false
-        };
-
-        match (local, external) {
-            (true, true) | (false, false) => 1,
-            (false, true) => 2,
-            (true, false) => 0
-        }
-    };
-
-    match record.as_str() {
-        "a" => {
-            let address = bencode.get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u32>().unwrap();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.clone().into());
-            row.insert("ttl", ttl.into());
-            row.insert("address", address.into());
-            row.insert("network", network.into());
-            database.insert("a", &row);
-        }
-        "aaaa" => {
-            let address = bencode.get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u128>().unwrap();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("address", address.into());
-            row.insert("network", network.into());
-            database.insert("aaaa", &row);
-        }
-        "cname" => {
-            let target = bencode.get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("target", target.into());
-            row.insert("network", network.into());
-            database.insert("cname", &row);
-        }
-        "dnskey" => {
-            let flags = bencode.get::<BencodeNumber>("flags").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Flags not found"))?.parse::<u16>().unwrap();
-            let protocol = bencode.get::<BencodeNumber>("protocol").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Protocol not found"))?.parse::<u8>().unwrap();
-            let algorithm = bencode.get::<BencodeNumber>("algorithm").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Algorithm not found"))?.parse::<u8>().unwrap();
-            let public_key = bencode.get::<BencodeBytes>("public_key").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Public Key not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("flags", flags.into());
-            row.insert("protocol", protocol.into());
-            row.insert("algorithm", algorithm.into());
-            row.insert("public_key", public_key.into());
-            row.insert("network", network.into());
-            database.insert("dnskey", &row);
-        }
-        "https" => {
-            let priority = bencode.get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            let target = bencode.get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            //let params = bencode.get::<BencodeNumber>("params").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Params not found"))?.parse::<u8>().unwrap();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("priority", priority.into());
-            row.insert("target", target.into());
-            //row.insert("params", params.into());
-            row.insert("network", network.into());
-            database.insert("https", &row);
-        }
-        "mx" => {
-            let priority = bencode.get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            let server = bencode.get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("priority", priority.into());
-            row.insert("server", server.into());
-            row.insert("network", network.into());
-            database.insert("mx", &row);
-        }
-        "ns" => {
-            let server = bencode.get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("server", server.into());
-            row.insert("network", network.into());
-            database.insert("ns", &row);
-        }
-        "ptr" => {
-            let domain = bencode.get::<BencodeBytes>("domain").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Domain not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("domain", domain.into());
-            row.insert("network", network.into());
-            database.insert("ptr", &row);
-        }
-        "srv" => {
-            let priority = bencode.get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            let weight = bencode.get::<BencodeNumber>("weight").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Weight not found"))?.parse::<u16>().unwrap();
-            let port = bencode.get::<BencodeNumber>("port").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Port not found"))?.parse::<u16>().unwrap();
-            let target = bencode.get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("priority", priority.into());
-            row.insert("weight", weight.into());
-            row.insert("port", port.into());
-            row.insert("target", target.into());
-            row.insert("network", network.into());
-            database.insert("srv", &row);
-        }
-        "txt" => {
-            let content = bencode.get::<BencodeBytes>("content").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Content not found"))?.to_string();
-
-            let mut row = HashMap::new();
-            row.insert("class", class.get_code().into());
-            row.insert("name", name.into());
-            row.insert("ttl", ttl.into());
-            row.insert("content", content.into());
-            row.insert("network", network.into());
-            database.insert("txt", &row);
-        }
-        _ => unreachable!()
+//FLUSHES THE NAME IF ONE IS GIVEN, OTHERWISE THE WHOLE CACHE
+fn on_flush_cache(cache: &Cache, bencode: Option<&BencodeObject>) -> io::Result<()> {
+    match bencode.and_then(|bencode| bencode.get::<BencodeBytes>("name")) {
+        Some(name) => cache.flush(name.as_str()),
+        None => cache.flush_all()
     }
 
     Ok(())
 }
 
-fn on_get_record(database: &Database, bencode: &BencodeObject) -> io::Result<BencodeArray> {
-    let record = bencode.get::<BencodeBytes>("record").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Record not found"))?.to_string();
-    let class = DnsClasses::from_str(bencode.get::<BencodeBytes>("class").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Class not found"))?.as_str())?;
-
-    let name = bencode.get::<BencodeBytes>("name").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Name not found"))?.to_string();
-    //let ttl = bencode.get::<BencodeNumber>("ttl").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "TTL not found"))?.parse::<u32>().unwrap();
-
-    /*
-    let is_bogon = {
-        let local = match bencode.get::<BencodeNumber>("local") {
-            Some(b) => b.parse::<u8>().unwrap() != 0,
-            None => false
-        };
-        let external = match bencode.get::<BencodeNumber>("external") {
-            Some(b) => b.parse::<u8>().unwrap() != 0,
-            None => false
-        };
-
-        match (local, external) {
-            (true, true) | (false, false) => "",
-            (false, true) => " AND network > 0",
-            (true, false) => " AND network < 2"
-        }
-    };
-    */
-
-    let records = match record.as_str() {
-        "a" => {
-            //let address = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u32>().unwrap();
-
-            database.get(
-                "a",
-                Some(vec!["class", "ttl", "address", "network"]),
-                Some(format!("class = {} AND name = '{}'", class.get_code(), name).as_str())
-            )
-        }
-        "aaaa" => {
-            //let address = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u128>().unwrap();
-
-            database.get(
-                "aaaa",
-                Some(vec!["class", "ttl", "address", "network"]),
-                Some(format!("class = {} AND name = '{}'", class.get_code(), name).as_str())
-            )
-        }
-        "cname" => {
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            todo!()
-        }
-        "dnskey" => {
-            //let flags = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("flags").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Flags not found"))?.parse::<u16>().unwrap();
-            //let protocol = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("protocol").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Protocol not found"))?.parse::<u8>().unwrap();
-            //let algorithm = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("algorithm").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Algorithm not found"))?.parse::<u8>().unwrap();
-            //let public_key = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("public_key").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Public Key not found"))?.to_string();
-            todo!()
-        }
-        "https" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            //let params = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("params").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Params not found"))?.parse::<u8>().unwrap();
-            todo!()
-        }
-        "mx" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let server = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-            todo!()
-        }
-        "ns" => {
-            //let server = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-            todo!()
-        }
-        "ptr" => {
-            //let domain = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("domain").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Domain not found"))?.to_string();
-            todo!()
-        }
-        "srv" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let weight = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("weight").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Weight not found"))?.parse::<u16>().unwrap();
-            //let port = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("port").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Port not found"))?.parse::<u16>().unwrap();
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            todo!()
-        }
-        "txt" => {
-            //let content = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("content").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Content not found"))?.to_string();
-            todo!()
-        }
-        _ => unreachable!()
-    };
-
-    let mut r = BencodeArray::new();
-    for record in records {
-        let mut obj = BencodeObject::new();
-        for (key, value) in record {
-            obj.put(key, value);
-        }
-        //obj.put("record", "a");
-        r.push(obj);
-    }
-
-    Ok(r)
-}
-
-fn on_remove_record(database: &Database, bencode: &BencodeObject) -> io::Result<(u16, Vec<HashMap<String, String>>)> {
-    let record = bencode.get::<BencodeBytes>("record").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Record not found"))?.to_string();
-    let class = DnsClasses::from_str(bencode.get::<BencodeBytes>("class").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Class not found"))?.as_str())?;
-
+//WEIGHT OF AN ADDRESS AT THE NAME FOR WEIGHTED ANSWER ORDERING
+fn on_set_weight(answer_order: &RwLock<AnswerOrder>, bencode: &BencodeObject) -> io::Result<()> {
     let name = bencode.get::<BencodeBytes>("name").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Name not found"))?.to_string();
-    //let ttl = bencode.get::<BencodeNumber>("ttl").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "TTL not found"))?.parse::<u32>().unwrap();
+    let address = bencode.get::<BencodeBytes>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?
+        .as_str().parse::<IpAddr>().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid IP Address"))?;
+    let weight = bencode.get::<BencodeNumber>("weight").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Weight not found"))?.parse::<u32>()?;
 
-    /*
-    let is_bogon = {
-        let local = match bencode.get::<BencodeNumber>("local") {
-            Some(b) => b.parse::<u8>().unwrap() != 0,
-            None => false
-        };
-        let external = match bencode.get::<BencodeNumber>("external") {
-            Some(b) => b.parse::<u8>().unwrap() != 0,
-            None => false
-        };
-
-        match (local, external) {
-            (true, true) | (false, false) => "",
-            (false, true) => " AND network > 0",
-            (true, false) => " AND network < 2"
-        }
-    };
-    */
-
-    Ok((0, match record.as_str() {
-        "a" => {
-            //let address = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u32>().unwrap();
-            todo!()
-        }
-        "aaaa" => {
-            //let address = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("address").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IP Address not found"))?.parse::<u128>().unwrap();
-            todo!()
-        }
-        "cname" => {
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            todo!()
-        }
-        "dnskey" => {
-            //let flags = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("flags").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Flags not found"))?.parse::<u16>().unwrap();
-            //let protocol = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("protocol").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Protocol not found"))?.parse::<u8>().unwrap();
-            //let algorithm = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("algorithm").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Algorithm not found"))?.parse::<u8>().unwrap();
-            //let public_key = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("public_key").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Public Key not found"))?.to_string();
-            todo!()
-        }
-        "https" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            //let params = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("params").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Params not found"))?.parse::<u8>().unwrap();
-            todo!()
-        }
-        "mx" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let server = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-            todo!()
-        }
-        "ns" => {
-            //let server = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("server").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server not found"))?.to_string();
-            todo!()
-        }
-        "ptr" => {
-            //let domain = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("domain").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Domain not found"))?.to_string();
-            todo!()
-        }
-        "srv" => {
-            //let priority = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("priority").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Priority not found"))?.parse::<u16>().unwrap();
-            //let weight = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("weight").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Weight not found"))?.parse::<u16>().unwrap();
-            //let port = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeNumber>("port").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Port not found"))?.parse::<u16>().unwrap();
-            //let target = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("target").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Target not found"))?.to_string();
-            todo!()
-        }
-        "txt" => {
-            //let content = bencode.get::<BencodeObject>("q").unwrap().get::<BencodeBytes>("content").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Content not found"))?.to_string();
-            todo!()
-        }
-        _ => unreachable!()
-    }))
+    answer_order.write().unwrap().set_weight(&name, address, weight);
+    Ok(())
 }
@@ -0,0 +1,49 @@
+const BASE32_HEX: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+//RFC 4648 section 7 without padding, as used by NSEC3 owner names
+pub fn encode_hex(buf: &[u8]) -> String {
+    let mut out = String::with_capacity((buf.len() * 8).div_ceil(5));
+    let mut n = 0u32;
+    let mut bits = 0;
+
+    for b in buf {
+        n = (n << 8) | *b as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_HEX[(n >> bits) as usize & 0x1f] as char);
+        }
+    }
+
+    if bits > 0 {
+        out.push(BASE32_HEX[(n << (5 - bits)) as usize & 0x1f] as char);
+    }
+
+    out
+}
+
+pub fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut n = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes() {
+        let value = match c.to_ascii_lowercase() {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'a'..=b'v' => c - b'a' + 10,
+            b'=' => break,
+            _ => return None
+        };
+
+        n = (n << 5) | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
@@ -0,0 +1,415 @@
+use std::sync::OnceLock;
+use crate::utils::crypto::u256::{Modulus, U256};
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha512::Sha512;
+
+//2^255 - 19
+const P: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xed
+];
+
+//2^252 + 27742317777372353535851937790883648493
+const L: [u8; 32] = [
+    0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x14, 0xde, 0xf9, 0xde, 0xa2, 0xf7, 0x9c, 0xd6, 0x58, 0x12, 0x63, 0x1a, 0x5c, 0xf5, 0xd3, 0xed
+];
+
+//y = 4/5 WITH AN EVEN x, IN THE ENCODED LITTLE ENDIAN FORM
+const G: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66
+];
+
+pub const PUBLIC_KEY_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 64;
+
+struct Curve {
+    p: Modulus,
+    l: Modulus,
+    d: U256,
+    d2: U256,
+    sqrt_m1: U256,
+    g: Point
+}
+
+fn curve() -> &'static Curve {
+    static CURVE: OnceLock<Curve> = OnceLock::new();
+
+    CURVE.get_or_init(|| {
+        let p = Modulus::new(U256::from_be_bytes(&P));
+        let l = Modulus::new(U256::from_be_bytes(&L));
+
+        //d = -121665 / 121666
+        let d = p.mul(&p.neg(&p.to_mont(&U256([121665, 0, 0, 0]))), &p.inv(&p.to_mont(&U256([121666, 0, 0, 0]))));
+        let d2 = p.add(&d, &d);
+
+        //sqrt(-1) = 2^((p - 1) / 4)
+        let exponent = U256::from_be_bytes(&P).overflowing_sub(&U256::ONE).0;
+        let exponent = shift_right(&exponent, 2);
+        let sqrt_m1 = p.pow(&p.to_mont(&U256([2, 0, 0, 0])), &exponent);
+
+        let mut c = Curve {
+            p,
+            l,
+            d,
+            d2,
+            sqrt_m1,
+            g: Point::identity_with(U256::ZERO, U256::ZERO)
+        };
+        c.g = Point::decode_with(&c, &G).unwrap();
+        c
+    })
+}
+
+/// Extended twisted Edwards point (X : Y : Z : T) with x = X / Z, y = Y / Z and xy = T / Z, the
+/// coordinates are held in Montgomery form.
+#[derive(Copy, Clone, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+    t: U256
+}
+
+impl Point {
+
+    fn identity_with(zero: U256, one: U256) -> Self {
+        Self {
+            x: zero,
+            y: one,
+            z: one,
+            t: zero
+        }
+    }
+
+    fn identity() -> Self {
+        let f = &curve().p;
+        Self::identity_with(U256::ZERO, f.one())
+    }
+
+    //RFC 8032 section 5.1.4
+    fn add(&self, other: &Point) -> Self {
+        let c = curve();
+        let f = &c.p;
+
+        let a = f.mul(&f.sub(&self.y, &self.x), &f.sub(&other.y, &other.x));
+        let b = f.mul(&f.add(&self.y, &self.x), &f.add(&other.y, &other.x));
+        let cc = f.mul(&f.mul(&self.t, &c.d2), &other.t);
+        let d = f.mul(&f.add(&self.z, &self.z), &other.z);
+
+        let e = f.sub(&b, &a);
+        let ff = f.sub(&d, &cc);
+        let g = f.add(&d, &cc);
+        let h = f.add(&b, &a);
+
+        Self {
+            x: f.mul(&e, &ff),
+            y: f.mul(&g, &h),
+            z: f.mul(&ff, &g),
+            t: f.mul(&e, &h)
+        }
+    }
+
+    //THE ADDITION FORMULA IS COMPLETE, SO ONLY THE CHOICE OF SUM NEEDS A SELECT
+    fn mul(&self, scalar: &U256) -> Self {
+        let mut result = Self::identity();
+
+        for i in (0..256).rev() {
+            result = result.add(&result);
+            let sum = result.add(self);
+            result = Self {
+                x: U256::select(&result.x, &sum.x, scalar.bit(i)),
+                y: U256::select(&result.y, &sum.y, scalar.bit(i)),
+                z: U256::select(&result.z, &sum.z, scalar.bit(i)),
+                t: U256::select(&result.t, &sum.t, scalar.bit(i))
+            };
+        }
+
+        result
+    }
+
+    fn encode(&self) -> [u8; 32] {
+        let f = &curve().p;
+        let z_inv = f.inv(&self.z);
+        let x = f.from_mont(&f.mul(&self.x, &z_inv));
+        let y = f.from_mont(&f.mul(&self.y, &z_inv));
+
+        let mut buf = to_le_bytes(&y);
+        if x.bit(0) {
+            buf[31] |= 0x80;
+        }
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        Self::decode_with(curve(), buf)
+    }
+
+    //RFC 8032 section 5.1.3
+    fn decode_with(c: &Curve, buf: &[u8]) -> Option<Self> {
+        if buf.len() != 32 {
+            return None;
+        }
+
+        let f = &c.p;
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(buf);
+        let sign = bytes[31] & 0x80 != 0;
+        bytes[31] &= 0x7f;
+
+        let y = from_le_bytes(&bytes);
+        if &y >= f.get_modulus() {
+            return None;
+        }
+
+        let one = f.one();
+        let y = f.to_mont(&y);
+        let yy = f.square(&y);
+        let u = f.sub(&yy, &one);
+        let v = f.add(&f.mul(&c.d, &yy), &one);
+
+        //x = u v^3 (u v^7)^((p - 5) / 8)
+        let v3 = f.mul(&f.square(&v), &v);
+        let v7 = f.mul(&f.square(&v3), &v);
+        let exponent = shift_right(&U256::from_be_bytes(&P).overflowing_sub(&U256([5, 0, 0, 0])).0, 3);
+        let mut x = f.mul(&f.mul(&u, &v3), &f.pow(&f.mul(&u, &v7), &exponent));
+
+        let vxx = f.mul(&v, &f.square(&x));
+        if vxx != u {
+            if vxx != f.neg(&u) {
+                return None;
+            }
+            x = f.mul(&x, &c.sqrt_m1);
+        }
+
+        let x_normal = f.from_mont(&x);
+        if x_normal.is_zero() && sign {
+            return None;
+        }
+
+        if x_normal.bit(0) != sign {
+            x = f.neg(&x);
+        }
+
+        Some(Self {
+            x,
+            y,
+            z: one,
+            t: f.mul(&x, &y)
+        })
+    }
+
+    fn equals(&self, other: &Point) -> bool {
+        let f = &curve().p;
+        f.mul(&self.x, &other.z) == f.mul(&other.x, &self.z) && f.mul(&self.y, &other.z) == f.mul(&other.y, &self.z)
+    }
+}
+
+/// Ed25519 private key, held as the 32 byte seed from RFC 8032 that BIND stores in `.private` files.
+#[derive(Clone)]
+pub struct Ed25519PrivateKey {
+    seed: [u8; 32],
+    scalar: U256,
+    prefix: [u8; 32],
+    public_key: [u8; PUBLIC_KEY_SIZE]
+}
+
+impl Ed25519PrivateKey {
+
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != 32 {
+            return None;
+        }
+
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(buf);
+
+        let h = sha512(&[&seed]);
+
+        let mut scalar = [0u8; 32];
+        scalar.copy_from_slice(&h[..32]);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let scalar = from_le_bytes(&scalar);
+
+        let mut prefix = [0u8; 32];
+        prefix.copy_from_slice(&h[32..]);
+
+        let public_key = curve().g.mul(&scalar).encode();
+
+        Some(Self {
+            seed,
+            scalar,
+            prefix,
+            public_key
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    pub fn get_public_key(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.public_key
+    }
+
+    //RFC 8032 section 5.1.6
+    pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        let c = curve();
+        let l = &c.l;
+
+        let r = reduce_scalar(&sha512(&[&self.prefix, message]));
+        let r_encoded = c.g.mul(&r).encode();
+
+        let k = reduce_scalar(&sha512(&[&r_encoded, &self.public_key, message]));
+
+        //S = (r + k * a) mod L
+        let ka = l.from_mont(&l.mul(&l.to_mont(&k), &l.to_mont(&self.scalar)));
+        let s = l.add(&r, &ka);
+
+        let mut buf = [0u8; SIGNATURE_SIZE];
+        buf[..32].copy_from_slice(&r_encoded);
+        buf[32..].copy_from_slice(&to_le_bytes(&s));
+        buf
+    }
+}
+
+//RFC 8032 section 5.1.7
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if public_key.len() != PUBLIC_KEY_SIZE || signature.len() != SIGNATURE_SIZE {
+        return false;
+    }
+
+    let c = curve();
+
+    let a = match Point::decode(public_key) {
+        Some(a) => a,
+        None => return false
+    };
+
+    let r = match Point::decode(&signature[..32]) {
+        Some(r) => r,
+        None => return false
+    };
+
+    let s = from_le_bytes(&signature[32..]);
+    if &s >= c.l.get_modulus() {
+        return false;
+    }
+
+    let k = reduce_scalar(&sha512(&[&signature[..32], public_key, message]));
+
+    c.g.mul(&s).equals(&r.add(&a.mul(&k)))
+}
+
+fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part, 0, part.len());
+    }
+    hasher.get_value()
+}
+
+/// Reduces a 64 byte little endian hash modulo L, the high half times 2^256 is exactly what
+/// converting into Montgomery form computes.
+fn reduce_scalar(h: &[u8; 64]) -> U256 {
+    let l = &curve().l;
+    let low = l.reduce(&from_le_bytes(&h[..32]));
+    let high = l.to_mont(&from_le_bytes(&h[32..]));
+    l.add(&low, &high)
+}
+
+fn from_le_bytes(buf: &[u8]) -> U256 {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(buf);
+    bytes.reverse();
+    U256::from_be_bytes(&bytes)
+}
+
+fn to_le_bytes(value: &U256) -> [u8; 32] {
+    let mut bytes = value.to_be_bytes();
+    bytes.reverse();
+    bytes
+}
+
+fn shift_right(value: &U256, bits: u32) -> U256 {
+    let mut limbs = value.0;
+    for i in 0..4 {
+        limbs[i] >>= bits;
+        if i < 3 {
+            limbs[i] |= limbs[i + 1] << (64 - bits);
+        }
+    }
+    U256(limbs)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hex::from_hex;
+
+    fn check(secret: &str, public: &str, message: &str, signature: &str) {
+        let key = Ed25519PrivateKey::from_bytes(&from_hex(secret).unwrap()).unwrap();
+        let message = from_hex(message).unwrap();
+        let signature = from_hex(signature).unwrap();
+
+        assert_eq!(key.get_public_key().to_vec(), from_hex(public).unwrap());
+        assert_eq!(key.sign(&message).to_vec(), signature);
+        assert!(verify(&key.get_public_key(), &message, &signature));
+    }
+
+    //RFC 8032 section 7.1
+    #[test]
+    fn rfc8032_test_1() {
+        check("9D61B19DEFFD5A60BA844AF492EC2CC44449C5697B326919703BAC031CAE7F60",
+            "D75A980182B10AB7D54BFED3C964073A0EE172F3DAA62325AF021A68F707511A",
+            "",
+            "E5564300C360AC729086E2CC806E828A84877F1EB8E5D974D873E065224901555FB8821590A33BACC61E39701CF9B46BD25BF5F0595BBE24655141438E7A100B");
+    }
+
+    #[test]
+    fn rfc8032_test_2() {
+        check("4CCD089B28FF96DA9DB6C346EC114E0F5B8A319F35ABA624DA8CF6ED4FB8A6FB",
+            "3D4017C3E843895A92B70AA74D1B7EBC9C982CCF2EC4968CC0CD55F12AF4660C",
+            "72",
+            "92A009A9F0D4CAB8720E820B5F642540A2B27B5416503F8FB3762223EBDB69DA085AC1E43E15996E458F3613D0F11D8C387B2EAEB4302AEEB00D291612BB0C00");
+    }
+
+    #[test]
+    fn rfc8032_test_3() {
+        check("C5AA8DF43F9F837BEDB7442F31DCB7B166D38535076F094B85CE3A2E0B4458F7",
+            "FC51CD8E6218A1A38DA47ED00230F0580816ED13BA3303AC5DEB911548908025",
+            "AF82",
+            "6291D657DEEC24024827E69C3ABE01A30CE548A284743A445E3680D7DB5AC3AC18FF9B538D16F290AE67F760984DC6594A7C15E9716ED28DC027BECEEA1EC40A");
+    }
+
+    #[test]
+    fn verify_rejects() {
+        let key = Ed25519PrivateKey::from_bytes(&[7u8; 32]).unwrap();
+        let signature = key.sign(b"find9.net");
+        assert!(verify(&key.get_public_key(), b"find9.net", &signature));
+        assert!(!verify(&key.get_public_key(), b"find9.com", &signature));
+
+        let mut tampered = signature;
+        tampered[40] ^= 1;
+        assert!(!verify(&key.get_public_key(), b"find9.net", &tampered));
+
+        //S MUST BE BELOW L
+        let mut high = signature;
+        high[32..].copy_from_slice(&[0xff; 32]);
+        assert!(!verify(&key.get_public_key(), b"find9.net", &high));
+
+        assert!(!verify(&key.get_public_key()[..31], b"find9.net", &signature));
+        assert!(!verify(&key.get_public_key(), b"find9.net", &signature[..63]));
+    }
+
+    #[test]
+    fn seed_round_trip() {
+        assert!(Ed25519PrivateKey::from_bytes(&[0u8; 31]).is_none());
+        let key = Ed25519PrivateKey::from_bytes(&[9u8; 32]).unwrap();
+        assert_eq!(key.to_bytes(), [9u8; 32]);
+    }
+}
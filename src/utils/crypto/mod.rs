@@ -0,0 +1,5 @@
+pub mod u256;
+pub mod p256;
+pub mod ed25519;
+pub mod rsa;
+pub mod random;
@@ -0,0 +1,523 @@
+use std::sync::OnceLock;
+use crate::utils::crypto::u256::{Modulus, U256};
+use crate::utils::hash::hmac::hmac;
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha256::Sha256;
+
+const P: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
+];
+
+const N: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51
+];
+
+const B: [u8; 32] = [
+    0x5a, 0xc6, 0x35, 0xd8, 0xaa, 0x3a, 0x93, 0xe7, 0xb3, 0xeb, 0xbd, 0x55, 0x76, 0x98, 0x86, 0xbc,
+    0x65, 0x1d, 0x06, 0xb0, 0xcc, 0x53, 0xb0, 0xf6, 0x3b, 0xce, 0x3c, 0x3e, 0x27, 0xd2, 0x60, 0x4b
+];
+
+const GX: [u8; 32] = [
+    0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47, 0xf8, 0xbc, 0xe6, 0xe5, 0x63, 0xa4, 0x40, 0xf2,
+    0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33, 0xa0, 0xf4, 0xa1, 0x39, 0x45, 0xd8, 0x98, 0xc2, 0x96
+];
+
+const GY: [u8; 32] = [
+    0x4f, 0xe3, 0x42, 0xe2, 0xfe, 0x1a, 0x7f, 0x9b, 0x8e, 0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e, 0x16,
+    0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e, 0xce, 0xcb, 0xb6, 0x40, 0x68, 0x37, 0xbf, 0x51, 0xf5
+];
+
+pub const PUBLIC_KEY_SIZE: usize = 64;
+pub const SIGNATURE_SIZE: usize = 64;
+
+struct Curve {
+    p: Modulus,
+    n: Modulus,
+    b: U256,
+    g: Point
+}
+
+fn curve() -> &'static Curve {
+    static CURVE: OnceLock<Curve> = OnceLock::new();
+
+    CURVE.get_or_init(|| {
+        let p = Modulus::new(U256::from_be_bytes(&P));
+        let n = Modulus::new(U256::from_be_bytes(&N));
+        let b = p.to_mont(&U256::from_be_bytes(&B));
+        let g = Point {
+            x: p.to_mont(&U256::from_be_bytes(&GX)),
+            y: p.to_mont(&U256::from_be_bytes(&GY)),
+            z: p.one()
+        };
+
+        Curve {
+            p,
+            n,
+            b,
+            g
+        }
+    })
+}
+
+/// Jacobian point with coordinates held in Montgomery form, z = 0 is the point at infinity.
+#[derive(Copy, Clone, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256
+}
+
+impl Point {
+
+    fn infinity() -> Self {
+        Self {
+            x: U256::ZERO,
+            y: U256::ZERO,
+            z: U256::ZERO
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn select(a: &Point, b: &Point, choice: bool) -> Self {
+        Self {
+            x: U256::select(&a.x, &b.x, choice),
+            y: U256::select(&a.y, &b.y, choice),
+            z: U256::select(&a.z, &b.z, choice)
+        }
+    }
+
+    //THE POINT AT INFINITY NEEDS NO SPECIAL CASE, z = 2yz COMES OUT AS 0 AGAIN, AND THE CURVE HAS
+    //PRIME ORDER SO THERE IS NO OTHER POINT WITH y = 0
+    fn double(&self) -> Self {
+        let f = &curve().p;
+        let delta = f.square(&self.z);
+        let gamma = f.square(&self.y);
+        let beta = f.mul(&self.x, &gamma);
+
+        let alpha = f.mul(&f.sub(&self.x, &delta), &f.add(&self.x, &delta));
+        let alpha = f.add(&f.add(&alpha, &alpha), &alpha);
+
+        let beta4 = f.add(&beta, &beta);
+        let beta4 = f.add(&beta4, &beta4);
+        let beta8 = f.add(&beta4, &beta4);
+
+        let x = f.sub(&f.square(&alpha), &beta8);
+
+        let z = f.square(&f.add(&self.y, &self.z));
+        let z = f.sub(&f.sub(&z, &gamma), &delta);
+
+        let gamma2 = f.square(&gamma);
+        let gamma8 = f.add(&gamma2, &gamma2);
+        let gamma8 = f.add(&gamma8, &gamma8);
+        let gamma8 = f.add(&gamma8, &gamma8);
+        let y = f.sub(&f.mul(&alpha, &f.sub(&beta4, &x)), &gamma8);
+
+        Self {
+            x,
+            y,
+            z
+        }
+    }
+
+    /// Every case is worked out and the right one picked with selects, so adding to the point at
+    /// infinity or to itself takes as long as any other sum.
+    fn add(&self, other: &Point) -> Self {
+        let f = &curve().p;
+        let z1z1 = f.square(&self.z);
+        let z2z2 = f.square(&other.z);
+        let u1 = f.mul(&self.x, &z2z2);
+        let u2 = f.mul(&other.x, &z1z1);
+        let s1 = f.mul(&f.mul(&self.y, &other.z), &z2z2);
+        let s2 = f.mul(&f.mul(&other.y, &self.z), &z1z1);
+
+        let h = f.sub(&u2, &u1);
+        let r = f.sub(&s2, &s1);
+
+        //h = 0 WITH r = 0 IS THE SAME POINT TWICE, WITH r != 0 IT IS P + -P AND z BELOW COMES OUT AS 0
+        let same = h.is_zero() & r.is_zero();
+
+        let h2 = f.add(&h, &h);
+        let i = f.square(&h2);
+        let j = f.mul(&h, &i);
+        let r = f.add(&r, &r);
+        let v = f.mul(&u1, &i);
+
+        let x = f.sub(&f.sub(&f.square(&r), &j), &f.add(&v, &v));
+
+        let s1j = f.mul(&s1, &j);
+        let y = f.sub(&f.mul(&r, &f.sub(&v, &x)), &f.add(&s1j, &s1j));
+
+        let z = f.square(&f.add(&self.z, &other.z));
+        let z = f.mul(&f.sub(&f.sub(&z, &z1z1), &z2z2), &h);
+
+        let sum = Self {
+            x,
+            y,
+            z
+        };
+
+        let sum = Self::select(&sum, &self.double(), same);
+        let sum = Self::select(&sum, other, self.is_infinity());
+        Self::select(&sum, self, other.is_infinity())
+    }
+
+    /// Montgomery ladder over all 256 bits of the scalar, each step is one add and one double
+    /// whatever the bit is, keeping r1 = r0 + P throughout.
+    fn mul(&self, scalar: &U256) -> Self {
+        let mut r0 = Self::infinity();
+        let mut r1 = *self;
+
+        for i in (0..256).rev() {
+            let bit = scalar.bit(i);
+
+            //SWAP SO THE DOUBLING ALWAYS LANDS ON r0, THEN SWAP BACK
+            let a = Self::select(&r0, &r1, bit);
+            let b = Self::select(&r1, &r0, bit);
+            let sum = a.add(&b);
+            let double = a.double();
+            r0 = Self::select(&double, &sum, bit);
+            r1 = Self::select(&sum, &double, bit);
+        }
+
+        r0
+    }
+
+    fn to_affine(self) -> Option<(U256, U256)> {
+        if self.is_infinity() {
+            return None;
+        }
+
+        let f = &curve().p;
+        let z_inv = f.inv(&self.z);
+        let z_inv2 = f.square(&z_inv);
+        let z_inv3 = f.mul(&z_inv2, &z_inv);
+
+        Some((f.from_mont(&f.mul(&self.x, &z_inv2)), f.from_mont(&f.mul(&self.y, &z_inv3))))
+    }
+
+    fn from_affine(x: &U256, y: &U256) -> Option<Self> {
+        let c = curve();
+        let f = &c.p;
+
+        if x >= f.get_modulus() || y >= f.get_modulus() {
+            return None;
+        }
+
+        let x = f.to_mont(x);
+        let y = f.to_mont(y);
+
+        //y^2 = x^3 - 3x + b
+        let rhs = f.mul(&f.square(&x), &x);
+        let x3 = f.add(&f.add(&x, &x), &x);
+        let rhs = f.add(&f.sub(&rhs, &x3), &c.b);
+
+        if f.square(&y) != rhs {
+            return None;
+        }
+
+        Some(Self {
+            x,
+            y,
+            z: f.one()
+        })
+    }
+}
+
+/// ECDSA P-256 private key, signatures and public keys use the fixed size r | s and x | y
+/// layouts from RFC 6605 rather than DER.
+#[derive(Clone)]
+pub struct P256PrivateKey {
+    d: U256
+}
+
+impl P256PrivateKey {
+
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != 32 {
+            return None;
+        }
+
+        let d = U256::from_be_bytes(buf);
+        if d.is_zero() || !d.overflowing_sub(curve().n.get_modulus()).1 {
+            return None;
+        }
+
+        Some(Self {
+            d
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.d.to_be_bytes()
+    }
+
+    pub fn get_public_key(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        let (x, y) = curve().g.mul(&self.d).to_affine().unwrap();
+
+        let mut buf = [0u8; PUBLIC_KEY_SIZE];
+        buf[..32].copy_from_slice(&x.to_be_bytes());
+        buf[32..].copy_from_slice(&y.to_be_bytes());
+        buf
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        let mut hasher = Sha256::new();
+        hasher.update(message, 0, message.len());
+        self.sign_digest(&hasher.get_value())
+    }
+
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> [u8; SIGNATURE_SIZE] {
+        let c = curve();
+        let n = &c.n;
+        let e = n.reduce(&U256::from_be_bytes(digest));
+
+        let mut nonces = Rfc6979::new(&self.d, &e);
+
+        loop {
+            let k = nonces.next();
+
+            let (x, _) = match c.g.mul(&k).to_affine() {
+                Some(point) => point,
+                None => continue
+            };
+
+            let r = n.reduce(&x);
+            if r.is_zero() {
+                continue;
+            }
+
+            //s = k^-1 * (e + r * d) mod n
+            let k_inv = n.inv(&n.to_mont(&k));
+            let rd = n.mul(&n.to_mont(&r), &n.to_mont(&self.d));
+            let s = n.from_mont(&n.mul(&k_inv, &n.add(&n.to_mont(&e), &rd)));
+            if s.is_zero() {
+                continue;
+            }
+
+            let mut buf = [0u8; SIGNATURE_SIZE];
+            buf[..32].copy_from_slice(&r.to_be_bytes());
+            buf[32..].copy_from_slice(&s.to_be_bytes());
+            return buf;
+        }
+    }
+}
+
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.update(message, 0, message.len());
+    verify_digest(public_key, &hasher.get_value(), signature)
+}
+
+pub fn verify_digest(public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> bool {
+    if public_key.len() != PUBLIC_KEY_SIZE || signature.len() != SIGNATURE_SIZE {
+        return false;
+    }
+
+    let c = curve();
+    let n = &c.n;
+
+    let q = match Point::from_affine(&U256::from_be_bytes(&public_key[..32]), &U256::from_be_bytes(&public_key[32..])) {
+        Some(q) => q,
+        None => return false
+    };
+
+    let r = U256::from_be_bytes(&signature[..32]);
+    let s = U256::from_be_bytes(&signature[32..]);
+    if r.is_zero() || s.is_zero() || &r >= n.get_modulus() || &s >= n.get_modulus() {
+        return false;
+    }
+
+    let e = n.reduce(&U256::from_be_bytes(digest));
+    let w = n.inv(&n.to_mont(&s));
+    let u1 = n.from_mont(&n.mul(&n.to_mont(&e), &w));
+    let u2 = n.from_mont(&n.mul(&n.to_mont(&r), &w));
+
+    match c.g.mul(&u1).add(&q.mul(&u2)).to_affine() {
+        Some((x, _)) => n.reduce(&x) == r,
+        None => false
+    }
+}
+
+/// Deterministic nonce generation from RFC 6979 section 3.2 using HMAC-SHA256.
+struct Rfc6979 {
+    k: [u8; 32],
+    v: [u8; 32],
+    first: bool
+}
+
+impl Rfc6979 {
+
+    fn new(d: &U256, e: &U256) -> Self {
+        let x = d.to_be_bytes();
+        let h = e.to_be_bytes();
+
+        let v = [0x01u8; 32];
+        let k = [0x00u8; 32];
+
+        let k = hmac::<Sha256>(&k, &[&v[..], &[0x00], &x, &h].concat());
+        let v = hmac::<Sha256>(&k, &v);
+        let k = hmac::<Sha256>(&k, &[&v[..], &[0x01], &x, &h].concat());
+        let v = hmac::<Sha256>(&k, &v);
+
+        Self {
+            k,
+            v,
+            first: true
+        }
+    }
+
+    fn next(&mut self) -> U256 {
+        let n = curve().n.get_modulus();
+
+        loop {
+            if !self.first {
+                self.k = hmac::<Sha256>(&self.k, &[&self.v[..], &[0x00]].concat());
+                self.v = hmac::<Sha256>(&self.k, &self.v);
+            }
+            self.first = false;
+
+            self.v = hmac::<Sha256>(&self.k, &self.v);
+            let k = U256::from_be_bytes(&self.v);
+
+            if !k.is_zero() && k.overflowing_sub(n).1 {
+                return k;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hex::from_hex;
+
+    //RFC 6979 section A.2.5 - ECDSA, 256 BITS (PRIME FIELD)
+    const PRIVATE_KEY: &str = "C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721";
+    const PUBLIC_KEY: &str = "60FED4BA255A9D31C961EB74C6356D68C049B8923B61FA6CE669622E60F29FB6\
+        7903FE1008B8BC99A41AE9E95628BC64F2F1B20C2D7E9F5177A3C294D4462299";
+
+    fn key() -> P256PrivateKey {
+        P256PrivateKey::from_bytes(&from_hex(PRIVATE_KEY).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn public_key() {
+        assert_eq!(key().get_public_key().to_vec(), from_hex(PUBLIC_KEY).unwrap());
+    }
+
+    #[test]
+    fn rfc6979_sample() {
+        let signature = key().sign(b"sample");
+        assert_eq!(signature.to_vec(), from_hex("EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716\
+            F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8").unwrap());
+    }
+
+    #[test]
+    fn rfc6979_test() {
+        let signature = key().sign(b"test");
+        assert_eq!(signature.to_vec(), from_hex("F1ABB023518351CD71D881567B1EA663ED3EFCF6C5132B354F28D3B0B7D38367\
+            019F4113742A2B14BD25926B49C649155F267E60D3814B4C0CC84250E46F0083").unwrap());
+    }
+
+    #[test]
+    fn rfc6979_nonce() {
+        let c = curve();
+        let d = U256::from_be_bytes(&from_hex(PRIVATE_KEY).unwrap());
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"sample", 0, 6);
+        let e = c.n.reduce(&U256::from_be_bytes(&hasher.get_value()));
+
+        let k = Rfc6979::new(&d, &e).next();
+        assert_eq!(k.to_be_bytes().to_vec(), from_hex("A6E3C57DD01ABE90086538398355DD4C3B17AA873382B0F24D6129493D8AAD60").unwrap());
+    }
+
+    #[test]
+    fn sign_verify() {
+        let key = key();
+        let public_key = key.get_public_key();
+
+        for message in [&b""[..], b"sample", b"find9.net. 3600 IN A 127.0.0.1"] {
+            let signature = key.sign(message);
+            assert!(verify(&public_key, message, &signature));
+        }
+    }
+
+    #[test]
+    fn verify_rejects() {
+        let key = key();
+        let public_key = key.get_public_key();
+        let signature = key.sign(b"sample");
+
+        assert!(!verify(&public_key, b"samplf", &signature));
+
+        let mut tampered = signature;
+        tampered[63] ^= 0x01;
+        assert!(!verify(&public_key, b"sample", &tampered));
+
+        //r AND s MUST BE IN [1, n - 1]
+        let mut zero = signature;
+        zero[..32].fill(0);
+        assert!(!verify(&public_key, b"sample", &zero));
+
+        let mut high = signature;
+        high[32..].copy_from_slice(&N);
+        assert!(!verify(&public_key, b"sample", &high));
+
+        //THE KEY HAS TO BE ON THE CURVE
+        let mut off_curve = public_key;
+        off_curve[63] ^= 0x01;
+        assert!(!verify(&off_curve, b"sample", &signature));
+
+        assert!(!verify(&public_key[..63], b"sample", &signature));
+    }
+
+    #[test]
+    fn scalar_edges() {
+        let c = curve();
+        let f = &c.p;
+        let n = *c.n.get_modulus();
+        let g = c.g.to_affine().unwrap();
+
+        assert!(c.g.mul(&U256::ZERO).is_infinity());
+        assert!(c.g.mul(&n).is_infinity());
+        assert_eq!(c.g.mul(&U256::ONE).to_affine(), Some(g));
+        assert_eq!(c.g.mul(&U256([2, 0, 0, 0])).to_affine(), c.g.double().to_affine());
+        assert_eq!(c.g.mul(&U256([3, 0, 0, 0])).to_affine(), c.g.double().add(&c.g).to_affine());
+
+        //(n - 1)G = -G
+        let minus = c.g.mul(&n.overflowing_sub(&U256::ONE).0).to_affine().unwrap();
+        assert_eq!(minus, (g.0, f.get_modulus().overflowing_sub(&g.1).0));
+    }
+
+    #[test]
+    fn add_cases() {
+        let c = curve();
+        let f = &c.p;
+        let negated = Point {
+            x: c.g.x,
+            y: f.neg(&c.g.y),
+            z: c.g.z
+        };
+
+        assert_eq!(c.g.add(&c.g).to_affine(), c.g.double().to_affine());
+        assert!(c.g.add(&negated).is_infinity());
+        assert_eq!(Point::infinity().add(&c.g).to_affine(), c.g.to_affine());
+        assert_eq!(c.g.add(&Point::infinity()).to_affine(), c.g.to_affine());
+        assert!(Point::infinity().add(&Point::infinity()).is_infinity());
+        assert!(Point::infinity().double().is_infinity());
+    }
+
+    #[test]
+    fn invalid_private_keys() {
+        assert!(P256PrivateKey::from_bytes(&[0u8; 32]).is_none());
+        assert!(P256PrivateKey::from_bytes(&N).is_none());
+        assert!(P256PrivateKey::from_bytes(&[1u8; 31]).is_none());
+    }
+}
@@ -0,0 +1,9 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+
+/// Fills the buffer from the kernel CSPRNG, used for key material so it must never fall back to
+/// anything weaker.
+pub fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    File::open("/dev/urandom")?.read_exact(buf)
+}
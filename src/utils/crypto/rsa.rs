@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use crate::utils::hash::inter::hash::Hash;
+use crate::utils::hash::sha256::Sha256;
+
+//RFC 8017 section 9.2 - DER encoding of the DigestInfo for SHA-256
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20
+];
+
+//RFC 3110 section 2 - KEYS FROM 512 TO 4096 BITS, RFC 8624 ONLY ASKS US TO VALIDATE 1024 AND UP
+const MIN_MODULUS_SIZE: usize = 128;
+const MAX_MODULUS_SIZE: usize = 512;
+
+//LIKE BIND AND UNBOUND, EXPONENTS LARGER THAN 4 BYTES ARE REFUSED, THEY ONLY MAKE VERIFYING SLOWER
+const MAX_EXPONENT_SIZE: usize = 4;
+
+/// Arbitrary size unsigned integer, just enough for RSA verification. Limbs are little endian and
+/// never have leading zero limbs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BigUint(Vec<u32>);
+
+impl BigUint {
+
+    fn from_be_bytes(buf: &[u8]) -> Self {
+        let mut limbs = Vec::with_capacity(buf.len() / 4 + 1);
+
+        for chunk in buf.rchunks(4) {
+            let mut limb = [0u8; 4];
+            limb[4 - chunk.len()..].copy_from_slice(chunk);
+            limbs.push(u32::from_be_bytes(limb));
+        }
+
+        let mut value = Self(limbs);
+        value.normalize();
+        value
+    }
+
+    fn to_be_bytes(&self, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+
+        for (i, limb) in self.0.iter().enumerate() {
+            for (j, byte) in limb.to_le_bytes().iter().enumerate() {
+                let pos = i * 4 + j;
+                if pos < len {
+                    buf[len - 1 - pos] = *byte;
+                }
+            }
+        }
+
+        buf
+    }
+
+    fn normalize(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn bits(&self) -> usize {
+        match self.0.last() {
+            Some(last) => (self.0.len() - 1) * 32 + (32 - last.leading_zeros() as usize),
+            None => 0
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0.get(i / 32).map(|limb| (limb >> (i % 32)) & 1 == 1).unwrap_or(false)
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match self.0.len().cmp(&other.0.len()) {
+            Ordering::Equal => self.0.iter().rev().cmp(other.0.iter().rev()),
+            ordering => ordering
+        }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0u32; self.0.len() + other.0.len()];
+
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.0.iter().enumerate() {
+                let t = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            limbs[i + other.0.len()] = carry as u32;
+        }
+
+        let mut value = Self(limbs);
+        value.normalize();
+        value
+    }
+
+    fn shl1_or(&mut self, bit: bool) {
+        let mut carry = bit as u32;
+        for limb in self.0.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+
+        if carry != 0 {
+            self.0.push(carry);
+        }
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        let mut borrow = 0i64;
+        for i in 0..self.0.len() {
+            let t = self.0[i] as i64 - other.0.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = (t < 0) as i64;
+            self.0[i] = (t + (borrow << 32)) as u32;
+        }
+
+        self.normalize();
+    }
+
+    //BIT BY BIT LONG DIVISION, SLOW BUT ONLY A HANDFUL ARE NEEDED PER SIGNATURE
+    fn rem(&self, m: &Self) -> Self {
+        let mut r = Self(Vec::new());
+
+        for i in (0..self.bits()).rev() {
+            r.shl1_or(self.bit(i));
+            if r.compare(m) != Ordering::Less {
+                r.sub_assign(m);
+            }
+        }
+
+        r
+    }
+
+    fn mod_pow(&self, exponent: &Self, m: &Self) -> Self {
+        let mut result = Self(vec![1]).rem(m);
+        let base = self.rem(m);
+
+        for i in (0..exponent.bits()).rev() {
+            result = result.mul(&result).rem(m);
+            if exponent.bit(i) {
+                result = result.mul(&base).rem(m);
+            }
+        }
+
+        result
+    }
+}
+
+/// Splits a DNSKEY public key in the RFC 3110 format into its exponent and modulus, without leading
+/// zeros. Keys with an exponent or modulus outside of what we verify are refused.
+pub fn parse_public_key(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, off) = match *buf.first()? {
+        0 => (u16::from_be_bytes([*buf.get(1)?, *buf.get(2)?]) as usize, 3),
+        len => (len as usize, 1)
+    };
+
+    let exponent = trim_leading_zeros(buf.get(off..off + len)?);
+    let modulus = trim_leading_zeros(buf.get(off + len..)?);
+
+    if exponent.is_empty() || exponent.len() > MAX_EXPONENT_SIZE || !(MIN_MODULUS_SIZE..=MAX_MODULUS_SIZE).contains(&modulus.len()) {
+        return None;
+    }
+
+    Some((exponent, modulus))
+}
+
+//RFC 5702 - RSA/SHA-256 DNSSEC SIGNATURES ARE RSASSA-PKCS1-v1_5
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.update(message, 0, message.len());
+    verify_digest(public_key, &hasher.get_value(), signature)
+}
+
+pub fn verify_digest(public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> bool {
+    let (exponent, modulus) = match parse_public_key(public_key) {
+        Some(key) => key,
+        None => return false
+    };
+
+    let k = modulus.len();
+    if signature.len() != k {
+        return false;
+    }
+
+    let n = BigUint::from_be_bytes(modulus);
+    let e = BigUint::from_be_bytes(exponent);
+    let s = BigUint::from_be_bytes(signature);
+
+    if s.compare(&n) != Ordering::Less {
+        return false;
+    }
+
+    //EM = 0x00 || 0x01 || PS || 0x00 || DigestInfo || H
+    let t_len = SHA256_DIGEST_INFO.len() + digest.len();
+    let mut expected = vec![0xffu8; k];
+    expected[0] = 0x00;
+    expected[1] = 0x01;
+    expected[k - t_len - 1] = 0x00;
+    expected[k - t_len..k - digest.len()].copy_from_slice(&SHA256_DIGEST_INFO);
+    expected[k - digest.len()..].copy_from_slice(digest);
+
+    s.mod_pow(&e, &n).to_be_bytes(k) == expected
+}
+
+fn trim_leading_zeros(buf: &[u8]) -> &[u8] {
+    let start = buf.iter().position(|b| *b != 0).unwrap_or(buf.len());
+    &buf[start..]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hex::from_hex;
+
+    //1024 BIT KEY WITH E = 65537 IN THE RFC 3110 FORMAT, THE SIGNATURE IS OVER "find9.net"
+    const PUBLIC_KEY: &str = "03010001ACEE4EECEB74EEF2608F371926E5991A3FD245284A8EDE5453A7964A084277B7A93F7E5890D883DC2249DFF3DF30A313D85D636EA0D564D6F1C42ED0F9C3075E59B71BD976A3F76578840C93513E71CAC474E8936FB55EB6DD3FE2513D16BD04995A024118830640B1BC13D7DDAC46CCFF32F863AE81ACE9AB990F940AEA2801";
+    const SIGNATURE: &str = "2FD7F94D9044162D320B517BADCD4356B9B4E9466E4F7D68BC37F4854A3A92DFF7FC6B7074E67C3C07CC66647DA2E7852B20C1CAD7619E5762ACECB95B5ECEA45298F96608B57709F08D44B0E752B79F8C014E825E75D9F90DFC5D1434E07DA7D39F9ACAD0F5432F41CA8A8F625B2C12A64033CB529B376F5D47A23DBA445679";
+
+    #[test]
+    fn verify_signature() {
+        let public_key = from_hex(PUBLIC_KEY).unwrap();
+        let signature = from_hex(SIGNATURE).unwrap();
+        assert!(verify(&public_key, b"find9.net", &signature));
+        assert!(!verify(&public_key, b"find9.com", &signature));
+
+        let mut tampered = signature.clone();
+        tampered[64] ^= 1;
+        assert!(!verify(&public_key, b"find9.net", &tampered));
+        assert!(!verify(&public_key, b"find9.net", &signature[1..]));
+    }
+
+    #[test]
+    fn parse_key() {
+        let public_key = from_hex(PUBLIC_KEY).unwrap();
+        let (exponent, modulus) = parse_public_key(&public_key).unwrap();
+        assert_eq!(exponent, &[0x01, 0x00, 0x01]);
+        assert_eq!(modulus.len(), 128);
+
+        //THE 3 BYTE LENGTH FORM
+        let mut long = vec![0x00, 0x00, 0x03];
+        long.extend_from_slice(&public_key[1..]);
+        assert_eq!(parse_public_key(&long), Some((exponent, modulus)));
+
+        assert_eq!(parse_public_key(&[]), None);
+        assert_eq!(parse_public_key(&[0x00, 0x01]), None);
+        assert_eq!(parse_public_key(&public_key[..3]), None);
+    }
+
+    #[test]
+    fn exponent_limits() {
+        let modulus = &from_hex(PUBLIC_KEY).unwrap()[4..];
+
+        let key = |exponent: &[u8]| {
+            let mut buf = vec![exponent.len() as u8];
+            buf.extend_from_slice(exponent);
+            buf.extend_from_slice(modulus);
+            buf
+        };
+
+        assert!(parse_public_key(&key(&[0x01, 0x00, 0x00, 0x01])).is_some());
+        assert!(parse_public_key(&key(&[0x00, 0x00, 0x01, 0x00, 0x01])).is_some());
+        assert!(parse_public_key(&key(&[0x01, 0x00, 0x00, 0x00, 0x01])).is_none());
+        assert!(parse_public_key(&key(&[0x00])).is_none());
+        assert!(parse_public_key(&key(&[])).is_none());
+    }
+
+    #[test]
+    fn modulus_limits() {
+        let key = |size: usize| {
+            let mut buf = vec![0x03, 0x01, 0x00, 0x01];
+            buf.extend(std::iter::repeat(0xff).take(size));
+            buf
+        };
+
+        assert!(parse_public_key(&key(MIN_MODULUS_SIZE - 1)).is_none());
+        assert!(parse_public_key(&key(MIN_MODULUS_SIZE)).is_some());
+        assert!(parse_public_key(&key(MAX_MODULUS_SIZE)).is_some());
+        assert!(parse_public_key(&key(MAX_MODULUS_SIZE + 1)).is_none());
+
+        //LEADING ZEROS DON'T COUNT TOWARDS THE SIZE
+        let mut padded = key(MIN_MODULUS_SIZE - 1);
+        padded.insert(4, 0x00);
+        assert!(parse_public_key(&padded).is_none());
+    }
+
+    #[test]
+    fn big_uint() {
+        let n = BigUint::from_be_bytes(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(n.bits(), 33);
+        assert_eq!(n.to_be_bytes(6), vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x01]);
+
+        //4^13 MOD 497 = 445
+        let r = BigUint::from_be_bytes(&[4]).mod_pow(&BigUint::from_be_bytes(&[13]), &BigUint::from_be_bytes(&[0x01, 0xf1]));
+        assert_eq!(r, BigUint::from_be_bytes(&[0x01, 0xbd]));
+    }
+}
@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct U256(pub [u64; 4]);
+
+impl U256 {
+
+    pub const ZERO: U256 = U256([0, 0, 0, 0]);
+    pub const ONE: U256 = U256([1, 0, 0, 0]);
+
+    pub fn from_be_bytes(buf: &[u8]) -> Self {
+        let mut padded = [0u8; 32];
+        let len = buf.len().min(32);
+        padded[32 - len..].copy_from_slice(&buf[buf.len() - len..]);
+
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            let mut limb = [0u8; 8];
+            limb.copy_from_slice(&padded[24 - i * 8..32 - i * 8]);
+            limbs[i] = u64::from_be_bytes(limb);
+        }
+
+        Self(limbs)
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        for i in 0..4 {
+            buf[24 - i * 8..32 - i * 8].copy_from_slice(&self.0[i].to_be_bytes());
+        }
+
+        buf
+    }
+
+    pub fn is_zero(&self) -> bool {
+        (self.0[0] | self.0[1] | self.0[2] | self.0[3]) == 0
+    }
+
+    pub fn bit(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn bits(&self) -> usize {
+        for i in (0..4).rev() {
+            if self.0[i] != 0 {
+                return i * 64 + 64 - self.0[i].leading_zeros() as usize;
+            }
+        }
+
+        0
+    }
+
+    pub fn overflowing_add(&self, other: &U256) -> (U256, bool) {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+
+        for (i, word) in out.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *word = sum;
+            carry = c1 | c2;
+        }
+
+        (U256(out), carry)
+    }
+
+    pub fn overflowing_sub(&self, other: &U256) -> (U256, bool) {
+        let mut out = [0u64; 4];
+        let mut borrow = false;
+
+        for (i, word) in out.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *word = diff;
+            borrow = b1 | b2;
+        }
+
+        (U256(out), borrow)
+    }
+
+    pub fn shift_left(&self, n: usize) -> U256 {
+        let words = n / 64;
+        let bits = n % 64;
+        let mut out = [0u64; 4];
+
+        for (i, word) in out.iter_mut().enumerate().skip(words) {
+            *word = self.0[i - words] << bits;
+            if bits != 0 && i > words {
+                *word |= self.0[i - words - 1] >> (64 - bits);
+            }
+        }
+
+        U256(out)
+    }
+
+    /// Picks `b` when `choice` is set and `a` otherwise through a mask, there is no branch on
+    /// `choice` for the timing to give away.
+    pub fn select(a: &U256, b: &U256, choice: bool) -> U256 {
+        let mask = (choice as u64).wrapping_neg();
+        let mut out = [0u64; 4];
+
+        for (i, word) in out.iter_mut().enumerate() {
+            *word = a.0[i] ^ (mask & (a.0[i] ^ b.0[i]));
+        }
+
+        U256(out)
+    }
+}
+
+impl PartialOrd for U256 {
+
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        for i in (0..4).rev() {
+            match self.0[i].cmp(&other.0[i]) {
+                Ordering::Equal => continue,
+                ordering => return ordering
+            }
+        }
+
+        Ordering::Equal
+    }
+}
+
+/// Arithmetic modulo an odd 256 bit modulus using Montgomery multiplication, values passed to
+/// `mul`, `square`, `pow` and `inv` are expected to already be in Montgomery form. Nothing here
+/// branches on the values, only on the modulus, so secret operands take the same time whatever
+/// they hold.
+pub struct Modulus {
+    m: U256,
+    inv: u64,
+    r2: U256
+}
+
+impl Modulus {
+
+    pub fn new(m: U256) -> Self {
+        let mut x = 1u64;
+        for _ in 0..6 {
+            x = x.wrapping_mul(2u64.wrapping_sub(m.0[0].wrapping_mul(x)));
+        }
+
+        let mut modulus = Self {
+            m,
+            inv: x.wrapping_neg(),
+            r2: U256::ZERO
+        };
+
+        let mut r2 = U256::ONE;
+        for _ in 0..512 {
+            r2 = modulus.add(&r2, &r2);
+        }
+        modulus.r2 = r2;
+
+        modulus
+    }
+
+    pub fn get_modulus(&self) -> &U256 {
+        &self.m
+    }
+
+    pub fn reduce(&self, a: &U256) -> U256 {
+        //ONE CONDITIONAL SUBTRACTION OF m * 2^i FOR EVERY BIT m IS SHORT OF 256, HIGHEST FIRST
+        let mut a = *a;
+        for shift in (0..=256 - self.m.bits()).rev() {
+            let (diff, borrow) = a.overflowing_sub(&self.m.shift_left(shift));
+            a = U256::select(&diff, &a, borrow);
+        }
+
+        a
+    }
+
+    pub fn add(&self, a: &U256, b: &U256) -> U256 {
+        let (sum, carry) = a.overflowing_add(b);
+        let (diff, borrow) = sum.overflowing_sub(&self.m);
+        U256::select(&sum, &diff, carry | !borrow)
+    }
+
+    pub fn sub(&self, a: &U256, b: &U256) -> U256 {
+        let (diff, borrow) = a.overflowing_sub(b);
+        let wrapped = diff.overflowing_add(&self.m).0;
+        U256::select(&diff, &wrapped, borrow)
+    }
+
+    pub fn neg(&self, a: &U256) -> U256 {
+        self.sub(&U256::ZERO, a)
+    }
+
+    pub fn mul(&self, a: &U256, b: &U256) -> U256 {
+        let mut t = [0u64; 6];
+
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for (j, word) in t.iter_mut().take(4).enumerate() {
+                let v = *word as u128 + (a.0[j] as u128) * (b.0[i] as u128) + carry;
+                *word = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[4] = v as u64;
+            t[5] = (v >> 64) as u64;
+
+            let m = t[0].wrapping_mul(self.inv);
+            let mut carry = (t[0] as u128 + (m as u128) * (self.m.0[0] as u128)) >> 64;
+            for j in 1..4 {
+                let v = t[j] as u128 + (m as u128) * (self.m.0[j] as u128) + carry;
+                t[j - 1] = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[3] = v as u64;
+            t[4] = t[5] + (v >> 64) as u64;
+        }
+
+        let result = U256([t[0], t[1], t[2], t[3]]);
+        let (diff, borrow) = result.overflowing_sub(&self.m);
+        U256::select(&result, &diff, (t[4] != 0) | !borrow)
+    }
+
+    pub fn square(&self, a: &U256) -> U256 {
+        self.mul(a, a)
+    }
+
+    pub fn to_mont(&self, a: &U256) -> U256 {
+        self.mul(&self.reduce(a), &self.r2)
+    }
+
+    pub fn from_mont(&self, a: &U256) -> U256 {
+        self.mul(a, &U256::ONE)
+    }
+
+    pub fn one(&self) -> U256 {
+        self.to_mont(&U256::ONE)
+    }
+
+    /// Square and multiply over all 256 bits of the exponent, the product is worked out for every
+    /// bit and only kept through a select.
+    pub fn pow(&self, base: &U256, exponent: &U256) -> U256 {
+        let mut result = self.one();
+
+        for i in (0..256).rev() {
+            result = self.square(&result);
+            let product = self.mul(&result, base);
+            result = U256::select(&result, &product, exponent.bit(i));
+        }
+
+        result
+    }
+
+    /// Fermat inversion, a^(m - 2), so the modulus has to be prime.
+    pub fn inv(&self, a: &U256) -> U256 {
+        let exponent = self.m.overflowing_sub(&U256([2, 0, 0, 0])).0;
+        self.pow(a, &exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hex::from_hex;
+
+    const P256_N: &str = "FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551";
+    const ED25519_L: &str = "1000000000000000000000000000000014DEF9DEA2F79CD65812631A5CF5D3ED";
+
+    #[test]
+    fn bytes() {
+        let buf = from_hex("0102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F20").unwrap();
+        let a = U256::from_be_bytes(&buf);
+        assert_eq!(a.to_be_bytes().to_vec(), buf);
+        assert_eq!(a.0[0], 0x191a1b1c1d1e1f20);
+
+        //SHORTER INPUT IS LEFT PADDED
+        assert_eq!(U256::from_be_bytes(&[0x01, 0x00]), U256([0x100, 0, 0, 0]));
+        assert_eq!(U256::from_be_bytes(&[0x01, 0x00]).bits(), 9);
+    }
+
+    #[test]
+    fn add_sub() {
+        let max = U256([u64::MAX; 4]);
+        assert_eq!(max.overflowing_add(&U256::ONE), (U256::ZERO, true));
+        assert_eq!(U256::ZERO.overflowing_sub(&U256::ONE), (max, true));
+        assert_eq!(U256([u64::MAX, 0, 0, 0]).overflowing_add(&U256::ONE), (U256([0, 1, 0, 0]), false));
+    }
+
+    #[test]
+    fn montgomery() {
+        let n = Modulus::new(U256::from_be_bytes(&from_hex(P256_N).unwrap()));
+        let a = U256::from_be_bytes(&from_hex("C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721").unwrap());
+        let b = U256([7, 0, 0, 0]);
+
+        assert_eq!(n.from_mont(&n.to_mont(&a)), a);
+
+        //(a * 7) - a - a - a - a - a - a - a = 0
+        let product = n.from_mont(&n.mul(&n.to_mont(&a), &n.to_mont(&b)));
+        let mut rest = product;
+        for _ in 0..7 {
+            rest = n.sub(&rest, &a);
+        }
+        assert!(rest.is_zero());
+
+        //a * a^-1 = 1
+        let a_mont = n.to_mont(&a);
+        assert_eq!(n.from_mont(&n.mul(&a_mont, &n.inv(&a_mont))), U256::ONE);
+
+        //a^(n - 1) = 1 FOR A PRIME n
+        let exponent = n.get_modulus().overflowing_sub(&U256::ONE).0;
+        assert_eq!(n.from_mont(&n.pow(&a_mont, &exponent)), U256::ONE);
+    }
+
+    #[test]
+    fn reduce() {
+        let n = Modulus::new(U256::from_be_bytes(&from_hex(P256_N).unwrap()));
+        let max = U256([u64::MAX; 4]);
+        let reduced = n.reduce(&max);
+        assert!(&reduced < n.get_modulus());
+        assert_eq!(n.add(&reduced, n.get_modulus()), reduced);
+        assert_eq!(n.neg(&U256::ZERO), U256::ZERO);
+
+        //A MODULUS WELL UNDER 2^256 NEEDS MORE THAN ONE SUBTRACTION, 15l + 5 = 5 MOD l
+        let l = U256::from_be_bytes(&from_hex(ED25519_L).unwrap());
+        let short = Modulus::new(l);
+        let mut a = U256([5, 0, 0, 0]);
+        for shift in 0..4 {
+            a = a.overflowing_add(&l.shift_left(shift)).0;
+        }
+        assert_eq!(short.reduce(&a), U256([5, 0, 0, 0]));
+        assert!(&short.reduce(&max) < short.get_modulus());
+    }
+
+    #[test]
+    fn select() {
+        let a = U256([1, 2, 3, 4]);
+        let b = U256([u64::MAX, 0, u64::MAX, 0]);
+        assert_eq!(U256::select(&a, &b, false), a);
+        assert_eq!(U256::select(&a, &b, true), b);
+    }
+
+    #[test]
+    fn shift_left() {
+        let a = U256([0x8000000000000001, 0, 0, 0]);
+        assert_eq!(a.shift_left(1), U256([2, 1, 0, 0]));
+        assert_eq!(a.shift_left(64), U256([0, 0x8000000000000001, 0, 0]));
+        assert_eq!(a.shift_left(130), U256([0, 0, 4, 2]));
+    }
+}
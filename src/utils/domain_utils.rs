@@ -0,0 +1,38 @@
+//RFC 1035 section 2.3.4
+pub const MAX_NAME_LENGTH: usize = 255;
+
+pub fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+pub fn get_labels(name: &str) -> Vec<&str> {
+    name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()).collect()
+}
+
+pub fn is_subdomain(name: &str, apex: &str) -> bool {
+    let name = normalize(name);
+    let apex = normalize(apex);
+
+    if apex.is_empty() || name == apex {
+        return true;
+    }
+
+    name.ends_with(&format!(".{}", apex))
+}
+
+pub fn get_parent(name: &str) -> Option<String> {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() {
+        return None;
+    }
+
+    match name.find('.') {
+        Some(i) => Some(name[i + 1..].to_string()),
+        None => Some(String::new())
+    }
+}
+
+/// Length of the name in wire format, IE with the length octets and the root label.
+pub fn get_wire_length(name: &str) -> usize {
+    get_labels(name).iter().map(|label| label.len() + 1).sum::<usize>() + 1
+}
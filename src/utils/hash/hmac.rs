@@ -35,3 +35,24 @@ where
 
     outer.get_value()
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hash::sha256::Sha256;
+    use crate::utils::hex::from_hex;
+
+    //RFC 4231 section 4
+    #[test]
+    fn hmac_sha256() {
+        assert_eq!(hmac::<Sha256>(&[0x0b; 20], b"Hi There").to_vec(),
+            from_hex("B0344C61D8DB38535CA8AFCEAF0BF12B881DC200C9833DA726E9376C2E32CFF7").unwrap());
+        assert_eq!(hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?").to_vec(),
+            from_hex("5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843").unwrap());
+
+        //KEYS LONGER THAN THE BLOCK ARE HASHED FIRST
+        assert_eq!(hmac::<Sha256>(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First").to_vec(),
+            from_hex("60E431591EE0B67F0D8A26AACBF5B77F8E0BC6213728C5140546040F0EE37F54").unwrap());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod inter;
 pub mod sha256;
+pub mod sha1;
+pub mod sha512;
 pub mod hmac;
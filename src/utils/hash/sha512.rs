@@ -0,0 +1,217 @@
+use crate::utils::hash::inter::hash::Hash;
+
+const K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817
+];
+
+pub struct Sha512 {
+    h: [u64; 8],
+    buffer: [u8; 128],
+    buffer_len: usize,
+    message_len: u128
+}
+
+impl Sha512 {
+
+    fn transform(&mut self, chunk: &[u8; 128]) {
+        let mut w = [0u64; 80];
+
+        for i in 0..16 {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&chunk[i * 8..i * 8 + 8]);
+            w[i] = u64::from_be_bytes(word);
+        }
+
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut a = self.h[0];
+        let mut b = self.h[1];
+        let mut c = self.h[2];
+        let mut d = self.h[3];
+        let mut e = self.h[4];
+        let mut f = self.h[5];
+        let mut g = self.h[6];
+        let mut h = self.h[7];
+
+        for i in 0..80 {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ ((!e) & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        self.h[0] = self.h[0].wrapping_add(a);
+        self.h[1] = self.h[1].wrapping_add(b);
+        self.h[2] = self.h[2].wrapping_add(c);
+        self.h[3] = self.h[3].wrapping_add(d);
+        self.h[4] = self.h[4].wrapping_add(e);
+        self.h[5] = self.h[5].wrapping_add(f);
+        self.h[6] = self.h[6].wrapping_add(g);
+        self.h[7] = self.h[7].wrapping_add(h);
+    }
+}
+
+impl Hash for Sha512 {
+
+    type Output = [u8; 64];
+    const BLOCK_SIZE: usize = 128;
+
+    fn new() -> Self {
+        Self {
+            h: [
+                0x6a09e667f3bcc908,
+                0xbb67ae8584caa73b,
+                0x3c6ef372fe94f82b,
+                0xa54ff53a5f1d36f1,
+                0x510e527fade682d1,
+                0x9b05688c2b3e6c1f,
+                0x1f83d9abfb41bd6b,
+                0x5be0cd19137e2179,
+            ],
+            buffer: [0u8; 128],
+            buffer_len: 0,
+            message_len: 0
+        }
+    }
+
+    fn get_value(&mut self) -> Self::Output {
+        let bit_len = self.message_len;
+        self.buffer[self.buffer_len] = 0x80;
+        self.buffer_len += 1;
+
+        if self.buffer_len > 112 {
+            for i in self.buffer_len..128 {
+                self.buffer[i] = 0;
+            }
+            let chunk = self.buffer;
+            self.transform(&chunk);
+            self.buffer_len = 0;
+        }
+
+        for i in self.buffer_len..112 {
+            self.buffer[i] = 0;
+        }
+
+        self.buffer[112..128].copy_from_slice(&bit_len.to_be_bytes());
+        let chunk = self.buffer;
+        self.transform(&chunk);
+
+        let mut result = [0u8; 64];
+        for (i, &word) in self.h.iter().enumerate() {
+            result[i * 8..i * 8 + 8].copy_from_slice(&word.to_be_bytes());
+        }
+
+        result
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn update(&mut self, data: &[u8], off: usize, len: usize) {
+        let mut input = &data[off..off + len];
+        self.message_len += (len as u128) * 8;
+
+        while !input.is_empty() {
+            let space = 128 - self.buffer_len;
+            let take = space.min(input.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&input[..take]);
+            self.buffer_len += take;
+            input = &input[take..];
+
+            if self.buffer_len == 128 {
+                let chunk = self.buffer;
+                self.transform(&chunk);
+                self.buffer_len = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::utils::hex::from_hex;
+
+    fn digest(parts: &[&[u8]]) -> Vec<u8> {
+        let mut hasher = Sha512::new();
+        for part in parts {
+            hasher.update(part, 0, part.len());
+        }
+        hasher.get_value().to_vec()
+    }
+
+    //FIPS 180-4 EXAMPLES, SHA-512
+    #[test]
+    fn fips_180_4() {
+        assert_eq!(digest(&[b"abc"]), from_hex("DDAF35A193617ABACC417349AE20413112E6FA4E89A97EA20A9EEEE64B55D39A2192992A274FC1A836BA3C23A3FEEBBD454D4423643CE80E2A9AC94FA54CA49F").unwrap());
+        assert_eq!(digest(&[b""]), from_hex("CF83E1357EEFB8BDF1542850D66D8007D620E4050B5715DC83F4A921D36CE9CE47D0D13C5D85F2B0FF8318D2877EEC2F63B931BD47417A81A538327AF927DA3E").unwrap());
+        assert_eq!(digest(&[b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"]),
+            from_hex("8E959B75DAE313DA8CF4F72814FC143F8F7779C6EB9F7FA17299AEADB6889018501D289E4900F7E4331B99DEC4B5433AC7D329EEB6DD26545E96E55B874BE909").unwrap());
+    }
+
+    #[test]
+    fn million_a() {
+        let block = [b'a'; 1000];
+        let mut hasher = Sha512::new();
+        for _ in 0..1000 {
+            hasher.update(&block, 0, block.len());
+        }
+        assert_eq!(hasher.get_value().to_vec(), from_hex("E718483D0CE769644E2E42C7BC15B4638E1F98B13B2044285632A803AFA973EBDE0FF244877EA60A4CB0432CE577C31BEB009C5C2C49AA2E4EADB217AD8CC09B").unwrap());
+    }
+
+    #[test]
+    fn split_updates() {
+        //UPDATES THAT STRADDLE THE 128 BYTE BLOCK MUST MATCH ONE UPDATE
+        let data = [0x5au8; 300];
+        assert_eq!(digest(&[&data[..1], &data[1..127], &data[127..129], &data[129..]]), digest(&[&data]));
+
+        let mut hasher = Sha512::new();
+        hasher.update(b"xyz", 0, 3);
+        hasher.reset();
+        hasher.update(b"abc", 0, 3);
+        assert_eq!(hasher.get_value().to_vec(), digest(&[b"abc"]));
+    }
+}
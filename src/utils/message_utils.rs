@@ -0,0 +1,91 @@
+use std::ops::Range;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::messages::message_base::MessageBase;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::rrsig_record::RRSigRecord;
+use crate::dns::tcp_server::MAX_TCP_MESSAGE_SIZE;
+use crate::utils::domain_utils::normalize;
+
+/// RFC 2181 section 9 - fits the response into the size the client can take by dropping whole
+/// RRsets, additional first, then authority, then answers. Additional data is optional so TC is
+/// only set once an authority or answer RRset had to go.
+pub fn fit_response(message: &mut MessageBase, max: usize) {
+    if get_size(message) <= max {
+        return;
+    }
+
+    //THE OPT RECORD CARRIES THE EDNS STATE OF THE RESPONSE AND IS NEVER DROPPED
+    while get_size(message) > max {
+        let records = message.get_additional_records_mut();
+        match get_rrsets(records).into_iter().rev().find(|range| records[range.start].1.get_type() != RRTypes::Opt) {
+            Some(range) => {
+                records.drain(range);
+            }
+            None => break
+        }
+    }
+
+    if get_size(message) <= max {
+        return;
+    }
+
+    message.set_truncated(true);
+
+    while get_size(message) > max {
+        let records = message.get_authority_records_mut();
+        match get_rrsets(records).pop() {
+            Some(range) => {
+                records.drain(range);
+            }
+            None => break
+        }
+    }
+
+    while get_size(message) > max {
+        let records = message.get_answers_mut();
+        match get_rrsets(records).pop() {
+            Some(range) => {
+                records.drain(range);
+            }
+            None => break
+        }
+    }
+}
+
+/// Splits a section into RRsets, the RRSIGs following an RRset belong to it.
+pub fn get_rrsets(records: &[(String, Box<dyn RecordBase>)]) -> Vec<Range<usize>> {
+    let mut rrsets = Vec::new();
+    let mut i = 0;
+
+    while i < records.len() {
+        let owner = normalize(&records[i].0);
+        let _type = records[i].1.get_type();
+
+        let mut end = i + 1;
+        while end < records.len() && normalize(&records[end].0) == owner {
+            let next = records[end].1.as_ref();
+            let same_set = match next.get_type() {
+                RRTypes::RRSig => next.as_any().downcast_ref::<RRSigRecord>()
+                    .and_then(|record| record.get_type_covered())
+                    .map(|covered| covered == _type)
+                    .unwrap_or(false),
+                next_type => next_type == _type
+            };
+
+            if !same_set {
+                break;
+            }
+
+            end += 1;
+        }
+
+        rrsets.push(i..end);
+        i = end;
+    }
+
+    rrsets
+}
+
+fn get_size(message: &MessageBase) -> usize {
+    message.to_bytes(MAX_TCP_MESSAGE_SIZE).len()
+}
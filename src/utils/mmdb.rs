@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+
+//THE METADATA STARTS AFTER THE LAST MARKER, WHICH SITS IN THE LAST 128KiB OF THE FILE
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+const MAX_METADATA_SIZE: usize = 128 * 1024;
+
+//THE SEARCH TREE AND THE DATA SECTION ARE SEPARATED BY 16 ZERO BYTES
+const DATA_SECTION_SEPARATOR: usize = 16;
+
+//NESTING DEEPER THAN THIS IS TAKEN AS A BROKEN OR HOSTILE FILE
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MmdbValue {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    UInt(u128),
+    Int(i32),
+    Map(HashMap<String, MmdbValue>),
+    Array(Vec<MmdbValue>),
+    Bool(bool),
+    Float(f32)
+}
+
+impl MmdbValue {
+
+    pub fn get(&self, key: &str) -> Option<&MmdbValue> {
+        match self {
+            MmdbValue::Map(map) => map.get(key),
+            _ => None
+        }
+    }
+
+    /// Walks down nested maps, IE `["country", "iso_code"]`.
+    pub fn get_path(&self, path: &[&str]) -> Option<&MmdbValue> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MmdbValue::String(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            MmdbValue::UInt(value) => u64::try_from(*value).ok(),
+            _ => None
+        }
+    }
+}
+
+/// Reader for MaxMind DB files, IE GeoLite2-Country.mmdb. The whole file is held in memory, lookups
+/// walk the binary search tree bit by bit and decode the record it ends on.
+pub struct Mmdb {
+    buf: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    ipv4_start: usize,
+    data_start: usize,
+    database_type: String,
+    build_epoch: u64
+}
+
+impl Mmdb {
+
+    pub fn open(file_path: &str) -> io::Result<Self> {
+        Self::from_bytes(fs::read(file_path)?)
+    }
+
+    pub fn from_bytes(buf: Vec<u8>) -> io::Result<Self> {
+        let search_start = buf.len().saturating_sub(MAX_METADATA_SIZE);
+        let marker = buf[search_start..].windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .map(|i| search_start + i + METADATA_MARKER.len())
+            .ok_or_else(|| invalid("No MaxMind DB metadata"))?;
+
+        let (metadata, _) = Decoder::new(&buf[marker..]).decode(0, 0)?;
+
+        let get = |key: &str| metadata.get(key).and_then(|value| value.as_u64()).ok_or_else(|| invalid("Missing metadata field"));
+        let node_count = get("node_count")? as usize;
+        let record_size = get("record_size")? as usize;
+        let ip_version = get("ip_version")? as u16;
+        let build_epoch = get("build_epoch").unwrap_or(0);
+        let database_type = metadata.get("database_type").and_then(|value| value.as_str()).unwrap_or("").to_string();
+
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(invalid("Unsupported record size"));
+        }
+
+        if !matches!(ip_version, 4 | 6) {
+            return Err(invalid("Unsupported IP version"));
+        }
+
+        let tree_size = node_count * record_size / 4;
+        let data_start = tree_size + DATA_SECTION_SEPARATOR;
+        if data_start > marker {
+            return Err(invalid("Search tree is larger than the file"));
+        }
+
+        let mut mmdb = Self {
+            buf,
+            node_count,
+            record_size,
+            ip_version,
+            ipv4_start: 0,
+            data_start,
+            database_type,
+            build_epoch
+        };
+
+        //IPV4 ADDRESSES LIVE UNDER ::/96 IN AN IPV6 TREE
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = mmdb.read_record(node, false)?;
+            }
+            mmdb.ipv4_start = node;
+        }
+
+        Ok(mmdb)
+    }
+
+    pub fn get_database_type(&self) -> &str {
+        &self.database_type
+    }
+
+    pub fn get_build_epoch(&self) -> u64 {
+        self.build_epoch
+    }
+
+    /// The record for the address along with the prefix length of the network it was found in, the
+    /// prefix is also given when there is no record so the miss can be scoped.
+    pub fn lookup(&self, address: IpAddr) -> io::Result<(Option<MmdbValue>, u8)> {
+        let (bytes, start) = match address {
+            IpAddr::V4(address) => (address.octets().to_vec(), self.ipv4_start),
+            IpAddr::V6(address) => {
+                if self.ip_version == 4 {
+                    return Err(invalid("IPv6 lookup in an IPv4 only database"));
+                }
+                (address.octets().to_vec(), 0)
+            }
+        };
+
+        let mut node = start;
+        let mut depth = 0;
+        while depth < bytes.len() * 8 && node < self.node_count {
+            let bit = (bytes[depth / 8] >> (7 - depth % 8)) & 1 == 1;
+            node = self.read_record(node, bit)?;
+            depth += 1;
+        }
+
+        match node {
+            node if node == self.node_count => Ok((None, depth as u8)),
+            node if node > self.node_count => {
+                let offset = (node - self.node_count).checked_sub(DATA_SECTION_SEPARATOR)
+                    .ok_or_else(|| invalid("Invalid data pointer"))?;
+                let (value, _) = Decoder::new(&self.buf[self.data_start..]).decode(offset, 0)?;
+                Ok((Some(value), depth as u8))
+            }
+            _ => Err(invalid("Search tree ran out of bits"))
+        }
+    }
+
+    fn read_record(&self, node: usize, right: bool) -> io::Result<usize> {
+        let size = self.record_size / 4;
+        let b = self.buf.get(node * size..node * size + size).ok_or_else(|| invalid("Node out of range"))?;
+
+        let value = match (self.record_size, right) {
+            (24, false) => u32::from_be_bytes([0, b[0], b[1], b[2]]),
+            (24, true) => u32::from_be_bytes([0, b[3], b[4], b[5]]),
+            //THE MIDDLE BYTE HOLDS THE HIGH NIBBLE OF BOTH RECORDS
+            (28, false) => u32::from_be_bytes([(b[3] & 0xf0) >> 4, b[0], b[1], b[2]]),
+            (28, true) => u32::from_be_bytes([b[3] & 0x0f, b[4], b[5], b[6]]),
+            (_, false) => u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            (_, true) => u32::from_be_bytes([b[4], b[5], b[6], b[7]])
+        };
+
+        Ok(value as usize)
+    }
+}
+
+/// Decodes the MaxMind DB data section format, pointers are offsets from the start of the section.
+struct Decoder<'a> {
+    buf: &'a [u8]
+}
+
+impl<'a> Decoder<'a> {
+
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf
+        }
+    }
+
+    fn decode(&self, offset: usize, depth: usize) -> io::Result<(MmdbValue, usize)> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Data nested too deep"));
+        }
+
+        let ctrl = self.byte(offset)?;
+        let mut offset = offset + 1;
+
+        let mut _type = ctrl >> 5;
+        if _type == 1 {
+            let (pointer, next) = self.read_pointer(ctrl, offset)?;
+            let (value, _) = self.decode(pointer, depth + 1)?;
+            return Ok((value, next));
+        }
+
+        if _type == 0 {
+            _type = self.byte(offset)?.checked_add(7).ok_or_else(|| invalid("Invalid extended type"))?;
+            offset += 1;
+        }
+
+        let (size, mut offset) = self.read_size(ctrl, offset)?;
+
+        let value = match _type {
+            2 => MmdbValue::String(String::from_utf8(self.bytes(offset, size)?.to_vec()).map_err(|_| invalid("Invalid UTF-8 string"))?),
+            3 => {
+                let bytes: [u8; 8] = self.bytes(offset, size)?.try_into().map_err(|_| invalid("Invalid double size"))?;
+                MmdbValue::Double(f64::from_be_bytes(bytes))
+            }
+            4 => MmdbValue::Bytes(self.bytes(offset, size)?.to_vec()),
+            5 | 6 | 9 | 10 => MmdbValue::UInt(self.read_uint(offset, size)?),
+            8 => MmdbValue::Int(self.read_int(offset, size)?),
+            7 => {
+                let mut map = HashMap::with_capacity(self.get_capacity(offset, size));
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    let key = match key {
+                        MmdbValue::String(key) => key,
+                        _ => return Err(invalid("Map key isn't a string"))
+                    };
+                    map.insert(key, value);
+                    offset = next;
+                }
+                return Ok((MmdbValue::Map(map), offset));
+            }
+            11 => {
+                let mut array = Vec::with_capacity(self.get_capacity(offset, size));
+                for _ in 0..size {
+                    let (value, next) = self.decode(offset, depth + 1)?;
+                    array.push(value);
+                    offset = next;
+                }
+                return Ok((MmdbValue::Array(array), offset));
+            }
+            //BOOLEANS HOLD THEIR VALUE IN THE SIZE
+            14 => return Ok((MmdbValue::Bool(size != 0), offset)),
+            15 => {
+                let bytes: [u8; 4] = self.bytes(offset, size)?.try_into().map_err(|_| invalid("Invalid float size"))?;
+                MmdbValue::Float(f32::from_be_bytes(bytes))
+            }
+            _ => return Err(invalid("Unknown data type"))
+        };
+
+        Ok((value, offset + size))
+    }
+
+    fn read_pointer(&self, ctrl: u8, offset: usize) -> io::Result<(usize, usize)> {
+        let size = ((ctrl >> 3) & 0x03) as usize;
+        let bits = (ctrl & 0x07) as usize;
+
+        let b = self.bytes(offset, size + 1)?;
+        let pointer = match size {
+            0 => (bits << 8) | b[0] as usize,
+            1 => ((bits << 16) | (b[0] as usize) << 8 | b[1] as usize) + 2048,
+            2 => ((bits << 24) | (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize) + 526336,
+            _ => u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize
+        };
+
+        Ok((pointer, offset + size + 1))
+    }
+
+    fn read_size(&self, ctrl: u8, offset: usize) -> io::Result<(usize, usize)> {
+        let size = (ctrl & 0x1f) as usize;
+
+        match size {
+            29 => Ok((29 + self.byte(offset)? as usize, offset + 1)),
+            30 => {
+                let b = self.bytes(offset, 2)?;
+                Ok((285 + u16::from_be_bytes([b[0], b[1]]) as usize, offset + 2))
+            }
+            31 => {
+                let b = self.bytes(offset, 3)?;
+                Ok((65821 + u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize, offset + 3))
+            }
+            size => Ok((size, offset))
+        }
+    }
+
+    fn read_uint(&self, offset: usize, size: usize) -> io::Result<u128> {
+        if size > 16 {
+            return Err(invalid("Integer too large"));
+        }
+
+        Ok(self.bytes(offset, size)?.iter().fold(0u128, |value, b| (value << 8) | *b as u128))
+    }
+
+    /// The int32 type drops leading zero bytes like the unsigned ones, so only a full 4 byte value can
+    /// be negative, the same way libmaxminddb reads it.
+    fn read_int(&self, offset: usize, size: usize) -> io::Result<i32> {
+        if size > 4 {
+            return Err(invalid("Integer too large"));
+        }
+
+        Ok(self.read_uint(offset, size)? as u32 as i32)
+    }
+
+    //EVERY ENTRY TAKES AT LEAST A BYTE, A SIZE LARGER THAN WHAT IS LEFT CAN'T BE TRUSTED FOR AN ALLOCATION
+    fn get_capacity(&self, offset: usize, size: usize) -> usize {
+        size.min(self.buf.len().saturating_sub(offset))
+    }
+
+    fn byte(&self, offset: usize) -> io::Result<u8> {
+        self.buf.get(offset).copied().ok_or_else(|| invalid("Data out of range"))
+    }
+
+    fn bytes(&self, offset: usize, size: usize) -> io::Result<&'a [u8]> {
+        self.buf.get(offset..offset + size).ok_or_else(|| invalid("Data out of range"))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const TEST_DATABASE: &str = "res/geo-test.mmdb";
+
+    fn get_codes(mmdb: &Mmdb, address: &str) -> (Option<String>, Option<String>, u8) {
+        let (record, prefix) = mmdb.lookup(address.parse().unwrap()).unwrap();
+        let get = |path: &[&str]| record.as_ref().and_then(|record| record.get_path(path)).and_then(|code| code.as_str()).map(|code| code.to_string());
+        (get(&["country", "iso_code"]), get(&["continent", "code"]), prefix)
+    }
+
+    #[test]
+    fn open_test_database() {
+        let mmdb = Mmdb::open(TEST_DATABASE).unwrap();
+        assert_eq!(mmdb.get_database_type(), "GeoLite2-Country");
+
+        assert_eq!(get_codes(&mmdb, "127.0.0.1"), (Some("GB".to_string()), Some("EU".to_string()), 8));
+        assert_eq!(get_codes(&mmdb, "192.0.2.53"), (Some("US".to_string()), Some("NA".to_string()), 24));
+        assert_eq!(get_codes(&mmdb, "198.51.100.1"), (Some("DE".to_string()), Some("EU".to_string()), 24));
+        assert_eq!(get_codes(&mmdb, "203.0.113.200"), (Some("JP".to_string()), Some("AS".to_string()), 24));
+        assert_eq!(get_codes(&mmdb, "::1"), (Some("GB".to_string()), Some("EU".to_string()), 128));
+        assert_eq!(get_codes(&mmdb, "2001:db8::1"), (Some("AU".to_string()), Some("OC".to_string()), 33));
+        assert_eq!(get_codes(&mmdb, "2001:db8:8000::1"), (Some("BR".to_string()), Some("SA".to_string()), 33));
+    }
+
+    #[test]
+    fn missing_address() {
+        let mmdb = Mmdb::open(TEST_DATABASE).unwrap();
+        let (record, prefix) = mmdb.lookup("8.8.8.8".parse().unwrap()).unwrap();
+        assert!(record.is_none());
+        assert!(prefix > 0);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert!(Mmdb::from_bytes(Vec::new()).is_err());
+        assert!(Mmdb::from_bytes(b"not a database".to_vec()).is_err());
+
+        //METADATA THAT CLAIMS A TREE LARGER THAN THE FILE
+        let mut buf = fs::read(TEST_DATABASE).unwrap();
+        let marker = buf.windows(METADATA_MARKER.len()).rposition(|window| window == METADATA_MARKER).unwrap();
+        buf.drain(..marker);
+        assert!(Mmdb::from_bytes(buf).is_err());
+    }
+
+    #[test]
+    fn int32() {
+        let decode = |buf: &[u8]| Decoder::new(buf).decode(0, 0).map(|(value, _)| value);
+        assert_eq!(decode(&[0x01, 0x01, 0x05]).unwrap(), MmdbValue::Int(5));
+        assert_eq!(decode(&[0x01, 0x01, 0x80]).unwrap(), MmdbValue::Int(128));
+        assert_eq!(decode(&[0x04, 0x01, 0xff, 0xff, 0xff, 0xfe]).unwrap(), MmdbValue::Int(-2));
+        assert!(decode(&[0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn oversized_containers() {
+        //A MAP AND AN ARRAY CLAIMING MILLIONS OF ENTRIES IN A FEW BYTES
+        let decode = |buf: &[u8]| Decoder::new(buf).decode(0, 0).map(|(value, _)| value);
+        assert!(decode(&[0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(decode(&[0x1f, 0x04, 0xff, 0xff, 0xff]).is_err());
+    }
+}
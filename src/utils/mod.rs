@@ -2,3 +2,12 @@ pub mod hash;
 pub mod net;
 pub mod spam_throttle;
 pub mod query_utils;
+pub mod message_utils;
+pub mod crypto;
+pub mod base64;
+pub mod base32;
+pub mod hex;
+pub mod domain_utils;
+pub mod zone_utils;
+pub mod time_utils;
+pub mod mmdb;
@@ -8,28 +8,37 @@ pub struct NetMask {
 impl NetMask {
 
     pub fn new(address: IpAddr, mask: u32) -> Result<Self, ()> {
-        if let IpAddr::V6(v6) = address {
-            let octets = v6.octets();
-
-            return Ok(Self {
-                address: octets,
-                mask
-            })
+        //V4 MASKS ARE HELD AS V4-MAPPED V6, IE 10.0.0.0/8 IS ::ffff:10.0.0.0/104
+        match address {
+            IpAddr::V4(v4) => {
+                if mask > 32 {
+                    return Err(());
+                }
+
+                Ok(Self {
+                    address: v4.to_ipv6_mapped().octets(),
+                    mask: mask + 96
+                })
+            }
+            IpAddr::V6(v6) => {
+                if mask > 128 {
+                    return Err(());
+                }
+
+                Ok(Self {
+                    address: v6.octets(),
+                    mask
+                })
+            }
         }
-
-        Err(())
     }
 
     pub fn contains(&self, other_address: IpAddr) -> bool {
         let other = match other_address {
-            IpAddr::V4(ipv4) => ipv4.octets().to_vec(),
-            IpAddr::V6(ipv6) => ipv6.octets().to_vec(),
+            IpAddr::V4(ipv4) => ipv4.to_ipv6_mapped().octets(),
+            IpAddr::V6(ipv6) => ipv6.octets(),
         };
 
-        if self.address.len() != other.len() {
-            return false;
-        }
-
         for i in 0..(self.mask / 8) as usize {
             if self.address[i] != other[i] {
                 return false;
@@ -1,143 +1,364 @@
 use std::sync::{Arc, RwLock};
 use rlibdns::messages::inter::response_codes::ResponseCodes;
 use rlibdns::messages::inter::rr_types::RRTypes;
-use rlibdns::records::cname_record::CNameRecord;
 use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::rrsig_record::RRSigRecord;
 use rlibdns::utils::fqdn_utils::fqdn_to_relative;
 use rlibdns::zone::zone::Zone;
 use rlibdns::zone::zone_store::ZoneStore;
-use crate::{MAX_ANSWERS, MAX_CNAME_CHAIN_SIZE};
+use rlibdns::messages::message_base::MessageBase;
 use crate::dns::dns::ResponseResult;
+use crate::dns::tcp_server::MAX_TCP_MESSAGE_SIZE;
+use crate::dnssec::signer::Signer;
 use crate::rpc::events::request_event::RequestEvent;
+use crate::utils::domain_utils::{get_wire_length, normalize};
+use crate::dns::zone_names::ZoneNames;
+use crate::utils::zone_utils::{get_negative_soa, is_presigned};
 
+/// Adds every A and AAAA record held at the name to the additional section while the response
+/// stays within its size, returns false once an RRset didn't fit.
+pub fn add_glue(zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str, size: &mut usize) -> bool {
+    let sub = match fqdn_to_relative(apex, name) {
+        Some(sub) => sub,
+        None => return true
+    };
 
-/*
-pub fn chain_cname2(zone: &Zone, apex: &str, name: &str, depth: u8) -> ResponseResult<Vec<(String, Box<dyn RecordBase>)> {
-    let sub = fqdn_to_relative(apex, name).unwrap();
+    for _type in [RRTypes::A, RRTypes::Aaaa] {
+        let records = match zone.get_records(&sub, &_type) {
+            Some(records) => records,
+            None => continue
+        };
 
-    match zone.get_records(&sub, &RRTypes::CName) {
-        Some(records) => {
-            if depth+1 >= MAX_CNAME_CHAIN_SIZE {
-                return Err(ResponseCodes::ServFail);
-            }
+        let added = event.get_additional_records().iter()
+            .any(|(owner, record)| record.get_type() == _type && normalize(owner) == normalize(name));
+        if added {
+            continue;
+        }
 
-            let record = records.first().unwrap();
-            //event.add_answer(&name, record.clone());
-            //let response = chain_cname(zone, apex, event, &record.as_any().downcast_ref::<CNameRecord>().unwrap().get_target().unwrap(), depth+1)?;
-            //Ok(response)
+        //RRSETS ARE ADDED WHOLE OR NOT AT ALL
+        let rrset_size = records.len() * get_address_record_size(name, &_type);
+        if *size + rrset_size > event.get_max_response_size() {
+            return false;
         }
-        None => {
-            /*
-            match zone.get_records("", &RRTypes::Soa) {
-                Some(records) => event.add_authority_record(&apex, records.first().unwrap().clone()),
-                None => return Err(ResponseCodes::Refused)
-            }
-            Err(ResponseCodes::NxDomain)
-            */
-            Ok(name.to_string())
+
+        *size += rrset_size;
+        for record in records {
+            event.add_additional_record(name, record.clone());
         }
     }
+
+    true
 }
-*/
-pub fn chain_cname(zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str, depth: u8) -> ResponseResult<String> {
-    let sub = fqdn_to_relative(apex, name).unwrap();
 
-    match zone.get_records(&sub, &RRTypes::CName) {
-        Some(records) => {
-            if depth+1 >= MAX_CNAME_CHAIN_SIZE {
-                return Err(ResponseCodes::ServFail);
-            }
+/// Worst case size of an A or AAAA record on the wire, without name compression.
+fn get_address_record_size(name: &str, _type: &RRTypes) -> usize {
+    let rdata = match _type {
+        RRTypes::Aaaa => 16,
+        _ => 4
+    };
+
+    get_wire_length(name) + 10 + rdata
+}
+
+/// The size of the response as it stands, OPT record included.
+pub fn get_response_size(event: &RequestEvent) -> usize {
+    let mut message = MessageBase::new(0);
+    message.add_query(event.get_query().clone());
 
-            let record = records.first().unwrap();
-            event.add_answer(&name, record.clone());
-            let response = chain_cname(zone, apex, event, &record.as_any().downcast_ref::<CNameRecord>().unwrap().get_target().unwrap(), depth+1)?;
-            Ok(response)
+    for (name, record) in event.get_answers() {
+        message.add_answer(name, record.clone());
+    }
+
+    for (name, record) in event.get_authority_records() {
+        message.add_authority_record(name, record.clone());
+    }
+
+    for (name, record) in event.get_additional_records() {
+        message.add_additional_record(name, record.clone());
+    }
+
+    //ROOT NAME, TYPE, CLASS, TTL AND RDLENGTH
+    let opt_size = match event.get_opt_record() {
+        Some(_) => 11,
+        None => 0
+    };
+
+    message.to_bytes(MAX_TCP_MESSAGE_SIZE).len() + opt_size
+}
+
+/// Adds the SOA for a NODATA or NXDOMAIN answer, RFC 2308 - the name exists (even as an empty
+/// non-terminal) for NODATA, otherwise NxDomain is returned. Signed zones decide between the two
+/// from the denial chain and add the NSEC/NSEC3 proof if the client asked for DNSSEC.
+pub fn add_negative_answer(signer: &Signer, names: &ZoneNames, zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str) -> ResponseResult<()> {
+    match get_negative_soa(zone) {
+        Some(record) => {
+            event.set_authoritative(zone.is_authority());
+            event.add_authority_record(apex, record);
         }
-        None => {
-            println!("FAIL NONE FOUND");
-            /*
-            match zone.get_records("", &RRTypes::Soa) {
-                Some(records) => event.add_authority_record(&apex, records.first().unwrap().clone()),
-                None => return Err(ResponseCodes::Refused)
+        None => return Ok(())
+    }
+
+    let nxdomain = match signer.get_denial_chain(event.get_view(), apex, zone) {
+        Some(chain) => {
+            let mut records = Vec::new();
+            let nxdomain = chain.add_negative_proof(name, &mut records);
+
+            if event.is_dnssec_ok() {
+                event.get_authority_records_mut().extend(records);
             }
-            Err(ResponseCodes::NxDomain)
-            */
-            Ok(name.to_string())
+
+            nxdomain
         }
+        None => !names.exists(name)
+    };
+
+    match nxdomain {
+        true => Err(ResponseCodes::NxDomain),
+        false => Ok(())
+    }
+}
+
+/// Adds an RRset synthesized from a wildcard under the query name. The RRSIGs are made over the
+/// wildcard owner so the label count tells the validator it was expanded (RFC 4035 section 5.3.4),
+/// along with the proof that the query name itself doesn't exist.
+pub fn add_wildcard_answer(signer: &Signer, zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str, wildcard: &str, records: Vec<Box<dyn RecordBase>>) {
+    if records.is_empty() {
+        return;
     }
 
-    /*
-    match store.read().unwrap().get_zone_exact(&name) {
-        Some(zone) => {
-            match zone.get_records(&RRTypes::CName) {
-                Some(records) => {
-                    if depth+1 >= MAX_CNAME_CHAIN_SIZE {
-                        return Err(ResponseCodes::ServFail);
-                    }
-
-                    let record = records.first().unwrap();
-                    let response = chain_cname(store, event, &record.as_any().downcast_ref::<CNameRecord>().unwrap().get_target().unwrap(), depth+1)?;
-                    event.add_answer(&name, record.clone());
-                    Ok(response)
-                }
-                None => Ok(name.to_string())
+    let signatures = match event.is_dnssec_ok() {
+        true => {
+            match signer.is_signed(apex) {
+                true => signer.sign_rrset(apex, wildcard, &records.iter().map(|record| record.as_ref()).collect::<Vec<_>>()),
+                false => get_zone_signatures(zone, apex, wildcard, &records[0].get_type())
             }
         }
-        None => {
-            match store.read().unwrap().get_deepest_zone_with_name(&name) {
-                Some((name, zone)) => {
-                    for record in zone.get_records(&RRTypes::Soa)
-                            .ok_or(ResponseCodes::Refused)?.iter().take(MAX_ANSWERS) {
-                        event.add_authority_record(&name, record.clone());
-                    }
-                }
-                None => return Err(ResponseCodes::Refused)
-            }
+        false => Vec::new()
+    };
+
+    for record in records.into_iter().chain(signatures) {
+        event.add_answer(name, record);
+    }
 
-            Err(ResponseCodes::NxDomain)
+    if event.is_dnssec_ok() {
+        if let Some(chain) = signer.get_denial_chain(event.get_view(), apex, zone) {
+            chain.add_wildcard_proof(name, event.get_authority_records_mut());
         }
-    }*/
-    //Ok("".to_string())
+    }
 }
 
-pub fn add_glue(zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str) {
-    let sub = fqdn_to_relative(apex, name).unwrap();
+/// NODATA for a name covered by a wildcard that has no records of the queried type, the name
+/// doesn't exist but the wildcard does so the rcode stays NOERROR.
+pub fn add_wildcard_no_data_answer(signer: &Signer, zone: &Zone, apex: &str, event: &mut RequestEvent, name: &str) {
+    match get_negative_soa(zone) {
+        Some(record) => {
+            event.set_authoritative(zone.is_authority());
+            event.add_authority_record(apex, record);
+        }
+        None => return
+    }
 
-    match zone.get_records(&sub, &RRTypes::A) {
-        Some(records) => {
-            event.add_additional_record(&name, records.first().unwrap().clone());
+    if event.is_dnssec_ok() {
+        if let Some(chain) = signer.get_denial_chain(event.get_view(), apex, zone) {
+            chain.add_wildcard_no_data_proof(name, event.get_authority_records_mut());
         }
-        None => {}
+    }
+}
+
+/// Adds the DS RRset for a referral or, for signed zones without one, the proof that it doesn't exist.
+pub fn add_delegation_proof(signer: &Signer, zone: &Zone, apex: &str, event: &mut RequestEvent, cut: &str) {
+    if !event.is_dnssec_ok() {
+        return;
     }
 
-    match zone.get_records(&sub, &RRTypes::Aaaa) {
-        Some(records) => {
-            event.add_additional_record(&name, records.first().unwrap().clone());
+    let records = get_ds_records(signer, zone, apex, cut);
+
+    match records.is_empty() {
+        true => {
+            if let Some(chain) = signer.get_denial_chain(event.get_view(), apex, zone) {
+                chain.add_delegation_proof(cut, event.get_authority_records_mut());
+            }
         }
-        None => {}
-    }
-    /*
-    match store.read().unwrap().get_zone_exact(&name) {
-        Some(zone) => {
-            match zone.get_records(&RRTypes::A) {
-                Some(records) => {
-                    event.add_additional_record(&name, records.first().unwrap().clone());
-                }
-                None => {}
+        false => {
+            for record in records {
+                event.add_authority_record(cut, record);
             }
         }
-        None => {}
     }
+}
 
-    match store.read().unwrap().get_zone_exact(&name) {
-        Some(zone) => {
-            match zone.get_records(&RRTypes::Aaaa) {
-                Some(records) => {
-                    event.add_additional_record(&name, records.first().unwrap().clone());
-                }
-                None => {}
+/// The DS RRset at a delegation, taken from the parent zone file or generated from the keys of a
+/// child zone we sign ourselves.
+pub fn get_ds_records(signer: &Signer, zone: &Zone, apex: &str, cut: &str) -> Vec<Box<dyn RecordBase>> {
+    let sub = fqdn_to_relative(apex, cut).unwrap();
+
+    match zone.get_records(&sub, &RRTypes::Ds) {
+        Some(records) => records.clone(),
+        None => {
+            match zone.get_records(&sub, &RRTypes::Ns).is_some() && normalize(cut) != normalize(apex) {
+                true => signer.get_ds_records(cut),
+                false => Vec::new()
             }
         }
-        None => {}
-    }*/
+    }
+}
+
+pub fn sign_response(signer: &Signer, zone: &Zone, apex: &str, event: &mut RequestEvent) {
+    //ZONES SIGNED OFFLINE ARE SERVED WITH THEIR OWN RRSIGS AND NEVER RE-SIGNED
+    let presigned = !signer.is_signed(apex) && is_presigned(zone);
+
+    signer.sign_records(apex, zone, event.get_answers_mut());
+    if presigned {
+        add_zone_signatures(zone, apex, event.get_answers_mut(), |_| true);
+    }
+
+    //REFERRALS ARE NOT AUTHORITATIVE FOR THE NS RRSET AT THE CUT, ONLY THE DS OR ITS DENIAL IS SIGNED
+    let filter = |_type: &RRTypes| matches!(_type, RRTypes::Ds | RRTypes::NSec | RRTypes::NSec3);
+    let authoritative = event.is_authoritative() && !is_referral(event);
+
+    match authoritative {
+        true => signer.sign_records(apex, zone, event.get_authority_records_mut()),
+        false => signer.sign_records_filtered(apex, zone, event.get_authority_records_mut(), filter)
+    }
+
+    if presigned {
+        add_zone_signatures(zone, apex, event.get_authority_records_mut(), |_type| authoritative || filter(_type));
+    }
+}
+
+/// The authority section holds the NS RRset of a cut, the NS RRset of an apex is never added there.
+/// A CNAME chain ending in a delegation is a referral as well.
+fn is_referral(event: &RequestEvent) -> bool {
+    event.get_authority_records().iter().any(|(_, record)| record.get_type() == RRTypes::Ns)
+}
+
+/// Places the RRSIGs held in the zone directly after each RRset that doesn't have any yet, the
+/// section has to be grouped into RRsets already.
+pub fn add_zone_signatures<F: Fn(&RRTypes) -> bool>(zone: &Zone, apex: &str, records: &mut Vec<(String, Box<dyn RecordBase>)>, filter: F) {
+    let mut signed: Vec<(String, RRTypes)> = records.iter()
+        .filter_map(|(name, record)| {
+            let covered = record.as_any().downcast_ref::<RRSigRecord>()?.get_type_covered()?;
+            Some((normalize(name), covered))
+        })
+        .collect();
+
+    let mut i = 0;
+    while i < records.len() {
+        let owner = normalize(&records[i].0);
+        let _type = records[i].1.get_type();
+
+        let mut end = i + 1;
+        while end < records.len() && records[end].1.get_type() == _type && normalize(&records[end].0) == owner {
+            end += 1;
+        }
+
+        if !matches!(_type, RRTypes::RRSig | RRTypes::Opt) && filter(&_type) && !signed.contains(&(owner.clone(), _type)) {
+            let name = records[i].0.clone();
+            let signatures: Vec<(String, Box<dyn RecordBase>)> = get_zone_signatures(zone, apex, &owner, &_type)
+                .into_iter()
+                .map(|record| (name.clone(), record))
+                .collect();
+
+            let count = signatures.len();
+            records.splice(end..end, signatures);
+            signed.push((owner, _type));
+            end += count;
+        }
+
+        i = end;
+    }
+}
+
+/// The RRSIGs in the zone covering the RRset at the name.
+pub fn get_zone_signatures(zone: &Zone, apex: &str, name: &str, _type: &RRTypes) -> Vec<Box<dyn RecordBase>> {
+    let sub = match fqdn_to_relative(apex, name) {
+        Some(sub) => sub,
+        None => return Vec::new()
+    };
+
+    match zone.get_records(&sub, &RRTypes::RRSig) {
+        Some(records) => {
+            records.iter()
+                .filter(|record| {
+                    record.as_any().downcast_ref::<RRSigRecord>()
+                        .and_then(|record| record.get_type_covered())
+                        .map(|covered| covered == *_type)
+                        .unwrap_or(false)
+                })
+                .cloned()
+                .collect()
+        }
+        None => Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rlibdns::messages::dns_query::DnsQuery;
+    use rlibdns::messages::inter::rr_classes::RRClasses;
+    use rlibdns::records::cname_record::CNameRecord;
+    use rlibdns::records::ns_record::NsRecord;
+    use rlibdns::records::soa_record::SoaRecord;
+    use rlibdns::zone::inter::zone_types::ZoneTypes;
+    use super::*;
+    use crate::dnssec::dnssec_key::{DnssecKey, PrivateKey, SECURE_ENTRY_POINT_FLAG, ZONE_KEY_FLAG};
+    use crate::utils::crypto::p256::P256PrivateKey;
+
+    fn get_signer() -> Signer {
+        let private_key = P256PrivateKey::from_bytes(&[7u8; 32]).unwrap();
+        let mut signer = Signer::new();
+        signer.add_key("test", DnssecKey::new("test", ZONE_KEY_FLAG | SECURE_ENTRY_POINT_FLAG, PrivateKey::EcdsaP256(private_key)));
+        signer
+    }
+
+    fn get_signed_types(records: &[(String, Box<dyn RecordBase>)]) -> Vec<(String, RRTypes)> {
+        records.iter()
+            .filter_map(|(owner, record)| Some((normalize(owner), record.as_any().downcast_ref::<RRSigRecord>()?.get_type_covered()?)))
+            .collect()
+    }
+
+    fn new_event(name: &str) -> RequestEvent {
+        let mut event = RequestEvent::new();
+        event.query = Some(DnsQuery::new(name, RRTypes::A, RRClasses::In));
+        event.set_authoritative(true);
+        event
+    }
+
+    #[test]
+    fn referral_after_cname() {
+        let signer = get_signer();
+        let zone = Zone::new(ZoneTypes::Master);
+
+        let mut cname = CNameRecord::new(300, RRClasses::In);
+        cname.set_target("host.sub.test.");
+        let mut ns = NsRecord::new(300, RRClasses::In);
+        ns.set_server("ns.sub.test.");
+
+        //THE CNAME IS OURS BUT THE NS RRSET AT THE CUT BELONGS TO THE CHILD
+        let mut event = new_event("www.test");
+        event.add_answer("www.test", cname.upcast());
+        event.add_authority_record("sub.test", ns.upcast());
+        sign_response(&signer, &zone, "test", &mut event);
+
+        assert_eq!(get_signed_types(event.get_answers()), vec![("www.test".to_string(), RRTypes::CName)]);
+        assert!(get_signed_types(event.get_authority_records()).is_empty());
+    }
+
+    #[test]
+    fn negative_answer() {
+        let signer = get_signer();
+        let zone = Zone::new(ZoneTypes::Master);
+
+        let mut soa = SoaRecord::new(300, RRClasses::In);
+        soa.set_fqdn("ns.test.");
+        soa.set_mailbox("hostmaster.test.");
+        soa.set_serial(1);
+
+        let mut event = new_event("none.test");
+        event.add_authority_record("test", soa.upcast());
+        sign_response(&signer, &zone, "test", &mut event);
+
+        assert_eq!(get_signed_types(event.get_authority_records()), vec![("test".to_string(), RRTypes::Soa)]);
+    }
 }
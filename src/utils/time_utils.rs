@@ -0,0 +1,102 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Seconds since the unix epoch, the clock used for TTLs, signature windows and key timing.
+pub fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+//DAYS SINCE THE EPOCH <-> CIVIL DATE, HOWARD HINNANT'S ALGORITHMS
+fn days_to_civil(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn civil_to_days(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Formats a unix time as the `YYYYMMDDHHmmSS` form used by BIND key files and RRSIG records.
+pub fn to_date_time(secs: u64) -> String {
+    let (year, month, day) = days_to_civil((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!("{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day, time / 3600, (time / 60) % 60, time % 60)
+}
+
+pub fn from_date_time(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.len() != 14 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let year = s[0..4].parse::<i64>().ok()?;
+    let month = s[4..6].parse::<u32>().ok()?;
+    let day = s[6..8].parse::<u32>().ok()?;
+    let hour = s[8..10].parse::<u64>().ok()?;
+    let minute = s[10..12].parse::<u64>().ok()?;
+    let second = s[12..14].parse::<u64>().ok()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = civil_to_days(year, month, day);
+    if days < 0 {
+        return None;
+    }
+
+    Some(days as u64 * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Human readable form IE `Mon Oct 19 00:00:00 2026`, used in key file comments.
+pub fn to_readable(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (year, month, day) = days_to_civil(days);
+    let time = secs % 86400;
+    format!("{} {} {:2} {:02}:{:02}:{:02} {}", DAYS[(days % 7) as usize], MONTHS[month as usize - 1], day,
+            time / 3600, (time / 60) % 60, time % 60, year)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn date_time() {
+        assert_eq!(to_date_time(0), "19700101000000");
+        assert_eq!(to_date_time(1792368000), "20261019000000");
+        assert_eq!(from_date_time("20261019000000"), Some(1792368000));
+        assert_eq!(from_date_time("20000229123456"), Some(951827696));
+        assert_eq!(to_date_time(951827696), "20000229123456");
+
+        assert_eq!(from_date_time("2026101900000"), None);
+        assert_eq!(from_date_time("20261319000000"), None);
+        assert_eq!(from_date_time("19691231235959"), None);
+    }
+
+    #[test]
+    fn readable() {
+        assert_eq!(to_readable(0), "Thu Jan  1 00:00:00 1970");
+        assert_eq!(to_readable(1792368000), "Mon Oct 19 00:00:00 2026");
+    }
+}
@@ -0,0 +1,98 @@
+use std::net::IpAddr;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::records::a_record::ARecord;
+use rlibdns::records::aaaa_record::AaaaRecord;
+use rlibdns::records::inter::record_base::RecordBase;
+use rlibdns::records::soa_record::SoaRecord;
+use rlibdns::zone::zone::Zone;
+use crate::utils::domain_utils::normalize;
+
+/// Lists every owner name in the zone as a normalized FQDN along with the types held there.
+pub fn get_all_names(zone: &Zone, apex: &str) -> Vec<(String, Vec<RRTypes>)> {
+    let apex = normalize(apex);
+    let mut names: Vec<(String, Vec<RRTypes>)> = Vec::new();
+
+    for (sub, records) in zone.get_all_records_recursive() {
+        let name = to_fqdn(&sub, &apex);
+
+        let mut types: Vec<RRTypes> = Vec::new();
+        for record in records {
+            if !types.contains(&record.get_type()) {
+                types.push(record.get_type());
+            }
+        }
+
+        match names.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                for _type in types {
+                    if !existing.contains(&_type) {
+                        existing.push(_type);
+                    }
+                }
+            }
+            None => names.push((name, types))
+        }
+    }
+
+    names
+}
+
+/// Every record of the type in the zone along with its owner as a normalized FQDN.
+pub fn get_all_records_of_type(zone: &Zone, apex: &str, _type: RRTypes) -> Vec<(String, Box<dyn RecordBase>)> {
+    let apex = normalize(apex);
+    let mut records = Vec::new();
+
+    for (sub, set) in zone.get_all_records_recursive() {
+        let name = to_fqdn(&sub, &apex);
+        for record in set.into_iter().filter(|record| record.get_type() == _type) {
+            records.push((name.clone(), record.clone()));
+        }
+    }
+
+    records
+}
+
+/// Zones carrying their own RRSIGs at the apex were signed offline and are served as they are.
+pub fn is_presigned(zone: &Zone) -> bool {
+    zone.get_records("", &RRTypes::RRSig).map(|records| !records.is_empty()).unwrap_or(false)
+}
+
+pub fn to_fqdn(sub: &str, apex: &str) -> String {
+    let sub = normalize(sub);
+    let apex = normalize(apex);
+
+    match sub.is_empty() {
+        true => apex,
+        false => match apex.is_empty() {
+            true => sub,
+            false => format!("{}.{}", sub, apex)
+        }
+    }
+}
+
+pub fn get_soa(zone: &Zone) -> Option<&SoaRecord> {
+    zone.get_records("", &RRTypes::Soa)?.first()?.as_any().downcast_ref::<SoaRecord>()
+}
+
+/// The SOA to put in the authority section of a negative answer, with the negative caching TTL.
+pub fn get_negative_soa(zone: &Zone) -> Option<Box<dyn RecordBase>> {
+    let ttl = get_negative_ttl(zone)?;
+    let mut record = zone.get_records("", &RRTypes::Soa)?.first()?.clone();
+    record.set_ttl(ttl);
+    Some(record)
+}
+
+//RFC 2308 section 5 - negative answers are cached for the lesser of the SOA TTL and SOA minimum
+pub fn get_negative_ttl(zone: &Zone) -> Option<u32> {
+    let soa = get_soa(zone)?;
+    Some(soa.get_ttl().min(soa.get_minimum_ttl()))
+}
+
+/// The address held by an A or AAAA record.
+pub fn get_record_address(record: &dyn RecordBase) -> Option<IpAddr> {
+    match record.get_type() {
+        RRTypes::A => record.as_any().downcast_ref::<ARecord>()?.get_address().map(IpAddr::V4),
+        RRTypes::Aaaa => record.as_any().downcast_ref::<AaaaRecord>()?.get_address().map(IpAddr::V6),
+        _ => None
+    }
+}
@@ -0,0 +1,159 @@
+use std::{env, fs, process};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use rlibdns::messages::inter::response_codes::ResponseCodes;
+use rlibdns::messages::inter::rr_types::RRTypes;
+use rlibdns::messages::message_base::MessageBase;
+use rlibdns::records::cname_record::CNameRecord;
+use find9::dns::dns::Dns;
+use find9::dns::upstream::query_server;
+use find9::utils::domain_utils::normalize;
+use find9::utils::zone_utils::get_record_address;
+
+const PORT: u16 = 16853;
+const ROOT: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+const TLD: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 3);
+const LEAF: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 4);
+const RESOLVER: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 5);
+
+const ROOT_ZONE: &str = "$ORIGIN .
+$TTL    300
+
+@       IN      SOA     a.root-servers.test. admin.test. ( 1 3600 600 86400 300 )
+@       IN      NS      a.root-servers.test.
+
+a.root-servers.test.    IN      A       127.0.0.2
+
+test.   IN      NS      ns.test.
+ns.test.        IN      A       127.0.0.3
+";
+
+const TLD_ZONE: &str = "$ORIGIN test.
+$TTL    300
+
+@       IN      SOA     ns.test. admin.test. ( 1 3600 600 86400 300 )
+@       IN      NS      ns.test.
+ns      IN      A       127.0.0.3
+
+example IN      NS      ns.example.test.
+ns.example      IN      A       127.0.0.4
+
+other   IN      NS      ns.other.test.
+ns.other        IN      A       127.0.0.4
+";
+
+const EXAMPLE_ZONE: &str = "$ORIGIN example.test.
+$TTL    300
+
+@       IN      SOA     ns.example.test. admin.example.test. ( 1 3600 600 86400 300 )
+@       IN      NS      ns.example.test.
+ns      IN      A       127.0.0.4
+
+www     IN      A       192.0.2.1
+alias   IN      CNAME   www.other.test.
+";
+
+const OTHER_ZONE: &str = "$ORIGIN other.test.
+$TTL    300
+
+@       IN      SOA     ns.other.test. admin.other.test. ( 1 3600 600 86400 300 )
+@       IN      NS      ns.other.test.
+ns      IN      A       127.0.0.4
+
+www     IN      A       192.0.2.2
+";
+
+const HINTS: &str = "$ORIGIN .
+$TTL    3600000
+
+@       IN      NS      a.root-servers.test.
+a.root-servers.test.    IN      A       127.0.0.2
+";
+
+fn write(directory: &Path, name: &str, contents: &str) -> String {
+    let path = directory.join(name);
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn serve(address: Ipv4Addr, zones: &[(&str, &str)]) -> Option<Dns> {
+    let dns = Dns::new();
+    for (file_path, domain) in zones {
+        dns.register_zone(file_path, domain).unwrap();
+    }
+
+    start(dns, address)
+}
+
+/// None if the address can't be bound IE loopback addresses other than 127.0.0.1 aren't routed here.
+fn start(mut dns: Dns, address: Ipv4Addr) -> Option<Dns> {
+    match dns.start_on(IpAddr::V4(address), PORT) {
+        Ok(_) => Some(dns),
+        Err(e) => {
+            println!("Skipping, unable to serve on {}: {}", address, e);
+            None
+        }
+    }
+}
+
+fn get_addresses(response: &MessageBase, name: &str) -> Vec<IpAddr> {
+    response.get_answers().iter()
+        .filter(|(owner, _)| normalize(owner) == name)
+        .filter_map(|(_, record)| get_record_address(record.as_ref()))
+        .collect()
+}
+
+#[test]
+fn resolve() {
+    let directory = env::temp_dir().join(format!("find9-resolver-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let root = write(&directory, "root.zone", ROOT_ZONE);
+    let tld = write(&directory, "test.zone", TLD_ZONE);
+    let example = write(&directory, "example.test.zone", EXAMPLE_ZONE);
+    let other = write(&directory, "other.test.zone", OTHER_ZONE);
+    let hints = write(&directory, "root.hints", HINTS);
+
+    //EACH SERVER HAS AN ADDRESS OF ITS OWN AS THE RESOLVER QUERIES THEM ALL ON THE ONE PORT
+    let servers = [
+        serve(ROOT, &[(&root, ".")]),
+        serve(TLD, &[(&tld, "test")]),
+        serve(LEAF, &[(&example, "example.test"), (&other, "other.test")])
+    ];
+
+    let resolver = Dns::new();
+    resolver.register_hints(&hints, PORT).unwrap();
+    resolver.allow_recursion(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 0)), 8).unwrap();
+    let resolver = match servers.iter().all(Option::is_some) {
+        true => start(resolver, RESOLVER),
+        false => None
+    };
+
+    if let Some(dns) = resolver {
+        let server = SocketAddr::new(IpAddr::V4(RESOLVER), PORT);
+
+        let response = query_server(server, "www.example.test", RRTypes::A, true, None).unwrap();
+        assert_eq!(response.get_response_code(), ResponseCodes::NoError);
+        assert_eq!(get_addresses(&response, "www.example.test"), vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+
+        //THE CNAME POINTS INTO A ZONE THE RESOLVER HAS TO BE REFERRED TO SEPARATELY
+        let response = query_server(server, "alias.example.test", RRTypes::A, true, None).unwrap();
+        assert_eq!(response.get_response_code(), ResponseCodes::NoError);
+        let target = response.get_answers().iter()
+            .find_map(|(_, record)| record.as_any().downcast_ref::<CNameRecord>()?.get_target())
+            .map(|target| normalize(&target));
+        assert_eq!(target.as_deref(), Some("www.other.test"));
+        assert_eq!(get_addresses(&response, "www.other.test"), vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))]);
+
+        let response = query_server(server, "missing.example.test", RRTypes::A, true, None).unwrap();
+        assert_eq!(response.get_response_code(), ResponseCodes::NxDomain);
+
+        dns.stop();
+    }
+
+    for dns in servers.iter().flatten() {
+        dns.stop();
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,127 @@
+use std::{env, fs, process};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::process::Command;
+use find9::dns::dns::Dns;
+use find9::dnssec::inter::dnssec_algorithms::DnssecAlgorithms;
+use find9::dnssec::key_manager::KeyPolicy;
+
+const PORT: u16 = 16854;
+const SERVER: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 6);
+const ZONES: [&str; 2] = ["nsec.test", "nsec3.test"];
+
+/// The names asked for in each zone, IE an NXDOMAIN, a NODATA and an answer synthesised from a
+/// wildcard, all of which need a proof of what doesn't exist.
+const QUERIES: [(&str, &str); 3] = [("missing", "A"), ("www", "AAAA"), ("host.wild", "A")];
+
+fn get_zone(apex: &str) -> String {
+    format!("$ORIGIN {apex}.
+$TTL    300
+
+@       IN      SOA     ns.{apex}. admin.{apex}. ( 1 3600 600 86400 300 )
+@       IN      NS      ns.{apex}.
+ns      IN      A       127.0.0.6
+
+www     IN      A       192.0.2.1
+*.wild  IN      A       192.0.2.2
+")
+}
+
+/// "apex. TTL IN DS TAG ALGORITHM DIGEST_TYPE DIGEST" as the static-ds anchor delv reads.
+fn to_anchor(ds: &str) -> String {
+    let parts: Vec<&str> = ds.split_whitespace().collect();
+    format!("\"{}\" static-ds {} {} {} \"{}\";", parts[0], parts[4], parts[5], parts[6], parts[7])
+}
+
+fn is_installed(tool: &str) -> bool {
+    Command::new(tool).arg("-h").output().is_ok()
+}
+
+fn run(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr))
+}
+
+fn delv(directory: &Path, ds: &[String]) {
+    let anchors: Vec<String> = ds.iter().map(|ds| to_anchor(ds)).collect();
+    let anchors_path = directory.join("anchors.conf");
+    fs::write(&anchors_path, format!("trust-anchors {{\n{}\n}};\n", anchors.join("\n"))).unwrap();
+
+    for apex in ZONES {
+        for (sub, _type) in QUERIES {
+            let output = run(Command::new("delv")
+                .arg(format!("@{}", SERVER))
+                .args(["-p", &PORT.to_string(), "-a", anchors_path.to_str().unwrap()])
+                .arg(format!("+root={}", apex))
+                .args([&format!("{}.{}", sub, apex), _type]));
+
+            assert!(output.contains("fully validated"), "delv {}.{} {}: {}", sub, apex, _type, output);
+        }
+    }
+}
+
+fn unbound(directory: &Path, ds: &[String]) {
+    let anchors_path = directory.join("anchors.ds");
+    fs::write(&anchors_path, ds.join("\n") + "\n").unwrap();
+
+    let mut config = format!("server:\n    trust-anchor-file: \"{}\"\n    do-not-query-localhost: no\n", anchors_path.to_str().unwrap());
+    for apex in ZONES {
+        config += &format!("stub-zone:\n    name: \"{}.\"\n    stub-addr: {}@{}\n", apex, SERVER, PORT);
+    }
+
+    let config_path = directory.join("unbound.conf");
+    fs::write(&config_path, config).unwrap();
+
+    for apex in ZONES {
+        for (sub, _type) in QUERIES {
+            let output = run(Command::new("unbound-host")
+                .args(["-C", config_path.to_str().unwrap(), "-v", "-t", _type])
+                .arg(format!("{}.{}", sub, apex)));
+
+            assert!(output.contains("(secure)"), "unbound-host {}.{} {}: {}", sub, apex, _type, output);
+        }
+    }
+}
+
+#[test]
+fn denial() {
+    let (has_delv, has_unbound) = (is_installed("delv"), is_installed("unbound-host"));
+    if !has_delv && !has_unbound {
+        println!("Skipping, neither delv nor unbound-host is installed");
+        return;
+    }
+
+    let directory = env::temp_dir().join(format!("find9-validation-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut dns = Dns::new();
+    for apex in ZONES {
+        let path = directory.join(format!("{}.zone", apex));
+        fs::write(&path, get_zone(apex)).unwrap();
+        dns.register_zone(path.to_str().unwrap(), apex).unwrap();
+
+        let keys = directory.join("keys").join(apex);
+        fs::create_dir_all(&keys).unwrap();
+        dns.register_key_directory(keys.to_str().unwrap(), apex, KeyPolicy::new(DnssecAlgorithms::EcdsaP256Sha256)).unwrap();
+    }
+    dns.set_nsec3("nsec3.test", 0, &[], false);
+
+    let ds: Vec<String> = ZONES.iter().flat_map(|apex| dns.get_ds_records(apex)).collect();
+
+    if let Err(e) = dns.start_on(IpAddr::V4(SERVER), PORT) {
+        println!("Skipping, unable to serve on {}: {}", SERVER, e);
+        fs::remove_dir_all(&directory).unwrap();
+        return;
+    }
+
+    if has_delv {
+        delv(&directory, &ds);
+    }
+
+    if has_unbound {
+        unbound(&directory, &ds);
+    }
+
+    dns.stop();
+    fs::remove_dir_all(&directory).unwrap();
+}